@@ -1,5 +1,6 @@
 pub mod api;
 pub mod consensus;
+pub mod ibc;
 pub mod mem_tracker;
 pub mod mempool;
 pub mod network;
@@ -12,8 +13,8 @@ pub use prometheus::{
 use derive_more::Display;
 use prometheus::{
     exponential_buckets, linear_buckets, register_counter_vec, register_histogram,
-    register_histogram_vec, register_int_counter, register_int_gauge, register_int_gauge_vec,
-    Encoder, TextEncoder,
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, TextEncoder,
 };
 use prometheus_static_metric::{auto_flush_from, make_auto_flush_static_metric};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
@@ -0,0 +1,12 @@
+use lazy_static::lazy_static;
+
+use crate::metrics::{register_int_counter_vec, IntCounterVec};
+
+lazy_static! {
+    pub static ref IBC_GRPC_UNSUPPORTED_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "axon_ibc_grpc_unsupported_total",
+        "Total number of the ibc grpc requests to the unsupported methods",
+        &["method"]
+    )
+    .expect("ibc grpc unsupported total");
+}
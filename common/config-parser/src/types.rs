@@ -5,7 +5,7 @@ use std::path::PathBuf;
 use serde::Deserialize;
 use tentacle_multiaddr::MultiAddr;
 
-use protocol::types::{Hex, H160, H256, U256};
+use protocol::types::{GasSchedule, Hex, H160, H256, U256};
 
 pub const DEFAULT_BROADCAST_TXS_SIZE: usize = 200;
 pub const DEFAULT_BROADCAST_TXS_INTERVAL: u64 = 200; // milliseconds
@@ -101,6 +101,8 @@ pub struct ConfigMempool {
 pub struct ConfigExecutor {
     pub light:             bool,
     pub triedb_cache_size: usize,
+    #[serde(default)]
+    pub gas_schedule:      GasSchedule,
 }
 
 fn default_cache_size() -> usize {
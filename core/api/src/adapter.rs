@@ -3,9 +3,9 @@ use std::sync::Arc;
 use core_executor::{AxonExecutor, AxonExecutorAdapter, MPTTrie};
 use protocol::traits::{APIAdapter, Context, Executor, ExecutorAdapter, MemPool, Network, Storage};
 use protocol::types::{
-    Account, BigEndianHash, Block, BlockNumber, Bytes, ExecutorContext, Hash, HashWithDirection,
-    Header, Proposal, Receipt, SignedTransaction, TxResp, H160, MAX_BLOCK_GAS_LIMIT, NIL_DATA,
-    RLP_NULL, U256,
+    Account, BigEndianHash, Block, BlockNumber, Bytes, ExecutorContext, GasSchedule, Hash,
+    HashWithDirection, Header, Proposal, Receipt, SignedTransaction, TxResp, H160,
+    MAX_BLOCK_GAS_LIMIT, NIL_DATA, RLP_NULL, U256,
 };
 use protocol::{async_trait, codec::ProtocolCodec, ProtocolResult};
 
@@ -13,10 +13,11 @@ use crate::APIError;
 
 #[derive(Clone)]
 pub struct DefaultAPIAdapter<M, S, DB, Net> {
-    mempool: Arc<M>,
-    storage: Arc<S>,
-    trie_db: Arc<DB>,
-    net:     Arc<Net>,
+    mempool:      Arc<M>,
+    storage:      Arc<S>,
+    trie_db:      Arc<DB>,
+    net:          Arc<Net>,
+    gas_schedule: GasSchedule,
 }
 
 impl<M, S, DB, Net> DefaultAPIAdapter<M, S, DB, Net>
@@ -26,12 +27,19 @@ where
     DB: cita_trie::DB + 'static,
     Net: Network + 'static,
 {
-    pub fn new(mempool: Arc<M>, storage: Arc<S>, trie_db: Arc<DB>, net: Arc<Net>) -> Self {
+    pub fn new(
+        mempool: Arc<M>,
+        storage: Arc<S>,
+        trie_db: Arc<DB>,
+        net: Arc<Net>,
+        gas_schedule: GasSchedule,
+    ) -> Self {
         Self {
             mempool,
             storage,
             trie_db,
             net,
+            gas_schedule,
         }
     }
 
@@ -50,7 +58,7 @@ where
             state_root,
             Arc::clone(&self.trie_db),
             Arc::clone(&self.storage),
-            ExecutorContext::from(proposal),
+            ExecutorContext::from(proposal).with_gas_schedule(self.gas_schedule.clone()),
         )
     }
 }
@@ -175,7 +183,8 @@ where
         state_root: Hash,
         mock_header: Proposal,
     ) -> ProtocolResult<TxResp> {
-        let mut exec_ctx = ExecutorContext::from(mock_header);
+        let mut exec_ctx =
+            ExecutorContext::from(mock_header).with_gas_schedule(self.gas_schedule.clone());
         exec_ctx.origin = from.unwrap_or_default();
         exec_ctx.gas_price = gas_price.unwrap_or_else(U256::one);
 
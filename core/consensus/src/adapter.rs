@@ -15,8 +15,9 @@ use protocol::traits::{
     SynchronizationAdapter,
 };
 use protocol::types::{
-    BatchSignedTxs, Block, BlockNumber, Bytes, ExecResp, Hash, Header, Hex, Log, MerkleRoot,
-    Metadata, PackedTxHashes, Proof, Proposal, Receipt, SignedTransaction, Validator, U256,
+    BatchSignedTxs, Block, BlockNumber, Bytes, ExecResp, ExecutorContext, GasSchedule, Hash,
+    Header, Hex, Log, MerkleRoot, Metadata, PackedTxHashes, Proof, Proposal, Receipt,
+    SignedTransaction, Validator, U256,
 };
 use protocol::{async_trait, tokio::task, ProtocolResult};
 
@@ -47,6 +48,8 @@ pub struct OverlordConsensusAdapter<
     metadata:         Arc<MT>,
     overlord_handler: RwLock<Option<OverlordHandler<Proposal>>>,
     crypto:           Arc<OverlordCrypto>,
+    gas_schedule:     GasSchedule,
+    executor:         AxonExecutor,
 }
 
 #[async_trait]
@@ -346,12 +349,12 @@ where
             last_state_root,
             Arc::clone(&self.trie_db),
             Arc::clone(&self.storage),
-            proposal.clone().into(),
+            ExecutorContext::from(proposal.clone()).with_gas_schedule(self.gas_schedule.clone()),
         )?;
 
         Ok(task::block_in_place(|| {
             let time = Instant::now();
-            let res = AxonExecutor::default().exec(&mut backend, signed_txs);
+            let res = self.executor.exec(&mut backend, signed_txs);
             common_apm::metrics::consensus::CONSENSUS_TIME_HISTOGRAM_VEC_STATIC
                 .exec
                 .observe(common_apm::metrics::duration_to_sec(time.elapsed()));
@@ -638,6 +641,7 @@ where
         cross_client: Arc<CS>,
         metadata: Arc<MT>,
         crypto: Arc<OverlordCrypto>,
+        gas_schedule: GasSchedule,
     ) -> ProtocolResult<Self> {
         Ok(OverlordConsensusAdapter {
             network,
@@ -648,6 +652,8 @@ where
             cross_client,
             overlord_handler: RwLock::new(None),
             crypto,
+            gas_schedule,
+            executor: AxonExecutor::default(),
         })
     }
 
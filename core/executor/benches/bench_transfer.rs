@@ -16,6 +16,7 @@ use protocol::types::{
     SignedTransaction, TransactionAction, UnsignedTransaction, UnverifiedTransaction, NIL_DATA,
     RLP_NULL, U256,
 };
+use tempfile::TempDir;
 
 lazy_static::lazy_static! {
     static ref PRIVITE_KEY: Secp256k1RecoverablePrivateKey
@@ -23,22 +24,26 @@ lazy_static::lazy_static! {
     static ref DISTRIBUTE_ADDRESS: Address = Address::from_hex("0x35e70c3f5a794a77efc2ec5ba964bffcc7fd2c0a").unwrap();
 }
 
-const STATE_PATH: &str = "../../free-space/rocks/state";
-const DATA_PATH: &str = "../../free-space/rocks/data";
-
+// The databases live in temporary directories which are removed when the
+// adapter is dropped, so it must outlive the backends built from it.
 struct BenchAdapter {
     trie_db: Arc<RocksTrieDB>,
     storage: Arc<ImplStorage<RocksAdapter>>,
+    _dir:    TempDir,
 }
 
 impl BenchAdapter {
     fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
         BenchAdapter {
-            trie_db: Arc::new(RocksTrieDB::new(STATE_PATH, Default::default(), 1000).unwrap()),
+            trie_db: Arc::new(
+                RocksTrieDB::new(dir.path().join("state"), Default::default(), 1000).unwrap(),
+            ),
             storage: Arc::new(ImplStorage::new(
-                Arc::new(RocksAdapter::new(DATA_PATH, Default::default()).unwrap()),
+                Arc::new(RocksAdapter::new(dir.path().join("data"), Default::default()).unwrap()),
                 100,
             )),
+            _dir:    dir,
         }
     }
 
@@ -77,6 +82,7 @@ impl BenchAdapter {
                 block_gas_limit:        100_000_000_000u64.into(),
                 block_base_fee_per_gas: Default::default(),
                 logs:                   vec![],
+                gas_schedule:           Default::default(),
             },
         )
         .unwrap()
@@ -139,7 +145,8 @@ fn mock_txs(number: u64) -> Vec<SignedTransaction> {
 fn criterion_100_txs(c: &mut Criterion) {
     let txs = mock_txs(100);
     let executor = AxonExecutor::default();
-    let mut backend = BenchAdapter::new().init_backend();
+    let adapter = BenchAdapter::new();
+    let mut backend = adapter.init_backend();
 
     c.bench_function("transfer 100", |b| {
         b.iter(|| executor.exec(&mut backend, &txs))
@@ -149,7 +156,8 @@ fn criterion_100_txs(c: &mut Criterion) {
 fn criterion_1000_txs(c: &mut Criterion) {
     let txs = mock_txs(1000);
     let executor = AxonExecutor::default();
-    let mut backend = BenchAdapter::new().init_backend();
+    let adapter = BenchAdapter::new();
+    let mut backend = adapter.init_backend();
 
     c.bench_function("transfer 1000", |b| {
         b.iter(|| executor.exec(&mut backend, &txs))
@@ -159,7 +167,8 @@ fn criterion_1000_txs(c: &mut Criterion) {
 fn criterion_10000_txs(c: &mut Criterion) {
     let txs = mock_txs(10000);
     let executor = AxonExecutor::default();
-    let mut backend = BenchAdapter::new().init_backend();
+    let adapter = BenchAdapter::new();
+    let mut backend = adapter.init_backend();
 
     c.bench_function("transfer 10000", |b| {
         b.iter(|| executor.exec(&mut backend, &txs))
@@ -72,7 +72,7 @@ fn criterion_10000_txs(c: &mut Criterion) {
     let txs = mock_transactions(10000);
     // MacOS M1 Pro, 16GB: time: 20.098ms
     c.bench_function("revm 10000 tx", |b| {
-        let storage = new_storage();
+        let (storage, _data_dir) = new_storage();
         let db = new_rocks_trie_db();
         let exec_ctx = mock_executor_context();
         let (account, addr) = init_account();
@@ -85,7 +85,7 @@ fn criterion_10000_txs(c: &mut Criterion) {
     });
     // MacOS M1 Pro, 16GB: time:54.987ms
     c.bench_function("evm 10000 tx", |b| {
-        let storage = new_storage();
+        let (storage, _data_dir) = new_storage();
         let db = new_rocks_trie_db();
         let exec_ctx = mock_executor_context();
         let (account, addr) = init_account();
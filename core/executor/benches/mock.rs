@@ -15,6 +15,7 @@ use protocol::{
         H256, H512, NIL_DATA, RLP_NULL, U256,
     },
 };
+use tempfile::TempDir;
 
 lazy_static::lazy_static! {
     static ref PRIVITE_KEY: Secp256k1RecoverablePrivateKey
@@ -22,18 +23,63 @@ lazy_static::lazy_static! {
     static ref DISTRIBUTE_ADDRESS: Address = Address::from_hex("0x35e70c3f5a794a77efc2ec5ba964bffcc7fd2c0a").unwrap();
 }
 
-const STATE_PATH: &str = "../../free-space/rocks/state";
-const DATA_PATH: &str = "../../free-space/rocks/data";
+/// A `RocksTrieDB` opened in a temporary directory, which is removed when the
+/// handle is dropped.
+pub struct TempRocksTrieDB {
+    // Declared before the directory so that the database is closed first.
+    db:   RocksTrieDB,
+    _dir: TempDir,
+}
+
+impl cita_trie::DB for TempRocksTrieDB {
+    type Error = <RocksTrieDB as cita_trie::DB>::Error;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.db.get(key)
+    }
+
+    fn contains(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        self.db.contains(key)
+    }
+
+    fn insert(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), Self::Error> {
+        self.db.insert(key, value)
+    }
+
+    fn insert_batch(&self, keys: Vec<Vec<u8>>, values: Vec<Vec<u8>>) -> Result<(), Self::Error> {
+        self.db.insert_batch(keys, values)
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<(), Self::Error> {
+        self.db.remove(key)
+    }
+
+    fn remove_batch(&self, keys: &[Vec<u8>]) -> Result<(), Self::Error> {
+        self.db.remove_batch(keys)
+    }
 
-pub fn new_rocks_trie_db() -> RocksTrieDB {
-    RocksTrieDB::new(STATE_PATH, Default::default(), 1000).unwrap()
+    fn flush(&self) -> Result<(), Self::Error> {
+        self.db.flush()
+    }
+}
+
+pub fn new_rocks_trie_db() -> TempRocksTrieDB {
+    let dir = tempfile::tempdir().unwrap();
+    TempRocksTrieDB {
+        db:   RocksTrieDB::new(dir.path(), Default::default(), 1000).unwrap(),
+        _dir: dir,
+    }
 }
 
-pub fn new_storage() -> ImplStorage<RocksAdapter> {
-    ImplStorage::new(
-        Arc::new(RocksAdapter::new(DATA_PATH, Default::default()).unwrap()),
+/// The returned directory must outlive the storage, the data is removed when
+/// it is dropped.
+pub fn new_storage() -> (ImplStorage<RocksAdapter>, TempDir) {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = ImplStorage::new(
+        Arc::new(RocksAdapter::new(dir.path(), Default::default()).unwrap()),
         100,
-    )
+    );
+    (storage, dir)
 }
 
 pub fn init_account() -> (Account, Address) {
@@ -59,6 +105,7 @@ pub fn mock_executor_context() -> ExecutorContext {
         block_gas_limit:        100_000_000_000u64.into(),
         block_base_fee_per_gas: Default::default(),
         logs:                   vec![],
+        gas_schedule:           Default::default(),
     }
 }
 
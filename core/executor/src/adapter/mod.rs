@@ -1,19 +1,29 @@
+mod read_only;
+mod state_override;
 mod trie;
 mod trie_db;
 
+pub use read_only::ReadOnlyBackend;
+pub use state_override::OverrideBackend;
 pub use trie::MPTTrie;
 pub use trie_db::RocksTrieDB;
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use evm::backend::{Apply, Basic};
+use parking_lot::RwLock;
 
-use protocol::traits::{ApplyBackend, Backend, Context, ExecutorAdapter, Storage};
+use protocol::codec::{hex_encode, ProtocolCodec};
+use protocol::traits::{ApplyBackend, Backend, CommonStorage, Context, ExecutorAdapter, Storage};
 use protocol::types::{
-    Account, Bytes, ExecutorContext, Hasher, Log, MerkleRoot, Proposal, H160, H256, NIL_DATA,
-    RLP_NULL, U256,
+    Account, Address, Bytes, ExecutorContext, Hasher, Log, MerkleRoot, Proposal, SignedTransaction,
+    StateDiff, StoreHeight, H160, H256, NIL_DATA, RLP_NULL, U256,
 };
-use protocol::{codec::ProtocolCodec, ProtocolResult};
+use protocol::{Display, ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+use crate::AxonExecutor;
 
 const GET_BLOCK_HASH_NUMBER_RANGE: u64 = 256;
 
@@ -29,10 +39,14 @@ macro_rules! blocking_async {
 }
 
 pub struct AxonExecutorAdapter<S, DB: cita_trie::DB> {
-    exec_ctx: ExecutorContext,
-    trie:     MPTTrie<DB>,
-    storage:  Arc<S>,
-    db:       Arc<DB>,
+    exec_ctx:        ExecutorContext,
+    trie:            MPTTrie<DB>,
+    storage:         Arc<S>,
+    db:              Arc<DB>,
+    /// The contract codes loaded during this execution, keyed by code hash.
+    code_cache:      RwLock<HashMap<H256, Vec<u8>>>,
+    /// The number of code reads served from `code_cache`.
+    code_cache_hits: AtomicU64,
 }
 
 impl<S, DB> ExecutorAdapter for AxonExecutorAdapter<S, DB>
@@ -178,9 +192,19 @@ where
             return Vec::new();
         }
 
+        if let Some(code) = self.code_cache.read().get(&code_hash) {
+            self.code_cache_hits.fetch_add(1, Ordering::Relaxed);
+            return code.clone();
+        }
+
         let res = blocking_async!(self, storage, get_code_by_hash, Context::new(), &code_hash);
+        let code = res.unwrap_or_default().to_vec();
+
+        if !code.is_empty() {
+            self.code_cache.write().insert(code_hash, code.clone());
+        }
 
-        res.unwrap_or_default().to_vec()
+        code
     }
 
     fn storage(&self, address: H160, index: H256) -> H256 {
@@ -262,6 +286,8 @@ where
             db,
             storage,
             exec_ctx,
+            code_cache: RwLock::new(HashMap::new()),
+            code_cache_hits: AtomicU64::new(0),
         })
     }
 
@@ -278,9 +304,149 @@ where
             db,
             storage,
             exec_ctx,
+            code_cache: RwLock::new(HashMap::new()),
+            code_cache_hits: AtomicU64::new(0),
         })
     }
 
+    /// The number of code reads served from the code cache so far.
+    pub fn code_cache_hits(&self) -> u64 {
+        self.code_cache_hits.load(Ordering::Relaxed)
+    }
+
+    /// Insert the genesis accounts and commit them all at once, returning the
+    /// genesis state root. Committing once is much faster than committing
+    /// after each account on a large allocation.
+    pub fn init_accounts(&mut self, accounts: &[(Address, Account)]) -> ProtocolResult<MerkleRoot> {
+        let mut keys = Vec::with_capacity(accounts.len());
+        let mut values = Vec::with_capacity(accounts.len());
+        for (address, account) in accounts.iter() {
+            keys.push(address.as_slice().to_vec());
+            values.push(account.encode()?.to_vec());
+        }
+
+        self.trie.insert_batch(keys, values)?;
+        self.trie.commit()
+    }
+
+    /// Open a view of the pending state, which is the state of `state_root`
+    /// with `txs` executed on top of it. The pending txs are the ones executed
+    /// into the block being built, whose state is not committed yet. The view
+    /// serves the queries with the `pending` tag and must not be committed.
+    pub fn pending_view(
+        state_root: MerkleRoot,
+        db: Arc<DB>,
+        storage: Arc<S>,
+        exec_ctx: ExecutorContext,
+        txs: &[SignedTransaction],
+    ) -> ProtocolResult<Self> {
+        let mut view = AxonExecutorAdapter::from_root(state_root, db, storage, exec_ctx)?;
+        AxonExecutor::default().exec_uncommitted(&mut view, txs);
+        Ok(view)
+    }
+
+    /// Open a read-only adapter on the state after the block `number`, which
+    /// serves the archive queries.
+    pub async fn state_at_block(
+        number: u64,
+        db: Arc<DB>,
+        storage: Arc<S>,
+    ) -> ProtocolResult<ReadOnlyBackend<Self>> {
+        let block = storage
+            .get_block(Context::new(), number)
+            .await?
+            .ok_or(AdapterError::BlockNotFound(number))?;
+        let state_root = block.header.state_root;
+
+        if !db
+            .contains(state_root.as_bytes())
+            .map_err(|e| AdapterError::TrieDb(e.to_string()))?
+        {
+            return Err(AdapterError::StatePruned { number, state_root }.into());
+        }
+
+        let exec_ctx = ExecutorContext::from(Proposal::from(&block));
+        AxonExecutorAdapter::from_root(state_root, db, storage, exec_ctx).map(ReadOnlyBackend::new)
+    }
+
+    /// The nonce of the account at `address` at `height`. The pending nonce is
+    /// read from the state of this adapter, so it counts the txs which have
+    /// been applied but not committed yet. The txs still in the mempool are
+    /// not counted. A stable height reads the state after that block.
+    pub async fn nonce_at(&self, address: H160, height: StoreHeight) -> ProtocolResult<U256> {
+        match height {
+            StoreHeight::Pending => Ok(self.basic(address).nonce),
+            StoreHeight::Stable(number) => {
+                let state = AxonExecutorAdapter::state_at_block(
+                    number,
+                    Arc::clone(&self.db),
+                    Arc::clone(&self.storage),
+                )
+                .await?;
+                Ok(state.basic(address).nonce)
+            }
+        }
+    }
+
+    /// Apply the state changes of a block computed elsewhere instead of
+    /// executing its txs, and commit them. The resulting state root is
+    /// returned, or an error if it is not the one the diff claims, in which
+    /// case the adapter is rolled back to the state it had before.
+    pub fn apply_state_diff(&mut self, diff: StateDiff) -> ProtocolResult<MerkleRoot> {
+        let pre_root = self.trie.commit()?;
+        let values = diff
+            .accounts
+            .into_iter()
+            .map(|(address, account)| match account {
+                Some(account) => Apply::Modify {
+                    address,
+                    basic: Basic {
+                        balance: account.balance,
+                        nonce:   account.nonce,
+                    },
+                    code: account.code,
+                    storage: account.storage,
+                    reset_storage: account.reset_storage,
+                },
+                None => Apply::Delete { address },
+            });
+        ApplyBackend::apply(self, values, Vec::<Log>::new(), true);
+
+        // The root can only be computed by committing the trie. The nodes of a
+        // mismatching state are left unreachable and the trie is reopened at
+        // the previous root.
+        let state_root = self.trie.commit()?;
+        if state_root != diff.state_root {
+            self.trie = MPTTrie::from_root(pre_root, Arc::clone(&self.db))?;
+            return Err(AdapterError::StateRootMismatch {
+                expect: diff.state_root,
+                actual: state_root,
+            }
+            .into());
+        }
+
+        Ok(state_root)
+    }
+
+    /// Open the storage slots of the account at `address`, which are read
+    /// from the trie db on iteration. An EOA or a missing account has no
+    /// slots.
+    pub fn storage_iter(&self, address: H160) -> ProtocolResult<StorageSlots<DB>> {
+        let storage_root = match self.trie.get(address.as_bytes())? {
+            Some(raw) => Account::decode(raw)?.storage_root,
+            None => RLP_NULL,
+        };
+
+        if storage_root == RLP_NULL {
+            return Ok(StorageSlots(None));
+        }
+
+        Ok(StorageSlots(Some(MPTTrie::from_root(
+            storage_root,
+            Arc::clone(&self.db),
+        )?)))
+    }
+
     fn apply<I: IntoIterator<Item = (H256, H256)>>(
         &mut self,
         address: H160,
@@ -332,8 +498,9 @@ where
                     Context::new(),
                     address.into(),
                     new_code_hash,
-                    c.into()
+                    c.clone().into()
                 );
+                self.code_cache.write().insert(new_code_hash, c);
 
                 new_account.code_hash = new_code_hash;
             }
@@ -352,3 +519,53 @@ where
             && new_account.code_hash.is_zero()
     }
 }
+
+/// The storage trie of an account, opened by
+/// `AxonExecutorAdapter::storage_iter`.
+pub struct StorageSlots<DB: cita_trie::DB>(Option<MPTTrie<DB>>);
+
+impl<DB: cita_trie::DB> StorageSlots<DB> {
+    /// Iterate over the slots lazily. An entry which is not a pair of words
+    /// is an error.
+    pub fn iter(&self) -> impl Iterator<Item = ProtocolResult<(H256, H256)>> + '_ {
+        self.0.iter().flat_map(|trie| trie.iter()).map(|(k, v)| {
+            if k.len() != H256::len_bytes() || v.len() != H256::len_bytes() {
+                return Err(AdapterError::InvalidStorageSlot(hex_encode(&k)).into());
+            }
+
+            Ok((H256::from_slice(&k), H256::from_slice(&v)))
+        })
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum AdapterError {
+    #[display(fmt = "Block {} not found", _0)]
+    BlockNotFound(u64),
+
+    #[display(fmt = "State {:?} of block {} is pruned", state_root, number)]
+    StatePruned {
+        number:     u64,
+        state_root: MerkleRoot,
+    },
+
+    #[display(fmt = "State root mismatch, expect {:?}, actual {:?}", expect, actual)]
+    StateRootMismatch {
+        expect: MerkleRoot,
+        actual: MerkleRoot,
+    },
+
+    #[display(fmt = "Trie db {}", _0)]
+    TrieDb(String),
+
+    #[display(fmt = "Invalid storage slot {}", _0)]
+    InvalidStorageSlot(String),
+}
+
+impl std::error::Error for AdapterError {}
+
+impl From<AdapterError> for ProtocolError {
+    fn from(err: AdapterError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Executor, Box::new(err))
+    }
+}
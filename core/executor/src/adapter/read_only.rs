@@ -0,0 +1,19 @@
+use std::ops::Deref;
+
+/// A backend which only serves reads. It derefs to the wrapped backend
+/// immutably, so the state can't be applied to or committed through it.
+pub struct ReadOnlyBackend<B>(B);
+
+impl<B> ReadOnlyBackend<B> {
+    pub fn new(backend: B) -> Self {
+        ReadOnlyBackend(backend)
+    }
+}
+
+impl<B> Deref for ReadOnlyBackend<B> {
+    type Target = B;
+
+    fn deref(&self) -> &B {
+        &self.0
+    }
+}
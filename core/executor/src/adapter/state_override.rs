@@ -0,0 +1,105 @@
+use evm::backend::Basic;
+
+use protocol::traits::Backend;
+use protocol::types::{StateOverride, H160, H256, U256};
+
+/// A read-only backend which serves the overridden account fields on top of
+/// the state of `backend`. The overrides only live in memory and are never
+/// committed.
+pub struct OverrideBackend<'a, B> {
+    backend:   &'a B,
+    overrides: &'a StateOverride,
+}
+
+impl<'a, B: Backend> OverrideBackend<'a, B> {
+    pub fn new(backend: &'a B, overrides: &'a StateOverride) -> Self {
+        OverrideBackend { backend, overrides }
+    }
+}
+
+impl<'a, B: Backend> Backend for OverrideBackend<'a, B> {
+    fn gas_price(&self) -> U256 {
+        self.backend.gas_price()
+    }
+
+    fn origin(&self) -> H160 {
+        self.backend.origin()
+    }
+
+    fn block_hash(&self, number: U256) -> H256 {
+        self.backend.block_hash(number)
+    }
+
+    fn block_number(&self) -> U256 {
+        self.backend.block_number()
+    }
+
+    fn block_coinbase(&self) -> H160 {
+        self.backend.block_coinbase()
+    }
+
+    fn block_timestamp(&self) -> U256 {
+        self.backend.block_timestamp()
+    }
+
+    fn block_difficulty(&self) -> U256 {
+        self.backend.block_difficulty()
+    }
+
+    fn block_gas_limit(&self) -> U256 {
+        self.backend.block_gas_limit()
+    }
+
+    fn block_base_fee_per_gas(&self) -> U256 {
+        self.backend.block_base_fee_per_gas()
+    }
+
+    fn chain_id(&self) -> U256 {
+        self.backend.chain_id()
+    }
+
+    fn exists(&self, address: H160) -> bool {
+        self.overrides.contains_key(&address) || self.backend.exists(address)
+    }
+
+    fn basic(&self, address: H160) -> Basic {
+        let mut basic = self.backend.basic(address);
+
+        if let Some(account) = self.overrides.get(&address) {
+            if let Some(balance) = account.balance {
+                basic.balance = balance;
+            }
+            if let Some(nonce) = account.nonce {
+                basic.nonce = nonce;
+            }
+        }
+
+        basic
+    }
+
+    fn code(&self, address: H160) -> Vec<u8> {
+        match self
+            .overrides
+            .get(&address)
+            .and_then(|account| account.code.as_ref())
+        {
+            Some(code) => code.clone(),
+            None => self.backend.code(address),
+        }
+    }
+
+    fn storage(&self, address: H160, index: H256) -> H256 {
+        match self
+            .overrides
+            .get(&address)
+            .and_then(|account| account.storage.get(&index))
+        {
+            Some(value) => *value,
+            None => self.backend.storage(address, index),
+        }
+    }
+
+    fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+        Some(self.storage(address, index))
+    }
+}
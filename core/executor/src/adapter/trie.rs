@@ -44,6 +44,19 @@ impl<DB: TrieDB> MPTTrie<DB> {
         Ok(())
     }
 
+    /// Insert all the pairs, which are only written to the database once on the
+    /// next commit.
+    pub fn insert_batch(&mut self, keys: Vec<Vec<u8>>, values: Vec<Vec<u8>>) -> ProtocolResult<()> {
+        if keys.len() != values.len() {
+            return Err(MPTTrieError::BatchLengthMismatch.into());
+        }
+
+        for (key, value) in keys.into_iter().zip(values.into_iter()) {
+            self.0.insert(key, value).map_err(MPTTrieError::from)?;
+        }
+        Ok(())
+    }
+
     pub fn remove(&mut self, key: &[u8]) -> ProtocolResult<()> {
         if self.0.remove(key).map_err(MPTTrieError::from)? {
             Ok(())
@@ -52,6 +65,10 @@ impl<DB: TrieDB> MPTTrie<DB> {
         }
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> + '_ {
+        self.0.iter()
+    }
+
     pub fn commit(&mut self) -> ProtocolResult<MerkleRoot> {
         Ok(MerkleRoot::from_slice(
             &self.0.root().map_err(MPTTrieError::from)?,
@@ -66,6 +83,9 @@ pub enum MPTTrieError {
 
     #[display(fmt = "Remove {:?} failed", _0)]
     RemoveFailed(String),
+
+    #[display(fmt = "Batch length mismatch")]
+    BatchLengthMismatch,
 }
 
 impl std::error::Error for MPTTrieError {}
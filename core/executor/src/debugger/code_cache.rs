@@ -0,0 +1,63 @@
+use protocol::traits::{Backend, Executor};
+use protocol::types::{TransactionAction, H160, U256};
+use protocol::{codec::hex_decode, tokio};
+
+use crate::debugger::{clear_data, mock_tx, EvmDebugger};
+use crate::AxonExecutor;
+
+// The runtime code returns 42 as a 32 bytes word.
+const RUNTIME_CODE: &str = "602a60005260206000f3";
+const INIT_CODE: &str = "600a600c600039600a6000f3602a60005260206000f3";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_code_cache_keeps_call_results() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let db_path = "free-space/db_code_cache";
+    let mut debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    let resp = debugger.exec(1, vec![mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Create,
+        U256::zero(),
+        hex_decode(INIT_CODE).unwrap(),
+    )]);
+    let contract: H160 = resp.tx_resp[0].code_address.unwrap().into();
+
+    // The first call loads the code from storage and the others hit the cache.
+    let nonce = debugger.nonce(sender);
+    let txs = (0..3u64)
+        .map(|i| {
+            mock_tx(
+                nonce + i,
+                sender,
+                TransactionAction::Call(contract),
+                U256::zero(),
+                vec![],
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut backend = debugger.backend(2);
+    let resp = AxonExecutor::default().exec(&mut backend, &txs);
+    assert!(backend.code_cache_hits() >= 2);
+
+    let mut expect = [0u8; 32];
+    expect[31] = 42;
+    for tx_resp in resp.tx_resp.iter() {
+        assert!(tx_resp.exit_reason.is_succeed());
+        assert_eq!(tx_resp.ret, expect.to_vec());
+        assert_eq!(tx_resp.gas_used, resp.tx_resp[0].gas_used);
+    }
+
+    let backend = debugger.backend(2);
+    let runtime_code = hex_decode(RUNTIME_CODE).unwrap();
+    assert_eq!(backend.code(contract), runtime_code);
+    assert_eq!(backend.code_cache_hits(), 0);
+    assert_eq!(backend.code(contract), runtime_code);
+    assert_eq!(backend.code_cache_hits(), 1);
+    assert!(backend.code(sender).is_empty());
+    assert_eq!(backend.code_cache_hits(), 1);
+
+    clear_data(db_path);
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use protocol::traits::{Executor, ExecutorAdapter};
+use protocol::types::{GasSchedule, TransactionAction, H160, U256};
+use protocol::{codec::hex_decode, tokio};
+
+use crate::adapter::AxonExecutorAdapter;
+use crate::debugger::{clear_data, mock_tx, EvmDebugger};
+use crate::AxonExecutor;
+
+// Stores 42 into the storage slot 0 on creation.
+const SSTORE_INIT_CODE: &str = "602a600055";
+
+#[test]
+fn test_default_gas_schedule() {
+    // The default schedule is the one of the London hard fork on mainnet.
+    let config = GasSchedule::default().config();
+    assert_eq!(config.gas_sstore_set, 20_000);
+    assert_eq!(config.gas_sstore_reset, 2_900);
+    assert_eq!(config.gas_sload_cold, 2_100);
+    assert_eq!(config.gas_storage_read_warm, 100);
+    assert_eq!(config.gas_account_access_cold, 2_600);
+    assert_eq!(config.refund_sstore_clears, 4_800);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_custom_sstore_cost() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let db_path = "free-space/db_gas_schedule";
+    let debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    // Execute the same tx on the same state with the given schedule.
+    let exec = |gas_schedule: GasSchedule| {
+        let mut exec_ctx = debugger.backend(1).get_ctx();
+        exec_ctx.gas_schedule = gas_schedule;
+        let mut backend = AxonExecutorAdapter::from_root(
+            debugger.state_root,
+            Arc::clone(&debugger.trie_db),
+            Arc::clone(&debugger.storage),
+            exec_ctx,
+        )
+        .unwrap();
+        let tx = mock_tx(
+            U256::zero(),
+            sender,
+            TransactionAction::Create,
+            U256::zero(),
+            hex_decode(SSTORE_INIT_CODE).unwrap(),
+        );
+        let resp = AxonExecutor::default().exec(&mut backend, &[tx]);
+        assert!(resp.tx_resp[0].exit_reason.is_succeed());
+        resp.gas_used
+    };
+
+    let default_gas = exec(GasSchedule::default());
+    let cheap_gas = exec(GasSchedule {
+        sstore_set: Some(100),
+        ..Default::default()
+    });
+    assert_eq!(default_gas - cheap_gas, 20_000 - 100);
+
+    clear_data(db_path);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_call_with_gas_schedule() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let db_path = "free-space/db_gas_schedule_call";
+    let debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    // Call the same creation on the same state with the given schedule.
+    let call = |gas_schedule: GasSchedule| {
+        let backend = AxonExecutorAdapter::from_root(
+            debugger.state_root,
+            Arc::clone(&debugger.trie_db),
+            Arc::clone(&debugger.storage),
+            debugger
+                .backend(1)
+                .get_ctx()
+                .with_gas_schedule(gas_schedule),
+        )
+        .unwrap();
+        let resp = AxonExecutor::default().call(
+            &backend,
+            1_000_000,
+            Some(sender),
+            None,
+            U256::zero(),
+            hex_decode(SSTORE_INIT_CODE).unwrap(),
+        );
+        assert!(resp.exit_reason.is_succeed());
+        resp.gas_used
+    };
+
+    let default_gas = call(GasSchedule::default());
+    let cheap_gas = call(GasSchedule {
+        sstore_set: Some(100),
+        ..Default::default()
+    });
+    assert_eq!(default_gas - cheap_gas, 20_000 - 100);
+
+    clear_data(db_path);
+}
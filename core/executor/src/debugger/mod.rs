@@ -1,7 +1,17 @@
 #![allow(dead_code)]
 
+mod code_cache;
 mod create2;
 mod crosschain;
+mod gas_schedule;
+mod nonce_at;
+mod pending_view;
+mod progress;
+mod refund;
+mod state_at_block;
+mod state_diff;
+mod state_override;
+mod storage_iter;
 mod uniswap2;
 
 use std::sync::Arc;
@@ -13,11 +23,11 @@ use getrandom::getrandom;
 use common_config_parser::parse_file;
 use common_crypto::{PrivateKey, Secp256k1RecoverablePrivateKey, Signature};
 use protocol::codec::{hex_decode, ProtocolCodec};
-use protocol::traits::{Backend, Executor};
+use protocol::traits::{Backend, CommonStorage, Context, Executor};
 use protocol::types::{
-    Account, Eip1559Transaction, ExecResp, ExecutorContext, Hash, Hasher, RichBlock,
-    SignedTransaction, TxResp, UnsignedTransaction, UnverifiedTransaction, H160, H256,
-    MAX_BLOCK_GAS_LIMIT, NIL_DATA, RLP_NULL, U256,
+    Account, Block, Eip1559Transaction, ExecResp, ExecutorContext, Hash, Hasher, Header, RichBlock,
+    SignedTransaction, TransactionAction, TxResp, UnsignedTransaction, UnverifiedTransaction, H160,
+    H256, MAX_BLOCK_GAS_LIMIT, NIL_DATA, RLP_NULL, U256,
 };
 
 use core_storage::{adapter::rocks::RocksAdapter, ImplStorage};
@@ -26,11 +36,13 @@ use crate::adapter::{AxonExecutorAdapter, MPTTrie};
 use crate::{AxonExecutor, RocksTrieDB};
 
 const GENESIS_PATH: &str = "../../devtools/chain/genesis_single_node.json";
+const MOCK_GAS_PRICE: u64 = 8;
 
 pub struct EvmDebugger {
     state_root: H256,
     storage:    Arc<ImplStorage<RocksAdapter>>,
     trie_db:    Arc<RocksTrieDB>,
+    executor:   AxonExecutor,
 }
 
 impl EvmDebugger {
@@ -66,6 +78,7 @@ impl EvmDebugger {
             state_root: mpt.commit().unwrap(),
             storage:    Arc::new(ImplStorage::new(rocks_adapter, 10)),
             trie_db:    trie,
+            executor:   AxonExecutor::default(),
         }
     }
 
@@ -76,8 +89,7 @@ impl EvmDebugger {
 
     pub fn exec(&mut self, number: u64, txs: Vec<SignedTransaction>) -> ExecResp {
         let mut backend = self.backend(number);
-        let evm = AxonExecutor::default();
-        let res = evm.exec(&mut backend, &txs);
+        let res = self.executor.exec(&mut backend, &txs);
         self.state_root = res.state_root;
         res
     }
@@ -109,6 +121,7 @@ impl EvmDebugger {
             block_gas_limit:        4294967295000u64.into(),
             block_base_fee_per_gas: 1337u64.into(),
             logs:                   vec![],
+            gas_schedule:           Default::default(),
         };
 
         AxonExecutorAdapter::from_root(
@@ -123,6 +136,23 @@ impl EvmDebugger {
     fn nonce(&self, addr: H160) -> U256 {
         self.backend(0).basic(addr).nonce
     }
+
+    /// Store the header of block `number` with the given state root, as the
+    /// committed blocks are.
+    async fn insert_block(&self, number: u64, state_root: H256) {
+        let block = Block {
+            header:    Header {
+                number,
+                state_root,
+                ..Default::default()
+            },
+            tx_hashes: vec![],
+        };
+        self.storage
+            .insert_block(Context::new(), block)
+            .await
+            .unwrap();
+    }
 }
 
 #[derive(Default)]
@@ -173,6 +203,29 @@ pub fn mock_signed_tx(tx: Eip1559Transaction, sender: H160) -> SignedTransaction
     }
 }
 
+/// A tx of `sender` paying `MOCK_GAS_PRICE` per gas with a limit of one
+/// million gas.
+pub fn mock_tx(
+    nonce: U256,
+    sender: H160,
+    action: TransactionAction,
+    value: U256,
+    data: Vec<u8>,
+) -> SignedTransaction {
+    let tx = Eip1559Transaction {
+        nonce,
+        max_priority_fee_per_gas: MOCK_GAS_PRICE.into(),
+        gas_price: MOCK_GAS_PRICE.into(),
+        gas_limit: 1_000_000u64.into(),
+        action,
+        value,
+        data: data.into(),
+        access_list: vec![],
+    };
+
+    mock_signed_tx(tx, sender)
+}
+
 pub fn clear_data(db_path: &str) {
     std::fs::remove_dir_all(db_path).unwrap()
 }
@@ -0,0 +1,86 @@
+use evm::backend::Apply;
+
+use protocol::traits::{ApplyBackend, Backend};
+use protocol::types::{Log, StoreHeight, TransactionAction, H160, H256, U256};
+use protocol::{codec::hex_decode, tokio};
+
+use crate::debugger::{clear_data, mock_tx, EvmDebugger};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_nonce_at() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let receiver = H160::random();
+    let db_path = "free-space/db_nonce_at";
+    let mut debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    let resp = debugger.exec(1, vec![mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Call(receiver),
+        100u64.into(),
+        vec![],
+    )]);
+    debugger.insert_block(1, resp.state_root).await;
+
+    // The committed nonce of the state after block 1.
+    let mut backend = debugger.backend(2);
+    assert_eq!(
+        backend
+            .nonce_at(sender, StoreHeight::Stable(1))
+            .await
+            .unwrap(),
+        U256::one()
+    );
+    assert_eq!(
+        backend
+            .nonce_at(sender, StoreHeight::Pending)
+            .await
+            .unwrap(),
+        U256::one()
+    );
+    assert!(backend
+        .nonce_at(sender, StoreHeight::Stable(2))
+        .await
+        .is_err());
+
+    // A tx applied to the adapter but not committed bumps the pending nonce
+    // only.
+    let mut basic = backend.basic(sender);
+    basic.nonce += U256::one();
+    ApplyBackend::apply(
+        &mut backend,
+        vec![Apply::Modify {
+            address: sender,
+            basic,
+            code: None,
+            storage: Vec::<(H256, H256)>::new(),
+            reset_storage: false,
+        }],
+        Vec::<Log>::new(),
+        false,
+    );
+    assert_eq!(
+        backend
+            .nonce_at(sender, StoreHeight::Pending)
+            .await
+            .unwrap(),
+        U256::from(2u64)
+    );
+    assert_eq!(
+        backend
+            .nonce_at(sender, StoreHeight::Stable(1))
+            .await
+            .unwrap(),
+        U256::one()
+    );
+    assert_eq!(
+        backend
+            .nonce_at(receiver, StoreHeight::Pending)
+            .await
+            .unwrap(),
+        U256::zero()
+    );
+
+    clear_data(db_path);
+}
@@ -0,0 +1,49 @@
+use protocol::traits::{Backend, ExecutorAdapter};
+use protocol::types::{TransactionAction, H160, U256};
+use protocol::{codec::hex_decode, tokio};
+
+use crate::adapter::AxonExecutorAdapter;
+use crate::debugger::{clear_data, mock_tx, EvmDebugger};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pending_view() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let receiver = H160::random();
+    let db_path = "free-space/db_pending_view";
+    let mut debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    debugger.exec(1, vec![mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Call(receiver),
+        100u64.into(),
+        vec![],
+    )]);
+
+    // A tx executed into the block being built, which is not committed.
+    let pending_tx = mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Call(receiver),
+        200u64.into(),
+        vec![],
+    );
+    let view = AxonExecutorAdapter::pending_view(
+        debugger.state_root,
+        debugger.trie_db.clone(),
+        debugger.storage.clone(),
+        debugger.backend(2).get_ctx(),
+        &[pending_tx],
+    )
+    .unwrap();
+    assert_eq!(view.basic(receiver).balance, U256::from(300u64));
+    assert_eq!(view.basic(sender).nonce, U256::from(2u64));
+
+    // The committed state is untouched.
+    let backend = debugger.backend(2);
+    assert_eq!(backend.basic(receiver).balance, U256::from(100u64));
+    assert_eq!(backend.basic(sender).nonce, U256::one());
+
+    clear_data(db_path);
+}
@@ -0,0 +1,64 @@
+use protocol::types::{H160, U256};
+use protocol::{codec::hex_decode, tokio};
+
+use crate::debugger::{clear_data, EvmDebugger};
+use crate::{AxonExecutor, ExecutedState};
+
+// A height above the genesis, so that an older block can be re-executed.
+const BASE_NUMBER: u64 = 10;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_executed_state_advances() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let db_path = "free-space/db_progress";
+    let mut debugger = EvmDebugger::new(vec![sender], U256::from(1_000_000u64), db_path);
+
+    let resp = debugger.exec(BASE_NUMBER, vec![]);
+    assert_eq!(debugger.executor.executed_state().number, BASE_NUMBER);
+    assert_eq!(
+        debugger.executor.executed_state().state_root,
+        resp.state_root
+    );
+
+    let resp = debugger.exec(BASE_NUMBER + 1, vec![]);
+    assert_eq!(debugger.executor.executed_state().number, BASE_NUMBER + 1);
+    assert_eq!(
+        debugger.executor.executed_state().state_root,
+        resp.state_root
+    );
+
+    // Both executed blocks are recorded in the fee history.
+    let history = debugger
+        .executor
+        .fee_history(2, BASE_NUMBER + 1, Some(&[50.0]))
+        .unwrap();
+    assert_eq!(history.oldest_block, BASE_NUMBER);
+    assert_eq!(history.base_fee_per_gas, vec![U256::from(1337u64); 3]);
+    assert_eq!(history.gas_used_ratio, vec![0.0, 0.0]);
+    assert_eq!(history.reward.unwrap(), vec![vec![U256::zero()]; 2]);
+
+    // Re-executing an old block does not move the progress backward.
+    debugger.exec(BASE_NUMBER - 1, vec![]);
+    assert_eq!(debugger.executor.executed_state().number, BASE_NUMBER + 1);
+    assert!(debugger
+        .executor
+        .fee_history(1, BASE_NUMBER - 1, None)
+        .is_err());
+    assert_eq!(
+        debugger
+            .executor
+            .fee_history(2, BASE_NUMBER + 1, None)
+            .unwrap()
+            .oldest_block,
+        BASE_NUMBER
+    );
+
+    // The progress and the fee history are owned by the executor, not shared
+    // by the process.
+    let executor = AxonExecutor::default();
+    assert_eq!(executor.executed_state(), ExecutedState::default());
+    assert!(executor.fee_history(1, BASE_NUMBER, None).is_err());
+
+    clear_data(db_path);
+}
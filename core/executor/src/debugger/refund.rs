@@ -0,0 +1,52 @@
+use protocol::types::{TransactionAction, H160, U256};
+use protocol::{codec::hex_decode, tokio};
+
+use crate::debugger::{clear_data, mock_tx, EvmDebugger, MOCK_GAS_PRICE};
+
+// The init code stores non-zero values in slot 0, 1 and 5, and deploys a
+// runtime code which clears all of them.
+const INIT_CODE: &str =
+    "6001600055600260015560036005556010601b60003960106000f360006000556000600155600060055500";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sstore_clear_refund_is_capped() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let db_path = "free-space/db_refund";
+    let mut debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    let resp = debugger.exec(1, vec![mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Create,
+        U256::zero(),
+        hex_decode(INIT_CODE).unwrap(),
+    )]);
+    let contract: H160 = resp.tx_resp[0].code_address.unwrap().into();
+
+    let before = debugger.backend(2).basic(sender).balance;
+    let resp = debugger.exec(2, vec![mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Call(contract),
+        U256::zero(),
+        vec![],
+    )]);
+    assert!(resp.tx_resp[0].exit_reason.is_succeed());
+
+    // 21000 intrinsic gas, 3 * 5000 for the cold sstore resets and 6 * 3 for
+    // the pushes. The 3 * 4800 clear refund exceeds the cap of a fifth of it.
+    let total_used_gas = 21_000 + 15_000 + 18;
+    assert_eq!(
+        resp.tx_resp[0].gas_used,
+        total_used_gas - total_used_gas / 5
+    );
+
+    let after = debugger.backend(2).basic(sender).balance;
+    assert_eq!(
+        before - after,
+        U256::from(resp.tx_resp[0].gas_used * MOCK_GAS_PRICE)
+    );
+
+    clear_data(db_path);
+}
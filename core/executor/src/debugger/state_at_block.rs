@@ -0,0 +1,49 @@
+use protocol::traits::Backend;
+use protocol::types::{TransactionAction, H160, H256, U256};
+use protocol::{codec::hex_decode, tokio};
+
+use crate::adapter::AxonExecutorAdapter;
+use crate::debugger::{clear_data, mock_tx, EvmDebugger};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_state_at_block() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let receiver = H160::random();
+    let db_path = "free-space/db_state_at_block";
+    let mut debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    for (number, value) in [(1u64, 100u64), (2, 200)] {
+        let resp = debugger.exec(number, vec![mock_tx(
+            debugger.nonce(sender),
+            sender,
+            TransactionAction::Call(receiver),
+            value.into(),
+            vec![],
+        )]);
+        debugger.insert_block(number, resp.state_root).await;
+    }
+
+    let state = |number| {
+        AxonExecutorAdapter::state_at_block(
+            number,
+            debugger.trie_db.clone(),
+            debugger.storage.clone(),
+        )
+    };
+
+    let backend = state(1).await.unwrap();
+    assert_eq!(backend.basic(receiver).balance, U256::from(100u64));
+    assert_eq!(backend.block_number(), U256::from(1u64));
+    let backend = state(2).await.unwrap();
+    assert_eq!(backend.basic(receiver).balance, U256::from(300u64));
+
+    assert!(state(3).await.is_err());
+
+    // The state root of a block whose state is not in the trie db any more.
+    debugger.insert_block(3, H256::random()).await;
+    let err = state(3).await.err().unwrap();
+    assert!(err.to_string().contains("pruned"));
+
+    clear_data(db_path);
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use protocol::codec::{hex_decode, ProtocolCodec};
+use protocol::traits::ExecutorAdapter;
+use protocol::types::{Account, AccountDiff, StateDiff, TransactionAction, H160, H256, U256};
+use protocol::{tokio, ProtocolResult};
+
+use core_storage::{adapter::rocks::RocksAdapter, ImplStorage};
+
+use crate::adapter::{AxonExecutorAdapter, MPTTrie};
+use crate::debugger::{clear_data, mock_tx, EvmDebugger};
+use crate::RocksTrieDB;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_apply_state_diff() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let receiver = H160::random();
+    let db_path = "free-space/db_state_diff";
+    let mut debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    let pre_root = debugger.state_root;
+    let resp = debugger.exec(1, vec![mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Call(receiver),
+        100u64.into(),
+        vec![],
+    )]);
+    let diff = state_diff(&debugger, pre_root, resp.state_root);
+    assert!(diff.accounts.contains_key(&receiver));
+
+    // Applying the diff on the pre-state reaches the root of the execution.
+    let mut backend = pre_state(&debugger, pre_root);
+    assert_eq!(
+        backend.apply_state_diff(diff.clone()).unwrap(),
+        resp.state_root
+    );
+
+    let mut tampered = diff.clone();
+    tampered
+        .accounts
+        .get_mut(&receiver)
+        .unwrap()
+        .as_mut()
+        .unwrap()
+        .balance += U256::one();
+    // A mismatching diff leaves the adapter at the pre-state.
+    let mut backend = pre_state(&debugger, pre_root);
+    assert!(backend.apply_state_diff(tampered).is_err());
+    assert_eq!(backend.commit(), pre_root);
+    assert_eq!(backend.apply_state_diff(diff).unwrap(), resp.state_root);
+
+    clear_data(db_path);
+}
+
+fn pre_state(
+    debugger: &EvmDebugger,
+    pre_root: H256,
+) -> AxonExecutorAdapter<ImplStorage<RocksAdapter>, RocksTrieDB> {
+    AxonExecutorAdapter::from_root(
+        pre_root,
+        Arc::clone(&debugger.trie_db),
+        Arc::clone(&debugger.storage),
+        debugger.backend(1).get_ctx(),
+    )
+    .unwrap()
+}
+
+// The diff of the accounts whose encoding changes between the two states.
+fn state_diff(debugger: &EvmDebugger, pre_root: H256, post_root: H256) -> StateDiff {
+    let pre = MPTTrie::from_root(pre_root, Arc::clone(&debugger.trie_db)).unwrap();
+    let post = MPTTrie::from_root(post_root, Arc::clone(&debugger.trie_db)).unwrap();
+    let post_state = pre_state(debugger, post_root);
+
+    let accounts = post
+        .iter()
+        .filter(|(key, value)| pre.get(key).unwrap().as_deref() != Some(value.as_slice()))
+        .map(|(key, value)| {
+            let address = H160::from_slice(&key);
+            let account = Account::decode(value).unwrap();
+            let diff = AccountDiff {
+                nonce:         account.nonce,
+                balance:       account.balance,
+                code:          None,
+                storage:       post_state
+                    .storage_iter(address)
+                    .unwrap()
+                    .iter()
+                    .collect::<ProtocolResult<_>>()
+                    .unwrap(),
+                reset_storage: true,
+            };
+            (address, Some(diff))
+        })
+        .collect();
+
+    StateDiff {
+        accounts,
+        state_root: post_root,
+    }
+}
@@ -0,0 +1,100 @@
+use protocol::traits::Backend;
+use protocol::types::{AccountOverride, StateOverride, TransactionAction, H160, H256, U256};
+use protocol::{codec::hex_decode, tokio};
+
+use crate::adapter::OverrideBackend;
+use crate::debugger::{clear_data, mock_tx, EvmDebugger};
+use crate::AxonExecutor;
+
+// The deployed runtime code returns 42 as a 32 bytes word.
+const INIT_CODE: &str = "600a600c600039600a6000f3602a60005260206000f3";
+// Returns 43 as a 32 bytes word.
+const OVERRIDE_CODE: &str = "602b60005260206000f3";
+// Returns the value of the storage slot 0 as a 32 bytes word.
+const SLOAD_CODE: &str = "60005460005260206000f3";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_call_with_state_override() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let db_path = "free-space/db_state_override";
+    let mut debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    let resp = debugger.exec(1, vec![mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Create,
+        U256::zero(),
+        hex_decode(INIT_CODE).unwrap(),
+    )]);
+    let contract: H160 = resp.tx_resp[0].code_address.unwrap().into();
+
+    let backend = debugger.backend(2);
+    let executor = AxonExecutor::default();
+    let call = |overrides: &StateOverride, from: H160, to: H160, value: U256| {
+        executor.call_with_override(
+            &backend,
+            overrides,
+            1_000_000,
+            Some(from),
+            Some(to),
+            value,
+            vec![],
+        )
+    };
+
+    // The overridden code is run instead of the deployed one.
+    let mut overrides = StateOverride::new();
+    overrides.insert(contract, AccountOverride {
+        code: Some(hex_decode(OVERRIDE_CODE).unwrap()),
+        ..Default::default()
+    });
+    let resp = call(&overrides, sender, contract, U256::zero());
+    assert!(resp.exit_reason.is_succeed());
+    assert_eq!(resp.ret, word(43));
+
+    // The storage slots are overridden one by one.
+    let other = H160::random();
+    overrides.insert(other, AccountOverride {
+        code: Some(hex_decode(SLOAD_CODE).unwrap()),
+        storage: vec![(H256::zero(), H256::from_low_u64_be(7))]
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    });
+    let resp = call(&overrides, sender, other, U256::zero());
+    assert!(resp.exit_reason.is_succeed());
+    assert_eq!(resp.ret, word(7));
+
+    // An account without balance can send value once the balance is
+    // overridden.
+    let poor = H160::random();
+    assert!(!call(&overrides, poor, contract, 100u64.into())
+        .exit_reason
+        .is_succeed());
+    overrides.insert(poor, AccountOverride {
+        balance: Some(1000u64.into()),
+        nonce: Some(5u64.into()),
+        ..Default::default()
+    });
+    assert!(call(&overrides, poor, contract, 100u64.into())
+        .exit_reason
+        .is_succeed());
+    assert_eq!(
+        OverrideBackend::new(&backend, &overrides).basic(poor).nonce,
+        U256::from(5u64)
+    );
+
+    // The base state is untouched.
+    let resp = call(&StateOverride::new(), sender, contract, U256::zero());
+    assert_eq!(resp.ret, word(42));
+    assert_eq!(backend.basic(poor).balance, U256::zero());
+
+    clear_data(db_path);
+}
+
+fn word(n: u8) -> Vec<u8> {
+    let mut word = [0u8; 32];
+    word[31] = n;
+    word.to_vec()
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use protocol::codec::{hex_decode, ProtocolCodec};
+use protocol::traits::ExecutorAdapter;
+use protocol::types::{Account, TransactionAction, H160, H256, NIL_DATA, U256};
+use protocol::{tokio, ProtocolResult};
+
+use crate::adapter::{AxonExecutorAdapter, MPTTrie};
+use crate::debugger::{clear_data, mock_tx, EvmDebugger};
+
+// The init code stores 1, 2 and 3 in slot 0, 1 and 5 and deploys a single
+// `STOP` byte as the runtime code.
+const INIT_CODE: &str = "600160005560026001556003600555600060005360016000f3";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_storage_iter() {
+    let sender =
+        H160::from_slice(&hex_decode("0x4af5ec5e3d29d9ddd7f4bf91a022131c41b72352").unwrap());
+    let db_path = "free-space/db_storage_iter";
+    let mut debugger = EvmDebugger::new(vec![sender], 10000000000000000u64.into(), db_path);
+
+    let resp = debugger.exec(1, vec![mock_tx(
+        debugger.nonce(sender),
+        sender,
+        TransactionAction::Create,
+        U256::zero(),
+        hex_decode(INIT_CODE).unwrap(),
+    )]);
+    let contract: H160 = resp.tx_resp[0].code_address.unwrap().into();
+
+    let backend = debugger.backend(1);
+    let slots = backend
+        .storage_iter(contract)
+        .unwrap()
+        .iter()
+        .collect::<ProtocolResult<BTreeMap<_, _>>>()
+        .unwrap();
+    let expect = [(0u64, 1u64), (1, 2), (5, 3)]
+        .iter()
+        .map(|(k, v)| (H256::from_low_u64_be(*k), H256::from_low_u64_be(*v)))
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(slots, expect);
+
+    // Neither an EOA nor a missing account has any storage slot.
+    assert_eq!(backend.storage_iter(sender).unwrap().iter().count(), 0);
+    assert_eq!(
+        backend.storage_iter(H160::random()).unwrap().iter().count(),
+        0
+    );
+
+    // The storage trie of an account is missing from the trie db.
+    let broken = H160::random();
+    let mut mpt = MPTTrie::from_root(debugger.state_root, Arc::clone(&debugger.trie_db)).unwrap();
+    let account = Account {
+        nonce:        U256::zero(),
+        balance:      U256::zero(),
+        storage_root: H256::random(),
+        code_hash:    NIL_DATA,
+    };
+    mpt.insert(broken.as_bytes(), account.encode().unwrap().as_ref())
+        .unwrap();
+    let backend = AxonExecutorAdapter::from_root(
+        mpt.commit().unwrap(),
+        Arc::clone(&debugger.trie_db),
+        Arc::clone(&debugger.storage),
+        backend.get_ctx(),
+    )
+    .unwrap();
+    assert!(backend.storage_iter(broken).is_err());
+
+    clear_data(db_path);
+}
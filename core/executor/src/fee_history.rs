@@ -0,0 +1,275 @@
+use std::collections::VecDeque;
+
+use protocol::types::{SignedTransaction, TxResp, U256};
+use protocol::{Display, ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+/// The default number of recent blocks kept by `FeeHistoryCache`.
+pub const DEFAULT_FEE_HISTORY_CAPACITY: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockFeeRecord {
+    pub number:    u64,
+    pub base_fee:  U256,
+    pub gas_used:  u64,
+    pub gas_limit: u64,
+    /// The effective priority fee and gas used of each transaction in the
+    /// block.
+    pub rewards:   Vec<(U256, u64)>,
+}
+
+impl BlockFeeRecord {
+    pub fn new(
+        number: u64,
+        base_fee: U256,
+        gas_limit: u64,
+        txs: &[SignedTransaction],
+        tx_resp: &[TxResp],
+    ) -> Self {
+        let rewards = txs
+            .iter()
+            .zip(tx_resp.iter())
+            .map(|(tx, resp)| {
+                let unsigned = &tx.transaction.unsigned;
+                let tip = unsigned.gas_price().saturating_sub(base_fee);
+                let reward = if unsigned.is_eip1559() {
+                    tip.min(*unsigned.max_priority_fee_per_gas())
+                } else {
+                    tip
+                };
+                (reward, resp.gas_used)
+            })
+            .collect::<Vec<_>>();
+
+        BlockFeeRecord {
+            number,
+            base_fee,
+            gas_used: tx_resp.iter().map(|r| r.gas_used).sum(),
+            gas_limit,
+            rewards,
+        }
+    }
+
+    fn gas_used_ratio(&self) -> f64 {
+        if self.gas_limit == 0 {
+            return 0.0;
+        }
+
+        self.gas_used as f64 / self.gas_limit as f64
+    }
+
+    fn percentile_rewards(&self, percentiles: &[f64]) -> Vec<U256> {
+        if self.rewards.is_empty() {
+            return vec![U256::zero(); percentiles.len()];
+        }
+
+        let mut sorted = self.rewards.clone();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut idx = 0;
+        let mut sum_gas_used = sorted[0].1;
+
+        percentiles
+            .iter()
+            .map(|p| {
+                let threshold = (self.gas_used as f64 * p / 100.0) as u64;
+                while sum_gas_used < threshold && idx < sorted.len() - 1 {
+                    idx += 1;
+                    sum_gas_used += sorted[idx].1;
+                }
+                sorted[idx].0
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeHistory {
+    pub oldest_block:     u64,
+    /// Contains one more entry than `gas_used_ratio`, which is the base fee of
+    /// the block after `newest_block`. Axon uses a constant base fee, so it is
+    /// the same as the base fee of `newest_block`.
+    pub base_fee_per_gas: Vec<U256>,
+    pub gas_used_ratio:   Vec<f64>,
+    pub reward:           Option<Vec<Vec<U256>>>,
+}
+
+/// A rolling window of the fee related data of recent blocks.
+pub struct FeeHistoryCache {
+    records:  VecDeque<BlockFeeRecord>,
+    capacity: usize,
+}
+
+impl Default for FeeHistoryCache {
+    fn default() -> Self {
+        FeeHistoryCache::new(DEFAULT_FEE_HISTORY_CAPACITY)
+    }
+}
+
+impl FeeHistoryCache {
+    pub fn new(capacity: usize) -> Self {
+        FeeHistoryCache {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Push the record of a new executed block. A record not above the newest
+    /// one is ignored, so re-executing an old block never rewrites the window,
+    /// and a non-consecutive record resets the window.
+    pub fn push(&mut self, record: BlockFeeRecord) {
+        if let Some(last) = self.records.back() {
+            if record.number <= last.number {
+                return;
+            }
+
+            if last.number + 1 != record.number {
+                self.records.clear();
+            }
+        }
+
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+
+        self.records.push_back(record);
+    }
+
+    pub fn fee_history(
+        &self,
+        block_count: u64,
+        newest_block: u64,
+        reward_percentiles: Option<&[f64]>,
+    ) -> ProtocolResult<FeeHistory> {
+        if let Some(percentiles) = reward_percentiles {
+            validate_percentiles(percentiles)?;
+        }
+
+        let (first, last) = match (self.records.front(), self.records.back()) {
+            (Some(f), Some(l)) => (f.number, l.number),
+            _ => return Err(FeeHistoryError::BlockNotFound(newest_block).into()),
+        };
+
+        if newest_block < first || newest_block > last {
+            return Err(FeeHistoryError::BlockNotFound(newest_block).into());
+        }
+
+        let available = newest_block - first + 1;
+        let count = block_count.min(available);
+        let oldest_block = newest_block + 1 - count;
+
+        let start = (oldest_block - first) as usize;
+        let records = self.records.range(start..start + count as usize);
+
+        let mut base_fee_per_gas = Vec::with_capacity(count as usize + 1);
+        let mut gas_used_ratio = Vec::with_capacity(count as usize);
+        let mut reward = reward_percentiles.map(|_| Vec::with_capacity(count as usize));
+
+        for record in records {
+            base_fee_per_gas.push(record.base_fee);
+            gas_used_ratio.push(record.gas_used_ratio());
+
+            if let (Some(reward), Some(percentiles)) = (reward.as_mut(), reward_percentiles) {
+                reward.push(record.percentile_rewards(percentiles));
+            }
+        }
+
+        if let Some(next_base_fee) = base_fee_per_gas.last().copied() {
+            base_fee_per_gas.push(next_base_fee);
+        }
+
+        Ok(FeeHistory {
+            oldest_block,
+            base_fee_per_gas,
+            gas_used_ratio,
+            reward,
+        })
+    }
+}
+
+fn validate_percentiles(percentiles: &[f64]) -> ProtocolResult<()> {
+    let mut prev = 0.0;
+    for p in percentiles.iter() {
+        if !(0.0..=100.0).contains(p) || *p < prev {
+            return Err(FeeHistoryError::InvalidPercentile(*p).into());
+        }
+        prev = *p;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Display)]
+pub enum FeeHistoryError {
+    #[display(fmt = "Block {} is not in fee history", _0)]
+    BlockNotFound(u64),
+
+    #[display(fmt = "Invalid reward percentile {}", _0)]
+    InvalidPercentile(f64),
+}
+
+impl std::error::Error for FeeHistoryError {}
+
+impl From<FeeHistoryError> for ProtocolError {
+    fn from(err: FeeHistoryError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Executor, Box::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_record(number: u64, rewards: Vec<(u64, u64)>) -> BlockFeeRecord {
+        let rewards = rewards
+            .into_iter()
+            .map(|(r, g)| (U256::from(r), g))
+            .collect::<Vec<_>>();
+        BlockFeeRecord {
+            number,
+            base_fee: U256::from(0x539u64),
+            gas_used: rewards.iter().map(|r| r.1).sum(),
+            gas_limit: 100_000,
+            rewards,
+        }
+    }
+
+    #[test]
+    fn test_fee_history_percentiles() {
+        let mut cache = FeeHistoryCache::new(3);
+        cache.push(mock_record(1, vec![(1, 21_000)]));
+        cache.push(mock_record(2, vec![
+            (30, 30_000),
+            (10, 10_000),
+            (20, 10_000),
+        ]));
+        cache.push(mock_record(3, vec![]));
+        cache.push(mock_record(4, vec![(5, 50_000)]));
+
+        let history = cache
+            .fee_history(10, 4, Some(&[0.0, 25.0, 50.0, 100.0]))
+            .unwrap();
+
+        // Block 1 has been evicted from the window.
+        assert_eq!(history.oldest_block, 2);
+        assert_eq!(history.base_fee_per_gas.len(), 4);
+        assert_eq!(history.gas_used_ratio, vec![0.5, 0.0, 0.5]);
+        assert_eq!(history.reward.unwrap(), vec![
+            vec![10u64.into(), 20u64.into(), 30u64.into(), 30u64.into()],
+            vec![U256::zero(); 4],
+            vec![5u64.into(); 4],
+        ]);
+    }
+
+    #[test]
+    fn test_fee_history_invalid_request() {
+        let mut cache = FeeHistoryCache::default();
+        cache.push(mock_record(1, vec![(1, 21_000)]));
+
+        assert!(cache.fee_history(1, 2, None).is_err());
+        assert!(cache.fee_history(1, 1, Some(&[50.0, 10.0])).is_err());
+
+        let history = cache.fee_history(1, 1, None).unwrap();
+        assert_eq!(history.oldest_block, 1);
+        assert!(history.reward.is_none());
+    }
+}
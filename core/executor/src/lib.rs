@@ -3,41 +3,57 @@
 pub mod adapter;
 #[cfg(test)]
 mod debugger;
+mod fee_history;
 mod precompiles;
+mod progress;
 mod system;
 #[cfg(test)]
 mod tests;
 mod utils;
 mod vm;
 
-pub use crate::adapter::{AxonExecutorAdapter, MPTTrie, RocksTrieDB};
+pub use crate::adapter::{
+    AdapterError, AxonExecutorAdapter, MPTTrie, OverrideBackend, ReadOnlyBackend, RocksTrieDB,
+    StorageSlots,
+};
+pub use crate::fee_history::{BlockFeeRecord, FeeHistory, FeeHistoryCache, FeeHistoryError};
+pub use crate::progress::{ExecutedState, ExecutionProgress};
 pub use crate::system::NATIVE_TOKEN_ISSUE_ADDRESS;
-pub use crate::utils::{code_address, decode_revert_msg, logs_bloom};
+pub use crate::utils::{code_address, decode_revert_msg, gas_used_after_refund, logs_bloom};
 pub use crate::vm::{
     CROSSCHAIN_CONTRACT_ADDRESS, METADATA_CONTRACT_ADDRESS, WCKB_CONTRACT_ADDRESS,
 };
 
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
-use evm::executor::stack::{MemoryStackState, PrecompileFn, StackExecutor, StackSubstateMetadata};
+use evm::executor::stack::{
+    MemoryStackState, PrecompileFn, StackExecutor, StackState, StackSubstateMetadata,
+};
 use evm::CreateScheme;
+use parking_lot::RwLock;
 
 use common_merkle::Merkle;
 use protocol::codec::ProtocolCodec;
 use protocol::traits::{ApplyBackend, Backend, Executor, ExecutorAdapter as Adapter};
 use protocol::types::{
-    data_gas_cost, Account, Config, ExecResp, Hasher, SignedTransaction, TransactionAction, TxResp,
-    GAS_CALL_TRANSACTION, GAS_CREATE_TRANSACTION, H160, NIL_DATA, RLP_NULL, U256,
+    data_gas_cost, Account, Config, ExecResp, Hasher, SignedTransaction, StateOverride,
+    TransactionAction, TxResp, GAS_CALL_TRANSACTION, GAS_CREATE_TRANSACTION, H160, NIL_DATA,
+    RLP_NULL, U256,
 };
+use protocol::ProtocolResult;
 
-use crate::{precompiles::build_precompile_set, system::SystemExecutor};
+use crate::{adapter::OverrideBackend, precompiles::build_precompile_set, system::SystemExecutor};
 
-#[derive(Default)]
-pub struct AxonExecutor;
+#[derive(Default, Clone)]
+pub struct AxonExecutor {
+    progress:    ExecutionProgress,
+    fee_history: Arc<RwLock<FeeHistoryCache>>,
+}
 
 impl Executor for AxonExecutor {
     // Used for query data API, this function will not modify the world state.
-    fn call<B: Backend>(
+    fn call<B: Backend + Adapter>(
         &self,
         backend: &B,
         gas_limit: u64,
@@ -46,11 +62,123 @@ impl Executor for AxonExecutor {
         value: U256,
         data: Vec<u8>,
     ) -> TxResp {
-        let config = Config::london();
-        let metadata = StackSubstateMetadata::new(gas_limit, &config);
+        let config = backend.get_ctx().gas_schedule.config();
+        self.call_with_config(backend, &config, gas_limit, from, to, value, data)
+    }
+
+    // Function execute returns exit_reason, ret_data and remain_gas.
+    fn exec<B: Backend + ApplyBackend + Adapter>(
+        &self,
+        backend: &mut B,
+        txs: &[SignedTransaction],
+    ) -> ExecResp {
+        let res = self.exec_uncommitted(backend, txs);
+        let hashes = res
+            .iter()
+            .map(|r| Hasher::digest(&r.ret))
+            .collect::<Vec<_>>();
+        let gas_use = res.iter().map(|r| r.gas_used).sum();
+
+        // commit changes by all txs included in this block only once
+        let new_state_root = backend.commit();
+        self.progress.update(backend.block_number(), new_state_root);
+        self.fee_history.write().push(BlockFeeRecord::new(
+            backend.block_number().low_u64(),
+            backend.block_base_fee_per_gas(),
+            backend.block_gas_limit().low_u64(),
+            txs,
+            &res,
+        ));
+
+        ExecResp {
+            state_root:   new_state_root,
+            receipt_root: Merkle::from_hashes(hashes)
+                .get_root_hash()
+                .unwrap_or_default(),
+            gas_used:     gas_use,
+            tx_resp:      res,
+        }
+    }
+
+    fn get_account<B: Backend + Adapter>(&self, backend: &B, address: &H160) -> Account {
+        match backend.get(address.as_bytes()) {
+            Some(bytes) => Account::decode(bytes).unwrap(),
+            None => Account {
+                nonce:        Default::default(),
+                balance:      Default::default(),
+                storage_root: RLP_NULL,
+                code_hash:    NIL_DATA,
+            },
+        }
+    }
+}
+
+impl AxonExecutor {
+    /// The highest block executed by this executor or its clones.
+    pub fn executed_state(&self) -> ExecutedState {
+        self.progress.executed_state()
+    }
+
+    /// The fee history of the recent blocks executed by this executor or its
+    /// clones.
+    pub fn fee_history(
+        &self,
+        block_count: u64,
+        newest_block: u64,
+        reward_percentiles: Option<&[f64]>,
+    ) -> ProtocolResult<FeeHistory> {
+        self.fee_history
+            .read()
+            .fee_history(block_count, newest_block, reward_percentiles)
+    }
+
+    /// Execute the txs one by one on top of the state of `backend` without
+    /// committing it, so the state of the txs can be read before a commit.
+    pub(crate) fn exec_uncommitted<B: Backend + ApplyBackend + Adapter>(
+        &self,
+        backend: &mut B,
+        txs: &[SignedTransaction],
+    ) -> Vec<TxResp> {
+        let mut res = Vec::with_capacity(txs.len());
+
+        let sys_executor = SystemExecutor::new();
+        let precompiles = build_precompile_set();
+        let config = backend.get_ctx().gas_schedule.config();
+
+        for tx in txs.iter() {
+            backend.set_gas_price(tx.transaction.unsigned.gas_price());
+            backend.set_origin(tx.sender);
+
+            let mut r = if is_call_system_script(tx.transaction.unsigned.action()) {
+                sys_executor.inner_exec(backend, tx)
+            } else {
+                Self::evm_exec(backend, &config, &precompiles, tx)
+            };
+
+            r.logs = backend.get_logs();
+            res.push(r);
+        }
+
+        res
+    }
+
+    /// The same as `call`, but runs with the EVM `config` instead of the one
+    /// of the gas schedule of the context, so that any `Backend` can be read.
+    #[allow(clippy::too_many_arguments)]
+    pub fn call_with_config<B: Backend>(
+        &self,
+        backend: &B,
+        config: &Config,
+        gas_limit: u64,
+        from: Option<H160>,
+        to: Option<H160>,
+        value: U256,
+        data: Vec<u8>,
+    ) -> TxResp {
+        let metadata = StackSubstateMetadata::new(gas_limit, config);
         let state = MemoryStackState::new(metadata, backend);
         let precompiles = build_precompile_set();
-        let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
+        let mut executor = StackExecutor::new_with_precompiles(state, config, &precompiles);
 
         let base_gas = if to.is_some() {
             GAS_CALL_TRANSACTION + data_gas_cost(&data)
@@ -92,65 +220,25 @@ impl Executor for AxonExecutor {
         }
     }
 
-    // Function execute returns exit_reason, ret_data and remain_gas.
-    fn exec<B: Backend + ApplyBackend + Adapter>(
+    /// The same as `call`, but runs on top of the state of `backend` with the
+    /// `overrides` applied, so that hypothetical balances, nonces, codes and
+    /// storage slots can be simulated. Nothing is committed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn call_with_override<B: Backend + Adapter>(
         &self,
-        backend: &mut B,
-        txs: &[SignedTransaction],
-    ) -> ExecResp {
-        let txs_len = txs.len();
-        let mut res = Vec::with_capacity(txs_len);
-        let mut hashes = Vec::with_capacity(txs_len);
-        let mut gas_use = 0u64;
-
-        let sys_executor = SystemExecutor::new();
-        let precompiles = build_precompile_set();
-        let config = Config::london();
-
-        for tx in txs.iter() {
-            backend.set_gas_price(tx.transaction.unsigned.gas_price());
-            backend.set_origin(tx.sender);
-
-            let mut r = if is_call_system_script(tx.transaction.unsigned.action()) {
-                sys_executor.inner_exec(backend, tx)
-            } else {
-                Self::evm_exec(backend, &config, &precompiles, tx)
-            };
-
-            r.logs = backend.get_logs();
-            gas_use += r.gas_used;
-
-            hashes.push(Hasher::digest(&r.ret));
-            res.push(r);
-        }
-
-        // commit changes by all txs included in this block only once
-        let new_state_root = backend.commit();
-
-        ExecResp {
-            state_root:   new_state_root,
-            receipt_root: Merkle::from_hashes(hashes)
-                .get_root_hash()
-                .unwrap_or_default(),
-            gas_used:     gas_use,
-            tx_resp:      res,
-        }
-    }
-
-    fn get_account<B: Backend + Adapter>(&self, backend: &B, address: &H160) -> Account {
-        match backend.get(address.as_bytes()) {
-            Some(bytes) => Account::decode(bytes).unwrap(),
-            None => Account {
-                nonce:        Default::default(),
-                balance:      Default::default(),
-                storage_root: RLP_NULL,
-                code_hash:    NIL_DATA,
-            },
-        }
+        backend: &B,
+        overrides: &StateOverride,
+        gas_limit: u64,
+        from: Option<H160>,
+        to: Option<H160>,
+        value: U256,
+        data: Vec<u8>,
+    ) -> TxResp {
+        let config = backend.get_ctx().gas_schedule.config();
+        let backend = OverrideBackend::new(backend, overrides);
+        self.call_with_config(&backend, &config, gas_limit, from, to, value, data)
     }
-}
 
-impl AxonExecutor {
     pub fn evm_exec<B: Backend + ApplyBackend + Adapter>(
         backend: &mut B,
         config: &Config,
@@ -202,8 +290,11 @@ impl AxonExecutor {
             ),
         };
 
-        let remain_gas = executor.gas();
-        let gas_used = executor.used_gas();
+        // The unused gas and the capped refund are both returned to the sender.
+        let gasometer = executor.state().metadata().gasometer();
+        let gas_used =
+            gas_used_after_refund(gasometer.total_used_gas(), gasometer.refunded_gas(), config);
+        let remain_gas = gas_limit.as_u64().saturating_sub(gas_used);
 
         let code_address = if tx.transaction.unsigned.action() == &TransactionAction::Create
             && exit_reason.is_succeed()
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use protocol::types::{MerkleRoot, U256};
+
+/// The highest block executed by an `AxonExecutor` and the state root after
+/// executing it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutedState {
+    pub number:     u64,
+    pub state_root: MerkleRoot,
+}
+
+/// The executed state of an executor, which is shared by its clones.
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionProgress(Arc<RwLock<ExecutedState>>);
+
+impl ExecutionProgress {
+    pub fn executed_state(&self) -> ExecutedState {
+        *self.0.read()
+    }
+
+    /// A block lower than the executed one is ignored, so the progress never
+    /// goes backward when an old block is re-executed.
+    pub(crate) fn update(&self, number: U256, state_root: MerkleRoot) {
+        let number = number.low_u64();
+        let mut executed = self.0.write();
+        if number >= executed.number {
+            *executed = ExecutedState { number, state_root };
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use cita_trie::MemoryDB;
+
+use core_storage::{adapter::memory::MemoryAdapter, ImplStorage};
+use protocol::codec::ProtocolCodec;
+use protocol::types::{Account, Address, H160, NIL_DATA, RLP_NULL, U256};
+
+use crate::{AxonExecutorAdapter, MPTTrie};
+
+#[test]
+fn test_init_accounts_root() {
+    let accounts = (0..100u64)
+        .map(|i| {
+            let account = Account {
+                nonce:        U256::zero(),
+                balance:      U256::from(i * 1000),
+                storage_root: RLP_NULL,
+                code_hash:    NIL_DATA,
+            };
+            (Address(H160::random()), account)
+        })
+        .collect::<Vec<_>>();
+
+    let mut adapter = AxonExecutorAdapter::new(
+        Arc::new(MemoryDB::new(false)),
+        Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new()), 10)),
+        Default::default(),
+    )
+    .unwrap();
+    let batched_root = adapter.init_accounts(&accounts).unwrap();
+
+    // Commit after each account, as the genesis used to do.
+    let mut mpt = MPTTrie::new(Arc::new(MemoryDB::new(false)));
+    for (address, account) in accounts.iter() {
+        mpt.insert(address.as_slice(), &account.encode().unwrap())
+            .unwrap();
+        mpt.commit().unwrap();
+    }
+
+    assert_eq!(batched_root, mpt.commit().unwrap());
+}
@@ -1,3 +1,4 @@
+mod genesis;
 mod system_script;
 
 use std::collections::BTreeMap;
@@ -182,8 +183,9 @@ fn test_simplestorage() {
 
     // let's call SimpleStorage.get() by call
     let executor = AxonExecutor::default();
-    let r = executor.call(
+    let r = executor.call_with_config(
         &backend,
+        &config,
         u64::MAX,
         None,
         Some(H160::from_str("0xc15d2ba57d126e6603240e89437efd419ce329d2").unwrap()),
@@ -1,4 +1,4 @@
-use protocol::types::{Bloom, Hasher, Log, H160, H256, U256};
+use protocol::types::{Bloom, Config, Hasher, Log, H160, H256, U256};
 
 const FUNC_SELECTOR_LEN: usize = 4;
 const U256_BE_BYTES_LEN: usize = 32;
@@ -14,6 +14,16 @@ pub fn code_address(sender: &H160, nonce: &U256) -> H256 {
     Hasher::digest(&stream.out())
 }
 
+/// The gas used by a transaction after the refund. Since EIP-3529 the refund
+/// is capped at `total_used_gas / max_refund_quotient`, and a negative refund
+/// counter is treated as no refund.
+pub fn gas_used_after_refund(total_used_gas: u64, refunded_gas: i64, config: &Config) -> u64 {
+    let refund = u64::try_from(refunded_gas)
+        .unwrap_or_default()
+        .min(total_used_gas / config.max_refund_quotient);
+    total_used_gas - refund
+}
+
 pub fn decode_revert_msg(input: &[u8]) -> String {
     if input.is_empty() {
         return EXEC_REVERT.to_string();
@@ -67,6 +77,15 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_gas_used_after_refund() {
+        let config = Config::london();
+        assert_eq!(gas_used_after_refund(50_000, 4_800, &config), 45_200);
+        // The refund is capped at a fifth of the gas used.
+        assert_eq!(gas_used_after_refund(50_000, 19_200, &config), 40_000);
+        assert_eq!(gas_used_after_refund(50_000, -100, &config), 50_000);
+    }
+
     #[test]
     fn test_code_address() {
         let sender = H160::from_slice(
@@ -6,25 +6,31 @@ use ibc::{
         ics03_connection::connection::ConnectionEnd,
         ics04_channel::channel::ChannelEnd,
         ics04_channel::commitment::{AcknowledgementCommitment, PacketCommitment},
-        ics04_channel::packet::{Receipt as IbcReceipt, Sequence},
+        ics04_channel::packet::{Packet, Receipt as IbcReceipt, Sequence},
         ics24_host::{
             identifier::{ChannelId, ClientId, ConnectionId, PortId},
             path::{
                 AcksPath, ChannelEndsPath, ClientConnectionsPath, ClientConsensusStatePath,
-                ClientStatePath, CommitmentsPath, ConnectionsPath, ReceiptsPath,
+                ClientStatePath, CommitmentsPath, ConnectionsPath, ReceiptsPath, SeqSendsPath,
             },
         },
     },
+    timestamp::Timestamp,
     Height,
 };
 use protocol::{
     async_trait,
-    traits::{Context, IbcAdapter, IbcCrossChainStorage, IbcGrpcAdapter, MetadataControl, Storage},
-    types::{Header, Metadata, Path, StoreHeight},
+    traits::{
+        Context, IbcAdapter, IbcCrossChainStorage, IbcGrpcAdapter, IterDirection, MetadataControl,
+        Storage,
+    },
+    types::{Header, Metadata, Path, StoreHeight, H160},
     ProtocolResult,
 };
 use std::sync::Arc;
 
+use crate::error::IbcError;
+
 macro_rules! blocking_async {
     ($self_: ident, $adapter: ident, $method: ident$ (, $args: expr)*) => {{
         let rt = protocol::tokio::runtime::Handle::current();
@@ -36,6 +42,10 @@ macro_rules! blocking_async {
     }};
 }
 
+/// The adapter of the IBC entries in the cross chain storage. The storage
+/// keeps only the latest state without versions, so the queries at a height
+/// (`QUERY_HEIGHT_METADATA`) and the snapshot reads of the gRPC services are
+/// disabled with this adapter, see `IbcGrpcAdapter::is_versioned`.
 pub struct DefaultIbcAdapter<S, MT> {
     storage:  Arc<S>,
     metadata: Arc<MT>,
@@ -116,12 +126,12 @@ where
     }
 
     fn get_opt(&self, _height: StoreHeight, path: &ReceiptsPath) -> ProtocolResult<Option<()>> {
-        self.storage.get_packet_receipt(&(
+        let receipt = self.storage.get_packet_receipt(&(
             path.port_id.clone(),
             path.channel_id.clone(),
             path.sequence,
         ))?;
-        Ok(Some(()))
+        Ok(receipt.map(|_| ()))
     }
 
     fn get_packet_commitment(
@@ -136,8 +146,49 @@ where
         ))
     }
 
-    fn get_paths_by_prefix(&self, _key_prefix: &Path) -> ProtocolResult<Vec<Path>> {
-        todo!()
+    fn get_seq_sends(
+        &self,
+        _height: StoreHeight,
+        path: &SeqSendsPath,
+    ) -> ProtocolResult<Option<Sequence>> {
+        self.storage
+            .get_next_sequence_send(&(path.0.clone(), path.1.clone()))
+    }
+
+    fn get_client_latest_height(
+        &self,
+        _height: StoreHeight,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Option<Height>> {
+        self.storage.get_client_latest_height(client_id)
+    }
+
+    fn get_paths_by_prefix(&self, key_prefix: &Path) -> ProtocolResult<Vec<Path>> {
+        self.iter_paths_by_prefix(key_prefix)?.collect()
+    }
+
+    fn iter_paths_by_prefix<'a>(
+        &'a self,
+        key_prefix: &Path,
+    ) -> ProtocolResult<Box<dyn Iterator<Item = ProtocolResult<Path>> + 'a>> {
+        let paths = self
+            .storage
+            .iter_paths_by_prefix(&key_prefix.to_string())?
+            .map(|path| Path::try_from(path?));
+        Ok(Box::new(paths))
+    }
+
+    fn get_proof(&self, _height: StoreHeight, _path: &Path) -> ProtocolResult<Option<Vec<u8>>> {
+        // The IBC entries are kept in the cross chain storage, which is not
+        // committed to the state root yet, so there is nothing to prove them
+        // with.
+        Ok(None)
+    }
+
+    fn is_versioned(&self) -> bool {
+        // The cross chain storage overwrites the entries in place, so a read
+        // at any height sees the latest state.
+        false
     }
 }
 
@@ -204,6 +255,18 @@ where
         self.storage.get_prev_consensus_state(client_id, height)
     }
 
+    fn get_consensus_heights(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        from: Option<Height>,
+        direction: IterDirection,
+        limit: usize,
+    ) -> ProtocolResult<Vec<Height>> {
+        self.storage
+            .get_consensus_heights(client_id, from, direction, limit)
+    }
+
     fn get_connection_end_by_id(
         &self,
         _ctx: Context,
@@ -220,6 +283,14 @@ where
         self.storage.get_channel_end(port_channel_id)
     }
 
+    fn get_connection_channels(
+        &self,
+        _ctx: Context,
+        conn_id: &ConnectionId,
+    ) -> ProtocolResult<Option<Vec<(PortId, ChannelId)>>> {
+        self.storage.get_connection_channels(conn_id)
+    }
+
     fn get_next_sequence_send(
         &self,
         _ctx: Context,
@@ -252,6 +323,14 @@ where
         self.storage.get_packet_commitment(key)
     }
 
+    fn get_packet(
+        &self,
+        _ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<Packet>> {
+        self.storage.get_packet(key)
+    }
+
     fn get_packet_receipt(
         &self,
         _ctx: Context,
@@ -268,6 +347,55 @@ where
         self.storage.get_packet_acknowledgement(key)
     }
 
+    fn get_acknowledgement(
+        &self,
+        _ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<Vec<u8>>> {
+        self.storage.get_acknowledgement(key)
+    }
+
+    fn get_interchain_account(
+        &self,
+        _ctx: Context,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> ProtocolResult<Option<H160>> {
+        self.storage.get_interchain_account(port_id, channel_id)
+    }
+
+    fn get_client_processed_time(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        height: Height,
+    ) -> ProtocolResult<Option<Timestamp>> {
+        match self.storage.get_client_processed_time(client_id, height)? {
+            Some(nanos) => Ok(Some(
+                Timestamp::from_nanoseconds(nanos)
+                    .map_err(|_| IbcError::InvalidTimestamp(nanos))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn get_client_processed_height(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        height: Height,
+    ) -> ProtocolResult<Option<Height>> {
+        self.storage.get_client_processed_height(client_id, height)
+    }
+
+    fn get_fork_consensus_states(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Vec<(Height, AnyConsensusState)>> {
+        self.storage.get_fork_consensus_states(client_id)
+    }
+
     fn set_client_type(
         &self,
         _ctx: Context,
@@ -297,6 +425,46 @@ where
             .set_consensus_state(client_id, height, consensus_state)
     }
 
+    fn set_client_latest_height(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+    ) -> ProtocolResult<()> {
+        self.storage.set_client_latest_height(client_id, height)
+    }
+
+    fn set_client_processed_time(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+        timestamp: Timestamp,
+    ) -> ProtocolResult<()> {
+        self.storage
+            .set_client_processed_time(client_id, height, timestamp.nanoseconds())
+    }
+
+    fn set_client_processed_height(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+        host_height: Height,
+    ) -> ProtocolResult<()> {
+        self.storage
+            .set_client_processed_height(client_id, height, host_height)
+    }
+
+    fn set_fork_consensus_states(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        states: Vec<(Height, AnyConsensusState)>,
+    ) -> ProtocolResult<()> {
+        self.storage.set_fork_consensus_states(client_id, states)
+    }
+
     fn set_connection_end(
         &self,
         _ctx: Context,
@@ -317,6 +485,14 @@ where
             .set_connection_to_client(connection_id, &client_id)
     }
 
+    fn get_connection_counter(&self, _ctx: Context) -> ProtocolResult<Option<u64>> {
+        self.storage.get_connection_counter()
+    }
+
+    fn set_connection_counter(&self, _ctx: Context, counter: u64) -> ProtocolResult<()> {
+        self.storage.set_connection_counter(counter)
+    }
+
     fn set_packet_commitment(
         &self,
         _ctx: Context,
@@ -326,6 +502,15 @@ where
         self.storage.set_packet_commitment(key, commitment)
     }
 
+    fn set_packet(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        packet: Packet,
+    ) -> ProtocolResult<()> {
+        self.storage.set_packet(key, packet)
+    }
+
     fn set_packet_receipt(
         &self,
         _ctx: Context,
@@ -344,6 +529,36 @@ where
         self.storage.set_packet_acknowledgement(key, ack_commitment)
     }
 
+    fn set_acknowledgement(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        ack: Vec<u8>,
+    ) -> ProtocolResult<()> {
+        self.storage.set_acknowledgement(key, ack)
+    }
+
+    fn set_interchain_account(
+        &self,
+        _ctx: Context,
+        port_id: PortId,
+        channel_id: ChannelId,
+        address: H160,
+    ) -> ProtocolResult<()> {
+        self.storage
+            .set_interchain_account(port_id, channel_id, address)
+    }
+
+    fn set_connection_channels(
+        &self,
+        _ctx: Context,
+        conn_id: ConnectionId,
+        port_channel_id: &(PortId, ChannelId),
+    ) -> ProtocolResult<()> {
+        self.storage
+            .set_connection_channels(conn_id, port_channel_id)
+    }
+
     fn set_channel(
         &self,
         _ctx: Context,
@@ -392,7 +607,70 @@ where
         self.storage.delete_packet_commitment(key)
     }
 
+    fn remove_packet(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<()> {
+        self.storage.delete_packet(key)
+    }
+
+    fn remove_consensus_state(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+    ) -> ProtocolResult<()> {
+        self.storage.delete_consensus_state(client_id, height)
+    }
+
+    fn begin_batch(&self, _ctx: Context) -> ProtocolResult<()> {
+        self.storage.begin_batch();
+        Ok(())
+    }
+
+    fn commit_batch(&self, _ctx: Context) -> ProtocolResult<()> {
+        self.storage.commit_batch()
+    }
+
+    fn discard_batch(&self, _ctx: Context) -> ProtocolResult<()> {
+        self.storage.discard_batch();
+        Ok(())
+    }
+
     fn current_height(&self) -> u64 {
         blocking_async!(self, storage, get_latest_block_header, Context::new()).number
     }
+
+    // The kept blocks are a range up to the latest one, so the oldest is
+    // searched for by bisection instead of a scan from the genesis.
+    fn oldest_height(&self) -> ProtocolResult<u64> {
+        let rt = protocol::tokio::runtime::Handle::current();
+        let storage = Arc::clone(&self.storage);
+
+        protocol::tokio::task::block_in_place(move || {
+            rt.block_on(async move {
+                let (mut low, mut high) = (
+                    0,
+                    storage
+                        .get_latest_block_header(Context::new())
+                        .await?
+                        .number,
+                );
+                while low < high {
+                    let mid = low + (high - low) / 2;
+                    if storage
+                        .get_block_header(Context::new(), mid)
+                        .await?
+                        .is_some()
+                    {
+                        high = mid;
+                    } else {
+                        low = mid + 1;
+                    }
+                }
+                Ok(low.max(1))
+            })
+        })
+    }
 }
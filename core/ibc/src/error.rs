@@ -5,6 +5,49 @@ use protocol::{Display, ProtocolError, ProtocolErrorKind};
 #[derive(Debug, Display)]
 pub enum IbcError {
     Protocol(Error),
+
+    #[display(fmt = "Invalid header timestamp {}", _0)]
+    InvalidTimestamp(u64),
+
+    #[display(fmt = "Handler error {}", _0)]
+    Handler(String),
+
+    #[display(
+        fmt = "Non monotonic consensus state timestamp of client {} at {}",
+        client_id,
+        height
+    )]
+    NonMonotonicTimestamp {
+        client_id: String,
+        height:    String,
+    },
+
+    #[display(
+        fmt = "Inconsistent sequence tracking of channel {}/{}: {}",
+        port_id,
+        channel_id,
+        reason
+    )]
+    InconsistentSequenceTracking {
+        port_id:    String,
+        channel_id: String,
+        reason:     String,
+    },
+
+    #[display(fmt = "Packet {} timed out: {}", sequence, reason)]
+    PacketTimedOut {
+        sequence: u64,
+        reason:   String,
+    },
+
+    #[display(fmt = "Invalid grpc address {}: {}", addr, reason)]
+    InvalidGrpcAddress {
+        addr:   String,
+        reason: String,
+    },
+
+    #[display(fmt = "Grpc transport error {}", _0)]
+    Transport(tonic::transport::Error),
 }
 
 impl std::error::Error for IbcError {}
@@ -0,0 +1,110 @@
+//! The ICS standard events of the connection and channel handshakes. The
+//! counterparty relayer builds the next handshake message from the attributes,
+//! so each event carries the complete attribute set of the stored end.
+
+use ibc::core::ics03_connection::connection::ConnectionEnd;
+use ibc::core::ics03_connection::events::{
+    Attributes as ConnectionAttributes, OpenAck as ConnectionOpenAck,
+    OpenConfirm as ConnectionOpenConfirm, OpenInit as ConnectionOpenInit,
+    OpenTry as ConnectionOpenTry,
+};
+use ibc::core::ics04_channel::channel::ChannelEnd;
+use ibc::core::ics04_channel::error::Error as ChannelError;
+use ibc::core::ics04_channel::events::{
+    OpenAck as ChannelOpenAck, OpenConfirm as ChannelOpenConfirm, OpenInit as ChannelOpenInit,
+    OpenTry as ChannelOpenTry,
+};
+use ibc::core::ics24_host::identifier::{ChannelId, ConnectionId, PortId};
+use ibc::events::IbcEvent;
+use ibc::Height;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandshakeStep {
+    OpenInit,
+    OpenTry,
+    OpenAck,
+    OpenConfirm,
+}
+
+/// Build the event of a connection handshake step from the connection end
+/// stored by the step.
+pub fn connection_handshake_event(
+    step: HandshakeStep,
+    height: Height,
+    connection_id: &ConnectionId,
+    connection_end: &ConnectionEnd,
+) -> IbcEvent {
+    let attributes = ConnectionAttributes {
+        height,
+        connection_id: Some(connection_id.clone()),
+        client_id: connection_end.client_id().clone(),
+        counterparty_connection_id: connection_end.counterparty().connection_id().cloned(),
+        counterparty_client_id: connection_end.counterparty().client_id().clone(),
+    };
+
+    match step {
+        HandshakeStep::OpenInit => IbcEvent::OpenInitConnection(ConnectionOpenInit(attributes)),
+        HandshakeStep::OpenTry => IbcEvent::OpenTryConnection(ConnectionOpenTry(attributes)),
+        HandshakeStep::OpenAck => IbcEvent::OpenAckConnection(ConnectionOpenAck(attributes)),
+        HandshakeStep::OpenConfirm => {
+            IbcEvent::OpenConfirmConnection(ConnectionOpenConfirm(attributes))
+        }
+    }
+}
+
+/// Build the event of a channel handshake step from the channel end stored by
+/// the step. The channel end must have exactly one connection hop.
+pub fn channel_handshake_event(
+    step: HandshakeStep,
+    height: Height,
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    channel_end: &ChannelEnd,
+) -> Result<IbcEvent, ChannelError> {
+    let connection_id = match channel_end.connection_hops() {
+        [connection_id] => connection_id.clone(),
+        hops => return Err(ChannelError::invalid_connection_hops_length(1, hops.len())),
+    };
+
+    let port_id = port_id.clone();
+    let channel_id = Some(channel_id.clone());
+    let counterparty_port_id = channel_end.counterparty().port_id().clone();
+    let counterparty_channel_id = channel_end.counterparty().channel_id().cloned();
+
+    let event = match step {
+        HandshakeStep::OpenInit => IbcEvent::OpenInitChannel(ChannelOpenInit {
+            height,
+            port_id,
+            channel_id,
+            connection_id,
+            counterparty_port_id,
+            counterparty_channel_id,
+        }),
+        HandshakeStep::OpenTry => IbcEvent::OpenTryChannel(ChannelOpenTry {
+            height,
+            port_id,
+            channel_id,
+            connection_id,
+            counterparty_port_id,
+            counterparty_channel_id,
+        }),
+        HandshakeStep::OpenAck => IbcEvent::OpenAckChannel(ChannelOpenAck {
+            height,
+            port_id,
+            channel_id,
+            connection_id,
+            counterparty_port_id,
+            counterparty_channel_id,
+        }),
+        HandshakeStep::OpenConfirm => IbcEvent::OpenConfirmChannel(ChannelOpenConfirm {
+            height,
+            port_id,
+            channel_id,
+            connection_id,
+            counterparty_port_id,
+            counterparty_channel_id,
+        }),
+    };
+
+    Ok(event)
+}
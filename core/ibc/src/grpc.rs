@@ -1,32 +1,72 @@
-use std::sync::{Arc, RwLock};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{
+    Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, TryLockResult,
+};
+use std::task::Poll;
+use std::time::{Duration, Instant};
 use std::{net::SocketAddr, str::FromStr};
 
+use ibc::core::ics02_client::client_consensus::ConsensusState as _;
+use ibc::core::ics02_client::client_state::{AnyClientState, ClientState};
+use ibc::core::ics02_client::client_type::ClientType;
 use ibc::core::ics02_client::context::{ClientKeeper, ClientReader};
+use ibc::core::ics02_client::error::{Error, ErrorDetail as ClientErrorDetail};
+use ibc::core::ics02_client::events::Attributes;
+use ibc::core::ics02_client::handler::{update_client, upgrade_client, ClientResult};
 use ibc::core::ics02_client::msgs::create_client::MsgCreateAnyClient;
-use ibc::core::ics02_client::{error::Error, events::Attributes, handler::ClientResult};
+use ibc::core::ics02_client::msgs::update_client::MsgUpdateAnyClient;
+use ibc::core::ics02_client::msgs::upgrade_client::MsgUpgradeAnyClient;
 use ibc::core::ics03_connection::connection::{ConnectionEnd, IdentifiedConnectionEnd};
+use ibc::core::ics03_connection::context::ConnectionReader;
+use ibc::core::ics03_connection::msgs::{
+    conn_open_ack::MsgConnectionOpenAck, conn_open_confirm::MsgConnectionOpenConfirm,
+    conn_open_init::MsgConnectionOpenInit, conn_open_try::MsgConnectionOpenTry, ConnectionMsg,
+};
 use ibc::core::ics04_channel::channel::{ChannelEnd, IdentifiedChannelEnd};
-use ibc::core::ics04_channel::packet::Sequence;
-use ibc::core::ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId};
+use ibc::core::ics04_channel::context::ChannelReader;
+use ibc::core::ics04_channel::msgs::{
+    chan_open_ack::MsgChannelOpenAck, chan_open_confirm::MsgChannelOpenConfirm,
+    chan_open_init::MsgChannelOpenInit, chan_open_try::MsgChannelOpenTry,
+    recv_packet::MsgRecvPacket, ChannelMsg, PacketMsg,
+};
+use ibc::core::ics04_channel::packet::{Packet, Sequence};
+use ibc::core::ics04_channel::Version;
+use ibc::core::ics05_port::context::PortReader;
+use ibc::core::ics24_host::identifier::{ChainId, ChannelId, ClientId, ConnectionId, PortId};
 use ibc::core::ics24_host::{path, Path as IbcPath};
 use ibc::core::ics26_routing::context::Ics26Context;
+use ibc::core::ics26_routing::{handler::dispatch, msgs::Ics26Envelope};
 use ibc::events::IbcEvent;
 use ibc::handler::{HandlerOutput, HandlerOutputBuilder};
+use ibc::timestamp::Timestamp;
 
+use ibc_proto::cosmos::base::query::v1beta1::{PageRequest, PageResponse};
+use ibc_proto::google::protobuf::Any;
 use ibc_proto::ibc::core::client::v1::{
     ConsensusStateWithHeight, QueryConsensusStateHeightsRequest, QueryConsensusStateHeightsResponse,
 };
 use ibc_proto::ibc::core::{
     channel::v1::{
+        msg_server::{Msg as ChannelMsgRpc, MsgServer as ChannelMsgServer},
         query_server::{Query as ChannelQuery, QueryServer as ChannelQueryServer},
-        PacketState, QueryChannelClientStateRequest, QueryChannelClientStateResponse,
-        QueryChannelConsensusStateRequest, QueryChannelConsensusStateResponse, QueryChannelRequest,
-        QueryChannelResponse, QueryChannelsRequest, QueryChannelsResponse,
-        QueryConnectionChannelsRequest, QueryConnectionChannelsResponse,
-        QueryNextSequenceReceiveRequest, QueryNextSequenceReceiveResponse,
-        QueryPacketAcknowledgementRequest, QueryPacketAcknowledgementResponse,
-        QueryPacketAcknowledgementsRequest, QueryPacketAcknowledgementsResponse,
-        QueryPacketCommitmentRequest, QueryPacketCommitmentResponse, QueryPacketCommitmentsRequest,
+        Channel as RawChannel, MsgAcknowledgement, MsgAcknowledgementResponse,
+        MsgChannelCloseConfirm, MsgChannelCloseConfirmResponse, MsgChannelCloseInit,
+        MsgChannelCloseInitResponse, MsgChannelOpenAck as RawMsgChannelOpenAck,
+        MsgChannelOpenAckResponse, MsgChannelOpenConfirm as RawMsgChannelOpenConfirm,
+        MsgChannelOpenConfirmResponse, MsgChannelOpenInit as RawMsgChannelOpenInit,
+        MsgChannelOpenInitResponse, MsgChannelOpenTry as RawMsgChannelOpenTry,
+        MsgChannelOpenTryResponse, MsgRecvPacket as RawMsgRecvPacket, MsgRecvPacketResponse,
+        MsgTimeout, MsgTimeoutOnClose, MsgTimeoutOnCloseResponse, MsgTimeoutResponse,
+        Packet as RawPacket, PacketState, QueryChannelClientStateRequest,
+        QueryChannelClientStateResponse, QueryChannelConsensusStateRequest,
+        QueryChannelConsensusStateResponse, QueryChannelRequest, QueryChannelResponse,
+        QueryChannelsRequest, QueryChannelsResponse, QueryConnectionChannelsRequest,
+        QueryConnectionChannelsResponse, QueryNextSequenceReceiveRequest,
+        QueryNextSequenceReceiveResponse, QueryPacketAcknowledgementRequest,
+        QueryPacketAcknowledgementResponse, QueryPacketAcknowledgementsRequest,
+        QueryPacketAcknowledgementsResponse, QueryPacketCommitmentRequest,
+        QueryPacketCommitmentResponse, QueryPacketCommitmentsRequest,
         QueryPacketCommitmentsResponse, QueryPacketReceiptRequest, QueryPacketReceiptResponse,
         QueryUnreceivedAcksRequest, QueryUnreceivedAcksResponse, QueryUnreceivedPacketsRequest,
         QueryUnreceivedPacketsResponse,
@@ -45,85 +85,483 @@ use ibc_proto::ibc::core::{
         QueryUpgradedConsensusStateRequest, QueryUpgradedConsensusStateResponse,
     },
     connection::v1::{
+        msg_server::{Msg as ConnectionMsgRpc, MsgServer as ConnectionMsgServer},
         query_server::{Query as ConnectionQuery, QueryServer as ConnectionQueryServer},
-        IdentifiedConnection as RawIdentifiedConnection, QueryClientConnectionsRequest,
-        QueryClientConnectionsResponse, QueryConnectionClientStateRequest,
-        QueryConnectionClientStateResponse, QueryConnectionConsensusStateRequest,
-        QueryConnectionConsensusStateResponse, QueryConnectionRequest, QueryConnectionResponse,
-        QueryConnectionsRequest, QueryConnectionsResponse,
+        ConnectionEnd as RawConnectionEnd, IdentifiedConnection as RawIdentifiedConnection,
+        MsgConnectionOpenAck as RawMsgConnectionOpenAck, MsgConnectionOpenAckResponse,
+        MsgConnectionOpenConfirm as RawMsgConnectionOpenConfirm, MsgConnectionOpenConfirmResponse,
+        MsgConnectionOpenInit as RawMsgConnectionOpenInit, MsgConnectionOpenInitResponse,
+        MsgConnectionOpenTry as RawMsgConnectionOpenTry, MsgConnectionOpenTryResponse,
+        QueryClientConnectionsRequest, QueryClientConnectionsResponse,
+        QueryConnectionClientStateRequest, QueryConnectionClientStateResponse,
+        QueryConnectionConsensusStateRequest, QueryConnectionConsensusStateResponse,
+        QueryConnectionRequest, QueryConnectionResponse, QueryConnectionsRequest,
+        QueryConnectionsResponse,
     },
 };
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::metadata::{Ascii, MetadataMap, MetadataValue};
 use tonic::{transport::Server, Request, Response, Status};
+use tower::{Layer, Service};
 
+use common_apm::metrics::ibc::IBC_GRPC_UNSUPPORTED_COUNTER_VEC;
+use protocol::tokio::sync::watch;
 use protocol::{
-    traits::IbcAdapter,
+    traits::{Context, IbcAdapter},
     types::{Path, StoreHeight as Height},
+    ProtocolResult,
+};
+
+use crate::error::IbcError;
+use crate::grpc_ext::{
+    ChannelExtQueryServer, ClientExtQueryServer, ConnectionExtQueryServer, ContextExtQueryServer,
+};
+use crate::{
+    channel_handshake_event, connection_handshake_event, HandshakeStep, IbcImpl, IbcRouter,
 };
 
-pub const CHAIN_REVISION_NUMBER: u64 = 0;
+/// The page size of the paginated queries whose request has no pagination.
+pub const DEFAULT_PAGE_LIMIT: u64 = 100;
+
+/// The default max number of store paths a single query is allowed to scan.
+pub const DEFAULT_MAX_QUERY_PATHS: usize = 100_000;
+
+/// The gRPC metadata key of the height which a query is asked to be served
+/// at, the same as the Cosmos SDK. Only a versioned store serves a height,
+/// `DefaultIbcAdapter` keeps only the latest state and rejects the queries
+/// with a height.
+pub const QUERY_HEIGHT_METADATA: &str = "x-cosmos-block-height";
+
+/// The gRPC metadata key of the minimum timestamp in nanoseconds of the
+/// consensus states returned by `ConsensusStates`, whose request has no field
+/// of it.
+pub const MIN_TIMESTAMP_METADATA: &str = "x-ibc-min-timestamp";
+
+/// The gRPC metadata key of the log lines of a msg handler, one entry per line.
+pub const HANDLER_LOG_METADATA: &str = "x-ibc-handler-log";
+
+/// The gRPC metadata key of the events emitted by a msg handler, one JSON
+/// encoded `IbcEvent` per entry.
+pub const HANDLER_EVENT_METADATA: &str = "x-ibc-handler-event";
+
+/// The default latency above which a gRPC request is logged as slow.
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(500);
+
+const LOCK_INITIAL_BACKOFF: Duration = Duration::from_millis(1);
+const LOCK_MAX_BACKOFF: Duration = Duration::from_millis(50);
 
 pub struct GrpcService<Adapter: IbcAdapter, Ctx: Ics26Context> {
-    adapter: Arc<Adapter>,
-    addr:    SocketAddr,
-    ctx:     Arc<RwLock<Ctx>>,
+    adapter:         Arc<Adapter>,
+    query_addr:      SocketAddr,
+    msg_addr:        SocketAddr,
+    ctx:             Arc<RwLock<Ctx>>,
+    revision_number: u64,
+    lock_timeout:    Option<Duration>,
+    slow_threshold:  Duration,
+    snapshot_reads:  bool,
 }
 
 impl<Adapter, Ctx> GrpcService<Adapter, Ctx>
 where
     Adapter: IbcAdapter + 'static,
-    Ctx: Ics26Context + Sync + Send + 'static,
+    Ctx: Ics26Context + IbcContextReader + Sync + Send + 'static,
 {
-    pub fn new(adapter: Arc<Adapter>, addr: String, ctx: Arc<RwLock<Ctx>>) -> Self {
-        GrpcService {
+    /// The query services are served on `query_addr` and the msg services on
+    /// `msg_addr`, so that the msg services can be kept on a private socket.
+    /// All of them are served on one socket if the two addresses are the same.
+    ///
+    /// The revision number of the query response heights is derived from the
+    /// chain id, in the `{chain_name}-{revision_number}` format.
+    pub fn new(
+        adapter: Arc<Adapter>,
+        query_addr: String,
+        msg_addr: String,
+        chain_id: &str,
+        ctx: Arc<RwLock<Ctx>>,
+    ) -> ProtocolResult<Self> {
+        Ok(GrpcService {
             adapter,
-            addr: addr.parse().unwrap(),
+            query_addr: parse_addr(&query_addr)?,
+            msg_addr: parse_addr(&msg_addr)?,
             ctx,
+            revision_number: ChainId::chain_version(chain_id),
+            lock_timeout: None,
+            slow_threshold: DEFAULT_SLOW_QUERY_THRESHOLD,
+            snapshot_reads: false,
+        })
+    }
+
+    /// Bound the wait for the context lock, which is held by the msg handlers,
+    /// so that the handlers and the queries answer `unavailable` instead of
+    /// stalling during a write burst, see `ContextLockLayer`. The wait is
+    /// unbounded by default.
+    pub fn with_lock_timeout(mut self, lock_timeout: Option<Duration>) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    /// Log the requests which take longer than `threshold` at warn level, so
+    /// that the calls in need of pagination stand out from the access log.
+    pub fn with_slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = threshold;
+        self
+    }
+
+    /// Serve the channel queries without a height on a snapshot, see
+    /// `IbcChannelService::with_snapshot_reads`. A store without versions has
+    /// no snapshot, so the option is ignored there with a warning.
+    pub fn with_snapshot_reads(mut self, snapshot_reads: bool) -> Self {
+        if snapshot_reads && !self.adapter.is_versioned() {
+            log::warn!(
+                "[ibc] the store keeps only the latest state, the snapshot reads are disabled"
+            );
         }
+        self.snapshot_reads = snapshot_reads;
+        self
     }
 
-    pub async fn run(self) {
-        log::info!("ibc run");
-        // [::1] ipv6, equal to 127.0.0.1
-        println!("addr {:?}", self.addr);
+    pub async fn run(self) -> Result<(), tonic::transport::Error> {
+        self.run_until(std::future::pending()).await
+    }
 
-        let ibc_client_service = self.client_service();
-        let ibc_conn_service = self.connection_service();
-        let ibc_channel_service = self.channel_service();
-        let ibc_client_msg_service = self.client_msg_service();
-        Server::builder()
-            .add_service(ibc_client_service)
-            .add_service(ibc_conn_service)
-            .add_service(ibc_channel_service)
-            .add_service(ibc_client_msg_service)
-            .serve(self.addr)
-            .await
-            .unwrap();
+    /// Serve until `shutdown` resolves, then stop accepting requests and wait
+    /// for the in-flight ones to finish. A transport error of either server,
+    /// such as an address in use, stops both and is returned to the caller.
+    pub async fn run_until<F: Future<Output = ()>>(
+        self,
+        shutdown: F,
+    ) -> Result<(), tonic::transport::Error> {
+        log::info!(
+            "ibc run, query addr {:?}, msg addr {:?}",
+            self.query_addr,
+            self.msg_addr
+        );
+
+        let (stop_tx, stop_rx) = watch::channel(());
+        let stop = async move {
+            shutdown.await;
+            let _ = stop_tx.send(());
+            Ok::<_, tonic::transport::Error>(())
+        };
+        let stopped = |mut stop_rx: watch::Receiver<()>| async move {
+            let _ = stop_rx.changed().await;
+        };
+
+        // The extra queries of the services, like
+        // `IbcChannelService::channel_overview`, have no RPC in the ICS protos,
+        // so they are served by the services of `proto/axon/ibc` next to them.
+        let slow_query_log = SlowQueryLayer::new(self.slow_threshold);
+        let query_server = Server::builder()
+            .layer(slow_query_log)
+            .layer(ContextLockLayer::new(
+                Arc::clone(&self.ctx),
+                self.lock_timeout,
+            ))
+            .add_service(self.client_service())
+            .add_service(self.client_ext_service())
+            .add_service(self.connection_service())
+            .add_service(self.connection_ext_service())
+            .add_service(self.channel_service())
+            .add_service(self.channel_ext_service())
+            .add_service(self.context_ext_service());
+
+        if self.query_addr == self.msg_addr {
+            let server = query_server
+                .add_service(self.client_msg_service())
+                .add_service(self.connection_msg_service())
+                .add_service(self.channel_msg_service())
+                .serve_with_shutdown(self.query_addr, stopped(stop_rx));
+            protocol::tokio::try_join!(server, stop)?;
+            return Ok(());
+        }
+
+        let msg_server = Server::builder()
+            .layer(slow_query_log)
+            .add_service(self.client_msg_service())
+            .add_service(self.connection_msg_service())
+            .add_service(self.channel_msg_service())
+            .serve_with_shutdown(self.msg_addr, stopped(stop_rx.clone()));
+        protocol::tokio::try_join!(
+            query_server.serve_with_shutdown(self.query_addr, stopped(stop_rx)),
+            msg_server,
+            stop
+        )?;
+        Ok(())
     }
 
     pub fn client_service(&self) -> ClientQueryServer<IbcClientService<Adapter>> {
-        ClientQueryServer::new(IbcClientService::new(Arc::clone(&self.adapter)))
+        ClientQueryServer::new(IbcClientService::new(
+            Arc::clone(&self.adapter),
+            self.revision_number,
+        ))
+    }
+
+    pub fn client_ext_service(&self) -> ClientExtQueryServer<Adapter> {
+        ClientExtQueryServer::new(IbcClientService::new(
+            Arc::clone(&self.adapter),
+            self.revision_number,
+        ))
     }
 
     pub fn connection_service(&self) -> ConnectionQueryServer<IbcConnectionService<Adapter>> {
-        ConnectionQueryServer::new(IbcConnectionService::new(Arc::clone(&self.adapter)))
+        ConnectionQueryServer::new(IbcConnectionService::new(
+            Arc::clone(&self.adapter),
+            self.revision_number,
+        ))
+    }
+
+    pub fn connection_ext_service(&self) -> ConnectionExtQueryServer<Adapter> {
+        ConnectionExtQueryServer::new(IbcConnectionService::new(
+            Arc::clone(&self.adapter),
+            self.revision_number,
+        ))
     }
 
     pub fn channel_service(&self) -> ChannelQueryServer<IbcChannelService<Adapter>> {
-        ChannelQueryServer::new(IbcChannelService::new(Arc::clone(&self.adapter)))
+        ChannelQueryServer::new(self.channel_query())
+    }
+
+    pub fn channel_ext_service(&self) -> ChannelExtQueryServer<Adapter> {
+        ChannelExtQueryServer::new(self.channel_query())
+    }
+
+    fn channel_query(&self) -> IbcChannelService<Adapter> {
+        IbcChannelService::new(Arc::clone(&self.adapter), self.revision_number)
+            .with_snapshot_reads(self.snapshot_reads)
+    }
+
+    pub fn context_ext_service(&self) -> ContextExtQueryServer<Ctx> {
+        ContextExtQueryServer::new(
+            IbcContextQueryService::new(Arc::clone(&self.ctx)).with_lock_timeout(self.lock_timeout),
+        )
     }
 
     pub fn client_msg_service(&self) -> ClientMsgServer<IbcClientMsgService<Ctx>> {
-        ClientMsgServer::new(IbcClientMsgService::new(Arc::clone(&self.ctx)))
+        ClientMsgServer::new(
+            IbcClientMsgService::new(Arc::clone(&self.ctx)).with_lock_timeout(self.lock_timeout),
+        )
+    }
+
+    pub fn connection_msg_service(&self) -> ConnectionMsgServer<IbcConnectionMsgService<Ctx>> {
+        ConnectionMsgServer::new(
+            IbcConnectionMsgService::new(Arc::clone(&self.ctx))
+                .with_lock_timeout(self.lock_timeout),
+        )
+    }
+
+    pub fn channel_msg_service(&self) -> ChannelMsgServer<IbcChannelMsgService<Ctx>> {
+        ChannelMsgServer::new(
+            IbcChannelMsgService::new(Arc::clone(&self.ctx)).with_lock_timeout(self.lock_timeout),
+        )
+    }
+}
+
+fn parse_addr(addr: &str) -> ProtocolResult<SocketAddr> {
+    addr.parse().map_err(|e: std::net::AddrParseError| {
+        IbcError::InvalidGrpcAddress {
+            addr:   addr.to_owned(),
+            reason: e.to_string(),
+        }
+        .into()
+    })
+}
+
+/// A middleware which logs the gRPC requests slower than a threshold.
+#[derive(Clone, Copy, Debug)]
+pub struct SlowQueryLayer {
+    threshold: Duration,
+}
+
+impl SlowQueryLayer {
+    pub fn new(threshold: Duration) -> Self {
+        SlowQueryLayer { threshold }
+    }
+}
+
+impl<S> Layer<S> for SlowQueryLayer {
+    type Service = SlowQueryLog<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowQueryLog {
+            inner,
+            threshold: self.threshold,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SlowQueryLog<S> {
+    inner:     S,
+    threshold: Duration,
+}
+
+impl<S, B> Service<http::Request<B>> for SlowQueryLog<S>
+where
+    S: Service<http::Request<B>>,
+    S::Future: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        // The path of a gRPC request is `/{package}.{service}/{method}`.
+        let method = req.uri().path().to_owned();
+        let threshold = self.threshold;
+        let start = Instant::now();
+        let resp = self.inner.call(req);
+
+        Box::pin(async move {
+            let resp = resp.await;
+            log_slow_query(&method, start.elapsed(), threshold);
+            resp
+        })
+    }
+}
+
+/// A middleware which holds the queries while a msg handler holds the context
+/// lock, so that a query doesn't read the writes staged by the msg in
+/// handling. The wait is bounded by the lock timeout like the msg services,
+/// and a query which waits longer is answered with `unavailable`. The lock is
+/// not held during the query, so the pending state may still change under a
+/// query without a height, see `IbcChannelService::with_snapshot_reads`.
+pub struct ContextLockLayer<Ctx> {
+    ctx:     Arc<RwLock<Ctx>>,
+    timeout: Option<Duration>,
+}
+
+impl<Ctx> ContextLockLayer<Ctx> {
+    pub fn new(ctx: Arc<RwLock<Ctx>>, timeout: Option<Duration>) -> Self {
+        ContextLockLayer { ctx, timeout }
+    }
+}
+
+impl<Ctx> Clone for ContextLockLayer<Ctx> {
+    fn clone(&self) -> Self {
+        ContextLockLayer {
+            ctx:     Arc::clone(&self.ctx),
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<S, Ctx> Layer<S> for ContextLockLayer<Ctx> {
+    type Service = ContextLockWait<S, Ctx>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ContextLockWait {
+            inner,
+            ctx: Arc::clone(&self.ctx),
+            timeout: self.timeout,
+        }
+    }
+}
+
+pub struct ContextLockWait<S, Ctx> {
+    inner:   S,
+    ctx:     Arc<RwLock<Ctx>>,
+    timeout: Option<Duration>,
+}
+
+impl<S: Clone, Ctx> Clone for ContextLockWait<S, Ctx> {
+    fn clone(&self) -> Self {
+        ContextLockWait {
+            inner:   self.inner.clone(),
+            ctx:     Arc::clone(&self.ctx),
+            timeout: self.timeout,
+        }
     }
 }
 
+impl<S, B, Ctx> Service<http::Request<B>> for ContextLockWait<S, Ctx>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+    Ctx: Send + Sync + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        // The service polled ready is the one to call, and a clone of it takes
+        // its place for the next request.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let ctx = Arc::clone(&self.ctx);
+        let timeout = self.timeout;
+
+        Box::pin(async move {
+            if let Err(status) = wait_for_context(&ctx, timeout).await {
+                return Ok(status.to_http());
+            }
+            inner.call(req).await
+        })
+    }
+}
+
+/// Wait until the context lock is not held by a writer, see
+/// `read_with_timeout`.
+async fn wait_for_context<T>(lock: &RwLock<T>, timeout: Option<Duration>) -> Result<(), Status> {
+    acquire_with_timeout(
+        || match lock.try_read() {
+            Ok(_) | Err(TryLockError::Poisoned(_)) => Ok(()),
+            Err(TryLockError::WouldBlock) => Err(TryLockError::WouldBlock),
+        },
+        timeout,
+    )
+    .await
+}
+
+/// Log a request which took `elapsed` if it is above `threshold`, returns
+/// whether it is logged.
+pub fn log_slow_query(method: &str, elapsed: Duration, threshold: Duration) -> bool {
+    if elapsed <= threshold {
+        return false;
+    }
+
+    log::warn!(
+        "[ibc] slow grpc request {} took {:?}, above {:?}",
+        method,
+        elapsed,
+        threshold
+    );
+    true
+}
+
 pub struct IbcClientService<Adapter: IbcAdapter> {
-    adapter: Arc<Adapter>,
+    adapter:         Arc<Adapter>,
+    revision_number: u64,
 }
 
 impl<Adapter: IbcAdapter> IbcClientService<Adapter> {
-    pub fn new(adapter: Arc<Adapter>) -> Self {
-        Self { adapter }
+    pub fn new(adapter: Arc<Adapter>, revision_number: u64) -> Self {
+        Self {
+            adapter,
+            revision_number,
+        }
+    }
+
+    fn query_height(&self, metadata: &MetadataMap) -> Result<Height, Status> {
+        resolve_query_height(metadata, self.adapter.as_ref(), Height::Pending)
+    }
+
+    fn response_height(&self, height: Height) -> RawHeight {
+        response_height(height, self.adapter.current_height(), self.revision_number)
+    }
+
+    fn proof_height(&self, height: Height) -> RawHeight {
+        proof_height(self.response_height(height))
     }
 }
 
@@ -131,9 +569,27 @@ impl<Adapter: IbcAdapter> IbcClientService<Adapter> {
 impl<Adapter: IbcAdapter + 'static> ClientQuery for IbcClientService<Adapter> {
     async fn client_state(
         &self,
-        _request: Request<QueryClientStateRequest>,
+        request: Request<QueryClientStateRequest>,
     ) -> Result<Response<QueryClientStateResponse>, Status> {
-        unimplemented!()
+        log::info!("Got client state request: {:?}", request);
+
+        let query_height = self.query_height(request.metadata())?;
+        let client_id = ClientId::from_str(&request.get_ref().client_id)
+            .map_err(|_| Status::invalid_argument("invalid client id"))?;
+        let path = path::ClientStatePath(client_id);
+
+        let client_state = self
+            .adapter
+            .get_client_state(query_height, &path)
+            .await
+            .map_err(|e| corrupt_entry(&path, e))?
+            .ok_or_else(|| Status::not_found(format!("client {} not found", path.0)))?;
+
+        Ok(Response::new(QueryClientStateResponse {
+            client_state: Some(client_state.into()),
+            proof:        vec![],
+            proof_height: Some(self.proof_height(query_height)),
+        }))
     }
 
     async fn client_states(
@@ -142,6 +598,7 @@ impl<Adapter: IbcAdapter + 'static> ClientQuery for IbcClientService<Adapter> {
     ) -> Result<Response<QueryClientStatesResponse>, Status> {
         log::info!("Got client states request: {:?}", request);
 
+        let query_height = self.query_height(request.metadata())?;
         let path = "clients"
             .to_owned()
             .try_into()
@@ -154,36 +611,88 @@ impl<Adapter: IbcAdapter + 'static> ClientQuery for IbcClientService<Adapter> {
             }
         };
 
-        let keys = self
+        let mut paths = self
             .adapter
             .get_paths_by_prefix(&path)
-            .map_err(Status::internal)?;
-        let mut client_states = Vec::with_capacity(keys.len());
-
-        for path in keys.into_iter().filter_map(client_state_paths) {
-            client_states.push(
-                self.adapter
-                    .get_client_state(Height::Pending, &path)
-                    .await
-                    .map(|client_state| IdentifiedClientState {
-                        client_id:    path.0.to_string(),
-                        client_state: Some(client_state.unwrap().into()),
-                    })
-                    .map_err(Status::data_loss)?,
-            );
+            .map_err(Status::internal)?
+            .into_iter()
+            .filter_map(client_state_paths)
+            .collect::<Vec<_>>();
+        paths.sort_unstable_by_key(|path| path.0.to_string());
+        // The page is sliced out of the paths, so that only the client states
+        // of the page are read.
+        let (paths, pagination) = paginate(
+            paths,
+            request.into_inner().pagination,
+            |path: &path::ClientStatePath| path.0.to_string(),
+        );
+        let mut client_states = Vec::with_capacity(paths.len());
+
+        for path in paths.into_iter() {
+            match self
+                .adapter
+                .get_client_state(query_height, &path)
+                .await
+                .map_err(|e| corrupt_entry(&path, e))?
+            {
+                Some(client_state) => client_states.push(IdentifiedClientState {
+                    client_id:    path.0.to_string(),
+                    client_state: Some(client_state.into()),
+                }),
+                None => skip_missing_entry(&path),
+            }
         }
 
         Ok(Response::new(QueryClientStatesResponse {
             client_states,
-            pagination: None,
+            pagination: Some(pagination),
         }))
     }
 
     async fn consensus_state(
         &self,
-        _request: Request<QueryConsensusStateRequest>,
+        request: Request<QueryConsensusStateRequest>,
     ) -> Result<Response<QueryConsensusStateResponse>, Status> {
-        unimplemented!()
+        log::info!("Got consensus state request: {:?}", request);
+
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let client_id = ClientId::from_str(&request.client_id)
+            .map_err(|_| Status::invalid_argument("invalid client id"))?;
+
+        // The latest height flag takes the newest stored consensus state and
+        // ignores the height of the request.
+        let path = if request.latest_height {
+            let latest_height = self
+                .adapter
+                .get_client_latest_height(query_height, &client_id)
+                .map_err(Status::internal)?
+                .ok_or_else(|| Status::not_found(format!("no consensus state of {}", client_id)))?;
+            path::ClientConsensusStatePath {
+                client_id,
+                epoch: latest_height.revision_number(),
+                height: latest_height.revision_height(),
+            }
+        } else {
+            path::ClientConsensusStatePath {
+                client_id,
+                epoch: request.revision_number,
+                height: request.revision_height,
+            }
+        };
+
+        let consensus_state = self
+            .adapter
+            .get_consensus_state(query_height, &path)
+            .await
+            .map_err(|e| corrupt_entry(&path, e))?
+            .ok_or_else(|| Status::not_found(format!("no consensus state at {}", path)))?;
+
+        Ok(Response::new(QueryConsensusStateResponse {
+            consensus_state: Some(consensus_state.into()),
+            proof:           vec![],
+            proof_height:    Some(self.proof_height(query_height)),
+        }))
     }
 
     async fn consensus_states(
@@ -192,7 +701,28 @@ impl<Adapter: IbcAdapter + 'static> ClientQuery for IbcClientService<Adapter> {
     ) -> Result<Response<QueryConsensusStatesResponse>, Status> {
         log::info!("Got consensus states request: {:?}", request);
 
-        let path = format!("clients/{}/consensusStates", request.get_ref().client_id)
+        let query_height = self.query_height(request.metadata())?;
+        let min_timestamp = min_timestamp(request.metadata())?;
+        let request = request.into_inner();
+
+        self.list_consensus_states(
+            query_height,
+            &request.client_id,
+            min_timestamp,
+            request.pagination,
+        )
+        .await
+        .map(Response::new)
+    }
+
+    async fn consensus_state_heights(
+        &self,
+        request: Request<QueryConsensusStateHeightsRequest>,
+    ) -> Result<Response<QueryConsensusStateHeightsResponse>, Status> {
+        log::info!("Got consensus state heights request: {:?}", request);
+        let request = request.into_inner();
+
+        let path = format!("clients/{}/consensusStates", request.client_id)
             .try_into()
             .map_err(|e| Status::invalid_argument(format!("{:?}", e)))?;
 
@@ -200,81 +730,585 @@ impl<Adapter: IbcAdapter + 'static> ClientQuery for IbcClientService<Adapter> {
             .adapter
             .get_paths_by_prefix(&path)
             .map_err(Status::internal)?;
-        let mut consensus_states = Vec::with_capacity(keys.len());
+        let mut heights = Vec::with_capacity(keys.len());
 
+        // Only the paths are needed, the states themselves are never read.
         for path in keys.into_iter() {
+            let raw_path = path.to_string();
             if let Ok(IbcPath::ClientConsensusState(path)) = path.try_into() {
-                let consensus_state = self
-                    .adapter
-                    .get_consensus_state(Height::Pending, &path)
-                    .await
-                    .map_err(Status::data_loss)?;
-                consensus_states.push(ConsensusStateWithHeight {
-                    height:          Some(RawHeight {
-                        revision_number: path.epoch,
-                        revision_height: path.height,
-                    }),
-                    consensus_state: consensus_state.map(|cs| cs.into()),
-                });
+                heights.push((path.epoch, path.height));
             } else {
-                panic!("unexpected path")
+                skip_unexpected_path(&raw_path);
             }
         }
-
-        Ok(Response::new(QueryConsensusStatesResponse {
-            consensus_states,
-            pagination: None,
+        // The store returns the paths in the order of their keys, which is
+        // not the order of the heights.
+        heights.sort_unstable();
+        let (heights, pagination) = paginate(
+            heights,
+            request.pagination,
+            |&(revision_number, revision_height): &(u64, u64)| {
+                height_page_key(revision_number, revision_height)
+            },
+        );
+
+        Ok(Response::new(QueryConsensusStateHeightsResponse {
+            consensus_state_heights: heights
+                .into_iter()
+                .map(|(revision_number, revision_height)| RawHeight {
+                    revision_number,
+                    revision_height,
+                })
+                .collect(),
+            pagination:              Some(pagination),
         }))
     }
 
-    async fn consensus_state_heights(
-        &self,
-        _request: Request<QueryConsensusStateHeightsRequest>,
-    ) -> Result<Response<QueryConsensusStateHeightsResponse>, Status> {
-        unimplemented!()
-    }
-
     async fn client_status(
         &self,
-        _request: Request<QueryClientStatusRequest>,
+        request: Request<QueryClientStatusRequest>,
     ) -> Result<Response<QueryClientStatusResponse>, Status> {
-        unimplemented!()
+        log::info!("Got client status request: {:?}", request);
+
+        let client_id = ClientId::from_str(&request.get_ref().client_id)
+            .map_err(|_| Status::invalid_argument("invalid client id"))?;
+        let path = path::ClientStatePath(client_id);
+
+        let client_state = self
+            .adapter
+            .get_client_state(Height::Pending, &path)
+            .await
+            .map_err(|e| corrupt_entry(&path, e))?
+            .ok_or_else(|| Status::not_found(format!("client {} not found", path.0)))?;
+        let now = self.host_timestamp().await?;
+        let status = self.client_status_of(&path.0, &client_state, now).await?;
+
+        Ok(Response::new(QueryClientStatusResponse {
+            status: status.to_owned(),
+        }))
     }
 
     async fn client_params(
         &self,
         _request: Request<QueryClientParamsRequest>,
     ) -> Result<Response<QueryClientParamsResponse>, Status> {
-        unimplemented!()
+        Err(unsupported("client_params"))
     }
 
     async fn upgraded_client_state(
         &self,
         _request: Request<QueryUpgradedClientStateRequest>,
     ) -> Result<Response<QueryUpgradedClientStateResponse>, Status> {
-        unimplemented!()
+        Err(unsupported("upgraded_client_state"))
     }
 
     async fn upgraded_consensus_state(
         &self,
         _request: Request<QueryUpgradedConsensusStateRequest>,
     ) -> Result<Response<QueryUpgradedConsensusStateResponse>, Status> {
-        unimplemented!()
+        Err(unsupported("upgraded_consensus_state"))
+    }
+}
+
+/// The `ConsensusStates` request with a lower bound on the timestamp of the
+/// returned consensus states, in nanoseconds since the unix epoch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryConsensusStatesSinceRequest {
+    pub client_id:     String,
+    pub min_timestamp: u64,
+    pub pagination:    Option<PageRequest>,
+}
+
+/// The request of the ids-only list queries, which return the identifiers
+/// without the encoded states. The ICS protos have no such queries, so they
+/// are served by the `axon.ibc` services.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryIdsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryClientIdsResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub client_ids: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryConnectionIdsResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub connection_ids: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PortChannelId {
+    #[prost(string, tag = "1")]
+    pub port_id:    String,
+    #[prost(string, tag = "2")]
+    pub channel_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryChannelIdsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub channels: Vec<PortChannelId>,
+}
+
+/// The request of the aggregate clients query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryClientsOverviewRequest {}
+
+/// A row of the clients overview. The status is one of `Active`, `Frozen`,
+/// `Expired` and `Unknown`, the same as the `ClientStatus` query of ibc-go.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ClientOverview {
+    #[prost(string, tag = "1")]
+    pub client_id:     String,
+    #[prost(string, tag = "2")]
+    pub client_type:   String,
+    #[prost(string, tag = "3")]
+    pub status:        String,
+    #[prost(message, optional, tag = "4")]
+    pub latest_height: Option<RawHeight>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryClientsOverviewResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub clients: Vec<ClientOverview>,
+}
+
+impl<Adapter: IbcAdapter + 'static> IbcClientService<Adapter> {
+    /// ClientIds returns the ids of all the clients like `ClientStates`,
+    /// without reading the client states.
+    pub async fn client_ids(
+        &self,
+        _request: Request<QueryIdsRequest>,
+    ) -> Result<Response<QueryClientIdsResponse>, Status> {
+        let client_ids = list_ids(self.adapter.as_ref(), "clients", |path| match path {
+            IbcPath::ClientState(path::ClientStatePath(client_id)) => Some(client_id.to_string()),
+            _ => None,
+        })?;
+
+        Ok(Response::new(QueryClientIdsResponse { client_ids }))
+    }
+
+    /// The timestamp of the host at the current height.
+    async fn host_timestamp(&self) -> Result<Timestamp, Status> {
+        let header = self
+            .adapter
+            .get_header_by_height(self.adapter.current_height())
+            .await
+            .map_err(Status::internal)?;
+        let timestamp = crate::host_consensus_state(&header)
+            .map_err(Status::internal)?
+            .timestamp();
+        Ok(timestamp)
+    }
+
+    /// The status of a client at the host time `now`, which is one of
+    /// `Active`, `Frozen`, `Expired` and `Unknown`. A client expires once its
+    /// latest consensus state is older than the trusting period.
+    async fn client_status_of(
+        &self,
+        client_id: &ClientId,
+        client_state: &AnyClientState,
+        now: Timestamp,
+    ) -> Result<&'static str, Status> {
+        if client_state.is_frozen() {
+            return Ok("Frozen");
+        }
+
+        let latest_height = client_state.latest_height();
+        let consensus_state = self
+            .adapter
+            .get_consensus_state(Height::Pending, &path::ClientConsensusStatePath {
+                client_id: client_id.clone(),
+                epoch:     latest_height.revision_number(),
+                height:    latest_height.revision_height(),
+            })
+            .await
+            .map_err(Status::data_loss)?;
+
+        let status = match (client_state, consensus_state) {
+            (AnyClientState::Tendermint(state), Some(consensus_state)) => {
+                let expiry = consensus_state
+                    .timestamp()
+                    .nanoseconds()
+                    .saturating_add(state.trusting_period.as_nanos() as u64);
+                if expiry > now.nanoseconds() {
+                    "Active"
+                } else {
+                    "Expired"
+                }
+            }
+            _ => "Unknown",
+        };
+        Ok(status)
+    }
+
+    /// ConsensusStatesSince returns the consensus states of a client like
+    /// `ConsensusStates`, without the ones older than `min_timestamp`, which
+    /// can't back a valid proof any more. The offset and limit of the
+    /// pagination apply to the filtered states. The relayers get the same
+    /// filter from the served `ConsensusStates` with `MIN_TIMESTAMP_METADATA`,
+    /// this one takes it in the request for the callers in process.
+    pub async fn consensus_states_since(
+        &self,
+        request: Request<QueryConsensusStatesSinceRequest>,
+    ) -> Result<Response<QueryConsensusStatesResponse>, Status> {
+        log::info!("Got consensus states since request: {:?}", request);
+
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+
+        self.list_consensus_states(
+            query_height,
+            &request.client_id,
+            request.min_timestamp,
+            request.pagination,
+        )
+        .await
+        .map(Response::new)
+    }
+
+    /// The consensus states of a client not older than `min_timestamp`, in
+    /// the order of their heights. The page is cut out of the filtered states.
+    async fn list_consensus_states(
+        &self,
+        query_height: Height,
+        client_id: &str,
+        min_timestamp: u64,
+        page: Option<PageRequest>,
+    ) -> Result<QueryConsensusStatesResponse, Status> {
+        let path = format!("clients/{}/consensusStates", client_id)
+            .try_into()
+            .map_err(|e| Status::invalid_argument(format!("{:?}", e)))?;
+
+        let mut paths = Vec::new();
+        for path in self
+            .adapter
+            .get_paths_by_prefix(&path)
+            .map_err(Status::internal)?
+        {
+            let raw_path = path.to_string();
+            match path.try_into() {
+                Ok(IbcPath::ClientConsensusState(path)) => paths.push(path),
+                _ => skip_unexpected_path(&raw_path),
+            }
+        }
+        // The pages are cut by the heights, not by the order of the keys.
+        paths.sort_unstable_by_key(|path| (path.epoch, path.height));
+        let mut consensus_states = Vec::new();
+
+        for path in paths.into_iter() {
+            let consensus_state = match self
+                .adapter
+                .get_consensus_state(query_height, &path)
+                .await
+                .map_err(|e| corrupt_entry(&path, e))?
+            {
+                Some(consensus_state) => consensus_state,
+                None => {
+                    skip_missing_entry(&path);
+                    continue;
+                }
+            };
+            if consensus_state.timestamp().nanoseconds() < min_timestamp {
+                continue;
+            }
+
+            consensus_states.push(ConsensusStateWithHeight {
+                height:          Some(RawHeight {
+                    revision_number: path.epoch,
+                    revision_height: path.height,
+                }),
+                consensus_state: Some(consensus_state.into()),
+            });
+        }
+
+        let (consensus_states, pagination) = paginate(
+            consensus_states,
+            page,
+            |state: &ConsensusStateWithHeight| {
+                let height = state.height.clone().unwrap_or_default();
+                height_page_key(height.revision_number, height.revision_height)
+            },
+        );
+
+        Ok(QueryConsensusStatesResponse {
+            consensus_states,
+            pagination: Some(pagination),
+        })
+    }
+
+    /// ClientsOverview returns the id, type, status and latest height of
+    /// every client in a single pass over the client prefix. It is served as
+    /// `axon.ibc.client.v1.Query`.
+    pub async fn clients_overview(
+        &self,
+        request: Request<QueryClientsOverviewRequest>,
+    ) -> Result<Response<QueryClientsOverviewResponse>, Status> {
+        log::info!("Got clients overview request: {:?}", request);
+
+        let path = "clients"
+            .to_owned()
+            .try_into()
+            .map_err(|e| Status::invalid_argument(format!("{:?}", e)))?;
+
+        let now = self.host_timestamp().await?;
+
+        let keys = self
+            .adapter
+            .get_paths_by_prefix(&path)
+            .map_err(Status::internal)?;
+        let mut clients = Vec::new();
+
+        for path in keys.into_iter() {
+            let client_id = match path.try_into() {
+                Ok(IbcPath::ClientState(path::ClientStatePath(client_id))) => client_id,
+                _ => continue,
+            };
+
+            let client_state_path = path::ClientStatePath(client_id.clone());
+            let client_state = match self
+                .adapter
+                .get_client_state(Height::Pending, &client_state_path)
+                .await
+                .map_err(|e| corrupt_entry(&client_state_path, e))?
+            {
+                Some(client_state) => client_state,
+                None => {
+                    skip_missing_entry(&client_state_path);
+                    continue;
+                }
+            };
+            let latest_height = client_state.latest_height();
+            let status = self
+                .client_status_of(&client_id, &client_state, now)
+                .await?;
+
+            clients.push(ClientOverview {
+                client_id:     client_id.to_string(),
+                client_type:   client_state.client_type().to_string(),
+                status:        status.to_owned(),
+                latest_height: Some(RawHeight {
+                    revision_number: latest_height.revision_number(),
+                    revision_height: latest_height.revision_height(),
+                }),
+            });
+        }
+
+        Ok(Response::new(QueryClientsOverviewResponse { clients }))
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryClientLatestHeightRequest {
+    #[prost(string, tag = "1")]
+    pub client_id: String,
+}
+
+/// The highest height of the consensus states stored for a client, which is
+/// the trusted height for the next proof of the counterparty.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryClientLatestHeightResponse {
+    #[prost(message, optional, tag = "1")]
+    pub latest_height: Option<RawHeight>,
+}
+
+/// The reads of the shared context which the context queries are served
+/// from, instead of the adapter.
+pub trait IbcContextReader {
+    /// The highest height of the consensus states stored for the client.
+    fn client_latest_height(&self, client_id: &ClientId) -> ProtocolResult<Option<ibc::Height>>;
+
+    /// The app version the module bound to the port proposes, `None` if the
+    /// port is not bound.
+    fn proposed_app_version(&self, port_id: &PortId) -> Option<Version>;
+
+    /// Check that a packet to receive has not timed out on the host.
+    fn check_recv_packet_timeout(&self, packet: &Packet) -> Result<(), IbcError>;
+}
+
+impl<Adapter> IbcContextReader for IbcImpl<Adapter, IbcRouter>
+where
+    Adapter: IbcAdapter + 'static,
+{
+    fn client_latest_height(&self, client_id: &ClientId) -> ProtocolResult<Option<ibc::Height>> {
+        IbcImpl::client_latest_height(self, client_id)
+    }
+
+    fn proposed_app_version(&self, port_id: &PortId) -> Option<Version> {
+        let module_id = self.lookup_module_by_port(port_id).ok()?;
+        Some(self.router().app_version(&module_id))
+    }
+
+    fn check_recv_packet_timeout(&self, packet: &Packet) -> Result<(), IbcError> {
+        IbcImpl::check_recv_packet_timeout(self, packet)
     }
 }
 
+/// The queries served from the shared context instead of the adapter.
+pub struct IbcContextQueryService<Ctx> {
+    ctx:          Arc<RwLock<Ctx>>,
+    lock_timeout: Option<Duration>,
+}
+
+impl<Ctx: IbcContextReader> IbcContextQueryService<Ctx> {
+    pub fn new(ctx: Arc<RwLock<Ctx>>) -> Self {
+        Self {
+            ctx,
+            lock_timeout: None,
+        }
+    }
+
+    /// Bound the wait for the context lock, see `read_with_timeout`.
+    pub fn with_lock_timeout(mut self, lock_timeout: Option<Duration>) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    /// ClientLatestHeight returns the highest height of the consensus states
+    /// of a client, which is tracked on each store. It is served as
+    /// `axon.ibc.context.v1.Query`.
+    pub async fn client_latest_height(
+        &self,
+        request: Request<QueryClientLatestHeightRequest>,
+    ) -> Result<Response<QueryClientLatestHeightResponse>, Status> {
+        let client_id = ClientId::from_str(&request.get_ref().client_id)
+            .map_err(|_| Status::invalid_argument("invalid client id"))?;
+
+        let latest_height = read_with_timeout(&self.ctx, self.lock_timeout)
+            .await?
+            .client_latest_height(&client_id)
+            .map_err(Status::internal)?
+            .ok_or_else(|| Status::not_found(format!("no consensus state of {}", client_id)))?;
+
+        Ok(Response::new(QueryClientLatestHeightResponse {
+            latest_height: Some(RawHeight {
+                revision_number: latest_height.revision_number(),
+                revision_height: latest_height.revision_height(),
+            }),
+        }))
+    }
+
+    /// ProposedAppVersion returns the app version the module bound to a port
+    /// proposes, so that the handshake can begin before any channel exists on
+    /// the port. It reads the router of the context, and is served as
+    /// `axon.ibc.context.v1.Query`.
+    pub async fn proposed_app_version(
+        &self,
+        request: Request<QueryProposedAppVersionRequest>,
+    ) -> Result<Response<QueryProposedAppVersionResponse>, Status> {
+        let port_id = PortId::from_str(&request.get_ref().port_id)
+            .map_err(|_| Status::invalid_argument("invalid port id"))?;
+
+        let version = read_with_timeout(&self.ctx, self.lock_timeout)
+            .await?
+            .proposed_app_version(&port_id)
+            .ok_or_else(|| Status::not_found(format!("port {} is not bound", port_id)))?;
+
+        Ok(Response::new(QueryProposedAppVersionResponse {
+            version: version.to_string(),
+        }))
+    }
+}
+
+/// The request of the proposed app version query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryProposedAppVersionRequest {
+    #[prost(string, tag = "1")]
+    pub port_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryProposedAppVersionResponse {
+    #[prost(string, tag = "1")]
+    pub version: String,
+}
+
+/// The request of the connection counter query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryConnectionCounterRequest {}
+
+/// The number of connections created on the chain, which is also the
+/// sequence of the next connection id.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryConnectionCounterResponse {
+    #[prost(uint64, tag = "1")]
+    pub connection_counter: u64,
+}
+
 pub struct IbcConnectionService<Adapter: IbcAdapter> {
     connection_end_adapter: Arc<Adapter>,
     connection_ids_adapter: Arc<Adapter>,
+    revision_number:        u64,
 }
 
 impl<Adapter: IbcAdapter> IbcConnectionService<Adapter> {
-    pub fn new(adapter: Arc<Adapter>) -> Self {
+    pub fn new(adapter: Arc<Adapter>, revision_number: u64) -> Self {
         Self {
             connection_end_adapter: Arc::clone(&adapter),
             connection_ids_adapter: Arc::clone(&adapter),
+            revision_number,
         }
     }
+
+    fn query_height(&self, metadata: &MetadataMap) -> Result<Height, Status> {
+        resolve_query_height(
+            metadata,
+            self.connection_end_adapter.as_ref(),
+            Height::Pending,
+        )
+    }
+
+    fn response_height(&self, height: Height) -> RawHeight {
+        response_height(
+            height,
+            self.connection_end_adapter.current_height(),
+            self.revision_number,
+        )
+    }
+
+    fn proof_height(&self, height: Height) -> RawHeight {
+        proof_height(self.response_height(height))
+    }
+}
+
+impl<Adapter: IbcAdapter + 'static> IbcConnectionService<Adapter> {
+    /// ConnectionIds returns the ids of all the connections like
+    /// `Connections`, without reading the connection ends.
+    pub async fn connection_ids(
+        &self,
+        _request: Request<QueryIdsRequest>,
+    ) -> Result<Response<QueryConnectionIdsResponse>, Status> {
+        let connection_ids = list_ids(
+            self.connection_end_adapter.as_ref(),
+            "connections",
+            |path| match path {
+                IbcPath::Connections(path::ConnectionsPath(conn_id)) => Some(conn_id.to_string()),
+                _ => None,
+            },
+        )?;
+
+        Ok(Response::new(QueryConnectionIdsResponse { connection_ids }))
+    }
+
+    /// ConnectionCounter returns the persisted connection counter. ibc-go
+    /// has no counter query, so it is served as
+    /// `axon.ibc.connection.v1.Query`.
+    pub async fn connection_counter(
+        &self,
+        _request: Request<QueryConnectionCounterRequest>,
+    ) -> Result<Response<QueryConnectionCounterResponse>, Status> {
+        let connection_counter = self
+            .connection_end_adapter
+            .get_connection_counter(Context::new())
+            .map_err(Status::internal)?
+            .unwrap_or_default();
+
+        Ok(Response::new(QueryConnectionCounterResponse {
+            connection_counter,
+        }))
+    }
 }
 
 #[tonic::async_trait]
@@ -283,57 +1317,72 @@ impl<Adapter: IbcAdapter + 'static> ConnectionQuery for IbcConnectionService<Ada
         &self,
         request: Request<QueryConnectionRequest>,
     ) -> Result<Response<QueryConnectionResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let conn_id = ConnectionId::from_str(&request.get_ref().connection_id)
             .map_err(|_| Status::invalid_argument("invalid connection id"))?;
+        let path = path::ConnectionsPath(conn_id);
         let conn: Option<ConnectionEnd> = self
             .connection_end_adapter
-            .get_connection_end(Height::Pending, &path::ConnectionsPath(conn_id))
+            .get_connection_end(query_height, &path)
             .await
             .map_err(Status::data_loss)?;
         Ok(Response::new(QueryConnectionResponse {
             connection:   conn.map(|c| c.into()),
-            proof:        vec![],
-            proof_height: None,
+            proof:        proof_of(&*self.connection_end_adapter, query_height, &path)?,
+            proof_height: Some(self.proof_height(query_height)),
         }))
     }
 
     async fn connections(
         &self,
-        _request: Request<QueryConnectionsRequest>,
+        request: Request<QueryConnectionsRequest>,
     ) -> Result<Response<QueryConnectionsResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let connection_path_prefix: Path = String::from("connections")
             .try_into()
             .expect("'connections' expected to be a valid Path");
 
-        let connection_paths = self
+        let mut connection_paths = Vec::new();
+        for path in self
             .connection_end_adapter
             .get_paths_by_prefix(&connection_path_prefix)
-            .map_err(Status::internal)?;
+            .map_err(Status::internal)?
+        {
+            let raw_path = path.to_string();
+            match path.try_into() {
+                Ok(IbcPath::Connections(connections_path)) => {
+                    connection_paths.push(connections_path)
+                }
+                _ => skip_unexpected_path(&raw_path),
+            }
+        }
+        connection_paths.sort_unstable_by_key(|path| path.to_string());
+        let (connection_paths, pagination) = paginate(
+            connection_paths,
+            request.into_inner().pagination,
+            |path: &path::ConnectionsPath| path.to_string(),
+        );
 
         let mut identified_connections: Vec<RawIdentifiedConnection> =
             Vec::with_capacity(connection_paths.len());
 
-        for path in connection_paths.into_iter() {
-            match path.try_into() {
-                Ok(IbcPath::Connections(connections_path)) => {
-                    let connection_end = self
-                        .connection_end_adapter
-                        .get_connection_end(Height::Pending, &connections_path)
-                        .await
-                        .map_err(Status::data_loss)?;
-                    identified_connections.push(
-                        IdentifiedConnectionEnd::new(connections_path.0, connection_end.unwrap())
-                            .into(),
-                    );
-                }
-                _ => panic!("unexpected path"),
+        for connections_path in connection_paths.into_iter() {
+            match self
+                .connection_end_adapter
+                .get_connection_end(query_height, &connections_path)
+                .await
+                .map_err(|e| corrupt_entry(&connections_path, e))?
+            {
+                Some(connection_end) => identified_connections
+                    .push(IdentifiedConnectionEnd::new(connections_path.0, connection_end).into()),
+                None => skip_missing_entry(&connections_path),
             }
         }
 
         Ok(Response::new(QueryConnectionsResponse {
             connections: identified_connections,
-            pagination:  None,
-            height:      None,
+            pagination:  Some(pagination),
+            height:      Some(self.response_height(query_height)),
         }))
     }
 
@@ -341,6 +1390,7 @@ impl<Adapter: IbcAdapter + 'static> ConnectionQuery for IbcConnectionService<Ada
         &self,
         request: Request<QueryClientConnectionsRequest>,
     ) -> Result<Response<QueryClientConnectionsResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let client_id = request
             .get_ref()
             .client_id
@@ -349,7 +1399,7 @@ impl<Adapter: IbcAdapter + 'static> ConnectionQuery for IbcConnectionService<Ada
         let path = path::ClientConnectionsPath(client_id);
         let connection_ids = self
             .connection_ids_adapter
-            .get_connection_ids(Height::Pending, &path)
+            .get_connection_ids(query_height, &path)
             .await
             .unwrap_or_default()
             .iter()
@@ -359,40 +1409,724 @@ impl<Adapter: IbcAdapter + 'static> ConnectionQuery for IbcConnectionService<Ada
         Ok(Response::new(QueryClientConnectionsResponse {
             connection_paths: connection_ids,
             proof:            vec![],
-            proof_height:     None,
+            proof_height:     Some(self.proof_height(query_height)),
+        }))
+    }
+
+    async fn connection_client_state(
+        &self,
+        _request: Request<QueryConnectionClientStateRequest>,
+    ) -> Result<Response<QueryConnectionClientStateResponse>, Status> {
+        Err(unsupported("connection_client_state"))
+    }
+
+    async fn connection_consensus_state(
+        &self,
+        request: Request<QueryConnectionConsensusStateRequest>,
+    ) -> Result<Response<QueryConnectionConsensusStateResponse>, Status> {
+        log::info!("Got connection consensus state request: {:?}", request);
+
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let conn_id = ConnectionId::from_str(&request.connection_id)
+            .map_err(|_| Status::invalid_argument("invalid connection id"))?;
+
+        let conn_path = path::ConnectionsPath(conn_id);
+        let conn = self
+            .connection_end_adapter
+            .get_connection_end(query_height, &conn_path)
+            .await
+            .map_err(|e| corrupt_entry(&conn_path, e))?
+            .ok_or_else(|| Status::not_found(format!("connection {} not found", conn_path.0)))?;
+
+        let path = path::ClientConsensusStatePath {
+            client_id: conn.client_id().clone(),
+            epoch:     request.revision_number,
+            height:    request.revision_height,
+        };
+        let consensus_state = self
+            .connection_end_adapter
+            .get_consensus_state(query_height, &path)
+            .await
+            .map_err(|e| corrupt_entry(&path, e))?
+            .ok_or_else(|| Status::not_found(format!("no consensus state at {}", path)))?;
+
+        Ok(Response::new(QueryConnectionConsensusStateResponse {
+            consensus_state: Some(consensus_state.into()),
+            client_id:       path.client_id.to_string(),
+            proof:           vec![],
+            proof_height:    Some(self.proof_height(query_height)),
+        }))
+    }
+}
+
+pub struct IbcChannelService<Adapter: IbcAdapter> {
+    channel_end_adapter:       Arc<Adapter>,
+    packet_commitment_adapter: Arc<Adapter>,
+    packet_ack_adapter:        Arc<Adapter>,
+    packet_receipt_adapter:    Arc<Adapter>,
+    revision_number:           u64,
+    max_query_paths:           usize,
+    snapshot_reads:            bool,
+}
+
+impl<Adapter: IbcAdapter> IbcChannelService<Adapter> {
+    pub fn new(adapter: Arc<Adapter>, revision_number: u64) -> Self {
+        Self {
+            channel_end_adapter: Arc::clone(&adapter),
+            packet_commitment_adapter: Arc::clone(&adapter),
+            packet_ack_adapter: Arc::clone(&adapter),
+            packet_receipt_adapter: Arc::clone(&adapter),
+            revision_number,
+            max_query_paths: DEFAULT_MAX_QUERY_PATHS,
+            snapshot_reads: false,
+        }
+    }
+
+    /// Serve the queries without a height on a snapshot of the latest
+    /// committed height, instead of the pending state. The several reads of a
+    /// query then see the same state, while at pending a block in execution
+    /// could write between them. It takes no effect on a store without
+    /// versions, see `IbcGrpcAdapter::is_versioned`, so it is disabled with
+    /// `DefaultIbcAdapter`.
+    pub fn with_snapshot_reads(mut self, snapshot_reads: bool) -> Self {
+        self.snapshot_reads = snapshot_reads;
+        self
+    }
+
+    /// Resolve the height a query is served at, which is the latest
+    /// committed height with snapshot reads when the query has none.
+    fn query_height(&self, metadata: &MetadataMap) -> Result<Height, Status> {
+        resolve_query_height(
+            metadata,
+            self.channel_end_adapter.as_ref(),
+            self.default_height(),
+        )
+    }
+
+    /// A store without versions has no snapshot to read, so the queries stay
+    /// at pending even with snapshot reads.
+    fn default_height(&self) -> Height {
+        if self.snapshot_reads && self.channel_end_adapter.is_versioned() {
+            Height::Stable(self.channel_end_adapter.current_height())
+        } else {
+            Height::Pending
+        }
+    }
+
+    /// The height stamped on a response, which is the height its data is
+    /// read at.
+    fn response_height(&self, height: Height) -> RawHeight {
+        response_height(
+            height,
+            self.channel_end_adapter.current_height(),
+            self.revision_number,
+        )
+    }
+
+    fn proof_height(&self, height: Height) -> RawHeight {
+        proof_height(self.response_height(height))
+    }
+
+    /// Set the max number of store paths a single query is allowed to scan.
+    pub fn with_max_query_paths(mut self, max_query_paths: usize) -> Self {
+        self.max_query_paths = max_query_paths;
+        self
+    }
+
+    /// The max number of paths a paginated query scans. A query which asks
+    /// for a page is not limited, since it only reads the values of the page.
+    fn scan_limit(&self, page: &Option<PageRequest>) -> Option<usize> {
+        match page {
+            Some(_) => None,
+            None => Some(self.max_query_paths),
+        }
+    }
+}
+
+/// The error of a stored entry which fails to be read, named by its path.
+fn corrupt_entry<P: std::fmt::Display, E: std::fmt::Display>(path: &P, e: E) -> Status {
+    Status::data_loss(format!("corrupt entry at {}: {}", path, e))
+}
+
+/// A listed path without a value, such as a partially written entry, is
+/// skipped instead of failing the whole query.
+fn skip_missing_entry<P: std::fmt::Display>(path: &P) {
+    log::warn!("[ibc] skip the entry at {} which has no value", path);
+}
+
+/// The minimum timestamp of the consensus states asked by the
+/// `MIN_TIMESTAMP_METADATA` of a query, or zero without it.
+fn min_timestamp(metadata: &MetadataMap) -> Result<u64, Status> {
+    match metadata.get(MIN_TIMESTAMP_METADATA) {
+        Some(timestamp) => timestamp
+            .to_str()
+            .ok()
+            .and_then(|timestamp| timestamp.parse::<u64>().ok())
+            .ok_or_else(|| Status::invalid_argument("invalid min timestamp")),
+        None => Ok(0),
+    }
+}
+
+/// Resolve the height a query is served at from its `QUERY_HEIGHT_METADATA`.
+/// A query without the height, or with zero like the Cosmos SDK, is served at
+/// `default`. A height below the oldest retained one is rejected, so that the
+/// relayer can retry at a retained height, and so is a height not committed
+/// yet. A store without versions can't serve any height but the latest state,
+/// so it rejects every height instead of answering with the wrong state. The
+/// oldest retained height is read from the storage on each query, since the
+/// blocks may be pruned while the node is running.
+fn resolve_query_height<Adapter: IbcAdapter>(
+    metadata: &MetadataMap,
+    adapter: &Adapter,
+    default: Height,
+) -> Result<Height, Status> {
+    let height = match metadata.get(QUERY_HEIGHT_METADATA) {
+        Some(height) => height
+            .to_str()
+            .ok()
+            .and_then(|height| height.parse::<u64>().ok())
+            .ok_or_else(|| Status::invalid_argument("invalid query height"))?,
+        None => return Ok(default),
+    };
+
+    if height == 0 {
+        return Ok(default);
+    }
+
+    if !adapter.is_versioned() {
+        return Err(Status::failed_precondition(format!(
+            "height {} can't be queried, the store keeps only the latest state and the queries at a height are disabled",
+            height
+        )));
+    }
+
+    let oldest_height = adapter
+        .oldest_height()
+        .map_err(|e| Status::internal(format!("read the oldest height: {}", e)))?;
+    if height < oldest_height {
+        return Err(Status::out_of_range(format!(
+            "height {} is pruned, the oldest retained height is {}",
+            height, oldest_height
+        )));
+    }
+
+    let current_height = adapter.current_height();
+    if height > current_height {
+        return Err(Status::out_of_range(format!(
+            "height {} is above the latest height {}",
+            height, current_height
+        )));
+    }
+
+    Ok(Height::Stable(height))
+}
+
+/// The height stamped on a response, which is the height its data is read at.
+fn response_height(height: Height, current_height: u64, revision_number: u64) -> RawHeight {
+    let revision_height = match height {
+        Height::Pending => current_height,
+        Height::Stable(height) => height,
+    };
+
+    RawHeight {
+        revision_number,
+        revision_height,
+    }
+}
+
+/// The height a proof of the state at the `response_height` is verified at.
+/// Following the IBC convention, the state written by a block is committed by
+/// the app hash in the header of the next block, so the counterparty verifies
+/// the proof against its consensus state at the height right above the
+/// queried one.
+fn proof_height(response_height: RawHeight) -> RawHeight {
+    RawHeight {
+        revision_number: response_height.revision_number,
+        revision_height: response_height.revision_height + 1,
+    }
+}
+
+/// The proof of the entry at `path` at `height`, served along with the entry
+/// for the counterparty to verify it. The entry is served with an empty proof
+/// by a store which can't prove it, so that the queries still answer.
+fn proof_of<Adapter, P>(adapter: &Adapter, height: Height, path: &P) -> Result<Vec<u8>, Status>
+where
+    Adapter: IbcAdapter,
+    P: std::fmt::Display,
+{
+    let store_path: Path = path
+        .to_string()
+        .try_into()
+        .map_err(|e| Status::internal(format!("{:?}", e)))?;
+    let proof = adapter
+        .get_proof(height, &store_path)
+        .map_err(Status::internal)?;
+
+    Ok(proof.unwrap_or_default())
+}
+
+fn skip_unexpected_path(path: &str) {
+    log::warn!("[ibc] skip the unexpected path {}", path);
+}
+
+/// The answer of the methods which are not supported yet. The hits are
+/// counted per method to find the ones the relayers rely on.
+fn unsupported(method: &str) -> Status {
+    IBC_GRPC_UNSUPPORTED_COUNTER_VEC
+        .with_label_values(&[method])
+        .inc();
+    log::warn!("[ibc] unsupported grpc method {}", method);
+    Status::unimplemented(format!("{} is not supported yet", method))
+}
+
+/// Parse the port, channel and sequence which identify a packet. The
+/// sequences start from 1.
+fn packet_key(
+    port_id: &str,
+    channel_id: &str,
+    sequence: u64,
+) -> Result<(PortId, ChannelId, Sequence), Status> {
+    let port_id =
+        PortId::from_str(port_id).map_err(|_| Status::invalid_argument("invalid port id"))?;
+    let channel_id = ChannelId::from_str(channel_id)
+        .map_err(|_| Status::invalid_argument("invalid channel id"))?;
+    if sequence == 0 {
+        return Err(Status::invalid_argument("invalid sequence"));
+    }
+
+    Ok((port_id, channel_id, Sequence::from(sequence)))
+}
+
+/// Slice a page out of `items`, which are sorted by their keys. A page starts
+/// at the `key` of the request, which is the `next_key` of the previous page,
+/// or else at the `offset`. A request without pagination gets the first
+/// `DEFAULT_PAGE_LIMIT` items, and the `next_key` is empty on the last page.
+fn paginate<T, K, F>(items: Vec<T>, page: Option<PageRequest>, key_of: F) -> (Vec<T>, PageResponse)
+where
+    K: AsRef<[u8]>,
+    F: Fn(&T) -> K,
+{
+    let page = page.unwrap_or_default();
+    let limit = if page.limit == 0 {
+        DEFAULT_PAGE_LIMIT
+    } else {
+        page.limit
+    };
+    let total = items.len() as u64;
+
+    // The item of the key may be gone since the previous page, so the page
+    // starts at the first key not below it.
+    let start = if page.key.is_empty() {
+        page.offset as usize
+    } else {
+        items
+            .iter()
+            .position(|item| key_of(item).as_ref() >= page.key.as_slice())
+            .unwrap_or(items.len())
+    };
+
+    let mut items = items.into_iter().skip(start);
+    let page_items = items.by_ref().take(limit as usize).collect();
+    let next_key = items
+        .next()
+        .map(|item| key_of(&item).as_ref().to_vec())
+        .unwrap_or_default();
+
+    (page_items, PageResponse {
+        next_key,
+        total: if page.count_total { total } else { 0 },
+    })
+}
+
+/// Paginate the packet paths of a channel by their sequences, so that only the
+/// values of a page are read. The paths are listed in the order of their keys,
+/// where `10` is before `9`, so they are sorted first for the cursors to be
+/// deterministic.
+fn paginate_packet_paths<P, F>(
+    mut paths: Vec<P>,
+    page: Option<PageRequest>,
+    sequence_of: F,
+) -> (Vec<P>, PageResponse)
+where
+    F: Fn(&P) -> Sequence,
+{
+    paths.sort_unstable_by_key(|path| u64::from(sequence_of(path)));
+    paginate(paths, page, |path: &P| {
+        u64::from(sequence_of(path)).to_be_bytes()
+    })
+}
+
+/// The page key of a height, whose bytes are in the order of the heights.
+fn height_page_key(revision_number: u64, revision_height: u64) -> Vec<u8> {
+    [revision_number.to_be_bytes(), revision_height.to_be_bytes()].concat()
+}
+
+/// List the ids parsed from the paths with the prefix, skipping the paths of
+/// other entries under the same prefix.
+fn list_ids<Adapter, T, F>(adapter: &Adapter, prefix: &str, id_of: F) -> Result<Vec<T>, Status>
+where
+    Adapter: IbcAdapter,
+    F: Fn(IbcPath) -> Option<T>,
+{
+    let prefix: Path = prefix
+        .to_owned()
+        .try_into()
+        .map_err(|e| Status::invalid_argument(format!("{:?}", e)))?;
+
+    let ids = adapter
+        .get_paths_by_prefix(&prefix)
+        .map_err(Status::internal)?
+        .into_iter()
+        .filter_map(|path| id_of(path.try_into().ok()?))
+        .collect();
+
+    Ok(ids)
+}
+
+/// Scan the paths with the prefix lazily and keep the ones selected by
+/// `select`, so that only the selected paths are held in memory. The query
+/// fails once more than `limit` paths are scanned, if there is a limit. The
+/// scan is done before returning, since the iterator of the adapter may not be
+/// held across an await.
+fn scan_paths_by_prefix<Adapter, T, F>(
+    adapter: &Adapter,
+    prefix: &Path,
+    limit: Option<usize>,
+    mut select: F,
+) -> Result<Vec<T>, Status>
+where
+    Adapter: IbcAdapter,
+    F: FnMut(Path) -> Option<T>,
+{
+    let mut selected = Vec::new();
+    let paths = adapter
+        .iter_paths_by_prefix(prefix)
+        .map_err(Status::internal)?;
+
+    for (i, path) in paths.enumerate() {
+        let path = path.map_err(Status::internal)?;
+        if let Some(limit) = limit.filter(|limit| i >= *limit) {
+            return Err(Status::resource_exhausted(format!(
+                "more than {} paths under prefix {}",
+                limit, prefix
+            )));
+        }
+        selected.extend(select(path));
+    }
+
+    Ok(selected)
+}
+
+/// The request of the aggregate channel query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryChannelOverviewRequest {
+    #[prost(string, tag = "1")]
+    pub port_id:    String,
+    #[prost(string, tag = "2")]
+    pub channel_id: String,
+}
+
+/// Everything a relayer needs to relay on a channel: the channel end, its
+/// connection end, the client state backing the connection and the consensus
+/// state at the latest height of the client.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryChannelOverviewResponse {
+    #[prost(message, optional, tag = "1")]
+    pub channel:         Option<RawChannel>,
+    #[prost(message, optional, tag = "2")]
+    pub connection:      Option<RawConnectionEnd>,
+    #[prost(message, optional, tag = "3")]
+    pub client_state:    Option<IdentifiedClientState>,
+    #[prost(message, optional, tag = "4")]
+    pub consensus_state: Option<Any>,
+    #[prost(message, optional, tag = "5")]
+    pub height:          Option<RawHeight>,
+}
+
+/// The request of the next send sequence query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryNextSequenceSendRequest {
+    #[prost(string, tag = "1")]
+    pub port_id:    String,
+    #[prost(string, tag = "2")]
+    pub channel_id: String,
+}
+
+/// The sequence the next packet sent on the channel will get.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryNextSequenceSendResponse {
+    #[prost(uint64, tag = "1")]
+    pub next_sequence_send: u64,
+    #[prost(bytes = "vec", tag = "2")]
+    pub proof:              Vec<u8>,
+    #[prost(message, optional, tag = "3")]
+    pub proof_height:       Option<RawHeight>,
+}
+
+/// The request of the full packet query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryPacketRequest {
+    #[prost(string, tag = "1")]
+    pub port_id:    String,
+    #[prost(string, tag = "2")]
+    pub channel_id: String,
+    #[prost(uint64, tag = "3")]
+    pub sequence:   u64,
+}
+
+/// A sent packet with its data, as long as its commitment is pending.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryPacketResponse {
+    #[prost(message, optional, tag = "1")]
+    pub packet: Option<RawPacket>,
+    #[prost(message, optional, tag = "2")]
+    pub height: Option<RawHeight>,
+}
+
+/// Whether an acknowledgement is a success or an error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AckStatus {
+    Success,
+    Error,
+}
+
+/// Classify acknowledgement bytes in the standard ICS-04 shape, which is a
+/// JSON object with either a `result` or an `error` field. Returns `None` for
+/// bytes not in that shape.
+pub fn classify_acknowledgement(data: &[u8]) -> Option<AckStatus> {
+    let value: serde_json::Value = serde_json::from_slice(data).ok()?;
+    let object = value.as_object()?;
+
+    match (object.contains_key("result"), object.contains_key("error")) {
+        (true, false) => Some(AckStatus::Success),
+        (false, true) => Some(AckStatus::Error),
+        _ => None,
+    }
+}
+
+/// The `PacketAcknowledgements` response with the status of each
+/// acknowledgement at the same index, which is `None` when the acknowledgement
+/// itself is not stored or not in the standard shape.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryPacketAcknowledgementsWithStatusResponse {
+    pub acknowledgements: Vec<PacketState>,
+    pub statuses:         Vec<Option<AckStatus>>,
+    pub height:           Option<RawHeight>,
+}
+
+impl<Adapter: IbcAdapter + 'static> IbcChannelService<Adapter> {
+    /// PacketAcknowledgementsWithStatus returns the acknowledgement
+    /// commitments like `PacketAcknowledgements`, along with whether each
+    /// acknowledgement is a success or an error. The commitments only keep a
+    /// hash, so the statuses are read from the acknowledgements written next
+    /// to them. The served `PacketAcknowledgements` has no field for the
+    /// statuses, so this one is only for the callers in the node.
+    pub async fn packet_acknowledgements_with_status(
+        &self,
+        request: Request<QueryPacketAcknowledgementsRequest>,
+    ) -> Result<Response<QueryPacketAcknowledgementsWithStatusResponse>, Status> {
+        let resp = self.packet_acknowledgements(request).await?.into_inner();
+        let mut statuses = Vec::with_capacity(resp.acknowledgements.len());
+        for state in resp.acknowledgements.iter() {
+            let key = packet_key(&state.port_id, &state.channel_id, state.sequence)?;
+            let ack = self
+                .packet_ack_adapter
+                .get_acknowledgement(Context::new(), &key)
+                .map_err(Status::data_loss)?;
+            statuses.push(ack.and_then(|ack| classify_acknowledgement(&ack)));
+        }
+
+        Ok(Response::new(
+            QueryPacketAcknowledgementsWithStatusResponse {
+                acknowledgements: resp.acknowledgements,
+                statuses,
+                height: resp.height,
+            },
+        ))
+    }
+
+    /// Read a channel end and the connection end of its first hop, which
+    /// leads to the client of the channel.
+    async fn channel_connection(
+        &self,
+        height: Height,
+        port_id: &str,
+        channel_id: &str,
+    ) -> Result<(ChannelEnd, ConnectionEnd), Status> {
+        let port_id =
+            PortId::from_str(port_id).map_err(|_| Status::invalid_argument("invalid port id"))?;
+        let channel_id = ChannelId::from_str(channel_id)
+            .map_err(|_| Status::invalid_argument("invalid channel id"))?;
+
+        let channel_end = self
+            .channel_end_adapter
+            .get_channel_end(height, &path::ChannelEndsPath(port_id, channel_id))
+            .await
+            .map_err(Status::data_loss)?
+            .ok_or_else(|| Status::not_found("channel not found"))?;
+        let conn_id = channel_end
+            .connection_hops()
+            .first()
+            .cloned()
+            .ok_or_else(|| Status::failed_precondition("channel has no connection hop"))?;
+
+        let connection_end = self
+            .channel_end_adapter
+            .get_connection_end(height, &path::ConnectionsPath(conn_id))
+            .await
+            .map_err(Status::data_loss)?
+            .ok_or_else(|| Status::not_found("connection not found"))?;
+
+        Ok((channel_end, connection_end))
+    }
+
+    /// ChannelOverview composes the channel, connection, client state and
+    /// consensus state queries into a single call, with all of them read at
+    /// the same height. It is served as `axon.ibc.channel.v1.Query`.
+    pub async fn channel_overview(
+        &self,
+        request: Request<QueryChannelOverviewRequest>,
+    ) -> Result<Response<QueryChannelOverviewResponse>, Status> {
+        let height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let (channel_end, connection_end) = self
+            .channel_connection(height, &request.port_id, &request.channel_id)
+            .await?;
+        let client_id = connection_end.client_id().clone();
+
+        let client_state = self
+            .channel_end_adapter
+            .get_client_state(height, &path::ClientStatePath(client_id.clone()))
+            .await
+            .map_err(Status::data_loss)?
+            .ok_or_else(|| Status::not_found("client state not found"))?;
+        let latest_height = client_state.latest_height();
+
+        let consensus_state = self
+            .channel_end_adapter
+            .get_consensus_state(height, &path::ClientConsensusStatePath {
+                client_id: client_id.clone(),
+                epoch:     latest_height.revision_number(),
+                height:    latest_height.revision_height(),
+            })
+            .await
+            .map_err(Status::data_loss)?;
+
+        Ok(Response::new(QueryChannelOverviewResponse {
+            channel:         Some(channel_end.into()),
+            connection:      Some(connection_end.into()),
+            client_state:    Some(IdentifiedClientState {
+                client_id:    client_id.to_string(),
+                client_state: Some(client_state.into()),
+            }),
+            consensus_state: consensus_state.map(|c| c.into()),
+            height:          Some(self.response_height(height)),
+        }))
+    }
+
+    /// Packet returns the full packet of a pending commitment, so that the
+    /// packet data doesn't have to be dug out of the send events. It is served
+    /// as `axon.ibc.channel.v1.Query`, since the ICS protos have no packet
+    /// query. The packet is deleted along with its commitment, so a packet
+    /// acknowledged since the query height is not found.
+    pub async fn packet(
+        &self,
+        request: Request<QueryPacketRequest>,
+    ) -> Result<Response<QueryPacketResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let (port_id, channel_id, sequence) =
+            packet_key(&request.port_id, &request.channel_id, request.sequence)?;
+
+        let path = path::CommitmentsPath {
+            port_id,
+            channel_id,
+            sequence,
+        };
+        // A deleted commitment is left as an empty value.
+        self.packet_commitment_adapter
+            .get_packet_commitment(query_height, &path)
+            .map_err(|e| corrupt_entry(&path, e))?
+            .map(|commitment| commitment.into_vec())
+            .filter(|data| !data.is_empty())
+            .ok_or_else(|| Status::not_found(format!("no packet commitment at {}", path)))?;
+        let packet = self
+            .channel_end_adapter
+            .get_packet(
+                Context::new(),
+                &(path.port_id, path.channel_id, path.sequence),
+            )
+            .map_err(Status::data_loss)?
+            .ok_or_else(|| Status::not_found("packet not found"))?;
+
+        Ok(Response::new(QueryPacketResponse {
+            packet: Some(packet.into()),
+            height: Some(self.response_height(query_height)),
         }))
     }
 
-    async fn connection_client_state(
+    /// ChannelIds returns the port and channel ids of all the channels like
+    /// `Channels`, without reading the channel ends.
+    pub async fn channel_ids(
         &self,
-        _request: Request<QueryConnectionClientStateRequest>,
-    ) -> Result<Response<QueryConnectionClientStateResponse>, Status> {
-        todo!()
+        _request: Request<QueryIdsRequest>,
+    ) -> Result<Response<QueryChannelIdsResponse>, Status> {
+        let channels = list_ids(
+            self.channel_end_adapter.as_ref(),
+            "channelEnds/ports",
+            |path| match path {
+                IbcPath::ChannelEnds(path::ChannelEndsPath(port_id, channel_id)) => {
+                    Some(PortChannelId {
+                        port_id:    port_id.to_string(),
+                        channel_id: channel_id.to_string(),
+                    })
+                }
+                _ => None,
+            },
+        )?;
+
+        Ok(Response::new(QueryChannelIdsResponse { channels }))
     }
 
-    async fn connection_consensus_state(
+    /// NextSequenceSend returns the next send sequence for a given channel. A
+    /// freshly opened channel starts with sequence 1. ibc-go has no such
+    /// query, so it is served as `axon.ibc.channel.v1.Query`.
+    pub async fn next_sequence_send(
         &self,
-        _request: Request<QueryConnectionConsensusStateRequest>,
-    ) -> Result<Response<QueryConnectionConsensusStateResponse>, Status> {
-        todo!()
-    }
-}
+        request: Request<QueryNextSequenceSendRequest>,
+    ) -> Result<Response<QueryNextSequenceSendResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let port_id = PortId::from_str(&request.port_id)
+            .map_err(|_| Status::invalid_argument("invalid port id"))?;
+        let channel_id = ChannelId::from_str(&request.channel_id)
+            .map_err(|_| Status::invalid_argument("invalid channel id"))?;
 
-pub struct IbcChannelService<Adapter: IbcAdapter> {
-    channel_end_adapter:       Arc<Adapter>,
-    packet_commitment_adapter: Arc<Adapter>,
-    packet_ack_adapter:        Arc<Adapter>,
-    packet_receipt_adapter:    Arc<Adapter>,
-}
+        let path = path::SeqSendsPath(port_id, channel_id);
+        let sequence = self
+            .channel_end_adapter
+            .get_seq_sends(query_height, &path)
+            .map_err(Status::data_loss)?;
 
-impl<Adapter: IbcAdapter> IbcChannelService<Adapter> {
-    pub fn new(adapter: Arc<Adapter>) -> Self {
-        Self {
-            channel_end_adapter:       Arc::clone(&adapter),
-            packet_commitment_adapter: Arc::clone(&adapter),
-            packet_ack_adapter:        Arc::clone(&adapter),
-            packet_receipt_adapter:    Arc::clone(&adapter),
-        }
+        let sequence = match sequence {
+            Some(sequence) => sequence,
+            None => {
+                let channel_path = path::ChannelEndsPath(path.0.clone(), path.1.clone());
+                self.channel_end_adapter
+                    .get_channel_end(query_height, &channel_path)
+                    .await
+                    .map_err(Status::data_loss)?
+                    .ok_or_else(|| Status::not_found("channel not found"))?;
+                Sequence::from(1)
+            }
+        };
+
+        Ok(Response::new(QueryNextSequenceSendResponse {
+            next_sequence_send: sequence.into(),
+            proof:              proof_of(&*self.channel_end_adapter, query_height, &path)?,
+            proof_height:       Some(self.proof_height(query_height)),
+        }))
     }
 }
 
@@ -402,66 +2136,76 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
         &self,
         request: Request<QueryChannelRequest>,
     ) -> Result<Response<QueryChannelResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let request = request.into_inner();
         let port_id = PortId::from_str(&request.port_id)
             .map_err(|_| Status::invalid_argument("invalid port id"))?;
         let channel_id = ChannelId::from_str(&request.channel_id)
             .map_err(|_| Status::invalid_argument("invalid channel id"))?;
 
+        let path = path::ChannelEndsPath(port_id, channel_id);
         let channel_opt = self
             .channel_end_adapter
-            .get_channel_end(Height::Pending, &path::ChannelEndsPath(port_id, channel_id))
+            .get_channel_end(query_height, &path)
             .await
             .map_err(Status::data_loss)?
             .map(|channel_end: ChannelEnd| channel_end.into());
 
         Ok(Response::new(QueryChannelResponse {
             channel:      channel_opt,
-            proof:        vec![],
-            proof_height: None,
+            proof:        proof_of(&*self.channel_end_adapter, query_height, &path)?,
+            proof_height: Some(self.proof_height(query_height)),
         }))
     }
 
     /// Channels queries all the IBC channels of a chain.
     async fn channels(
         &self,
-        _request: Request<QueryChannelsRequest>,
+        request: Request<QueryChannelsRequest>,
     ) -> Result<Response<QueryChannelsResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let channel_path_prefix: Path = String::from("channelEnds/ports")
             .try_into()
             .expect("'channelEnds/ports' expected to be a valid Path");
 
-        let channel_paths = self
+        let mut channel_paths = Vec::new();
+        for path in self
             .channel_end_adapter
             .get_paths_by_prefix(&channel_path_prefix)
-            .map_err(Status::internal)?;
+            .map_err(Status::internal)?
+        {
+            let raw_path = path.to_string();
+            match path.try_into() {
+                Ok(IbcPath::ChannelEnds(channels_path)) => channel_paths.push(channels_path),
+                _ => skip_unexpected_path(&raw_path),
+            }
+        }
+        channel_paths.sort_unstable_by_key(|path| path.to_string());
+        let (channel_paths, pagination) = paginate(
+            channel_paths,
+            request.into_inner().pagination,
+            |path: &path::ChannelEndsPath| path.to_string(),
+        );
         let mut identified_channels = Vec::with_capacity(channel_paths.len());
 
-        for path in channel_paths.into_iter() {
-            match path.try_into() {
-                Ok(IbcPath::ChannelEnds(channels_path)) => {
-                    let channel_end = self
-                        .channel_end_adapter
-                        .get_channel_end(Height::Pending, &channels_path)
-                        .await
-                        .map_err(Status::data_loss)?
-                        .expect("channel path returned by get_keys() had no associated channel");
-                    identified_channels.push(
-                        IdentifiedChannelEnd::new(channels_path.0, channels_path.1, channel_end)
-                            .into(),
-                    );
-                }
-                _ => panic!("unexpected path"),
+        for channels_path in channel_paths.into_iter() {
+            match self
+                .channel_end_adapter
+                .get_channel_end(query_height, &channels_path)
+                .await
+                .map_err(|e| corrupt_entry(&channels_path, e))?
+            {
+                Some(channel_end) => identified_channels.push(
+                    IdentifiedChannelEnd::new(channels_path.0, channels_path.1, channel_end).into(),
+                ),
+                None => skip_missing_entry(&channels_path),
             }
         }
 
         Ok(Response::new(QueryChannelsResponse {
             channels:   identified_channels,
-            pagination: None,
-            height:     Some(RawHeight {
-                revision_number: CHAIN_REVISION_NUMBER,
-                revision_height: self.channel_end_adapter.current_height(),
-            }),
+            pagination: Some(pagination),
+            height:     Some(self.response_height(query_height)),
         }))
     }
 
@@ -471,43 +2215,45 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
         &self,
         request: Request<QueryConnectionChannelsRequest>,
     ) -> Result<Response<QueryConnectionChannelsResponse>, Status> {
-        let conn_id = ConnectionId::from_str(&request.get_ref().connection)
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let conn_id = ConnectionId::from_str(&request.connection)
             .map_err(|_| Status::invalid_argument("invalid connection id"))?;
 
-        let path = "channelEnds"
-            .to_owned()
-            .try_into()
-            .expect("'commitments/ports' expected to be a valid Path");
-
-        let keys = self
+        let mut channel_paths = self
             .channel_end_adapter
-            .get_paths_by_prefix(&path)
-            .map_err(Status::internal)?;
-        let mut identified_channels = Vec::with_capacity(keys.len());
+            .get_connection_channels(Context::new(), &conn_id)
+            .map_err(Status::data_loss)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(port_id, channel_id)| path::ChannelEndsPath(port_id, channel_id))
+            .collect::<Vec<_>>();
+        // The pages are cut like the ones of `Channels`, by the channel paths.
+        channel_paths.sort_unstable_by_key(|path| path.to_string());
+        let (channel_paths, pagination) = paginate(
+            channel_paths,
+            request.pagination,
+            |path: &path::ChannelEndsPath| path.to_string(),
+        );
+        let mut identified_channels = Vec::with_capacity(channel_paths.len());
 
-        for path in keys.into_iter() {
-            if let Ok(IbcPath::ChannelEnds(path)) = path.try_into() {
-                if let Some(channel_end) = self
-                    .channel_end_adapter
-                    .get_channel_end(Height::Pending, &path)
-                    .await
-                    .map_err(Status::data_loss)?
-                {
-                    if channel_end.connection_hops.first() == Some(&conn_id) {
-                        identified_channels
-                            .push(IdentifiedChannelEnd::new(path.0, path.1, channel_end).into());
-                    }
-                }
+        // The channels indexed after the query height are not found at it.
+        for path in channel_paths.into_iter() {
+            if let Some(channel_end) = self
+                .channel_end_adapter
+                .get_channel_end(query_height, &path)
+                .await
+                .map_err(Status::data_loss)?
+            {
+                identified_channels
+                    .push(IdentifiedChannelEnd::new(path.0, path.1, channel_end).into());
             }
         }
 
         Ok(Response::new(QueryConnectionChannelsResponse {
             channels:   identified_channels,
-            pagination: None,
-            height:     Some(RawHeight {
-                revision_number: CHAIN_REVISION_NUMBER,
-                revision_height: self.channel_end_adapter.current_height(),
-            }),
+            pagination: Some(pagination),
+            height:     Some(self.response_height(query_height)),
         }))
     }
 
@@ -517,23 +2263,69 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
         &self,
         _request: Request<QueryChannelClientStateRequest>,
     ) -> Result<Response<QueryChannelClientStateResponse>, Status> {
-        todo!()
+        Err(unsupported("channel_client_state"))
     }
 
     /// ChannelConsensusState queries for the consensus state for the channel
     /// associated with the provided channel identifiers.
     async fn channel_consensus_state(
         &self,
-        _request: Request<QueryChannelConsensusStateRequest>,
+        request: Request<QueryChannelConsensusStateRequest>,
     ) -> Result<Response<QueryChannelConsensusStateResponse>, Status> {
-        todo!()
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+
+        let (_, connection_end) = self
+            .channel_connection(query_height, &request.port_id, &request.channel_id)
+            .await?;
+        let path = path::ClientConsensusStatePath {
+            client_id: connection_end.client_id().clone(),
+            epoch:     request.revision_number,
+            height:    request.revision_height,
+        };
+        let consensus_state = self
+            .channel_end_adapter
+            .get_consensus_state(query_height, &path)
+            .await
+            .map_err(Status::data_loss)?
+            .ok_or_else(|| Status::not_found(format!("no consensus state at {}", path)))?;
+
+        Ok(Response::new(QueryChannelConsensusStateResponse {
+            consensus_state: Some(consensus_state.into()),
+            client_id:       path.client_id.to_string(),
+            proof:           vec![],
+            proof_height:    Some(self.proof_height(query_height)),
+        }))
     }
 
     async fn packet_commitment(
         &self,
-        _request: Request<QueryPacketCommitmentRequest>,
+        request: Request<QueryPacketCommitmentRequest>,
     ) -> Result<Response<QueryPacketCommitmentResponse>, Status> {
-        todo!()
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let (port_id, channel_id, sequence) =
+            packet_key(&request.port_id, &request.channel_id, request.sequence)?;
+
+        let path = path::CommitmentsPath {
+            port_id,
+            channel_id,
+            sequence,
+        };
+        // A deleted commitment is left as an empty value.
+        let commitment = self
+            .packet_commitment_adapter
+            .get_packet_commitment(query_height, &path)
+            .map_err(|e| corrupt_entry(&path, e))?
+            .map(|commitment| commitment.into_vec())
+            .filter(|data| !data.is_empty())
+            .ok_or_else(|| Status::not_found(format!("no packet commitment at {}", path)))?;
+
+        Ok(Response::new(QueryPacketCommitmentResponse {
+            commitment,
+            proof: proof_of(&*self.packet_commitment_adapter, query_height, &path)?,
+            proof_height: Some(self.proof_height(query_height)),
+        }))
     }
 
     /// PacketCommitments returns all the packet commitments hashes associated
@@ -542,43 +2334,49 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
         &self,
         request: Request<QueryPacketCommitmentsRequest>,
     ) -> Result<Response<QueryPacketCommitmentsResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let request = request.into_inner();
         let port_id = PortId::from_str(&request.port_id)
             .map_err(|_| Status::invalid_argument("invalid port id"))?;
         let channel_id = ChannelId::from_str(&request.channel_id)
             .map_err(|_| Status::invalid_argument("invalid channel id"))?;
 
-        let commitment_paths = {
-            let prefix: Path = String::from("commitments/ports")
-                .try_into()
-                .expect("'commitments/ports' expected to be a valid Path");
-            self.packet_commitment_adapter
-                .get_paths_by_prefix(&prefix)
-                .map_err(Status::internal)?
-        };
-
-        let matching_commitment_paths = |path: Path| -> Option<path::CommitmentsPath> {
-            match path.try_into() {
-                Ok(IbcPath::Commitments(p))
-                    if p.port_id == port_id && p.channel_id == channel_id =>
-                {
-                    Some(p)
-                }
+        // Only the paths of the channel are scanned. A requested page is
+        // served however many paths the channel has, since only the values of
+        // the page are read.
+        let prefix: Path = format!(
+            "commitments/ports/{}/channels/{}/sequences",
+            port_id, channel_id
+        )
+        .try_into()
+        .map_err(|_| Status::invalid_argument("invalid commitments path"))?;
+        let limit = self.scan_limit(&request.pagination);
+        let commitment_paths = scan_paths_by_prefix(
+            self.packet_commitment_adapter.as_ref(),
+            &prefix,
+            limit,
+            |path| match path.try_into() {
+                Ok(IbcPath::Commitments(p)) => Some(p),
                 _ => None,
-            }
-        };
+            },
+        )?;
+        let (commitment_paths, pagination) =
+            paginate_packet_paths(commitment_paths, request.pagination, |path| path.sequence);
 
-        let mut packet_states = Vec::with_capacity(commitment_paths.len());
+        let mut packet_states = Vec::new();
 
-        for path in commitment_paths
-            .into_iter()
-            .filter_map(matching_commitment_paths)
-        {
-            let commitment = self
+        for path in commitment_paths {
+            let commitment = match self
                 .packet_commitment_adapter
-                .get_packet_commitment(Height::Pending, &path)
-                .map_err(Status::data_loss)?
-                .unwrap();
+                .get_packet_commitment(query_height, &path)
+                .map_err(|e| corrupt_entry(&path, e))?
+            {
+                Some(commitment) => commitment,
+                None => {
+                    skip_missing_entry(&path);
+                    continue;
+                }
+            };
             let data = commitment.into_vec();
             if !data.is_empty() {
                 packet_states.push(PacketState {
@@ -592,11 +2390,8 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
 
         Ok(Response::new(QueryPacketCommitmentsResponse {
             commitments: packet_states,
-            pagination:  None,
-            height:      Some(RawHeight {
-                revision_number: CHAIN_REVISION_NUMBER,
-                revision_height: self.packet_commitment_adapter.current_height(),
-            }),
+            pagination:  Some(pagination),
+            height:      Some(self.response_height(query_height)),
         }))
     }
 
@@ -604,16 +2399,59 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
     /// the queried chain
     async fn packet_receipt(
         &self,
-        _request: Request<QueryPacketReceiptRequest>,
+        request: Request<QueryPacketReceiptRequest>,
     ) -> Result<Response<QueryPacketReceiptResponse>, Status> {
-        todo!()
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let (port_id, channel_id, sequence) =
+            packet_key(&request.port_id, &request.channel_id, request.sequence)?;
+
+        let path = path::ReceiptsPath {
+            port_id,
+            channel_id,
+            sequence,
+        };
+        let received = self
+            .packet_receipt_adapter
+            .get_opt(query_height, &path)
+            .map_err(|e| corrupt_entry(&path, e))?
+            .is_some();
+
+        Ok(Response::new(QueryPacketReceiptResponse {
+            received,
+            proof: vec![],
+            proof_height: Some(self.proof_height(query_height)),
+        }))
     }
 
     async fn packet_acknowledgement(
         &self,
-        _request: Request<QueryPacketAcknowledgementRequest>,
+        request: Request<QueryPacketAcknowledgementRequest>,
     ) -> Result<Response<QueryPacketAcknowledgementResponse>, Status> {
-        todo!()
+        let query_height = self.query_height(request.metadata())?;
+        let request = request.into_inner();
+        let (port_id, channel_id, sequence) =
+            packet_key(&request.port_id, &request.channel_id, request.sequence)?;
+
+        let path = path::AcksPath {
+            port_id,
+            channel_id,
+            sequence,
+        };
+        let acknowledgement = self
+            .packet_ack_adapter
+            .get_acknowledgement_commitment(query_height, &path)
+            .await
+            .map_err(|e| corrupt_entry(&path, e))?
+            .map(|commitment| commitment.into_vec())
+            .filter(|data| !data.is_empty())
+            .ok_or_else(|| Status::not_found(format!("no acknowledgement at {}", path)))?;
+
+        Ok(Response::new(QueryPacketAcknowledgementResponse {
+            acknowledgement,
+            proof: proof_of(&*self.packet_ack_adapter, query_height, &path)?,
+            proof_height: Some(self.proof_height(query_height)),
+        }))
     }
 
     /// PacketAcknowledgements returns all the packet acknowledgements
@@ -622,38 +2460,37 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
         &self,
         request: Request<QueryPacketAcknowledgementsRequest>,
     ) -> Result<Response<QueryPacketAcknowledgementsResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let request = request.into_inner();
         let port_id = PortId::from_str(&request.port_id)
             .map_err(|_| Status::invalid_argument("invalid port id"))?;
         let channel_id = ChannelId::from_str(&request.channel_id)
             .map_err(|_| Status::invalid_argument("invalid channel id"))?;
 
-        let ack_paths = {
-            let prefix: Path = String::from("acks/ports")
-                .try_into()
-                .expect("'acks/ports' expected to be a valid Path");
-            self.packet_ack_adapter
-                .get_paths_by_prefix(&prefix)
-                .map_err(Status::internal)?
-        };
-
-        let matching_ack_paths = |path: Path| -> Option<path::AcksPath> {
-            match path.try_into() {
-                Ok(IbcPath::Acks(p)) if p.port_id == port_id && p.channel_id == channel_id => {
-                    Some(p)
-                }
+        let prefix: Path = format!("acks/ports/{}/channels/{}/sequences", port_id, channel_id)
+            .try_into()
+            .map_err(|_| Status::invalid_argument("invalid acks path"))?;
+        let limit = self.scan_limit(&request.pagination);
+        let ack_paths = scan_paths_by_prefix(
+            self.packet_ack_adapter.as_ref(),
+            &prefix,
+            limit,
+            |path| match path.try_into() {
+                Ok(IbcPath::Acks(p)) => Some(p),
                 _ => None,
-            }
-        };
+            },
+        )?;
+        let (ack_paths, pagination) =
+            paginate_packet_paths(ack_paths, request.pagination, |path| path.sequence);
 
-        let mut packet_states = Vec::with_capacity(ack_paths.len());
+        let mut packet_states = Vec::new();
 
-        for path in ack_paths.into_iter().filter_map(matching_ack_paths) {
+        for path in ack_paths {
             if let Some(commitment) = self
                 .packet_ack_adapter
-                .get_acknowledgement_commitment(Height::Pending, &path)
+                .get_acknowledgement_commitment(query_height, &path)
                 .await
-                .map_err(Status::data_loss)?
+                .map_err(|e| corrupt_entry(&path, e))?
             {
                 let data = commitment.into_vec();
                 if !data.is_empty() {
@@ -669,11 +2506,8 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
 
         Ok(Response::new(QueryPacketAcknowledgementsResponse {
             acknowledgements: packet_states,
-            pagination:       None,
-            height:           Some(RawHeight {
-                revision_number: CHAIN_REVISION_NUMBER,
-                revision_height: self.packet_ack_adapter.current_height(),
-            }),
+            pagination:       Some(pagination),
+            height:           Some(self.response_height(query_height)),
         }))
     }
 
@@ -687,6 +2521,7 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
         &self,
         request: Request<QueryUnreceivedPacketsRequest>,
     ) -> Result<Response<QueryUnreceivedPacketsResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let request = request.into_inner();
         let port_id = PortId::from_str(&request.port_id)
             .map_err(|_| Status::invalid_argument("invalid port id"))?;
@@ -704,7 +2539,7 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
                 };
                 let packet_receipt: Option<()> = self
                     .packet_receipt_adapter
-                    .get_opt(Height::Pending, &receipts_path)
+                    .get_opt(query_height, &receipts_path)
                     .ok()
                     .flatten();
                 packet_receipt.is_none()
@@ -713,10 +2548,7 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
 
         Ok(Response::new(QueryUnreceivedPacketsResponse {
             sequences: unreceived_sequences,
-            height:    Some(RawHeight {
-                revision_number: CHAIN_REVISION_NUMBER,
-                revision_height: self.packet_receipt_adapter.current_height(),
-            }),
+            height:    Some(self.response_height(query_height)),
         }))
     }
 
@@ -726,6 +2558,7 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
         &self,
         request: Request<QueryUnreceivedAcksRequest>,
     ) -> Result<Response<QueryUnreceivedAcksResponse>, Status> {
+        let query_height = self.query_height(request.metadata())?;
         let request = request.into_inner();
         let port_id = PortId::from_str(&request.port_id)
             .map_err(|_| Status::invalid_argument("invalid port id"))?;
@@ -746,7 +2579,7 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
                 };
 
                 self.packet_commitment_adapter
-                    .get_packet_commitment(Height::Pending, &commitments_path)
+                    .get_packet_commitment(query_height, &commitments_path)
                     .ok()
                     .flatten()
                     .is_some()
@@ -755,10 +2588,7 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
 
         Ok(Response::new(QueryUnreceivedAcksResponse {
             sequences: unreceived_sequences,
-            height:    Some(RawHeight {
-                revision_number: CHAIN_REVISION_NUMBER,
-                revision_height: self.packet_commitment_adapter.current_height(),
-            }),
+            height:    Some(self.response_height(query_height)),
         }))
     }
 
@@ -768,17 +2598,178 @@ impl<Adapter: IbcAdapter + 'static> ChannelQuery for IbcChannelService<Adapter>
         &self,
         _request: Request<QueryNextSequenceReceiveRequest>,
     ) -> Result<Response<QueryNextSequenceReceiveResponse>, Status> {
-        todo!()
+        Err(unsupported("next_sequence_receive"))
+    }
+}
+
+/// Log the lines of a msg handler output and attach them to the response
+/// metadata, so that the generated identifiers and the diagnostics are
+/// observable by the caller.
+fn handler_response<T>(handler: &str, message: T, log: &[String]) -> Response<T> {
+    let mut response = Response::new(message);
+
+    for line in log.iter() {
+        log::info!("[ibc] {}: {}", handler, line);
+        match line.parse::<MetadataValue<Ascii>>() {
+            Ok(value) => {
+                let _ = response.metadata_mut().append(HANDLER_LOG_METADATA, value);
+            }
+            Err(_) => log::warn!("[ibc] {}: log line is not valid metadata", handler),
+        }
+    }
+
+    response
+}
+
+/// The same as `handler_response`, with the events emitted by the handler.
+fn handler_response_with_events<T>(
+    handler: &str,
+    message: T,
+    output: &DispatchOutput,
+) -> Response<T> {
+    let mut response = handler_response(handler, message, &output.log);
+
+    for event in output.events.iter() {
+        let value = serde_json::to_string(event)
+            .ok()
+            .and_then(|json| json.parse::<MetadataValue<Ascii>>().ok());
+        match value {
+            Some(value) => {
+                let _ = response
+                    .metadata_mut()
+                    .append(HANDLER_EVENT_METADATA, value);
+            }
+            None => log::warn!("[ibc] {}: event is not valid metadata", handler),
+        }
+    }
+
+    response
+}
+
+/// Recover the guard of the context lock poisoned by a panicked handler. The
+/// handlers store their results only after a msg is verified, so one bad msg
+/// leaves the context usable, and must not fail every later call.
+pub(crate) fn recover_poisoned<G>(poisoned: PoisonError<G>) -> G {
+    log::warn!("[ibc] recover the context lock poisoned by a panicked handler");
+    poisoned.into_inner()
+}
+
+/// Acquire the read lock of the context, retrying with backoff while it is
+/// held by a writer. Give up with `unavailable` after `timeout`, or wait
+/// without bound if it is `None`.
+pub async fn read_with_timeout<T>(
+    lock: &RwLock<T>,
+    timeout: Option<Duration>,
+) -> Result<RwLockReadGuard<'_, T>, Status> {
+    acquire_with_timeout(|| lock.try_read(), timeout).await
+}
+
+/// The same as `read_with_timeout`, but for the write lock.
+pub async fn write_with_timeout<T>(
+    lock: &RwLock<T>,
+    timeout: Option<Duration>,
+) -> Result<RwLockWriteGuard<'_, T>, Status> {
+    acquire_with_timeout(|| lock.try_write(), timeout).await
+}
+
+async fn acquire_with_timeout<G, F>(try_lock: F, timeout: Option<Duration>) -> Result<G, Status>
+where
+    F: Fn() -> TryLockResult<G>,
+{
+    let start = Instant::now();
+    let mut backoff = LOCK_INITIAL_BACKOFF;
+
+    loop {
+        match try_lock() {
+            Ok(guard) => return Ok(guard),
+            Err(TryLockError::Poisoned(poisoned)) => return Ok(recover_poisoned(poisoned)),
+            Err(TryLockError::WouldBlock) => (),
+        }
+
+        if let Some(timeout) = timeout {
+            if start.elapsed() >= timeout {
+                return Err(Status::unavailable(format!(
+                    "ibc context is busy, lock not acquired in {:?}",
+                    timeout
+                )));
+            }
+        }
+
+        protocol::tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(LOCK_MAX_BACKOFF);
+    }
+}
+
+/// The status of a client msg rejected by its handler. A missing or frozen
+/// client is told apart from a msg which fails the verification.
+fn client_handler_error(handler: &str, e: Error) -> Status {
+    let message = format!("{}: {}", handler, e);
+    match e.detail() {
+        ClientErrorDetail::ClientNotFound(_) => Status::not_found(message),
+        ClientErrorDetail::ClientFrozen(_) => Status::failed_precondition(message),
+        _ => Status::invalid_argument(message),
+    }
+}
+
+/// The status of a client result the context fails to store. A consensus
+/// state the context rejects is a bad msg, any other failure is the store's.
+fn store_client_error(e: Error) -> Status {
+    let message = format!("store client result: {}", e);
+    match e.detail() {
+        ClientErrorDetail::HeaderVerificationFailure(_) => Status::invalid_argument(message),
+        _ => Status::internal(message),
     }
 }
 
+/// The number of the latest clients a create client msg is checked against
+/// for a duplicate.
+pub const CREATE_CLIENT_DEDUP_WINDOW: u64 = 16;
+
 pub struct IbcClientMsgService<Ctx: ClientReader + ClientKeeper> {
-    ctx: Arc<RwLock<Ctx>>,
+    ctx:                 Arc<RwLock<Ctx>>,
+    lock_timeout:        Option<Duration>,
+    dedup_create_client: bool,
 }
 
 impl<Ctx: ClientReader + ClientKeeper> IbcClientMsgService<Ctx> {
     pub fn new(ctx: Arc<RwLock<Ctx>>) -> Self {
-        Self { ctx }
+        Self {
+            ctx,
+            lock_timeout: None,
+            dedup_create_client: false,
+        }
+    }
+
+    /// Bound the wait for the context lock, see `read_with_timeout`.
+    pub fn with_lock_timeout(mut self, lock_timeout: Option<Duration>) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    /// Make `create_client` idempotent, so that a relayer retrying a create
+    /// client msg gets the client created by the first attempt instead of a
+    /// new one. Disabled by default.
+    pub fn with_create_client_dedup(mut self, dedup_create_client: bool) -> Self {
+        self.dedup_create_client = dedup_create_client;
+        self
+    }
+
+    /// Find a client among the latest `CREATE_CLIENT_DEDUP_WINDOW` ones which
+    /// has the same client state as the msg, and the same consensus state at
+    /// its latest height.
+    fn find_duplicate_client(&self, ctx: &Ctx, msg: &MsgCreateAnyClient) -> Option<ClientId> {
+        let client_counter = ctx.client_counter().ok()?;
+        let client_type = msg.client_state.client_type();
+        let height = msg.client_state.latest_height();
+
+        (client_counter.saturating_sub(CREATE_CLIENT_DEDUP_WINDOW)..client_counter)
+            .rev()
+            .filter_map(|counter| ClientId::new(client_type, counter).ok())
+            .find(|client_id| {
+                ctx.client_state(client_id).ok().as_ref() == Some(&msg.client_state)
+                    && ctx.consensus_state(client_id, height).ok().as_ref()
+                        == Some(&msg.consensus_state)
+            })
     }
 }
 
@@ -792,18 +2783,36 @@ impl<Ctx: ClientReader + ClientKeeper + Sync + Send + 'static> ClientMsg
         request: tonic::Request<MsgCreateClient>,
     ) -> Result<tonic::Response<MsgCreateClientResponse>, tonic::Status> {
         let raw = request.get_ref();
-        let msg = MsgCreateAnyClient::try_from(raw.clone()).unwrap();
+        let msg = MsgCreateAnyClient::try_from(raw.clone())
+            .map_err(|e| Status::invalid_argument(format!("invalid MsgCreateClient: {}", e)))?;
 
         let mut output: HandlerOutputBuilder<ClientResult> = HandlerOutput::builder();
 
-        // Construct this client's identifier
-        let mut ctx = self.ctx.write().unwrap();
-        let id_counter = ctx.client_counter().unwrap();
+        let mut ctx = write_with_timeout(&self.ctx, self.lock_timeout).await?;
+        if self.dedup_create_client {
+            if let Some(client_id) = self.find_duplicate_client(&ctx, &msg) {
+                let log = format!(
+                    "success: client {} is created already, skip the duplicate",
+                    client_id
+                );
+                return Ok(handler_response(
+                    "create_client",
+                    MsgCreateClientResponse {},
+                    &[log],
+                ));
+            }
+        }
+
+        // Construct this client's identifier. A failure to read the counter
+        // is one of the context rather than of the msg.
+        let id_counter = ctx
+            .client_counter()
+            .map_err(|e| Status::internal(format!("client counter: {}", e)))?;
         let client_id = ClientId::new(msg.client_state.client_type(), id_counter)
             .map_err(|e| {
                 Error::client_identifier_constructor(msg.client_state.client_type(), id_counter, e)
             })
-            .unwrap();
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
 
         output.log(format!(
             "success: generated new client identifier: {}",
@@ -820,40 +2829,495 @@ impl<Ctx: ClientReader + ClientKeeper + Sync + Send + 'static> ClientMsg
         });
 
         let event_attributes = Attributes {
+            height: ctx.host_height(),
             client_id,
-            ..Default::default()
+            client_type: msg.client_state.client_type(),
+            consensus_height: msg.client_state.latest_height(),
         };
         output.emit(IbcEvent::CreateClient(event_attributes.into()));
 
         // Apply the result to the context (host chain store).
-        ctx.store_client_result(result)
-            .map_err(|_v| tonic::Status::invalid_argument("store_client_result"))?;
-
-        let res = tonic::Response::<MsgCreateClientResponse>::new(MsgCreateClientResponse {});
-
-        Ok(res)
+        let output = output.with_result(result);
+        ctx.store_client_result(output.result)
+            .map_err(store_client_error)?;
+
+        Ok(handler_response(
+            "create_client",
+            MsgCreateClientResponse {},
+            &output.log,
+        ))
     }
 
     /// UpdateClient defines a rpc handler method for MsgUpdateClient.
     async fn update_client(
         &self,
-        _request: tonic::Request<MsgUpdateClient>,
+        request: tonic::Request<MsgUpdateClient>,
     ) -> Result<tonic::Response<MsgUpdateClientResponse>, tonic::Status> {
-        unimplemented!()
+        let msg = MsgUpdateAnyClient::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("invalid MsgUpdateClient: {}", e)))?;
+
+        let mut ctx = write_with_timeout(&self.ctx, self.lock_timeout).await?;
+        // The handler verifies the header against the stored client and
+        // consensus states, and emits the `UpdateClient` event.
+        let output = update_client::process(&*ctx, msg)
+            .map_err(|e| client_handler_error("update client", e))?;
+
+        ctx.store_client_result(output.result)
+            .map_err(store_client_error)?;
+
+        Ok(handler_response(
+            "update_client",
+            MsgUpdateClientResponse {},
+            &output.log,
+        ))
     }
 
     /// UpgradeClient defines a rpc handler method for MsgUpgradeClient.
     async fn upgrade_client(
         &self,
-        _request: tonic::Request<MsgUpgradeClient>,
+        request: tonic::Request<MsgUpgradeClient>,
     ) -> Result<tonic::Response<MsgUpgradeClientResponse>, tonic::Status> {
-        unimplemented!()
+        let msg = MsgUpgradeAnyClient::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("invalid MsgUpgradeClient: {}", e)))?;
+
+        let mut ctx = write_with_timeout(&self.ctx, self.lock_timeout).await?;
+        // Not every light client of ibc implements the verification of an
+        // upgrade, and the ones without it panic, so their clients are turned
+        // away before the handler. A missing client is left to the handler.
+        if let Ok(client_type) = ctx.client_type(&msg.client_id) {
+            if !verifies_upgrade(client_type) {
+                return Err(Status::unimplemented(format!(
+                    "upgrade client: the {} light client can't verify an upgrade",
+                    client_type
+                )));
+            }
+        }
+
+        // The handler verifies the proofs of the upgraded client and consensus
+        // states against the stored ones, and emits the `UpgradeClient` event.
+        let output = upgrade_client::process(&*ctx, msg)
+            .map_err(|e| client_handler_error("upgrade client", e))?;
+
+        ctx.store_client_result(output.result)
+            .map_err(store_client_error)?;
+
+        Ok(handler_response(
+            "upgrade_client",
+            MsgUpgradeClientResponse {},
+            &output.log,
+        ))
     }
 
     async fn submit_misbehaviour(
         &self,
         _request: tonic::Request<MsgSubmitMisbehaviour>,
     ) -> Result<tonic::Response<MsgSubmitMisbehaviourResponse>, tonic::Status> {
-        unimplemented!()
+        Err(unsupported("submit_misbehaviour"))
+    }
+}
+
+/// Whether the light client of `client_type` verifies the upgrade of a
+/// client. The Tendermint light client of ibc leaves it as a `todo!`.
+fn verifies_upgrade(client_type: ClientType) -> bool {
+    client_type != ClientType::Tendermint
+}
+
+/// The log and the events of a dispatched msg.
+struct DispatchOutput {
+    log:    Vec<String>,
+    events: Vec<IbcEvent>,
+}
+
+/// Run a msg through the ICS26 handler under the write lock of the context,
+/// which routes the channel handshakes to the callbacks of the bound module.
+/// Returns the log of the handler and its events, whose handshake events are
+/// rebuilt from the stored ends, see `handshake_event`.
+async fn dispatch_msg<Ctx: Ics26Context>(
+    ctx: &RwLock<Ctx>,
+    lock_timeout: Option<Duration>,
+    handler: &str,
+    msg: Ics26Envelope,
+) -> Result<DispatchOutput, Status> {
+    let mut ctx = write_with_timeout(ctx, lock_timeout).await?;
+    dispatch_locked(&mut *ctx, handler, msg)
+}
+
+/// The same as `dispatch_msg`, with the context lock held by the caller, so
+/// that it can check the msg against the context before the dispatch.
+fn dispatch_locked<Ctx: Ics26Context>(
+    ctx: &mut Ctx,
+    handler: &str,
+    msg: Ics26Envelope,
+) -> Result<DispatchOutput, Status> {
+    let output =
+        dispatch(ctx, msg).map_err(|e| Status::invalid_argument(format!("{}: {}", handler, e)))?;
+
+    let events = output
+        .events
+        .into_iter()
+        .map(|event| handshake_event(ctx, &event).unwrap_or(event))
+        .collect();
+    Ok(DispatchOutput {
+        log: output.log,
+        events,
+    })
+}
+
+/// The handshake event with the complete attribute set of the end stored by
+/// a handshake step, for the event of the step emitted by the ICS handler.
+/// Other events are `None`.
+fn handshake_event<Ctx: Ics26Context>(ctx: &Ctx, event: &IbcEvent) -> Option<IbcEvent> {
+    let height = ctx.host_height();
+    let connection = |step, connection_id: Option<&ConnectionId>| {
+        let connection_id = connection_id?;
+        let connection_end = ConnectionReader::connection_end(ctx, connection_id).ok()?;
+        Some(connection_handshake_event(
+            step,
+            height,
+            connection_id,
+            &connection_end,
+        ))
+    };
+    let channel = |step, port_id: &PortId, channel_id: Option<&ChannelId>| {
+        let channel_id = channel_id?;
+        let channel_end =
+            ChannelReader::channel_end(ctx, &(port_id.clone(), channel_id.clone())).ok()?;
+        channel_handshake_event(step, height, port_id, channel_id, &channel_end).ok()
+    };
+
+    match event {
+        IbcEvent::OpenInitConnection(e) => connection(
+            HandshakeStep::OpenInit,
+            e.attributes().connection_id.as_ref(),
+        ),
+        IbcEvent::OpenTryConnection(e) => connection(
+            HandshakeStep::OpenTry,
+            e.attributes().connection_id.as_ref(),
+        ),
+        IbcEvent::OpenAckConnection(e) => connection(
+            HandshakeStep::OpenAck,
+            e.attributes().connection_id.as_ref(),
+        ),
+        IbcEvent::OpenConfirmConnection(e) => connection(
+            HandshakeStep::OpenConfirm,
+            e.attributes().connection_id.as_ref(),
+        ),
+        IbcEvent::OpenInitChannel(e) => {
+            channel(HandshakeStep::OpenInit, &e.port_id, e.channel_id.as_ref())
+        }
+        IbcEvent::OpenTryChannel(e) => {
+            channel(HandshakeStep::OpenTry, &e.port_id, e.channel_id.as_ref())
+        }
+        IbcEvent::OpenAckChannel(e) => {
+            channel(HandshakeStep::OpenAck, &e.port_id, e.channel_id.as_ref())
+        }
+        IbcEvent::OpenConfirmChannel(e) => channel(
+            HandshakeStep::OpenConfirm,
+            &e.port_id,
+            e.channel_id.as_ref(),
+        ),
+        _ => None,
+    }
+}
+
+pub struct IbcConnectionMsgService<Ctx: Ics26Context> {
+    ctx:          Arc<RwLock<Ctx>>,
+    lock_timeout: Option<Duration>,
+}
+
+impl<Ctx: Ics26Context> IbcConnectionMsgService<Ctx> {
+    pub fn new(ctx: Arc<RwLock<Ctx>>) -> Self {
+        Self {
+            ctx,
+            lock_timeout: None,
+        }
+    }
+
+    /// Bound the wait for the context lock, see `read_with_timeout`.
+    pub fn with_lock_timeout(mut self, lock_timeout: Option<Duration>) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    async fn dispatch(&self, handler: &str, msg: ConnectionMsg) -> Result<DispatchOutput, Status> {
+        dispatch_msg(
+            &self.ctx,
+            self.lock_timeout,
+            handler,
+            Ics26Envelope::Ics3Msg(msg),
+        )
+        .await
+    }
+}
+
+#[tonic::async_trait]
+impl<Ctx: Ics26Context + Sync + Send + 'static> ConnectionMsgRpc for IbcConnectionMsgService<Ctx> {
+    /// ConnectionOpenInit defines a rpc handler method for
+    /// MsgConnectionOpenInit.
+    async fn connection_open_init(
+        &self,
+        request: Request<RawMsgConnectionOpenInit>,
+    ) -> Result<Response<MsgConnectionOpenInitResponse>, Status> {
+        let msg = MsgConnectionOpenInit::try_from(request.into_inner()).map_err(|e| {
+            Status::invalid_argument(format!("invalid MsgConnectionOpenInit: {}", e))
+        })?;
+        let output = self
+            .dispatch(
+                "connection_open_init",
+                ConnectionMsg::ConnectionOpenInit(msg),
+            )
+            .await?;
+
+        Ok(handler_response_with_events(
+            "connection_open_init",
+            MsgConnectionOpenInitResponse {},
+            &output,
+        ))
+    }
+
+    /// ConnectionOpenTry defines a rpc handler method for
+    /// MsgConnectionOpenTry.
+    async fn connection_open_try(
+        &self,
+        request: Request<RawMsgConnectionOpenTry>,
+    ) -> Result<Response<MsgConnectionOpenTryResponse>, Status> {
+        let msg = MsgConnectionOpenTry::try_from(request.into_inner()).map_err(|e| {
+            Status::invalid_argument(format!("invalid MsgConnectionOpenTry: {}", e))
+        })?;
+        let output = self
+            .dispatch(
+                "connection_open_try",
+                ConnectionMsg::ConnectionOpenTry(Box::new(msg)),
+            )
+            .await?;
+
+        Ok(handler_response_with_events(
+            "connection_open_try",
+            MsgConnectionOpenTryResponse {},
+            &output,
+        ))
+    }
+
+    /// ConnectionOpenAck defines a rpc handler method for
+    /// MsgConnectionOpenAck.
+    async fn connection_open_ack(
+        &self,
+        request: Request<RawMsgConnectionOpenAck>,
+    ) -> Result<Response<MsgConnectionOpenAckResponse>, Status> {
+        let msg = MsgConnectionOpenAck::try_from(request.into_inner()).map_err(|e| {
+            Status::invalid_argument(format!("invalid MsgConnectionOpenAck: {}", e))
+        })?;
+        let output = self
+            .dispatch(
+                "connection_open_ack",
+                ConnectionMsg::ConnectionOpenAck(Box::new(msg)),
+            )
+            .await?;
+
+        Ok(handler_response_with_events(
+            "connection_open_ack",
+            MsgConnectionOpenAckResponse {},
+            &output,
+        ))
+    }
+
+    /// ConnectionOpenConfirm defines a rpc handler method for
+    /// MsgConnectionOpenConfirm.
+    async fn connection_open_confirm(
+        &self,
+        request: Request<RawMsgConnectionOpenConfirm>,
+    ) -> Result<Response<MsgConnectionOpenConfirmResponse>, Status> {
+        let msg = MsgConnectionOpenConfirm::try_from(request.into_inner()).map_err(|e| {
+            Status::invalid_argument(format!("invalid MsgConnectionOpenConfirm: {}", e))
+        })?;
+        let output = self
+            .dispatch(
+                "connection_open_confirm",
+                ConnectionMsg::ConnectionOpenConfirm(msg),
+            )
+            .await?;
+
+        Ok(handler_response_with_events(
+            "connection_open_confirm",
+            MsgConnectionOpenConfirmResponse {},
+            &output,
+        ))
+    }
+}
+
+pub struct IbcChannelMsgService<Ctx: Ics26Context> {
+    ctx:          Arc<RwLock<Ctx>>,
+    lock_timeout: Option<Duration>,
+}
+
+impl<Ctx: Ics26Context> IbcChannelMsgService<Ctx> {
+    pub fn new(ctx: Arc<RwLock<Ctx>>) -> Self {
+        Self {
+            ctx,
+            lock_timeout: None,
+        }
+    }
+
+    /// Bound the wait for the context lock, see `read_with_timeout`.
+    pub fn with_lock_timeout(mut self, lock_timeout: Option<Duration>) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    async fn dispatch(&self, handler: &str, msg: ChannelMsg) -> Result<DispatchOutput, Status> {
+        dispatch_msg(
+            &self.ctx,
+            self.lock_timeout,
+            handler,
+            Ics26Envelope::Ics4ChannelMsg(msg),
+        )
+        .await
+    }
+}
+
+#[tonic::async_trait]
+impl<Ctx> ChannelMsgRpc for IbcChannelMsgService<Ctx>
+where
+    Ctx: Ics26Context + IbcContextReader + Sync + Send + 'static,
+{
+    /// ChannelOpenInit defines a rpc handler method for MsgChannelOpenInit.
+    async fn channel_open_init(
+        &self,
+        request: Request<RawMsgChannelOpenInit>,
+    ) -> Result<Response<MsgChannelOpenInitResponse>, Status> {
+        let msg = MsgChannelOpenInit::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("invalid MsgChannelOpenInit: {}", e)))?;
+        let output = self
+            .dispatch("channel_open_init", ChannelMsg::ChannelOpenInit(msg))
+            .await?;
+
+        Ok(handler_response_with_events(
+            "channel_open_init",
+            MsgChannelOpenInitResponse::default(),
+            &output,
+        ))
+    }
+
+    /// ChannelOpenTry defines a rpc handler method for MsgChannelOpenTry.
+    async fn channel_open_try(
+        &self,
+        request: Request<RawMsgChannelOpenTry>,
+    ) -> Result<Response<MsgChannelOpenTryResponse>, Status> {
+        let msg = MsgChannelOpenTry::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("invalid MsgChannelOpenTry: {}", e)))?;
+        let output = self
+            .dispatch("channel_open_try", ChannelMsg::ChannelOpenTry(msg))
+            .await?;
+
+        Ok(handler_response_with_events(
+            "channel_open_try",
+            MsgChannelOpenTryResponse::default(),
+            &output,
+        ))
+    }
+
+    /// ChannelOpenAck defines a rpc handler method for MsgChannelOpenAck.
+    async fn channel_open_ack(
+        &self,
+        request: Request<RawMsgChannelOpenAck>,
+    ) -> Result<Response<MsgChannelOpenAckResponse>, Status> {
+        let msg = MsgChannelOpenAck::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("invalid MsgChannelOpenAck: {}", e)))?;
+        let output = self
+            .dispatch("channel_open_ack", ChannelMsg::ChannelOpenAck(msg))
+            .await?;
+
+        Ok(handler_response_with_events(
+            "channel_open_ack",
+            MsgChannelOpenAckResponse {},
+            &output,
+        ))
+    }
+
+    /// ChannelOpenConfirm defines a rpc handler method for
+    /// MsgChannelOpenConfirm.
+    async fn channel_open_confirm(
+        &self,
+        request: Request<RawMsgChannelOpenConfirm>,
+    ) -> Result<Response<MsgChannelOpenConfirmResponse>, Status> {
+        let msg = MsgChannelOpenConfirm::try_from(request.into_inner()).map_err(|e| {
+            Status::invalid_argument(format!("invalid MsgChannelOpenConfirm: {}", e))
+        })?;
+        let output = self
+            .dispatch("channel_open_confirm", ChannelMsg::ChannelOpenConfirm(msg))
+            .await?;
+
+        Ok(handler_response_with_events(
+            "channel_open_confirm",
+            MsgChannelOpenConfirmResponse {},
+            &output,
+        ))
+    }
+
+    async fn channel_close_init(
+        &self,
+        _request: Request<MsgChannelCloseInit>,
+    ) -> Result<Response<MsgChannelCloseInitResponse>, Status> {
+        Err(unsupported("channel_close_init"))
+    }
+
+    async fn channel_close_confirm(
+        &self,
+        _request: Request<MsgChannelCloseConfirm>,
+    ) -> Result<Response<MsgChannelCloseConfirmResponse>, Status> {
+        Err(unsupported("channel_close_confirm"))
+    }
+
+    /// RecvPacket defines a rpc handler method for MsgRecvPacket. A packet
+    /// timed out on the host is rejected before the dispatch, since it is to
+    /// be timed out on the source chain instead.
+    async fn recv_packet(
+        &self,
+        request: Request<RawMsgRecvPacket>,
+    ) -> Result<Response<MsgRecvPacketResponse>, Status> {
+        let msg = MsgRecvPacket::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("invalid MsgRecvPacket: {}", e)))?;
+
+        let mut ctx = write_with_timeout(&self.ctx, self.lock_timeout).await?;
+        ctx.check_recv_packet_timeout(&msg.packet)
+            .map_err(|e| match e {
+                IbcError::PacketTimedOut { .. } => {
+                    Status::failed_precondition(format!("recv_packet: {}", e))
+                }
+                e => Status::internal(format!("recv_packet: {}", e)),
+            })?;
+        let output = dispatch_locked(
+            &mut *ctx,
+            "recv_packet",
+            Ics26Envelope::Ics4PacketMsg(PacketMsg::RecvPacket(msg)),
+        )?;
+        drop(ctx);
+
+        Ok(handler_response_with_events(
+            "recv_packet",
+            MsgRecvPacketResponse::default(),
+            &output,
+        ))
+    }
+
+    async fn timeout(
+        &self,
+        _request: Request<MsgTimeout>,
+    ) -> Result<Response<MsgTimeoutResponse>, Status> {
+        Err(unsupported("timeout"))
+    }
+
+    async fn timeout_on_close(
+        &self,
+        _request: Request<MsgTimeoutOnClose>,
+    ) -> Result<Response<MsgTimeoutOnCloseResponse>, Status> {
+        Err(unsupported("timeout_on_close"))
+    }
+
+    async fn acknowledgement(
+        &self,
+        _request: Request<MsgAcknowledgement>,
+    ) -> Result<Response<MsgAcknowledgementResponse>, Status> {
+        Err(unsupported("acknowledgement"))
     }
 }
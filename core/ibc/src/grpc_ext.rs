@@ -0,0 +1,183 @@
+//! The gRPC servers of the queries the ICS protos have no RPC for. Their
+//! messages are defined in `proto/axon/ibc`, and the servers are written by
+//! hand in the shape `tonic-build` generates, since a build script would only
+//! be needed for these few methods.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::Poll;
+
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{empty_body, http, Body, BoxFuture, StdError};
+use tonic::server::{Grpc, NamedService};
+use tonic::{Request, Response, Status};
+use tower::Service;
+
+use protocol::traits::IbcAdapter;
+
+use crate::grpc::{
+    IbcChannelService, IbcClientService, IbcConnectionService, IbcContextQueryService,
+    IbcContextReader,
+};
+
+/// A method of a query service as the unary service of `Grpc`.
+struct UnaryMethod<F>(F);
+
+impl<Req, Resp, F, Fut> Service<Request<Req>> for UnaryMethod<F>
+where
+    F: FnMut(Request<Req>) -> Fut,
+    Fut: Future<Output = Result<Response<Resp>, Status>>,
+{
+    type Error = Status;
+    type Future = Fut;
+    type Response = Response<Resp>;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Req>) -> Self::Future {
+        (self.0)(request)
+    }
+}
+
+/// Serve a unary request with `method`, decoding the request and encoding the
+/// response with prost.
+fn unary<Req, Resp, B, F, Fut>(
+    req: http::Request<B>,
+    method: F,
+) -> BoxFuture<http::Response<BoxBody>, Infallible>
+where
+    Req: prost::Message + Default + Send + 'static,
+    Resp: prost::Message + Send + 'static,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+    F: FnMut(Request<Req>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Response<Resp>, Status>> + Send + 'static,
+{
+    Box::pin(async move {
+        let mut grpc = Grpc::new(ProstCodec::<Resp, Req>::default());
+        Ok(grpc.unary(UnaryMethod(method), req).await)
+    })
+}
+
+/// The answer to a method the server doesn't have, which is `unimplemented`
+/// like the generated servers.
+fn unimplemented_method() -> BoxFuture<http::Response<BoxBody>, Infallible> {
+    Box::pin(async move {
+        Ok(http::Response::builder()
+            .status(200)
+            .header("grpc-status", "12")
+            .header("content-type", "application/grpc")
+            .body(empty_body())
+            .unwrap())
+    })
+}
+
+/// Declare the server of a query service, which routes the methods named in
+/// `{ "Method" => handler }` to the handlers of the inner service.
+macro_rules! ext_query_server {
+    (
+        $(#[$doc:meta])*
+        $server:ident<$param:ident: $bound:path>($service:ident), $name:literal, {
+            $($method:literal => $handler:ident,)*
+        }
+    ) => {
+        $(#[$doc])*
+        pub struct $server<$param: $bound> {
+            inner: Arc<$service<$param>>,
+        }
+
+        impl<$param: $bound> $server<$param> {
+            pub fn new(inner: $service<$param>) -> Self {
+                $server {
+                    inner: Arc::new(inner),
+                }
+            }
+        }
+
+        impl<$param: $bound> Clone for $server<$param> {
+            fn clone(&self) -> Self {
+                $server {
+                    inner: Arc::clone(&self.inner),
+                }
+            }
+        }
+
+        impl<$param: $bound> NamedService for $server<$param> {
+            const NAME: &'static str = $name;
+        }
+
+        impl<$param, B> Service<http::Request<B>> for $server<$param>
+        where
+            $param: $bound + Send + Sync + 'static,
+            B: Body + Send + 'static,
+            B::Error: Into<StdError> + Send + 'static,
+        {
+            type Error = Infallible;
+            type Future = BoxFuture<Self::Response, Self::Error>;
+            type Response = http::Response<BoxBody>;
+
+            fn poll_ready(
+                &mut self,
+                _cx: &mut std::task::Context<'_>,
+            ) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, req: http::Request<B>) -> Self::Future {
+                let inner = Arc::clone(&self.inner);
+
+                match req.uri().path() {
+                    $(concat!("/", $name, "/", $method) => unary(req, move |request| {
+                        let inner = Arc::clone(&inner);
+                        async move { inner.$handler(request).await }
+                    }),)*
+                    _ => unimplemented_method(),
+                }
+            }
+        }
+    };
+}
+
+ext_query_server! {
+    /// The `axon.ibc.client.v1.Query` service, which serves the extra queries
+    /// of `IbcClientService`.
+    ClientExtQueryServer<Adapter: IbcAdapter>(IbcClientService), "axon.ibc.client.v1.Query", {
+        "ClientIds" => client_ids,
+        "ClientsOverview" => clients_overview,
+    }
+}
+
+ext_query_server! {
+    /// The `axon.ibc.connection.v1.Query` service, which serves the extra
+    /// queries of `IbcConnectionService`.
+    ConnectionExtQueryServer<Adapter: IbcAdapter>(IbcConnectionService),
+    "axon.ibc.connection.v1.Query", {
+        "ConnectionCounter" => connection_counter,
+        "ConnectionIds" => connection_ids,
+    }
+}
+
+ext_query_server! {
+    /// The `axon.ibc.context.v1.Query` service, which serves the queries of
+    /// `IbcContextQueryService` from the shared context.
+    ContextExtQueryServer<Ctx: IbcContextReader>(IbcContextQueryService),
+    "axon.ibc.context.v1.Query", {
+        "ClientLatestHeight" => client_latest_height,
+        "ProposedAppVersion" => proposed_app_version,
+    }
+}
+
+ext_query_server! {
+    /// The `axon.ibc.channel.v1.Query` service, which serves the extra queries
+    /// of `IbcChannelService`.
+    ChannelExtQueryServer<Adapter: IbcAdapter>(IbcChannelService), "axon.ibc.channel.v1.Query", {
+        "ChannelIds" => channel_ids,
+        "ChannelOverview" => channel_overview,
+        "NextSequenceSend" => next_sequence_send,
+        "Packet" => packet,
+    }
+}
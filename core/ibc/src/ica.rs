@@ -0,0 +1,282 @@
+//! A minimal ICS-27 interchain accounts host module.
+//!
+//! The packet data of a host packet is a single EVM call encoded as
+//! `to (20 bytes) | value (32 bytes, big endian) | call data`. The call is
+//! executed from the interchain account registered for the channel, and the
+//! result is returned in the acknowledgement. The accounts are stored through
+//! the IBC adapter, so they survive a restart.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use ibc::core::ics04_channel::channel::{Counterparty, Order};
+use ibc::core::ics04_channel::error::Error as ChannelError;
+use ibc::core::ics04_channel::packet::Packet;
+use ibc::core::ics04_channel::Version;
+use ibc::core::ics24_host::identifier::{ChannelId, ConnectionId, PortId};
+use ibc::core::ics26_routing::context::{
+    Acknowledgement, Module, ModuleOutputBuilder, OnRecvPacketAck,
+};
+use ibc::signer::Signer;
+
+use protocol::codec::hex_encode;
+use protocol::traits::{Context, IbcAdapter};
+use protocol::types::{Hasher, H160, U256};
+use protocol::ProtocolResult;
+
+pub const ICA_HOST_PORT_ID: &str = "icahost";
+pub const ICA_VERSION: &str = "ics27-1";
+
+const ADDRESS_LEN: usize = 20;
+const VALUE_LEN: usize = 32;
+
+/// Executes the calls of interchain accounts, which is implemented by the node
+/// on top of the EVM executor.
+pub trait IcaExecutor: Send + Sync {
+    fn execute(&self, sender: H160, call: &IcaCall) -> Result<Vec<u8>, String>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IcaCall {
+    pub to:    H160,
+    pub value: U256,
+    pub data:  Vec<u8>,
+}
+
+impl IcaCall {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut value = [0u8; VALUE_LEN];
+        self.value.to_big_endian(&mut value);
+
+        let mut ret = Vec::with_capacity(ADDRESS_LEN + VALUE_LEN + self.data.len());
+        ret.extend_from_slice(self.to.as_bytes());
+        ret.extend_from_slice(&value);
+        ret.extend_from_slice(&self.data);
+        ret
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, String> {
+        if raw.len() < ADDRESS_LEN + VALUE_LEN {
+            return Err(format!("invalid ica packet data length {}", raw.len()));
+        }
+
+        Ok(IcaCall {
+            to:    H160::from_slice(&raw[..ADDRESS_LEN]),
+            value: U256::from_big_endian(&raw[ADDRESS_LEN..ADDRESS_LEN + VALUE_LEN]),
+            data:  raw[ADDRESS_LEN + VALUE_LEN..].to_vec(),
+        })
+    }
+}
+
+/// The acknowledgement of an ICA host packet, encoded as
+/// `{"result":"<hex>"}` on success and `{"error":"<reason>"}` on failure.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IcaAcknowledgement(Vec<u8>);
+
+impl IcaAcknowledgement {
+    pub fn success(ret: &[u8]) -> Self {
+        IcaAcknowledgement(
+            serde_json::json!({ "result": hex_encode(ret) })
+                .to_string()
+                .into_bytes(),
+        )
+    }
+
+    pub fn error(reason: &str) -> Self {
+        IcaAcknowledgement(
+            serde_json::json!({ "error": reason })
+                .to_string()
+                .into_bytes(),
+        )
+    }
+}
+
+impl AsRef<[u8]> for IcaAcknowledgement {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Acknowledgement for IcaAcknowledgement {}
+
+/// The ICS-27 channel version, which is the JSON metadata negotiated by the
+/// controller and the host.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IcaMetadata {
+    pub version:                  String,
+    pub controller_connection_id: String,
+    pub host_connection_id:       String,
+    pub address:                  String,
+    pub encoding:                 String,
+    pub tx_type:                  String,
+}
+
+impl IcaMetadata {
+    pub fn encode(&self) -> String {
+        serde_json::json!({
+            "version": self.version,
+            "controller_connection_id": self.controller_connection_id,
+            "host_connection_id": self.host_connection_id,
+            "address": self.address,
+            "encoding": self.encoding,
+            "tx_type": self.tx_type,
+        })
+        .to_string()
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(raw).map_err(|e| format!("invalid ica metadata: {}", e))?;
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(ToString::to_string)
+                .ok_or_else(|| format!("missing ica metadata field {}", name))
+        };
+
+        Ok(IcaMetadata {
+            version:                  field("version")?,
+            controller_connection_id: field("controller_connection_id")?,
+            host_connection_id:       field("host_connection_id")?,
+            // The address is empty until the host registers the account.
+            address:                  field("address").unwrap_or_default(),
+            encoding:                 field("encoding")?,
+            tx_type:                  field("tx_type")?,
+        })
+    }
+}
+
+pub struct IcaHostModule<E, Adapter> {
+    executor: Arc<E>,
+    adapter:  Arc<Adapter>,
+}
+
+impl<E: IcaExecutor, Adapter: IbcAdapter> IcaHostModule<E, Adapter> {
+    pub fn new(executor: Arc<E>, adapter: Arc<Adapter>) -> Self {
+        IcaHostModule { executor, adapter }
+    }
+
+    pub fn port_id() -> PortId {
+        PortId::from_str(ICA_HOST_PORT_ID).unwrap()
+    }
+
+    pub fn account(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> ProtocolResult<Option<H160>> {
+        self.adapter
+            .get_interchain_account(Context::new(), port_id, channel_id)
+    }
+
+    /// The interchain account address is derived from the connection and the
+    /// controller port, so that a reopened channel controls the same account.
+    pub fn account_address(connection_id: &ConnectionId, controller_port_id: &PortId) -> H160 {
+        Hasher::digest(format!("{}/{}", connection_id, controller_port_id)).into()
+    }
+}
+
+impl<E, Adapter> Module for IcaHostModule<E, Adapter>
+where
+    E: IcaExecutor + 'static,
+    Adapter: IbcAdapter + 'static,
+{
+    fn on_chan_open_init(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        _order: Order,
+        _connection_hops: &[ConnectionId],
+        _port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty: &Counterparty,
+        _version: &Version,
+    ) -> Result<(), ChannelError> {
+        Err(ChannelError::app_module(
+            "ica host channels are opened by the controller".to_string(),
+        ))
+    }
+
+    fn on_chan_open_try(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        order: Order,
+        connection_hops: &[ConnectionId],
+        port_id: &PortId,
+        channel_id: &ChannelId,
+        counterparty: &Counterparty,
+        _version: &Version,
+        counterparty_version: &Version,
+    ) -> Result<Version, ChannelError> {
+        if order != Order::Ordered {
+            return Err(ChannelError::app_module(
+                "ica channels must be ordered".to_string(),
+            ));
+        }
+
+        let mut metadata = IcaMetadata::decode(&counterparty_version.to_string())
+            .map_err(ChannelError::app_module)?;
+        if metadata.version != ICA_VERSION {
+            return Err(ChannelError::app_module(format!(
+                "unsupported ica version {}",
+                metadata.version
+            )));
+        }
+
+        let connection_id = connection_hops
+            .first()
+            .ok_or_else(|| ChannelError::app_module("missing connection hop".to_string()))?;
+        if metadata.host_connection_id != connection_id.as_str() {
+            return Err(ChannelError::app_module(format!(
+                "ica host connection {} is not {}",
+                metadata.host_connection_id, connection_id
+            )));
+        }
+
+        let address = Self::account_address(connection_id, counterparty.port_id());
+        log::info!(
+            "[ibc] ica: register account {:?} on {}/{}",
+            address,
+            port_id,
+            channel_id
+        );
+        self.adapter
+            .set_interchain_account(Context::new(), port_id.clone(), channel_id.clone(), address)
+            .map_err(|e| ChannelError::app_module(e.to_string()))?;
+
+        metadata.address = format!("{:?}", address);
+        Ok(Version::new(metadata.encode()))
+    }
+
+    fn on_recv_packet(
+        &self,
+        _output: &mut ModuleOutputBuilder,
+        packet: &Packet,
+        _relayer: &Signer,
+    ) -> OnRecvPacketAck {
+        let sender = match self.account(&packet.destination_port, &packet.destination_channel) {
+            Ok(Some(addr)) => addr,
+            Ok(None) => {
+                return OnRecvPacketAck::Failed(Box::new(IcaAcknowledgement::error(
+                    "interchain account not found",
+                )))
+            }
+            Err(e) => {
+                return OnRecvPacketAck::Failed(Box::new(IcaAcknowledgement::error(&e.to_string())))
+            }
+        };
+
+        let call = match IcaCall::decode(&packet.data) {
+            Ok(call) => call,
+            Err(e) => return OnRecvPacketAck::Failed(Box::new(IcaAcknowledgement::error(&e))),
+        };
+
+        match self.executor.execute(sender, &call) {
+            Ok(ret) => OnRecvPacketAck::Successful(
+                Box::new(IcaAcknowledgement::success(&ret)),
+                Box::new(|_| Ok(())),
+            ),
+            Err(e) => OnRecvPacketAck::Failed(Box::new(IcaAcknowledgement::error(&e))),
+        }
+    }
+}
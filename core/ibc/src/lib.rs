@@ -1,70 +1,1034 @@
 mod adapter;
 mod client;
 mod error;
+mod events;
 mod grpc;
+mod grpc_ext;
+mod ica;
+#[cfg(test)]
+mod tests;
 mod transfer;
 
 pub use adapter::DefaultIbcAdapter;
+pub use events::{channel_handshake_event, connection_handshake_event, HandshakeStep};
+pub use grpc::{classify_acknowledgement, AckStatus};
+pub use ica::{IcaAcknowledgement, IcaCall, IcaExecutor, IcaHostModule, IcaMetadata};
 use std::borrow::Borrow;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::num::NonZeroUsize;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
+pub use transfer::{
+    denom_prefix, escrow_address, is_receiver_chain_source, is_sender_chain_source, recv_transfer,
+    refund_transfer, resolve_recv_denom, send_transfer, RecvDenom, TransferError, NATIVE_DENOM,
+};
+pub use transfer::{AxonTransferExecutor, TransferExecutor, TransferModule, TRANSFER_MODULE_ID};
 
 use ibc::clients::ics07_tendermint::consensus_state::ConsensusState;
+use ibc::core::ics23_commitment::commitment::CommitmentRoot;
 use ibc::timestamp::Timestamp;
 use ibc::{
     core::{
-        ics02_client::client_consensus::AnyConsensusState,
+        ics02_client::client_consensus::{
+            AnyConsensusState, ConsensusState as ClientConsensusState,
+        },
         ics02_client::context::ClientReader,
         ics02_client::error::Error as ClientError,
         ics02_client::{
-            client_state::AnyClientState, client_type::ClientType, context::ClientKeeper,
+            client_state::{AnyClientState, ClientState},
+            client_type::ClientType,
+            context::ClientKeeper,
         },
         ics03_connection::connection::ConnectionEnd,
         ics03_connection::context::{ConnectionKeeper, ConnectionReader},
         ics03_connection::error::Error as ConnectionError,
+        ics04_channel::channel::{Order, State as ChannelState},
         ics04_channel::commitment::{AcknowledgementCommitment, PacketCommitment},
         ics04_channel::context::ChannelReader,
         ics04_channel::error::Error as ChannelError,
-        ics04_channel::packet::{Receipt, Sequence},
-        ics04_channel::{channel::ChannelEnd, context::ChannelKeeper},
+        ics04_channel::events::{SendPacket, WriteAcknowledgement},
+        ics04_channel::handler::send_packet,
+        ics04_channel::packet::{Packet, Receipt, Sequence},
+        ics04_channel::{channel::ChannelEnd, context::ChannelKeeper, Version},
         ics05_port::context::PortReader,
         ics05_port::error::Error as PortError,
         ics23_commitment::commitment::CommitmentPrefix,
-        ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
+        ics24_host::identifier::{ChainId, ChannelId, ClientId, ConnectionId, PortId},
+        ics24_host::{
+            path::{ChannelEndsPath, ClientStatePath, ConnectionsPath},
+            Path as IbcPath,
+        },
         ics26_routing::context::{Ics26Context, Module, ModuleId, Router},
     },
+    events::IbcEvent,
     Height,
 };
 
-use protocol::traits::{Context, IbcAdapter};
-use protocol::types::Hasher;
-
-use crate::grpc::GrpcService;
-
-pub async fn run_ibc_grpc<
-    Adapter: IbcAdapter + 'static,
-    Ctx: Ics26Context + Sync + Send + 'static,
->(
+use lru::LruCache;
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use tendermint::{Hash as TmHash, Time as TmTime};
+
+use protocol::tokio::runtime::RuntimeFlavor;
+use protocol::traits::{Context, IbcAdapter, IterDirection};
+use protocol::types::{Hash, Hasher, Header, Path, StoreHeight};
+use protocol::ProtocolResult;
+
+use crate::error::IbcError;
+use crate::grpc::{recover_poisoned, GrpcService};
+
+/// Serve the IBC gRPC services until `shutdown` resolves, then flush the
+/// context so that the effects of the handled messages are not lost. An
+/// invalid listen address fails before anything is served, and a transport
+/// error of the servers, such as an address in use, is returned after the
+/// flush, so that the node can decide to retry or to shut down.
+///
+/// The host heights of `ctx` are reported in the revision of `chain_id`, and
+/// its block interval is the one in the metadata of the chain.
+pub async fn run_ibc_grpc<Adapter, F>(
     adapter: Adapter,
-    addr: String,
-    ctx: Ctx,
-) {
+    query_addr: String,
+    msg_addr: String,
+    chain_id: String,
+    ctx: IbcImpl<Adapter, IbcRouter>,
+    shutdown: F,
+) -> ProtocolResult<()>
+where
+    Adapter: IbcAdapter + 'static,
+    F: Future<Output = ()>,
+{
     log::info!("ibc start");
-    GrpcService::new(Arc::new(adapter), addr, Arc::new(RwLock::new(ctx)))
-        .run()
-        .await;
+    if !adapter.is_versioned() {
+        log::warn!(
+            "[ibc] the store keeps only the latest state, the queries at a height are disabled"
+        );
+    }
+    let metadata = adapter.get_metadata(adapter.current_height()).await?;
+    let mut ctx = ctx.with_block_interval(Duration::from_millis(metadata.interval));
+    ctx.set_revision_number(ChainId::chain_version(&chain_id));
+    ctx.warm_latest_consensus_states().await?;
+    let ctx = Arc::new(RwLock::new(ctx));
+    let served = GrpcService::new(
+        Arc::new(adapter),
+        query_addr,
+        msg_addr,
+        &chain_id,
+        Arc::clone(&ctx),
+    )?
+    .run_until(shutdown)
+    .await;
+
+    if let Err(e) = ctx.write().unwrap_or_else(recover_poisoned).flush() {
+        log::error!("[ibc] flush on shutdown: {}", e);
+    }
+    log::info!("ibc stop");
+    served.map_err(|e| IbcError::Transport(e).into())
 }
 
+/// The default number of the host consensus states cached in memory.
+pub const DEFAULT_CONSENSUS_STATE_CACHE_SIZE: usize = 1024;
+
+/// The block interval of the host, until the one of the chain is configured.
+pub const DEFAULT_BLOCK_INTERVAL: Duration = Duration::from_secs(3);
+
+/// The number of the consensus heights read at once by a prune.
+const PRUNE_CHUNK_SIZE: usize = 64;
+
 pub struct IbcImpl<Adapter, Router> {
-    adapter:                  Arc<Adapter>,
-    router:                   Router,
-    client_counter:           u64,
-    channel_counter:          u64,
-    conn_counter:             u64,
-    port_to_module_map:       BTreeMap<PortId, ModuleId>,
-    client_processed_times:   HashMap<(ClientId, Height), Timestamp>,
-    client_processed_heights: HashMap<(ClientId, Height), Height>,
-    consensus_states:         HashMap<u64, ConsensusState>,
+    adapter:                     Arc<Adapter>,
+    router:                      Router,
+    client_counter:              u64,
+    channel_counter:             u64,
+    conn_counter:                u64,
+    port_to_module_map:          BTreeMap<PortId, ModuleId>,
+    consensus_states:            Mutex<LruCache<u64, ConsensusState>>,
+    write_batch:                 Option<WriteBatch>,
+    consensus_state_retention:   Option<Duration>,
+    max_client_consensus_states: Option<usize>,
+    revision_number:             u64,
+    max_channels_per_connection: Option<usize>,
+    block_interval:              Duration,
+}
+
+/// The state of the context before a message, to restore on rollback. The
+/// keeper writes of the message are staged by the adapter.
+pub struct WriteBatch {
+    counters: (u64, u64, u64),
+}
+
+impl<Adapter, Router> IbcImpl<Adapter, Router>
+where
+    Adapter: IbcAdapter + 'static,
+{
+    /// A context with no bound ports, which resumes the id counters from the
+    /// adapter, so that a restarted node never reuses an id, see
+    /// `reconcile_counters`. The host consensus states are warmed up by
+    /// `run_ibc_grpc`.
+    pub fn new(adapter: Arc<Adapter>, router: Router) -> ProtocolResult<Self> {
+        let mut ibc = IbcImpl {
+            adapter,
+            router,
+            client_counter: 0,
+            channel_counter: 0,
+            conn_counter: 0,
+            port_to_module_map: BTreeMap::new(),
+            consensus_states: Mutex::new(LruCache::new(
+                NonZeroUsize::new(DEFAULT_CONSENSUS_STATE_CACHE_SIZE)
+                    .expect("the cache size is non-zero"),
+            )),
+            write_batch: None,
+            consensus_state_retention: None,
+            max_client_consensus_states: None,
+            revision_number: 0,
+            max_channels_per_connection: None,
+            block_interval: DEFAULT_BLOCK_INTERVAL,
+        };
+        ibc.load_connection_counter()?;
+        ibc.reconcile_counters()?;
+
+        Ok(ibc)
+    }
+
+    /// Bind the ports to the modules handling their channels, see `bind_port`.
+    pub fn with_ports<I>(mut self, ports: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (PortId, ModuleId)>,
+    {
+        for (port_id, module_id) in ports {
+            self.bind_port(port_id, module_id)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Set the block interval of the host, which the connections derive the
+    /// block delay of a packet from, `DEFAULT_BLOCK_INTERVAL` by default.
+    /// `run_ibc_grpc` sets the interval in the metadata of the chain.
+    pub fn with_block_interval(mut self, block_interval: Duration) -> Self {
+        self.block_interval = block_interval;
+        self
+    }
+
+    /// Keep at most `max` consensus states of each client, along with their
+    /// processed times and heights, even if the older ones are not expired
+    /// yet. It bounds the memory of a client updated more often than its
+    /// trusting period allows to prune. There is no limit by default.
+    pub fn with_max_client_consensus_states(mut self, max: usize) -> Self {
+        self.max_client_consensus_states = Some(max);
+        self
+    }
+
+    /// Preload the host consensus states of the heights, such as the ones
+    /// kept by the node, instead of reading the headers from the adapter.
+    pub fn with_host_consensus_states<I>(mut self, states: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, ConsensusState)>,
+    {
+        for (height, consensus_state) in states {
+            self.push_host_consensus_state(height, consensus_state);
+        }
+
+        self
+    }
+
+    /// Record the host consensus state of a committed block, which the node
+    /// should do for each block, so that the handshakes verify against it
+    /// without reading the header back from the adapter. Only the
+    /// `set_consensus_state_cache_size` most recently used states are kept,
+    /// `DEFAULT_CONSENSUS_STATE_CACHE_SIZE` by default, and an evicted one is
+    /// reloaded from the adapter when it is accessed again.
+    pub fn push_host_consensus_state(&mut self, height: u64, consensus_state: ConsensusState) {
+        let _ = self.consensus_states.get_mut().put(height, consensus_state);
+    }
+
+    /// Load the host consensus states of the blocks in `[from_height,
+    /// to_height]` from the adapter. The `consensus_states` map is in-memory
+    /// only, so it should be warmed up when the node restarts.
+    pub async fn warm_consensus_states(
+        &mut self,
+        from_height: u64,
+        to_height: u64,
+    ) -> ProtocolResult<()> {
+        for height in from_height..=to_height {
+            let header = self.adapter.get_header_by_height(height).await?;
+            let _ = self
+                .consensus_states
+                .get_mut()
+                .put(height, host_consensus_state(&header)?);
+        }
+
+        Ok(())
+    }
+
+    /// Warm up the host consensus states of the latest blocks kept by the
+    /// adapter, as many as the cache holds.
+    pub async fn warm_latest_consensus_states(&mut self) -> ProtocolResult<()> {
+        let current_height = self.adapter.current_height();
+        if current_height == 0 {
+            return Ok(());
+        }
+
+        let cache_size = usize::from(self.consensus_states.get_mut().cap()) as u64;
+        let from_height = self
+            .adapter
+            .oldest_height()?
+            .max((current_height + 1).saturating_sub(cache_size));
+        self.warm_consensus_states(from_height, current_height)
+            .await
+    }
+
+    /// Load the connection counter persisted by the adapter. The counter is
+    /// kept in memory, so it is loaded by `new`.
+    pub fn load_connection_counter(&mut self) -> ProtocolResult<()> {
+        if let Some(counter) = self.adapter.get_connection_counter(Context::new())? {
+            self.conn_counter = counter;
+        }
+
+        Ok(())
+    }
+
+    /// Raise each id counter above the largest id stored under its prefix.
+    /// A crash between increasing a counter and storing the created entry
+    /// leaves them out of step, so this runs in `new` after the persisted
+    /// counters are loaded, to never reuse an id.
+    pub fn reconcile_counters(&mut self) -> ProtocolResult<()> {
+        let next_client = self.next_id_counter("clients", |path| match path {
+            IbcPath::ClientState(ClientStatePath(client_id)) => Some(client_id.to_string()),
+            _ => None,
+        })?;
+        let next_conn = self.next_id_counter("connections", |path| match path {
+            IbcPath::Connections(ConnectionsPath(conn_id)) => Some(conn_id.to_string()),
+            _ => None,
+        })?;
+        let next_channel = self.next_id_counter("channelEnds/ports", |path| match path {
+            IbcPath::ChannelEnds(ChannelEndsPath(_, channel_id)) => Some(channel_id.to_string()),
+            _ => None,
+        })?;
+
+        for (name, counter, next) in [
+            ("client", &mut self.client_counter, next_client),
+            ("connection", &mut self.conn_counter, next_conn),
+            ("channel", &mut self.channel_counter, next_channel),
+        ] {
+            if *counter < next {
+                log::info!("[ibc] raise {} counter from {} to {}", name, counter, next);
+                *counter = next;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// One above the largest sequence of the ids stored under the prefix, in
+    /// the `{prefix}-{sequence}` format of the identifiers.
+    fn next_id_counter<F>(&self, prefix: &str, id_of: F) -> ProtocolResult<u64>
+    where
+        F: Fn(IbcPath) -> Option<String>,
+    {
+        let prefix: Path = prefix.to_owned().try_into()?;
+        let next = self
+            .adapter
+            .get_paths_by_prefix(&prefix)?
+            .into_iter()
+            .filter_map(|path| id_of(path.try_into().ok()?))
+            .filter_map(|id| id.rsplit('-').next()?.parse::<u64>().ok())
+            .map(|sequence| sequence + 1)
+            .max()
+            .unwrap_or_default();
+
+        Ok(next)
+    }
+
+    /// Open a write batch. Until it is committed or discarded, the keeper
+    /// writes are staged by the adapter instead of being applied. The reads
+    /// observe the staged writes, so a handler sees its own writes. Opening a
+    /// batch when one is already open is a no-op.
+    pub fn begin_write_batch(&mut self) -> ProtocolResult<()> {
+        if self.write_batch.is_none() {
+            self.adapter.begin_batch(Context::new())?;
+            self.write_batch = Some(WriteBatch {
+                counters: (self.client_counter, self.conn_counter, self.channel_counter),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Apply all the writes of the open batch atomically.
+    pub fn commit_write_batch(&mut self) -> ProtocolResult<()> {
+        if self.write_batch.take().is_some() {
+            self.adapter.commit_batch(Context::new())?;
+        }
+
+        Ok(())
+    }
+
+    /// Drop all the writes of the open batch and restore the counters.
+    pub fn discard_write_batch(&mut self) -> ProtocolResult<()> {
+        if let Some(batch) = self.write_batch.take() {
+            let (client_counter, conn_counter, channel_counter) = batch.counters;
+            self.client_counter = client_counter;
+            self.conn_counter = conn_counter;
+            self.channel_counter = channel_counter;
+            self.adapter.discard_batch(Context::new())?;
+        }
+
+        Ok(())
+    }
+
+    /// Run a message handler in a write batch, which is committed if the
+    /// handler succeeds and discarded entirely if it fails.
+    pub fn with_write_batch<T, E, F>(&mut self, handler: F) -> ProtocolResult<T>
+    where
+        E: std::fmt::Display,
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        self.begin_write_batch()?;
+        match handler(self) {
+            Ok(ret) => {
+                self.commit_write_batch()?;
+                Ok(ret)
+            }
+            Err(e) => {
+                self.discard_write_batch()?;
+                Err(IbcError::Handler(e.to_string()).into())
+            }
+        }
+    }
+
+    /// Write everything held in memory to the adapter, which is done on a
+    /// graceful shutdown. The open write batch is committed and the
+    /// connection counter is persisted. The host consensus states are rebuilt
+    /// from the headers, so they are not written.
+    pub fn flush(&mut self) -> ProtocolResult<()> {
+        self.commit_write_batch()?;
+        self.adapter
+            .set_connection_counter(Context::new(), self.conn_counter)
+    }
+
+    // The writes of an open batch are staged by the adapter itself.
+    fn write<F>(&mut self, write: F) -> ProtocolResult<()>
+    where
+        F: FnOnce(&Adapter) -> ProtocolResult<()>,
+    {
+        write(&self.adapter)
+    }
+
+    /// Store an alternative consensus state of a forked counterparty at
+    /// `height`, keyed by the hash of its commitment root. It is persisted
+    /// aside from the client store until it is confirmed, or until a client
+    /// update canonicalizes the height. A frozen client holds no
+    /// alternatives, and an alternative conflicting with the canonical
+    /// consensus state at `height` is evidence of a misbehaving counterparty,
+    /// so both are rejected.
+    pub fn store_fork_consensus_state(
+        &mut self,
+        client_id: ClientId,
+        height: Height,
+        consensus_state: AnyConsensusState,
+    ) -> Result<Hash, ClientError> {
+        let hash = fork_hash(&consensus_state);
+
+        let client_state = self
+            .adapter
+            .get_current_client_state(Context::new(), &client_id)
+            .map_err(|_| ClientError::implementation_specific())?;
+        if client_state.map_or(false, |state| state.is_frozen()) {
+            return Err(ClientError::client_frozen(client_id));
+        }
+
+        let canonical = self
+            .adapter
+            .get_current_consensus_state(
+                Context::new(),
+                &client_id,
+                height.revision_number(),
+                height.revision_height(),
+            )
+            .map_err(|_| ClientError::implementation_specific())?;
+        if let Some(canonical) = canonical {
+            if fork_hash(&canonical) == hash {
+                return Ok(hash);
+            }
+            log::warn!(
+                "[ibc] fork consensus state of client {} conflicts with the canonical one at {}",
+                client_id,
+                height
+            );
+            return Err(ClientError::header_verification_failure(format!(
+                "consensus state {:?} conflicts with the canonical one at {}",
+                hash, height
+            )));
+        }
+
+        let mut states = self.read_fork_consensus_states(&client_id)?;
+        if !states
+            .iter()
+            .any(|(h, state)| *h == height && fork_hash(state) == hash)
+        {
+            states.push((height, consensus_state));
+        }
+        self.write(move |adapter| {
+            adapter.set_fork_consensus_states(Context::new(), client_id, states)
+        })
+        .map_err(|_| ClientError::implementation_specific())?;
+
+        Ok(hash)
+    }
+
+    pub fn fork_consensus_states(
+        &self,
+        client_id: &ClientId,
+        height: Height,
+    ) -> Result<Vec<(Hash, AnyConsensusState)>, ClientError> {
+        Ok(self
+            .read_fork_consensus_states(client_id)?
+            .into_iter()
+            .filter(|(h, _)| *h == height)
+            .map(|(_, state)| (fork_hash(&state), state))
+            .collect())
+    }
+
+    /// Confirm the alternative consensus state `hash` at `height` as the
+    /// canonical one and write it to the client store, which discards the
+    /// other alternatives, see `resolve_fork_consensus_states`.
+    pub fn confirm_fork_consensus_state(
+        &mut self,
+        client_id: ClientId,
+        height: Height,
+        hash: Hash,
+    ) -> Result<(), ClientError> {
+        let consensus_state = self
+            .fork_consensus_states(&client_id, height)?
+            .into_iter()
+            .find(|(h, _)| *h == hash)
+            .map(|(_, state)| state)
+            .ok_or_else(|| ClientError::consensus_state_not_found(client_id.clone(), height))?;
+
+        self.store_consensus_state(client_id, height, consensus_state)
+    }
+
+    fn read_fork_consensus_states(
+        &self,
+        client_id: &ClientId,
+    ) -> Result<Vec<(Height, AnyConsensusState)>, ClientError> {
+        self.adapter
+            .get_fork_consensus_states(Context::new(), client_id)
+            .map_err(|_| ClientError::implementation_specific())
+    }
+
+    /// Once a client update stores the verified consensus state at `height`,
+    /// the alternatives at it and at the heights below it can never be
+    /// confirmed any more, so they are discarded. An alternative at `height`
+    /// other than the verified one was on a branch the counterparty
+    /// abandoned.
+    fn resolve_fork_consensus_states(
+        &mut self,
+        client_id: &ClientId,
+        height: Height,
+        canonical: &AnyConsensusState,
+    ) -> Result<(), ClientError> {
+        let (resolved, pending): (Vec<_>, Vec<_>) = self
+            .read_fork_consensus_states(client_id)?
+            .into_iter()
+            .partition(|(h, _)| *h <= height);
+        if resolved.is_empty() {
+            return Ok(());
+        }
+
+        let canonical_hash = fork_hash(canonical);
+        for (_, state) in resolved.iter().filter(|(h, _)| *h == height) {
+            if fork_hash(state) != canonical_hash {
+                log::info!(
+                    "[ibc] discard the fork consensus state {:?} of client {} at {}",
+                    fork_hash(state),
+                    client_id,
+                    height
+                );
+            }
+        }
+
+        let client_id = client_id.clone();
+        self.write(move |adapter| {
+            adapter.set_fork_consensus_states(Context::new(), client_id, pending)
+        })
+        .map_err(|_| ClientError::implementation_specific())
+    }
+
+    /// Keep the consensus states of the clients for at least `retention`
+    /// instead of only the trusting period of each client.
+    pub fn set_consensus_state_retention(&mut self, retention: Option<Duration>) {
+        self.consensus_state_retention = retention;
+    }
+
+    /// Set the revision of the host chain, which all the host heights are
+    /// reported in.
+    pub fn set_revision_number(&mut self, revision_number: u64) {
+        self.revision_number = revision_number;
+    }
+
+    /// Write the acknowledgement of a received packet whose module answered
+    /// `OnRecvPacketAck::Nil` on receipt, because its processing spans beyond
+    /// the receiving transaction. The ack commitment is stored and the
+    /// `WriteAcknowledgement` event to relay is returned.
+    pub fn write_acknowledgement(
+        &mut self,
+        packet: Packet,
+        ack: Vec<u8>,
+    ) -> Result<IbcEvent, ChannelError> {
+        if ack.is_empty() {
+            return Err(ChannelError::invalid_acknowledgement());
+        }
+
+        let port_channel_id = (
+            packet.destination_port.clone(),
+            packet.destination_channel.clone(),
+        );
+        let channel_end = ChannelReader::channel_end(self, &port_channel_id)?;
+        let received = if channel_end.ordering() == &Order::Ordered {
+            self.get_next_sequence_recv(&port_channel_id)? > packet.sequence
+        } else {
+            self.get_packet_receipt(&(
+                port_channel_id.0.clone(),
+                port_channel_id.1.clone(),
+                packet.sequence,
+            ))
+            .is_ok()
+        };
+        if !received {
+            log::warn!(
+                "[ibc] write acknowledgement of packet {} which is not received",
+                packet.sequence
+            );
+            return Err(ChannelError::implementation_specific());
+        }
+
+        let key = (port_channel_id.0, port_channel_id.1, packet.sequence);
+        if self.get_packet_acknowledgement(&key).is_ok() {
+            return Err(ChannelError::acknowledgement_exists(packet.sequence));
+        }
+
+        let commitment = AcknowledgementCommitment::from(ChannelReader::hash(self, ack.clone()));
+        self.store_packet_acknowledgement(key.clone(), commitment)?;
+        self.store_written_acknowledgement(key, ack.clone())?;
+
+        Ok(IbcEvent::WriteAcknowledgement(WriteAcknowledgement {
+            height: self.current_host_height(),
+            packet,
+            ack,
+        }))
+    }
+
+    /// Send a packet on its source channel. The packet is checked by the
+    /// ICS-04 send handler, which stores its commitment and advances the next
+    /// send sequence of the channel.
+    pub fn send_packet(&mut self, packet: Packet) -> Result<IbcEvent, ChannelError> {
+        let output = send_packet::send_packet(&*self, packet.clone())?;
+        self.store_packet_result(output.result)?;
+        self.store_sent_packet(packet.clone())?;
+
+        Ok(IbcEvent::SendPacket(SendPacket {
+            height: self.current_host_height(),
+            packet,
+        }))
+    }
+
+    /// Store a sent packet in full next to its commitment, which only keeps
+    /// its hash, so that a relayer can query the packet data back until it is
+    /// acknowledged or timed out.
+    fn store_sent_packet(&mut self, packet: Packet) -> Result<(), ChannelError> {
+        let key = (
+            packet.source_port.clone(),
+            packet.source_channel.clone(),
+            packet.sequence,
+        );
+        match self.write(move |adapter| adapter.set_packet(Context::new(), key, packet)) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(ChannelError::implementation_specific()),
+        }
+    }
+
+    /// Store a written acknowledgement next to its commitment, which only
+    /// keeps its hash, so that the queries can tell a success from an error.
+    fn store_written_acknowledgement(
+        &mut self,
+        key: (PortId, ChannelId, Sequence),
+        ack: Vec<u8>,
+    ) -> Result<(), ChannelError> {
+        match self.write(move |adapter| adapter.set_acknowledgement(Context::new(), key, ack)) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(ChannelError::implementation_specific()),
+        }
+    }
+
+    /// Check that the sequences of a channel are tracked the way its ordering
+    /// requires. An ordered channel advances its next recv sequence and never
+    /// stores receipts, while an unordered one stores receipts and keeps its
+    /// next recv sequence at the initial 1. A violation is a handler bug.
+    pub fn check_sequence_tracking(
+        &self,
+        port_channel_id: &(PortId, ChannelId),
+    ) -> Result<(), IbcError> {
+        let (port_id, channel_id) = port_channel_id;
+        let inconsistent = |reason: String| IbcError::InconsistentSequenceTracking {
+            port_id: port_id.to_string(),
+            channel_id: channel_id.to_string(),
+            reason,
+        };
+
+        let channel_end = ChannelReader::channel_end(self, port_channel_id)
+            .map_err(|e| inconsistent(format!("channel end: {}", e)))?;
+
+        if channel_end.ordering() == &Order::Ordered {
+            let prefix: Path = format!(
+                "receipts/ports/{}/channels/{}/sequences",
+                port_id, channel_id
+            )
+            .try_into()
+            .map_err(|e| inconsistent(format!("receipts path: {:?}", e)))?;
+            let receipts = self
+                .adapter
+                .get_paths_by_prefix(&prefix)
+                .map_err(|e| inconsistent(format!("receipts: {}", e)))?;
+            if !receipts.is_empty() {
+                return Err(inconsistent(format!(
+                    "ordered channel has {} receipts",
+                    receipts.len()
+                )));
+            }
+        } else if let Ok(seq) = self.get_next_sequence_recv(port_channel_id) {
+            if seq > Sequence::from(1) {
+                return Err(inconsistent(format!(
+                    "unordered channel has next recv sequence {}",
+                    seq
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that a packet to receive has not timed out on the host, which
+    /// must be done before handling a `MsgRecvPacket`. A packet past its
+    /// timeout height or timestamp is to be timed out on the source chain
+    /// instead. A zero timeout height or timestamp is unbounded.
+    pub fn check_recv_packet_timeout(&self, packet: &Packet) -> Result<(), IbcError> {
+        let host_height = self.current_host_height();
+        let timed_out = |reason: String| IbcError::PacketTimedOut {
+            sequence: u64::from(packet.sequence),
+            reason,
+        };
+
+        if packet.timeout_height.has_expired(host_height) {
+            return Err(timed_out(format!(
+                "timeout height is reached at host height {}",
+                host_height
+            )));
+        }
+
+        let timeout_timestamp = packet.timeout_timestamp.nanoseconds();
+        if timeout_timestamp != 0 {
+            let host_timestamp = ClientReader::host_consensus_state(self, host_height)
+                .map_err(|e| IbcError::Handler(e.to_string()))?
+                .timestamp()
+                .nanoseconds();
+            if host_timestamp >= timeout_timestamp {
+                return Err(timed_out(format!(
+                    "timeout timestamp {} is reached at host timestamp {}",
+                    timeout_timestamp, host_timestamp
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The hard check of the keeper calls, which is only run in debug builds.
+    #[cfg(debug_assertions)]
+    fn debug_check_ordering(
+        &self,
+        port_channel_id: &(PortId, ChannelId),
+        expected: Order,
+        call: &str,
+    ) {
+        if let Ok(channel_end) = ChannelReader::channel_end(self, port_channel_id) {
+            if channel_end.ordering() != &expected {
+                panic!(
+                    "{} on {:?} channel {}/{}",
+                    call,
+                    channel_end.ordering(),
+                    port_channel_id.0,
+                    port_channel_id.1
+                );
+            }
+        }
+    }
+
+    /// The highest height of the consensus states stored for the client,
+    /// which is tracked on each store instead of scanning all the heights. It
+    /// is persisted by the adapter, so it survives a restart and is rolled
+    /// back along with a discarded write batch.
+    pub fn client_latest_height(&self, client_id: &ClientId) -> ProtocolResult<Option<Height>> {
+        self.adapter
+            .get_client_latest_height(StoreHeight::Pending, client_id)
+    }
+
+    /// Bound the number of the host consensus states cached in memory. The
+    /// least recently used ones are evicted, and reloaded from the adapter
+    /// when they are accessed again.
+    pub fn set_consensus_state_cache_size(&mut self, size: NonZeroUsize) {
+        self.consensus_states.get_mut().resize(size);
+    }
+
+    // The adapter reads the headers asynchronously, so an evicted consensus
+    // state can only be reloaded in a multi-thread runtime. Elsewhere the
+    // reload fails as an error of the context, since the state may well be
+    // kept by the node, and only a header the adapter doesn't have is
+    // reported as a missing consensus state.
+    fn reload_host_consensus_state(
+        &self,
+        height: ibc::Height,
+    ) -> Result<ConsensusState, ClientError> {
+        let rt = match protocol::tokio::runtime::Handle::try_current() {
+            Ok(rt) if rt.runtime_flavor() == RuntimeFlavor::MultiThread => rt,
+            _ => {
+                log::error!(
+                    "[ibc] reload host consensus state at {}: not in a multi-thread runtime",
+                    height
+                );
+                return Err(ClientError::implementation_specific());
+            }
+        };
+
+        let header = protocol::tokio::task::block_in_place(|| {
+            rt.block_on(self.adapter.get_header_by_height(height.revision_height()))
+        })
+        .map_err(|_| ClientError::missing_local_consensus_state(height))?;
+        host_consensus_state(&header).map_err(|e| {
+            log::error!("[ibc] reload host consensus state at {}: {}", height, e);
+            ClientError::implementation_specific()
+        })
+    }
+
+    // A channel in the handshake or open must go over existing open
+    // connections, or it can never relay a packet.
+    fn check_connection_hops(&self, channel_end: &ChannelEnd) -> Result<(), ChannelError> {
+        if *channel_end.state() == ChannelState::Closed {
+            return Ok(());
+        }
+
+        for conn_id in channel_end.connection_hops() {
+            match self
+                .adapter
+                .get_connection_end_by_id(Context::new(), conn_id)
+            {
+                Ok(Some(connection_end)) if connection_end.is_open() => {}
+                Ok(Some(_)) => return Err(ChannelError::connection_not_open(conn_id.clone())),
+                Ok(None) => return Err(ChannelError::missing_connection(conn_id.clone())),
+                Err(_) => return Err(ChannelError::implementation_specific()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Bind a port to the module handling its channels.
+    pub fn bind_port(&mut self, port_id: PortId, module_id: ModuleId) -> Result<(), String> {
+        if self.port_to_module_map.contains_key(&port_id) {
+            return Err(format!("port {} is bound already", port_id));
+        }
+
+        self.port_to_module_map.insert(port_id, module_id);
+        Ok(())
+    }
+
+    /// The adapter reports height 0 before the genesis block is committed, but
+    /// an IBC height must be non-zero, so it is clamped to the oldest height.
+    fn current_host_height(&self) -> Height {
+        let revision_height = self.adapter.current_height().max(1);
+        Height::new(self.revision_number, revision_height).expect("the revision height is non-zero")
+    }
+
+    /// Reject opening a new channel on a connection which already has `max`
+    /// channels. `None` means unlimited.
+    pub fn set_max_channels_per_connection(&mut self, max: Option<usize>) {
+        self.max_channels_per_connection = max;
+    }
+
+    /// Remove the consensus states of a client which are older than its
+    /// trusting period at `now`, since they can no longer be used to verify
+    /// proofs. The consensus state at the latest height of the client is
+    /// always kept. The oldest ones beyond `with_max_client_consensus_states`
+    /// are removed as well. Returns the number of the removed consensus
+    /// states.
+    pub fn prune_consensus_states(
+        &mut self,
+        client_id: &ClientId,
+        now: Timestamp,
+    ) -> Result<usize, ClientError> {
+        let client_state = ClientReader::client_state(self, client_id)?;
+        let trusting_period = match &client_state {
+            AnyClientState::Tendermint(state) => state.trusting_period,
+            #[allow(unreachable_patterns)]
+            _ => return Ok(0),
+        };
+        let retention = self
+            .consensus_state_retention
+            .map_or(trusting_period, |r| r.max(trusting_period));
+        let latest_height = client_state.latest_height();
+
+        // The timestamps are monotonic in the heights, so the walk stops at
+        // the first state which is not expired.
+        let mut pruned = self.prune_oldest_consensus_states(client_id, |ibc, height| {
+            if height >= latest_height {
+                return Ok(false);
+            }
+            let consensus_state = ibc
+                .adapter
+                .get_current_consensus_state(
+                    Context::new(),
+                    client_id,
+                    height.revision_number(),
+                    height.revision_height(),
+                )
+                .map_err(|_| ClientError::implementation_specific())?;
+
+            Ok(consensus_state.map_or(true, |state| {
+                let expiry = state
+                    .timestamp()
+                    .nanoseconds()
+                    .saturating_add(retention.as_nanos() as u64);
+                expiry <= now.nanoseconds()
+            }))
+        })?;
+
+        if let Some(max) = self.max_client_consensus_states {
+            let newest = self
+                .adapter
+                .get_consensus_heights(Context::new(), client_id, None, IterDirection::Reverse, max)
+                .map_err(|_| ClientError::implementation_specific())?;
+
+            // The states below the oldest of the newest `max` ones are excess.
+            if newest.len() == max {
+                let oldest_kept = newest
+                    .last()
+                    .map_or(latest_height, |h| (*h).min(latest_height));
+                pruned += self.prune_oldest_consensus_states(client_id, |_, height| {
+                    Ok(height < oldest_kept)
+                })?;
+            }
+        }
+
+        Ok(pruned)
+    }
+
+    /// Remove the consensus states of a client from the oldest one, as long as
+    /// `expired` holds for their heights. The heights are read in chunks from
+    /// the ordered index of the store, so that a prune only reads the states
+    /// it removes and the one it stops at. Returns the number of the removed
+    /// consensus states.
+    fn prune_oldest_consensus_states<F>(
+        &mut self,
+        client_id: &ClientId,
+        mut expired: F,
+    ) -> Result<usize, ClientError>
+    where
+        F: FnMut(&Self, Height) -> Result<bool, ClientError>,
+    {
+        let mut pruned = 0;
+
+        loop {
+            // The removed states are gone from the next chunk.
+            let heights = self
+                .adapter
+                .get_consensus_heights(
+                    Context::new(),
+                    client_id,
+                    None,
+                    IterDirection::Forward,
+                    PRUNE_CHUNK_SIZE,
+                )
+                .map_err(|_| ClientError::implementation_specific())?;
+            let chunk_len = heights.len();
+
+            for height in heights {
+                if !expired(self, height)? {
+                    return Ok(pruned);
+                }
+
+                let id = client_id.clone();
+                self.write(move |adapter| {
+                    adapter.remove_consensus_state(Context::new(), id, height)
+                })
+                .map_err(|_| ClientError::implementation_specific())?;
+                pruned += 1;
+            }
+
+            if chunk_len < PRUNE_CHUNK_SIZE {
+                return Ok(pruned);
+            }
+        }
+    }
+
+    /// IBC assumes the time of a client is monotonic, so the timestamp of a
+    /// consensus state must be after the one at the previous stored height and
+    /// before the one at the next stored height.
+    pub fn check_consensus_state_timestamp(
+        &self,
+        client_id: &ClientId,
+        height: Height,
+        consensus_state: &AnyConsensusState,
+    ) -> Result<(), IbcError> {
+        let timestamp = consensus_state.timestamp().nanoseconds();
+        let err = || IbcError::NonMonotonicTimestamp {
+            client_id: client_id.to_string(),
+            height:    height.to_string(),
+        };
+
+        // A failed read can not tell the state is monotonic, so it rejects
+        // the state as well.
+        let prev = self
+            .adapter
+            .get_prev_consensus_state(Context::new(), client_id, height)
+            .map_err(|e| IbcError::Handler(e.to_string()))?;
+        if let Some(prev) = prev {
+            if prev.timestamp().nanoseconds() >= timestamp {
+                return Err(err());
+            }
+        }
+
+        let next = self
+            .adapter
+            .get_next_consensus_state(Context::new(), client_id, height)
+            .map_err(|e| IbcError::Handler(e.to_string()))?;
+        if let Some(next) = next {
+            if next.timestamp().nanoseconds() <= timestamp {
+                return Err(err());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The key of an alternative consensus state, which is the hash of its
+/// commitment root.
+fn fork_hash(consensus_state: &AnyConsensusState) -> Hash {
+    Hasher::digest(consensus_state.root().as_bytes())
+}
+
+fn host_consensus_state(header: &Header) -> ProtocolResult<ConsensusState> {
+    let timestamp = TmTime::from_unix_timestamp(header.timestamp as i64, 0)
+        .map_err(|_| IbcError::InvalidTimestamp(header.timestamp))?;
+
+    // Axon does not have a tendermint validator set, so the next validators
+    // hash is left empty.
+    Ok(ConsensusState::new(
+        CommitmentRoot::from_bytes(header.state_root.as_bytes()),
+        timestamp,
+        TmHash::None,
+    ))
 }
 
 impl<Adapter, Router> ClientReader for IbcImpl<Adapter, Router>
@@ -135,15 +1099,21 @@ where
     }
 
     fn host_height(&self) -> ibc::Height {
-        Height::new(0, self.adapter.current_height()).unwrap()
+        self.current_host_height()
     }
 
     fn host_consensus_state(&self, height: ibc::Height) -> Result<AnyConsensusState, ClientError> {
-        let consensus_state = self
+        let revision_height = height.revision_height();
+        if let Some(consensus_state) = self.consensus_states.lock().get(&revision_height) {
+            return Ok(AnyConsensusState::Tendermint(consensus_state.clone()));
+        }
+
+        let consensus_state = self.reload_host_consensus_state(height)?;
+        let _ = self
             .consensus_states
-            .get(&height.revision_height())
-            .ok_or_else(|| ClientError::missing_local_consensus_state(height))?;
-        Ok(AnyConsensusState::Tendermint(consensus_state.clone()))
+            .lock()
+            .put(revision_height, consensus_state.clone());
+        Ok(AnyConsensusState::Tendermint(consensus_state))
     }
 
     fn pending_host_consensus_state(&self) -> Result<AnyConsensusState, ClientError> {
@@ -166,8 +1136,7 @@ where
         client_type: ClientType,
     ) -> Result<(), ClientError> {
         match self
-            .adapter
-            .set_client_type(Context::new(), client_id, client_type)
+            .write(move |adapter| adapter.set_client_type(Context::new(), client_id, client_type))
         {
             Ok(_) => Ok(()),
             Err(_) => Err(ClientError::implementation_specific()),
@@ -179,10 +1148,15 @@ where
         client_id: ClientId,
         client_state: AnyClientState,
     ) -> Result<(), ClientError> {
-        match self
-            .adapter
-            .set_client_state(Context::new(), client_id, client_state)
-        {
+        // A client frozen for a misbehaviour is never updated again, so its
+        // alternative consensus states are dropped with it.
+        let frozen = client_state.is_frozen();
+        match self.write(move |adapter| {
+            if frozen {
+                adapter.set_fork_consensus_states(Context::new(), client_id.clone(), vec![])?;
+            }
+            adapter.set_client_state(Context::new(), client_id, client_state)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ClientError::implementation_specific()),
         }
@@ -194,13 +1168,40 @@ where
         height: ibc::Height,
         consensus_state: AnyConsensusState,
     ) -> Result<(), ClientError> {
-        match self
-            .adapter
-            .set_consensus_state(Context::new(), client_id, height, consensus_state)
+        // A consensus state out of the order of the stored ones is a bad msg,
+        // while a failed read is a failure of the store.
+        match self.check_consensus_state_timestamp(&client_id, height, &consensus_state) {
+            Ok(()) => {}
+            Err(e @ IbcError::NonMonotonicTimestamp { .. }) => {
+                log::warn!("[ibc] reject consensus state: {}", e);
+                return Err(ClientError::header_verification_failure(e.to_string()));
+            }
+            Err(e) => {
+                log::warn!("[ibc] check consensus state: {}", e);
+                return Err(ClientError::implementation_specific());
+            }
+        }
+
+        let is_latest = match self.client_latest_height(&client_id) {
+            Ok(latest_height) => latest_height.map_or(true, |latest| height > latest),
+            Err(_) => return Err(ClientError::implementation_specific()),
+        };
+
+        let stored = consensus_state.clone();
+        let id = client_id.clone();
+        if self
+            .write(move |adapter| {
+                if is_latest {
+                    adapter.set_client_latest_height(Context::new(), id.clone(), height)?;
+                }
+                adapter.set_consensus_state(Context::new(), id, height, stored)
+            })
+            .is_err()
         {
-            Ok(_) => Ok(()),
-            Err(_) => Err(ClientError::implementation_specific()),
+            return Err(ClientError::implementation_specific());
         }
+
+        self.resolve_fork_consensus_states(&client_id, height, &consensus_state)
     }
 
     fn increase_client_counter(&mut self) {
@@ -213,10 +1214,10 @@ where
         height: ibc::Height,
         timestamp: Timestamp,
     ) -> Result<(), ClientError> {
-        let _ = self
-            .client_processed_times
-            .insert((client_id, height), timestamp);
-        Ok(())
+        self.write(move |adapter| {
+            adapter.set_client_processed_time(Context::new(), client_id, height, timestamp)
+        })
+        .map_err(|_| ClientError::implementation_specific())
     }
 
     fn store_update_height(
@@ -225,9 +1226,30 @@ where
         height: ibc::Height,
         host_height: ibc::Height,
     ) -> Result<(), ClientError> {
-        let _ = self
-            .client_processed_heights
-            .insert((client_id, height), host_height);
+        let id = client_id.clone();
+        self.write(move |adapter| {
+            adapter.set_client_processed_height(Context::new(), id, height, host_height)
+        })
+        .map_err(|_| ClientError::implementation_specific())?;
+
+        // This is the last write of a client update, so the consensus states
+        // expired at the processed time of the new one are pruned here. The
+        // pruning is best effort and never fails the update.
+        if let Ok(Some(now)) =
+            self.adapter
+                .get_client_processed_time(Context::new(), &client_id, height)
+        {
+            match self.prune_consensus_states(&client_id, now) {
+                Ok(0) => (),
+                Ok(pruned) => log::info!(
+                    "[ibc] prune {} consensus states of client {}",
+                    pruned,
+                    client_id
+                ),
+                Err(e) => log::warn!("[ibc] prune consensus states of {}: {}", client_id, e),
+            }
+        }
+
         Ok(())
     }
 }
@@ -241,10 +1263,10 @@ where
         connection_id: ConnectionId,
         connection_end: &ConnectionEnd,
     ) -> Result<(), ConnectionError> {
-        match self
-            .adapter
-            .set_connection_end(Context::new(), connection_id, connection_end.clone())
-        {
+        let connection_end = connection_end.clone();
+        match self.write(move |adapter| {
+            adapter.set_connection_end(Context::new(), connection_id, connection_end)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ConnectionError::implementation_specific()),
         }
@@ -255,11 +1277,10 @@ where
         connection_id: ConnectionId,
         client_id: &ClientId,
     ) -> Result<(), ConnectionError> {
-        match self.adapter.set_connection_to_client(
-            Context::new(),
-            connection_id,
-            client_id.clone(),
-        ) {
+        let client_id = client_id.clone();
+        match self.write(move |adapter| {
+            adapter.set_connection_to_client(Context::new(), connection_id, client_id)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ConnectionError::implementation_specific()),
         }
@@ -267,6 +1288,13 @@ where
 
     fn increase_connection_counter(&mut self) {
         self.conn_counter += 1;
+
+        let counter = self.conn_counter;
+        if let Err(e) =
+            self.write(move |adapter| adapter.set_connection_counter(Context::new(), counter))
+        {
+            log::warn!("[ibc] persist connection counter {}: {}", counter, e);
+        }
     }
 }
 
@@ -292,8 +1320,17 @@ where
         ClientReader::host_height(self)
     }
 
+    // The reader has no way to report an error, so a failed read falls back
+    // to the current height, which rejects the consensus heights below it
+    // instead of accepting ones whose blocks may be gone.
     fn host_oldest_height(&self) -> ibc::Height {
-        Height::new(0, 1).unwrap()
+        let current_height = self.current_host_height();
+        match self.adapter.oldest_height() {
+            Ok(oldest) => Height::new(self.revision_number, oldest.max(1))
+                .expect("the revision height is non-zero")
+                .min(current_height),
+            Err(_) => current_height,
+        }
     }
 
     fn commitment_prefix(&self) -> CommitmentPrefix {
@@ -326,8 +1363,14 @@ where
     Adapter: IbcAdapter + 'static,
 {
     fn lookup_module_by_port(&self, port_id: &PortId) -> Result<ModuleId, PortError> {
-        self.port_to_module_map
-            .get(port_id)
+        let module_id = self.port_to_module_map.get(port_id);
+        log::trace!(
+            "[ibc] lookup port {} resolves to module {:?}",
+            port_id,
+            module_id
+        );
+
+        module_id
             .ok_or_else(|| PortError::unknown_port(port_id.clone()))
             .map(Clone::clone)
     }
@@ -343,8 +1386,7 @@ where
         commitment: PacketCommitment,
     ) -> Result<(), ChannelError> {
         match self
-            .adapter
-            .set_packet_commitment(Context::new(), key, commitment)
+            .write(move |adapter| adapter.set_packet_commitment(Context::new(), key, commitment))
         {
             Ok(_) => Ok(()),
             Err(_) => Err(ChannelError::implementation_specific()),
@@ -355,7 +1397,10 @@ where
         &mut self,
         key: (PortId, ChannelId, Sequence),
     ) -> Result<(), ChannelError> {
-        match self.adapter.remove_packet_commitment(Context::new(), key) {
+        match self.write(move |adapter| {
+            adapter.remove_packet_commitment(Context::new(), key.clone())?;
+            adapter.remove_packet(Context::new(), key)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ChannelError::implementation_specific()),
         }
@@ -366,10 +1411,14 @@ where
         key: (PortId, ChannelId, Sequence),
         receipt: Receipt,
     ) -> Result<(), ChannelError> {
-        match self
-            .adapter
-            .set_packet_receipt(Context::new(), key, receipt)
-        {
+        #[cfg(debug_assertions)]
+        self.debug_check_ordering(
+            &(key.0.clone(), key.1.clone()),
+            Order::Unordered,
+            "store packet receipt",
+        );
+
+        match self.write(move |adapter| adapter.set_packet_receipt(Context::new(), key, receipt)) {
             Ok(_) => Ok(()),
             Err(_) => Err(ChannelError::implementation_specific()),
         }
@@ -380,10 +1429,9 @@ where
         key: (PortId, ChannelId, Sequence),
         ack_commitment: AcknowledgementCommitment,
     ) -> Result<(), ChannelError> {
-        match self
-            .adapter
-            .set_packet_acknowledgement(Context::new(), key, ack_commitment)
-        {
+        match self.write(move |adapter| {
+            adapter.set_packet_acknowledgement(Context::new(), key, ack_commitment)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ChannelError::implementation_specific()),
         }
@@ -396,12 +1444,20 @@ where
         self.store_packet_acknowledgement(key, vec![].into())
     }
 
+    // The channel is also indexed by `store_channel`, and the index ignores
+    // the channels already in it.
     fn store_connection_channels(
         &mut self,
-        _conn_id: ConnectionId,
-        _port_channel_id: &(PortId, ChannelId),
+        conn_id: ConnectionId,
+        port_channel_id: &(PortId, ChannelId),
     ) -> Result<(), ChannelError> {
-        todo!()
+        let port_channel_id = port_channel_id.clone();
+        match self.write(move |adapter| {
+            adapter.set_connection_channels(Context::new(), conn_id, &port_channel_id)
+        }) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(ChannelError::implementation_specific()),
+        }
     }
 
     fn store_channel(
@@ -409,10 +1465,42 @@ where
         (port_id, chan_id): (PortId, ChannelId),
         channel_end: &ibc::core::ics04_channel::channel::ChannelEnd,
     ) -> Result<(), ChannelError> {
-        match self
-            .adapter
-            .set_channel(Context::new(), port_id, chan_id, channel_end.clone())
-        {
+        self.check_connection_hops(channel_end)?;
+
+        if let (Some(max), Some(conn_id)) = (
+            self.max_channels_per_connection,
+            channel_end.connection_hops().first(),
+        ) {
+            let port_channel_id = (port_id.clone(), chan_id.clone());
+            let is_new = !matches!(
+                self.adapter
+                    .get_channel_end_by_id(Context::new(), &port_channel_id),
+                Ok(Some(_))
+            );
+            if is_new && self.connection_channels(conn_id)?.len() >= max {
+                log::warn!(
+                    "[ibc] reject channel {}/{}: connection {} has reached the limit of {} channels",
+                    port_id,
+                    chan_id,
+                    conn_id,
+                    max
+                );
+                return Err(ChannelError::implementation_specific());
+            }
+        }
+
+        let conn_id = channel_end.connection_hops().first().cloned();
+        let channel_end = channel_end.clone();
+        match self.write(move |adapter| {
+            if let Some(conn_id) = conn_id {
+                adapter.set_connection_channels(
+                    Context::new(),
+                    conn_id,
+                    &(port_id.clone(), chan_id.clone()),
+                )?;
+            }
+            adapter.set_channel(Context::new(), port_id, chan_id, channel_end)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ChannelError::implementation_specific()),
         }
@@ -423,10 +1511,9 @@ where
         (port_id, chan_id): (PortId, ChannelId),
         seq: Sequence,
     ) -> Result<(), ChannelError> {
-        match self
-            .adapter
-            .set_next_sequence_send(Context::new(), port_id, chan_id, seq)
-        {
+        match self.write(move |adapter| {
+            adapter.set_next_sequence_send(Context::new(), port_id, chan_id, seq)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ChannelError::implementation_specific()),
         }
@@ -437,10 +1524,19 @@ where
         (port_id, chan_id): (PortId, ChannelId),
         seq: Sequence,
     ) -> Result<(), ChannelError> {
-        match self
-            .adapter
-            .set_next_sequence_recv(Context::new(), port_id, chan_id, seq)
-        {
+        // The next recv sequence of every channel is initialized to 1 on open.
+        #[cfg(debug_assertions)]
+        if seq > Sequence::from(1) {
+            self.debug_check_ordering(
+                &(port_id.clone(), chan_id.clone()),
+                Order::Ordered,
+                "advance next recv sequence",
+            );
+        }
+
+        match self.write(move |adapter| {
+            adapter.set_next_sequence_recv(Context::new(), port_id, chan_id, seq)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ChannelError::implementation_specific()),
         }
@@ -451,10 +1547,9 @@ where
         (port_id, chan_id): (PortId, ChannelId),
         seq: Sequence,
     ) -> Result<(), ChannelError> {
-        match self
-            .adapter
-            .set_next_sequence_ack(Context::new(), port_id, chan_id, seq)
-        {
+        match self.write(move |adapter| {
+            adapter.set_next_sequence_ack(Context::new(), port_id, chan_id, seq)
+        }) {
             Ok(_) => Ok(()),
             Err(_) => Err(ChannelError::implementation_specific()),
         }
@@ -494,9 +1589,12 @@ where
 
     fn connection_channels(
         &self,
-        _cid: &ConnectionId,
+        cid: &ConnectionId,
     ) -> Result<Vec<(PortId, ChannelId)>, ChannelError> {
-        unimplemented!()
+        match self.adapter.get_connection_channels(Context::new(), cid) {
+            Ok(channels) => Ok(channels.unwrap_or_default()),
+            Err(_) => Err(ChannelError::implementation_specific()),
+        }
     }
 
     fn client_state(&self, client_id: &ClientId) -> Result<AnyClientState, ChannelError> {
@@ -573,7 +1671,8 @@ where
             .get_current_packet_commitment(Context::new(), key)
         {
             Ok(Some(c)) => Ok(c),
-            _ => Err(ChannelError::implementation_specific()),
+            Ok(None) => Err(ChannelError::packet_commitment_not_found(key.2)),
+            Err(_) => Err(ChannelError::implementation_specific()),
         }
     }
 
@@ -583,7 +1682,8 @@ where
     ) -> Result<Receipt, ChannelError> {
         match self.adapter.get_packet_receipt(Context::new(), key) {
             Ok(Some(r)) => Ok(r),
-            _ => Err(ChannelError::implementation_specific()),
+            Ok(None) => Err(ChannelError::packet_receipt_not_found(key.2)),
+            Err(_) => Err(ChannelError::implementation_specific()),
         }
     }
 
@@ -593,27 +1693,28 @@ where
     ) -> Result<AcknowledgementCommitment, ChannelError> {
         match self.adapter.get_packet_acknowledgement(Context::new(), key) {
             Ok(Some(r)) => Ok(r),
-            _ => Err(ChannelError::implementation_specific()),
+            Ok(None) => Err(ChannelError::packet_acknowledgement_not_found(key.2)),
+            Err(_) => Err(ChannelError::implementation_specific()),
         }
     }
 
+    // ICS024 requires the commitments to be SHA-256, which the keccak
+    // `Hasher` of Axon is not.
     fn hash(&self, value: Vec<u8>) -> Vec<u8> {
-        Hasher::digest(value).as_bytes().to_vec()
+        Sha256::digest(&value).to_vec()
     }
 
     fn host_height(&self) -> ibc::Height {
-        Height::new(0, self.adapter.current_height()).unwrap()
+        self.current_host_height()
     }
 
-    fn host_consensus_state(
-        &self,
-        _height: ibc::Height,
-    ) -> Result<AnyConsensusState, ChannelError> {
-        unimplemented!()
+    fn host_consensus_state(&self, height: ibc::Height) -> Result<AnyConsensusState, ChannelError> {
+        ConnectionReader::host_consensus_state(self, height).map_err(ChannelError::ics03_connection)
     }
 
     fn pending_host_consensus_state(&self) -> Result<AnyConsensusState, ChannelError> {
-        unimplemented!()
+        ClientReader::pending_host_consensus_state(self)
+            .map_err(|e| ChannelError::ics03_connection(ConnectionError::ics02_client(e)))
     }
 
     fn client_update_time(
@@ -622,20 +1723,34 @@ where
         height: ibc::Height,
     ) -> Result<Timestamp, ChannelError> {
         match self
-            .client_processed_times
-            .get(&(client_id.clone(), height))
+            .adapter
+            .get_client_processed_time(Context::new(), client_id, height)
         {
-            Some(t) => Ok(*t),
-            None => Err(ChannelError::implementation_specific()),
+            Ok(Some(t)) => Ok(t),
+            Ok(None) => Err(ChannelError::processed_time_not_found(
+                client_id.clone(),
+                height,
+            )),
+            Err(_) => Err(ChannelError::implementation_specific()),
         }
     }
 
     fn client_update_height(
         &self,
-        _client_id: &ClientId,
-        _height: ibc::Height,
+        client_id: &ClientId,
+        height: ibc::Height,
     ) -> Result<ibc::Height, ChannelError> {
-        unimplemented!()
+        match self
+            .adapter
+            .get_client_processed_height(Context::new(), client_id, height)
+        {
+            Ok(Some(h)) => Ok(h),
+            Ok(None) => Err(ChannelError::processed_height_not_found(
+                client_id.clone(),
+                height,
+            )),
+            Err(_) => Err(ChannelError::implementation_specific()),
+        }
     }
 
     fn channel_counter(&self) -> Result<u64, ChannelError> {
@@ -643,7 +1758,7 @@ where
     }
 
     fn max_expected_time_per_block(&self) -> std::time::Duration {
-        unimplemented!()
+        self.block_interval
     }
 }
 
@@ -659,14 +1774,85 @@ impl<Adapter: IbcAdapter + 'static> Ics26Context for IbcImpl<Adapter, IbcRouter>
     }
 }
 
-pub struct IbcRouter;
+impl<Adapter: IbcAdapter + 'static> IbcImpl<Adapter, IbcRouter> {
+    /// Register the ICS-20 transfer module under `TRANSFER_MODULE_ID`, which
+    /// is `transfer`, and bind the `transfer` port to it.
+    pub fn setup_transfer_module<E: TransferExecutor + 'static>(
+        &mut self,
+        executor: Arc<E>,
+    ) -> Result<(), String> {
+        let module_id = TransferModule::<E>::module_id();
+        self.router.add_route_with_version(
+            module_id.clone(),
+            TransferModule::new(executor),
+            Version::new(transfer::TRANSFER_VERSION.to_string()),
+        )?;
+        self.bind_port(PortId::transfer(), module_id)
+    }
+}
+
+/// Routes the channel and packet callbacks to the modules by module id.
+#[derive(Default)]
+pub struct IbcRouter {
+    modules:      BTreeMap<ModuleId, Box<dyn Module>>,
+    app_versions: BTreeMap<ModuleId, Version>,
+}
+
+impl IbcRouter {
+    pub fn add_route(&mut self, module_id: ModuleId, module: impl Module) -> Result<(), String> {
+        if self.modules.contains_key(&module_id) {
+            return Err(format!("duplicate module id {}", module_id));
+        }
+
+        self.modules.insert(module_id, Box::new(module));
+        Ok(())
+    }
+
+    /// Add a module with the app version it proposes when a channel is opened
+    /// on its ports, such as `ics20-1` for the transfer module.
+    pub fn add_route_with_version(
+        &mut self,
+        module_id: ModuleId,
+        module: impl Module,
+        app_version: Version,
+    ) -> Result<(), String> {
+        self.add_route(module_id.clone(), module)?;
+        self.app_versions.insert(module_id, app_version);
+        Ok(())
+    }
+
+    /// The app version proposed by the module, which is empty to let the
+    /// counterparty choose if the module does not propose one.
+    pub fn app_version(&self, module_id: &ModuleId) -> Version {
+        self.app_versions
+            .get(module_id)
+            .cloned()
+            .unwrap_or_else(|| Version::new(String::new()))
+    }
+}
 
 impl Router for IbcRouter {
-    fn get_route_mut(&mut self, _module_id: &impl Borrow<ModuleId>) -> Option<&mut dyn Module> {
-        todo!()
+    fn get_route_mut(&mut self, module_id: &impl Borrow<ModuleId>) -> Option<&mut dyn Module> {
+        let module_id = module_id.borrow();
+        let module = self.modules.get_mut(module_id);
+        log::trace!(
+            "[ibc] dispatch to module {}, route found: {}",
+            module_id,
+            module.is_some()
+        );
+
+        module.map(|module| module.as_mut())
     }
 
-    fn has_route(&self, _module_id: &impl Borrow<ModuleId>) -> bool {
-        todo!()
+    fn has_route(&self, module_id: &impl Borrow<ModuleId>) -> bool {
+        let module_id = module_id.borrow();
+        let found = self.modules.contains_key(module_id);
+        log::trace!(
+            "[ibc] check route of module {}, found: {}",
+            module_id,
+            found
+        );
+
+        found
     }
 }
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use ibc::core::ics04_channel::context::{ChannelKeeper, ChannelReader};
+use ibc::core::ics04_channel::error::Error as ChannelError;
+use ibc::core::ics04_channel::packet::{Receipt, Sequence};
+use ibc::core::ics24_host::identifier::{ChannelId, PortId};
+use ibc::core::ics24_host::path::ReceiptsPath;
+
+use protocol::traits::IbcGrpcAdapter;
+use protocol::types::StoreHeight;
+
+use crate::tests::{mock_ibc_impl, MockIbcAdapter};
+use crate::{IbcImpl, IbcRouter};
+
+fn recv_packet(
+    ibc: &mut IbcImpl<MockIbcAdapter, IbcRouter>,
+    seq: u64,
+    fail: bool,
+) -> Result<(), ChannelError> {
+    let port_channel = (PortId::transfer(), ChannelId::new(0));
+    let key = (
+        port_channel.0.clone(),
+        port_channel.1.clone(),
+        Sequence::from(seq),
+    );
+
+    ibc.store_packet_receipt(key.clone(), Receipt::Ok)?;
+    ibc.store_packet_acknowledgement(key, vec![1u8; 32].into())?;
+    ibc.increase_channel_counter();
+    if fail {
+        return Err(ChannelError::implementation_specific());
+    }
+    ibc.store_next_sequence_recv(port_channel, Sequence::from(seq + 1))
+}
+
+#[test]
+fn test_failed_handler_leaves_no_partial_writes() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let port_channel = (PortId::transfer(), ChannelId::new(0));
+    let key = |seq: u64| {
+        (
+            port_channel.0.clone(),
+            port_channel.1.clone(),
+            Sequence::from(seq),
+        )
+    };
+
+    assert!(ibc
+        .with_write_batch(|ibc| recv_packet(ibc, 1, true))
+        .is_err());
+    assert!(ibc.get_packet_receipt(&key(1)).is_err());
+    assert!(ibc.get_packet_acknowledgement(&key(1)).is_err());
+    assert_eq!(ibc.channel_counter().unwrap(), 0);
+
+    ibc.with_write_batch(|ibc| recv_packet(ibc, 1, false))
+        .unwrap();
+    assert!(ibc.get_packet_receipt(&key(1)).is_ok());
+    assert!(ibc.get_packet_acknowledgement(&key(1)).is_ok());
+    assert_eq!(
+        ibc.get_next_sequence_recv(&port_channel).unwrap(),
+        Sequence::from(2)
+    );
+    assert_eq!(ibc.channel_counter().unwrap(), 1);
+}
+
+#[test]
+fn test_write_batch_is_applied_on_commit() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(1));
+    let path = ReceiptsPath {
+        port_id:    key.0.clone(),
+        channel_id: key.1.clone(),
+        sequence:   key.2,
+    };
+
+    // The handler sees its staged write, which is not applied to the store
+    // until the batch is committed.
+    ibc.begin_write_batch().unwrap();
+    ibc.store_packet_receipt(key.clone(), Receipt::Ok).unwrap();
+    assert!(ibc.get_packet_receipt(&key).is_ok());
+    assert!(ibc
+        .adapter
+        .get_opt(StoreHeight::Stable(1), &path)
+        .unwrap()
+        .is_none());
+
+    ibc.commit_write_batch().unwrap();
+    assert!(ibc.get_packet_receipt(&key).is_ok());
+    assert!(ibc
+        .adapter
+        .get_opt(StoreHeight::Stable(1), &path)
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn test_flush_survives_reopen() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(1));
+
+    // A batch is still open when the node is stopped.
+    ibc.begin_write_batch().unwrap();
+    recv_packet(&mut ibc, 1, false).unwrap();
+    ibc.flush().unwrap();
+
+    let mut reopened = mock_ibc_impl(MockIbcAdapter::default());
+    reopened.adapter = Arc::clone(&ibc.adapter);
+    assert!(reopened.get_packet_receipt(&key).is_ok());
+    assert!(reopened.get_packet_acknowledgement(&key).is_ok());
+    assert_eq!(
+        reopened.get_next_sequence_recv(&(key.0, key.1)).unwrap(),
+        Sequence::from(2)
+    );
+}
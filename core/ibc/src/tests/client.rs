@@ -0,0 +1,389 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ibc::core::ics02_client::client_consensus::AnyConsensusState;
+use ibc::core::ics02_client::client_state::AnyClientState;
+use ibc::core::ics02_client::client_type::ClientType;
+use ibc::core::ics02_client::context::{ClientKeeper, ClientReader};
+use ibc::core::ics03_connection::context::ConnectionReader;
+use ibc::core::ics04_channel::context::ChannelReader;
+use ibc::core::ics24_host::identifier::ClientId;
+use ibc::timestamp::Timestamp;
+use ibc::Height;
+
+use protocol::traits::{Context, IbcAdapter};
+use protocol::types::{Header, H256};
+
+use crate::tests::{
+    mock_client_state, mock_consensus_state, mock_header, mock_ibc_impl, MockIbcAdapter,
+};
+use crate::{host_consensus_state, IbcImpl, IbcRouter, DEFAULT_BLOCK_INTERVAL};
+
+fn fork_consensus_state(number: u64) -> AnyConsensusState {
+    let header = Header {
+        state_root: H256::random(),
+        ..mock_header(number)
+    };
+    AnyConsensusState::Tendermint(host_consensus_state(&header).unwrap())
+}
+
+#[test]
+fn test_fork_consensus_states() {
+    let adapter = Arc::new(MockIbcAdapter::default());
+    let mut ibc = IbcImpl::new(Arc::clone(&adapter), IbcRouter::default()).unwrap();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let height_1 = Height::new(0, 1).unwrap();
+    let height_2 = Height::new(0, 2).unwrap();
+
+    let branch_a = fork_consensus_state(1);
+    let branch_b = fork_consensus_state(1);
+    let hash_a = ibc
+        .store_fork_consensus_state(client_id.clone(), height_1, branch_a.clone())
+        .unwrap();
+    let hash_b = ibc
+        .store_fork_consensus_state(client_id.clone(), height_1, branch_b)
+        .unwrap();
+    let hash_c = ibc
+        .store_fork_consensus_state(client_id.clone(), height_2, fork_consensus_state(2))
+        .unwrap();
+    assert_ne!(hash_a, hash_b);
+
+    // The alternatives are persisted, a new context on the same store reads
+    // them back.
+    let mut ibc = IbcImpl::new(adapter, IbcRouter::default()).unwrap();
+    assert_eq!(
+        ibc.fork_consensus_states(&client_id, height_1)
+            .unwrap()
+            .len(),
+        2
+    );
+
+    // Nothing reaches the client store before an alternative is confirmed.
+    assert!(ibc.consensus_state(&client_id, height_1).is_err());
+    assert!(ibc
+        .confirm_fork_consensus_state(client_id.clone(), height_1, H256::random())
+        .is_err());
+
+    ibc.confirm_fork_consensus_state(client_id.clone(), height_1, hash_a)
+        .unwrap();
+    assert_eq!(ibc.consensus_state(&client_id, height_1).unwrap(), branch_a);
+    assert!(ibc
+        .fork_consensus_states(&client_id, height_1)
+        .unwrap()
+        .is_empty());
+    assert!(ibc
+        .confirm_fork_consensus_state(client_id.clone(), height_1, hash_b)
+        .is_err());
+
+    // An alternative conflicting with a canonical consensus state is the
+    // evidence of a misbehaviour, while the canonical one itself is accepted.
+    assert!(ibc
+        .store_fork_consensus_state(client_id.clone(), height_1, fork_consensus_state(1))
+        .is_err());
+    assert_eq!(
+        ibc.store_fork_consensus_state(client_id.clone(), height_1, branch_a)
+            .unwrap(),
+        hash_a
+    );
+
+    // The alternatives of the higher heights are still pending.
+    assert_eq!(
+        ibc.fork_consensus_states(&client_id, height_2)
+            .unwrap()
+            .len(),
+        1
+    );
+    ibc.confirm_fork_consensus_state(client_id.clone(), height_2, hash_c)
+        .unwrap();
+    assert!(ibc.consensus_state(&client_id, height_2).is_ok());
+}
+
+#[test]
+fn test_client_update_resolves_fork_consensus_states() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let height = |h| Height::new(0, h).unwrap();
+
+    for h in 1..=3 {
+        ibc.store_fork_consensus_state(client_id.clone(), height(h), fork_consensus_state(h))
+            .unwrap();
+    }
+
+    // The update verifies a consensus state at height 2 other than the
+    // alternative, which discards the alternatives up to it.
+    ibc.store_consensus_state(client_id.clone(), height(2), mock_consensus_state(2))
+        .unwrap();
+    assert!(ibc
+        .fork_consensus_states(&client_id, height(1))
+        .unwrap()
+        .is_empty());
+    assert!(ibc
+        .fork_consensus_states(&client_id, height(2))
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        ibc.fork_consensus_states(&client_id, height(3))
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // A client frozen for a misbehaviour drops its alternatives and takes
+    // no new ones.
+    let frozen = match mock_client_state(3) {
+        AnyClientState::Tendermint(state) => {
+            AnyClientState::Tendermint(state.with_frozen_height(height(3)).unwrap())
+        }
+        _ => unreachable!(),
+    };
+    ibc.store_client_state(client_id.clone(), frozen).unwrap();
+    assert!(ibc
+        .fork_consensus_states(&client_id, height(3))
+        .unwrap()
+        .is_empty());
+    assert!(ibc
+        .store_fork_consensus_state(client_id.clone(), height(4), fork_consensus_state(4))
+        .is_err());
+}
+
+#[test]
+fn test_reject_non_monotonic_consensus_timestamp() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let height = |h| Height::new(0, h).unwrap();
+
+    ibc.store_consensus_state(client_id.clone(), height(5), mock_consensus_state(5))
+        .unwrap();
+    ibc.store_consensus_state(client_id.clone(), height(10), mock_consensus_state(10))
+        .unwrap();
+
+    // A higher height with the timestamp of a lower one goes backwards.
+    let backwards = mock_consensus_state(4);
+    assert!(ibc
+        .check_consensus_state_timestamp(&client_id, height(12), &backwards)
+        .is_err());
+    assert!(ibc
+        .store_consensus_state(client_id.clone(), height(12), backwards)
+        .is_err());
+    assert!(ibc.consensus_state(&client_id, height(12)).is_err());
+
+    // An equal timestamp to the next stored state is rejected as well.
+    assert!(ibc
+        .store_consensus_state(client_id.clone(), height(7), mock_consensus_state(10))
+        .is_err());
+
+    ibc.store_consensus_state(client_id.clone(), height(7), mock_consensus_state(7))
+        .unwrap();
+    ibc.store_consensus_state(client_id.clone(), height(12), mock_consensus_state(12))
+        .unwrap();
+}
+
+#[test]
+fn test_prune_expired_consensus_states() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    adapter
+        .set_client_state(Context::new(), client_id.clone(), mock_client_state(3))
+        .unwrap();
+    let mut ibc = mock_ibc_impl(adapter);
+    let height = |h| Height::new(0, h).unwrap();
+
+    for h in 1..=3 {
+        ibc.store_consensus_state(client_id.clone(), height(h), mock_consensus_state(h))
+            .unwrap();
+        ibc.store_update_time(client_id.clone(), height(h), timestamp_secs(0))
+            .unwrap();
+        ibc.store_update_height(client_id.clone(), height(h), height(h))
+            .unwrap();
+    }
+
+    // The trusting period of the mock client state is 64000 seconds, the
+    // consensus state at height 1 has just expired.
+    let now = timestamp_secs(mock_header(1).timestamp + 64_000);
+    ibc.set_consensus_state_retention(Some(Duration::from_secs(128_000)));
+    assert_eq!(ibc.prune_consensus_states(&client_id, now).unwrap(), 0);
+
+    ibc.set_consensus_state_retention(None);
+    assert_eq!(ibc.prune_consensus_states(&client_id, now).unwrap(), 1);
+    assert!(ibc.consensus_state(&client_id, height(1)).is_err());
+    assert!(ibc.consensus_state(&client_id, height(2)).is_ok());
+    // The processed time and height go with the consensus state.
+    assert!(ChannelReader::client_update_time(&ibc, &client_id, height(1)).is_err());
+    assert!(ChannelReader::client_update_height(&ibc, &client_id, height(1)).is_err());
+    assert!(ChannelReader::client_update_time(&ibc, &client_id, height(2)).is_ok());
+
+    // The consensus state at the latest height of the client is always kept.
+    let now = timestamp_secs(mock_header(3).timestamp + 1_000_000);
+    assert_eq!(ibc.prune_consensus_states(&client_id, now).unwrap(), 1);
+    assert!(ibc.consensus_state(&client_id, height(2)).is_err());
+    assert!(ibc.consensus_state(&client_id, height(3)).is_ok());
+}
+
+#[test]
+fn test_prune_consensus_states_stored_before_restart() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    adapter
+        .set_client_state(Context::new(), client_id.clone(), mock_client_state(3))
+        .unwrap();
+    let height = |h| Height::new(0, h).unwrap();
+    // The consensus states have no processed time in the memory of the new
+    // context.
+    for h in 1..=3 {
+        adapter
+            .set_consensus_state(
+                Context::new(),
+                client_id.clone(),
+                height(h),
+                mock_consensus_state(h),
+            )
+            .unwrap();
+    }
+    let mut ibc = mock_ibc_impl(adapter);
+
+    let now = timestamp_secs(mock_header(2).timestamp + 64_000);
+    assert_eq!(ibc.prune_consensus_states(&client_id, now).unwrap(), 2);
+    assert!(ibc.consensus_state(&client_id, height(1)).is_err());
+    assert!(ibc.consensus_state(&client_id, height(2)).is_err());
+    assert!(ibc.consensus_state(&client_id, height(3)).is_ok());
+}
+
+#[test]
+fn test_prune_consensus_states_in_chunks() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    adapter
+        .set_client_state(Context::new(), client_id.clone(), mock_client_state(100))
+        .unwrap();
+    let height = |h| Height::new(0, h).unwrap();
+    for h in 1..=100 {
+        adapter
+            .set_consensus_state(
+                Context::new(),
+                client_id.clone(),
+                height(h),
+                mock_consensus_state(h),
+            )
+            .unwrap();
+    }
+    let mut ibc = mock_ibc_impl(adapter);
+
+    // The expired states span more than one chunk of heights, and the walk
+    // stops at the first state which is kept.
+    let now = timestamp_secs(mock_header(70).timestamp + 64_000);
+    assert_eq!(ibc.prune_consensus_states(&client_id, now).unwrap(), 70);
+    assert!(ibc.consensus_state(&client_id, height(70)).is_err());
+    assert!(ibc.consensus_state(&client_id, height(71)).is_ok());
+    assert_eq!(ibc.prune_consensus_states(&client_id, now).unwrap(), 0);
+}
+
+#[test]
+fn test_max_client_consensus_states() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    adapter
+        .set_client_state(Context::new(), client_id.clone(), mock_client_state(5))
+        .unwrap();
+    let mut ibc = mock_ibc_impl(adapter).with_max_client_consensus_states(2);
+    let height = |h| Height::new(0, h).unwrap();
+
+    // None of the consensus states is expired, the oldest ones are pruned by
+    // the updates once the client has more than 2.
+    for h in 1..=4 {
+        ibc.store_consensus_state(client_id.clone(), height(h), mock_consensus_state(h))
+            .unwrap();
+        ibc.store_update_time(client_id.clone(), height(h), timestamp_secs(0))
+            .unwrap();
+        ibc.store_update_height(client_id.clone(), height(h), height(h))
+            .unwrap();
+    }
+
+    assert!(ibc.consensus_state(&client_id, height(1)).is_err());
+    assert!(ibc.consensus_state(&client_id, height(2)).is_err());
+    assert!(ibc.consensus_state(&client_id, height(3)).is_ok());
+    assert!(ibc.consensus_state(&client_id, height(4)).is_ok());
+    assert!(ChannelReader::client_update_height(&ibc, &client_id, height(2)).is_err());
+    assert!(ChannelReader::client_update_height(&ibc, &client_id, height(3)).is_ok());
+}
+
+#[test]
+fn test_client_processed_time_is_persisted() {
+    let adapter = Arc::new(MockIbcAdapter::default());
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let height = Height::new(0, 3).unwrap();
+    let mut ibc = IbcImpl::new(Arc::clone(&adapter), IbcRouter::default()).unwrap();
+    ibc.store_update_time(client_id.clone(), height, timestamp_secs(7))
+        .unwrap();
+    ibc.store_update_height(client_id.clone(), height, Height::new(0, 9).unwrap())
+        .unwrap();
+
+    // A new context on the same store reads them back.
+    let ibc = IbcImpl::new(adapter, IbcRouter::default()).unwrap();
+    assert_eq!(
+        ChannelReader::client_update_time(&ibc, &client_id, height).unwrap(),
+        timestamp_secs(7)
+    );
+    assert_eq!(
+        ChannelReader::client_update_height(&ibc, &client_id, height).unwrap(),
+        Height::new(0, 9).unwrap()
+    );
+}
+
+#[test]
+fn test_max_expected_time_per_block() {
+    let ibc = mock_ibc_impl(MockIbcAdapter::default());
+    assert_eq!(
+        ChannelReader::max_expected_time_per_block(&ibc),
+        DEFAULT_BLOCK_INTERVAL
+    );
+
+    let ibc = ibc.with_block_interval(Duration::from_millis(500));
+    assert_eq!(
+        ChannelReader::max_expected_time_per_block(&ibc),
+        Duration::from_millis(500)
+    );
+}
+
+#[test]
+fn test_host_height_revision_number() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_height(10);
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.set_revision_number(2);
+
+    let expect = Height::new(2, 10).unwrap();
+    assert_eq!(ClientReader::host_height(&ibc), expect);
+    assert_eq!(ChannelReader::host_height(&ibc), expect);
+    assert_eq!(ConnectionReader::host_current_height(&ibc), expect);
+    assert_eq!(
+        ConnectionReader::host_oldest_height(&ibc).revision_number(),
+        2
+    );
+}
+
+#[test]
+fn test_host_oldest_height() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_height(10);
+    adapter.set_oldest_height(4);
+    let ibc = mock_ibc_impl(adapter);
+
+    assert_eq!(
+        ConnectionReader::host_oldest_height(&ibc),
+        Height::new(0, 4).unwrap()
+    );
+}
+
+#[test]
+fn test_host_height_at_genesis() {
+    let ibc = mock_ibc_impl(MockIbcAdapter::default());
+
+    let oldest = ConnectionReader::host_oldest_height(&ibc);
+    assert_eq!(ClientReader::host_height(&ibc), oldest);
+    assert_eq!(ChannelReader::host_height(&ibc), oldest);
+    assert_eq!(ConnectionReader::host_current_height(&ibc), oldest);
+}
+
+fn timestamp_secs(secs: u64) -> Timestamp {
+    Timestamp::from_nanoseconds(secs * 1_000_000_000).unwrap()
+}
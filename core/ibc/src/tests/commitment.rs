@@ -0,0 +1,58 @@
+use ibc::core::ics04_channel::commitment::{AcknowledgementCommitment, PacketCommitment};
+use ibc::core::ics04_channel::context::ChannelReader;
+use ibc::timestamp::Timestamp;
+use ibc::Height;
+
+use protocol::codec::hex_decode;
+
+use crate::tests::{mock_ibc_impl, MockIbcAdapter};
+
+// The vectors follow the commitment encoding of ICS004, which must match the
+// counterparty chains to prove the packets.
+
+#[test]
+fn test_hash_is_sha256() {
+    let ibc = mock_ibc_impl(MockIbcAdapter::default());
+
+    assert_eq!(
+        ChannelReader::hash(&ibc, vec![]),
+        hex_decode("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap()
+    );
+    assert_eq!(
+        ChannelReader::hash(&ibc, b"ack".to_vec()),
+        hex_decode("64a37929fb113e18daa6263a1fb1f90c51d262552efa5a50596f5f653ba955f8").unwrap()
+    );
+}
+
+#[test]
+fn test_packet_commitment_vector() {
+    let ibc = mock_ibc_impl(MockIbcAdapter::default());
+
+    // sha256(timeout_timestamp || revision_number || revision_height ||
+    // sha256(data)), with the numbers in big endian.
+    let commitment = ChannelReader::packet_commitment(
+        &ibc,
+        b"packet data".to_vec(),
+        Height::new(0, 10).unwrap().into(),
+        Timestamp::from_nanoseconds(1_000_000_000).unwrap(),
+    );
+    assert_eq!(
+        commitment,
+        PacketCommitment::from(
+            hex_decode("a5aee5c73348e89d21009b205348114902cb8a0050a2d945834d72fa91a74c4a").unwrap()
+        )
+    );
+}
+
+#[test]
+fn test_ack_commitment_vector() {
+    let ibc = mock_ibc_impl(MockIbcAdapter::default());
+
+    let commitment = AcknowledgementCommitment::from(ChannelReader::hash(&ibc, b"ack".to_vec()));
+    assert_eq!(
+        commitment,
+        AcknowledgementCommitment::from(
+            hex_decode("64a37929fb113e18daa6263a1fb1f90c51d262552efa5a50596f5f653ba955f8").unwrap()
+        )
+    );
+}
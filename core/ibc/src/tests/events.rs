@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use ibc::core::ics02_client::client_type::ClientType;
+use ibc::core::ics03_connection::connection::{
+    ConnectionEnd, Counterparty as ConnectionCounterparty, State as ConnectionState,
+};
+use ibc::core::ics03_connection::version::get_compatible_versions;
+use ibc::core::ics04_channel::channel::{
+    ChannelEnd, Counterparty as ChannelCounterparty, Order, State as ChannelState,
+};
+use ibc::core::ics04_channel::Version;
+use ibc::core::ics23_commitment::commitment::CommitmentPrefix;
+use ibc::core::ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId};
+use ibc::events::IbcEvent;
+use ibc::Height;
+
+use crate::{channel_handshake_event, connection_handshake_event, HandshakeStep};
+
+const STEPS: [HandshakeStep; 4] = [
+    HandshakeStep::OpenInit,
+    HandshakeStep::OpenTry,
+    HandshakeStep::OpenAck,
+    HandshakeStep::OpenConfirm,
+];
+
+#[test]
+fn test_connection_handshake_event_attributes() {
+    let height = Height::new(0, 10).unwrap();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let counterparty_client_id = ClientId::new(ClientType::Tendermint, 3).unwrap();
+    let connection_end = ConnectionEnd::new(
+        ConnectionState::TryOpen,
+        client_id.clone(),
+        ConnectionCounterparty::new(
+            counterparty_client_id.clone(),
+            Some(ConnectionId::new(2)),
+            CommitmentPrefix::try_from(b"ibc".to_vec()).unwrap(),
+        ),
+        get_compatible_versions(),
+        Duration::from_secs(0),
+    );
+
+    for step in STEPS {
+        let event =
+            connection_handshake_event(step, height, &ConnectionId::new(1), &connection_end);
+        let attributes = match (step, &event) {
+            (HandshakeStep::OpenInit, IbcEvent::OpenInitConnection(e)) => e.attributes(),
+            (HandshakeStep::OpenTry, IbcEvent::OpenTryConnection(e)) => e.attributes(),
+            (HandshakeStep::OpenAck, IbcEvent::OpenAckConnection(e)) => e.attributes(),
+            (HandshakeStep::OpenConfirm, IbcEvent::OpenConfirmConnection(e)) => e.attributes(),
+            _ => panic!("unexpected event {:?} of {:?}", event, step),
+        };
+
+        assert_eq!(attributes.height, height);
+        assert_eq!(attributes.connection_id, Some(ConnectionId::new(1)));
+        assert_eq!(attributes.client_id, client_id);
+        assert_eq!(
+            attributes.counterparty_connection_id,
+            Some(ConnectionId::new(2))
+        );
+        assert_eq!(attributes.counterparty_client_id, counterparty_client_id);
+    }
+}
+
+#[test]
+fn test_channel_handshake_event_attributes() {
+    let height = Height::new(0, 10).unwrap();
+    let port_id = PortId::transfer();
+    let channel_end = ChannelEnd::new(
+        ChannelState::TryOpen,
+        Order::Unordered,
+        ChannelCounterparty::new(port_id.clone(), Some(ChannelId::new(5))),
+        vec![ConnectionId::new(1)],
+        Version::new("ics20-1".to_string()),
+    );
+
+    for step in STEPS {
+        let event =
+            channel_handshake_event(step, height, &port_id, &ChannelId::new(0), &channel_end)
+                .unwrap();
+        let attributes = match (step, event) {
+            (HandshakeStep::OpenInit, IbcEvent::OpenInitChannel(e)) => (
+                e.height,
+                e.port_id,
+                e.channel_id,
+                e.connection_id,
+                e.counterparty_port_id,
+                e.counterparty_channel_id,
+            ),
+            (HandshakeStep::OpenTry, IbcEvent::OpenTryChannel(e)) => (
+                e.height,
+                e.port_id,
+                e.channel_id,
+                e.connection_id,
+                e.counterparty_port_id,
+                e.counterparty_channel_id,
+            ),
+            (HandshakeStep::OpenAck, IbcEvent::OpenAckChannel(e)) => (
+                e.height,
+                e.port_id,
+                e.channel_id,
+                e.connection_id,
+                e.counterparty_port_id,
+                e.counterparty_channel_id,
+            ),
+            (HandshakeStep::OpenConfirm, IbcEvent::OpenConfirmChannel(e)) => (
+                e.height,
+                e.port_id,
+                e.channel_id,
+                e.connection_id,
+                e.counterparty_port_id,
+                e.counterparty_channel_id,
+            ),
+            (step, event) => panic!("unexpected event {:?} of {:?}", event, step),
+        };
+
+        assert_eq!(
+            attributes,
+            (
+                height,
+                port_id.clone(),
+                Some(ChannelId::new(0)),
+                ConnectionId::new(1),
+                port_id.clone(),
+                Some(ChannelId::new(5)),
+            )
+        );
+    }
+}
+
+#[test]
+fn test_channel_handshake_event_without_connection_hop() {
+    let channel_end = ChannelEnd::new(
+        ChannelState::Init,
+        Order::Unordered,
+        ChannelCounterparty::new(PortId::transfer(), None),
+        vec![],
+        Version::new("ics20-1".to_string()),
+    );
+
+    assert!(channel_handshake_event(
+        HandshakeStep::OpenInit,
+        Height::new(0, 1).unwrap(),
+        &PortId::transfer(),
+        &ChannelId::new(0),
+        &channel_end,
+    )
+    .is_err());
+}
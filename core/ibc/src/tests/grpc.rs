@@ -0,0 +1,2791 @@
+use std::borrow::Cow;
+use std::net::TcpListener;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use ibc::core::ics02_client::client_type::ClientType;
+use ibc::core::ics02_client::context::{ClientKeeper, ClientReader};
+use ibc::core::ics03_connection::connection::{
+    Counterparty as ConnectionCounterparty, State as ConnectionState,
+};
+use ibc::core::ics03_connection::context::{ConnectionKeeper, ConnectionReader};
+use ibc::core::ics03_connection::version::get_compatible_versions;
+use ibc::core::ics04_channel::commitment::{AcknowledgementCommitment, PacketCommitment};
+use ibc::core::ics04_channel::context::ChannelKeeper;
+use ibc::core::ics04_channel::packet::{Packet, Receipt, Sequence};
+use ibc::core::ics04_channel::Version;
+use ibc::core::ics23_commitment::commitment::CommitmentPrefix;
+use ibc::core::ics24_host::identifier::{ChainId, ChannelId, ClientId, ConnectionId, PortId};
+use ibc::core::ics24_host::path;
+use ibc::core::ics26_routing::context::ModuleId;
+use ibc::events::IbcEvent;
+use ibc::Height;
+use ibc_proto::cosmos::base::query::v1beta1::PageRequest;
+use ibc_proto::google::protobuf::Any;
+use ibc_proto::ibc::core::channel::v1::{
+    msg_server::Msg as ChannelMsg, query_server::Query as ChannelQuery, MsgChannelOpenInit,
+    MsgRecvPacket, Packet as RawPacket, PacketState, QueryChannelConsensusStateRequest,
+    QueryChannelRequest, QueryChannelResponse, QueryChannelsRequest, QueryChannelsResponse,
+    QueryConnectionChannelsRequest, QueryConnectionChannelsResponse,
+    QueryPacketAcknowledgementRequest, QueryPacketAcknowledgementsRequest,
+    QueryPacketCommitmentRequest, QueryPacketCommitmentsRequest, QueryPacketReceiptRequest,
+};
+use ibc_proto::ibc::core::client::v1::{
+    msg_client::MsgClient as ClientMsgClient, msg_server::Msg as ClientMsg,
+    query_client::QueryClient as ClientQueryClient, query_server::Query as ClientQuery,
+    Height as RawHeight, MsgCreateClient, MsgUpdateClient, MsgUpgradeClient,
+    QueryClientStateRequest, QueryClientStatesRequest, QueryClientStatesResponse,
+    QueryClientStatusRequest, QueryConsensusStateHeightsRequest,
+    QueryConsensusStateHeightsResponse, QueryConsensusStateRequest, QueryConsensusStatesRequest,
+    QueryConsensusStatesResponse, QueryUpgradedClientStateRequest,
+};
+use ibc_proto::ibc::core::connection::v1::{
+    msg_server::Msg as ConnectionMsg, query_server::Query as ConnectionQuery,
+    MsgConnectionOpenInit, QueryClientConnectionsRequest, QueryConnectionConsensusStateRequest,
+    QueryConnectionRequest, QueryConnectionsRequest,
+};
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Endpoint;
+use tonic::{Code, Request};
+
+use common_apm::metrics::ibc::IBC_GRPC_UNSUPPORTED_COUNTER_VEC;
+use protocol::tokio;
+use protocol::traits::{Context, IbcAdapter, IbcGrpcAdapter};
+use protocol::types::StoreHeight;
+
+use crate::grpc::{
+    classify_acknowledgement, log_slow_query, read_with_timeout, AckStatus, GrpcService,
+    IbcChannelMsgService, IbcChannelService, IbcClientMsgService, IbcClientService,
+    IbcConnectionMsgService, IbcConnectionService, IbcContextQueryService, PortChannelId,
+    QueryChannelIdsResponse, QueryChannelOverviewRequest, QueryChannelOverviewResponse,
+    QueryClientIdsResponse, QueryClientLatestHeightRequest, QueryClientLatestHeightResponse,
+    QueryClientsOverviewRequest, QueryClientsOverviewResponse, QueryConnectionCounterRequest,
+    QueryConnectionCounterResponse, QueryConnectionIdsResponse, QueryConsensusStatesSinceRequest,
+    QueryIdsRequest, QueryNextSequenceSendRequest, QueryNextSequenceSendResponse,
+    QueryPacketRequest, QueryPacketResponse, QueryProposedAppVersionRequest,
+    QueryProposedAppVersionResponse, DEFAULT_SLOW_QUERY_THRESHOLD, HANDLER_EVENT_METADATA,
+    HANDLER_LOG_METADATA, MIN_TIMESTAMP_METADATA, QUERY_HEIGHT_METADATA,
+};
+use crate::tests::router::MockModule;
+use crate::tests::{
+    mock_client_state, mock_consensus_state, mock_header, mock_ibc_impl, setup_open_channel,
+    MockIbcAdapter,
+};
+use crate::{IbcImpl, IbcRouter};
+
+#[tokio::test]
+async fn test_create_client_with_garbage_msg() {
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(MockIbcAdapter::default())));
+    let service = IbcClientMsgService::new(ctx);
+
+    let garbage = MsgCreateClient {
+        client_state:    Some(Any {
+            type_url: "/ibc.lightclients.unknown".to_string(),
+            value:    vec![0xde, 0xad, 0xbe, 0xef],
+        }),
+        consensus_state: None,
+        signer:          "relayer".to_string(),
+    };
+
+    let status = service
+        .create_client(Request::new(garbage))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_update_client_with_garbage_header() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(adapter)));
+    let service = IbcClientMsgService::new(Arc::clone(&ctx));
+
+    let garbage = MsgUpdateClient {
+        client_id: "07-tendermint-0".to_string(),
+        header:    Some(Any {
+            type_url: "/ibc.lightclients.tendermint.v1.Header".to_string(),
+            value:    vec![0xde, 0xad, 0xbe, 0xef],
+        }),
+        signer:    "relayer".to_string(),
+    };
+
+    let status = service
+        .update_client(Request::new(garbage))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    // The client is left as it was.
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    assert_eq!(
+        ctx.read().unwrap().client_state(&client_id).unwrap(),
+        mock_client_state(5)
+    );
+}
+
+#[tokio::test]
+async fn test_upgrade_client_with_garbage_msg() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(adapter)));
+    let service = IbcClientMsgService::new(Arc::clone(&ctx));
+
+    // The proofs are not merkle proofs.
+    let garbage = MsgUpgradeClient {
+        client_id:                     "07-tendermint-0".to_string(),
+        client_state:                  Some(mock_client_state(10).into()),
+        consensus_state:               Some(mock_consensus_state(10).into()),
+        proof_upgrade_client:          vec![0xde, 0xad, 0xbe, 0xef],
+        proof_upgrade_consensus_state: vec![0xde, 0xad, 0xbe, 0xef],
+        signer:                        "relayer".to_string(),
+    };
+
+    let status = service
+        .upgrade_client(Request::new(garbage))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    assert_eq!(
+        ctx.read().unwrap().client_state(&client_id).unwrap(),
+        mock_client_state(5)
+    );
+}
+
+#[tokio::test]
+async fn test_upgrade_client_unverifiable() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(adapter)));
+    let service = IbcClientMsgService::new(Arc::clone(&ctx));
+
+    // The empty proofs decode, so the msg reaches the check of the light
+    // client, which can't verify the upgrade of a Tendermint client.
+    let msg = MsgUpgradeClient {
+        client_id:                     "07-tendermint-0".to_string(),
+        client_state:                  Some(mock_client_state(10).into()),
+        consensus_state:               Some(mock_consensus_state(10).into()),
+        proof_upgrade_client:          vec![],
+        proof_upgrade_consensus_state: vec![],
+        signer:                        "relayer".to_string(),
+    };
+
+    let status = service.upgrade_client(Request::new(msg)).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    assert_eq!(
+        ctx.read().unwrap().client_state(&client_id).unwrap(),
+        mock_client_state(5)
+    );
+}
+
+#[tokio::test]
+async fn test_connection_open_init_msg() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    adapter
+        .set_client_state(Context::new(), client_id.clone(), mock_client_state(5))
+        .unwrap();
+    adapter
+        .set_consensus_state(
+            Context::new(),
+            client_id.clone(),
+            Height::new(0, 5).unwrap(),
+            mock_consensus_state(5),
+        )
+        .unwrap();
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(adapter)));
+    let service = IbcConnectionMsgService::new(Arc::clone(&ctx));
+
+    let msg = MsgConnectionOpenInit {
+        client_id:    client_id.to_string(),
+        counterparty: Some(
+            ConnectionCounterparty::new(
+                client_id.clone(),
+                None,
+                CommitmentPrefix::try_from(b"ibc".to_vec()).unwrap(),
+            )
+            .into(),
+        ),
+        version:      Some(get_compatible_versions()[0].clone().into()),
+        delay_period: 0,
+        signer:       "relayer".to_string(),
+    };
+    let response = service
+        .connection_open_init(Request::new(msg.clone()))
+        .await
+        .unwrap();
+
+    // The event carries the complete attribute set of the stored end.
+    let events = response
+        .metadata()
+        .get_all(HANDLER_EVENT_METADATA)
+        .iter()
+        .map(|value| serde_json::from_str::<IbcEvent>(value.to_str().unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    match events.as_slice() {
+        [IbcEvent::OpenInitConnection(event)] => {
+            let attributes = event.attributes();
+            assert_eq!(attributes.connection_id, Some(ConnectionId::new(0)));
+            assert_eq!(attributes.client_id, client_id);
+            assert_eq!(attributes.counterparty_client_id, client_id);
+            assert_eq!(attributes.counterparty_connection_id, None);
+        }
+        events => panic!("unexpected events {:?}", events),
+    }
+
+    let connection_end = ctx
+        .read()
+        .unwrap()
+        .connection_end(&ConnectionId::new(0))
+        .unwrap();
+    assert_eq!(connection_end.state(), &ConnectionState::Init);
+    assert_eq!(connection_end.client_id(), &client_id);
+
+    // The client of the connection must exist.
+    let status = service
+        .connection_open_init(Request::new(MsgConnectionOpenInit {
+            client_id: "07-tendermint-1".to_string(),
+            ..msg
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_channel_open_init_with_garbage_msg() {
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(MockIbcAdapter::default())));
+    let service = IbcChannelMsgService::new(ctx);
+
+    let status = service
+        .channel_open_init(Request::new(MsgChannelOpenInit {
+            port_id: "transfer".to_string(),
+            channel: None,
+            signer:  "relayer".to_string(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_recv_packet_timed_out() {
+    let adapter = MockIbcAdapter::default();
+    (1..=5).for_each(|n| adapter.insert_header(mock_header(n)));
+    setup_open_channel(&adapter, 5);
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.warm_consensus_states(5, 5).await.unwrap();
+    let service = IbcChannelMsgService::new(Arc::new(RwLock::new(ibc)));
+
+    let msg = |timeout_height: u64| MsgRecvPacket {
+        packet:           Some(RawPacket {
+            sequence:            1,
+            source_port:         "transfer".to_string(),
+            source_channel:      "channel-0".to_string(),
+            destination_port:    "transfer".to_string(),
+            destination_channel: "channel-0".to_string(),
+            data:                b"data".to_vec(),
+            timeout_height:      Some(RawHeight {
+                revision_number: 0,
+                revision_height: timeout_height,
+            }),
+            timeout_timestamp:   0,
+        }),
+        proof_commitment: b"proof".to_vec(),
+        proof_height:     Some(RawHeight {
+            revision_number: 0,
+            revision_height: 5,
+        }),
+        signer:           "relayer".to_string(),
+    };
+
+    // The packet past its timeout height at the host is rejected before the
+    // dispatch.
+    let status = service.recv_packet(Request::new(msg(5))).await.unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert!(status.message().contains("timed out"));
+
+    // A packet in time is dispatched, and fails on the garbage proof.
+    let status = service
+        .recv_packet(Request::new(msg(10)))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_response_height_revision_number() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_height(10);
+    let service = IbcChannelService::new(Arc::new(adapter), ChainId::chain_version("axon-3"));
+
+    let height = service
+        .channels(Request::new(QueryChannelsRequest { pagination: None }))
+        .await
+        .unwrap()
+        .into_inner()
+        .height
+        .unwrap();
+    assert_eq!(height.revision_number, 3);
+    assert_eq!(height.revision_height, 10);
+}
+
+#[tokio::test]
+async fn test_connection_channels_pagination() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let conn_id = ConnectionId::new(0);
+    let channel_end = adapter
+        .get_channel_end_by_id(Context::new(), &(PortId::transfer(), ChannelId::new(0)))
+        .unwrap()
+        .unwrap();
+    for n in [1, 2] {
+        let port_channel_id = (PortId::transfer(), ChannelId::new(n));
+        adapter
+            .set_connection_channels(Context::new(), conn_id.clone(), &port_channel_id)
+            .unwrap();
+        adapter
+            .set_channel(
+                Context::new(),
+                port_channel_id.0,
+                port_channel_id.1,
+                channel_end.clone(),
+            )
+            .unwrap();
+    }
+    let service = IbcChannelService::new(Arc::new(adapter), 0);
+    let request = |pagination| {
+        Request::new(QueryConnectionChannelsRequest {
+            connection: "connection-0".to_string(),
+            pagination,
+        })
+    };
+    let channel_ids = |resp: &QueryConnectionChannelsResponse| {
+        resp.channels
+            .iter()
+            .map(|channel| channel.channel_id.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let resp = service
+        .connection_channels(request(Some(PageRequest {
+            limit: 2,
+            count_total: true,
+            ..Default::default()
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(channel_ids(&resp), vec!["channel-0", "channel-1"]);
+    let pagination = resp.pagination.unwrap();
+    assert_eq!(pagination.total, 3);
+
+    let resp = service
+        .connection_channels(request(Some(PageRequest {
+            key: pagination.next_key,
+            limit: 2,
+            ..Default::default()
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(channel_ids(&resp), vec!["channel-2"]);
+    assert!(resp.pagination.unwrap().next_key.is_empty());
+}
+
+#[tokio::test]
+async fn test_channel_overview() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let service = IbcChannelService::new(Arc::new(adapter), 0);
+
+    let overview = service
+        .channel_overview(Request::new(QueryChannelOverviewRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(overview.channel.unwrap().connection_hops, vec![
+        "connection-0".to_string()
+    ]);
+    assert_eq!(overview.connection.unwrap().client_id, "07-tendermint-0");
+    let client_state = overview.client_state.unwrap();
+    assert_eq!(client_state.client_id, "07-tendermint-0");
+    assert!(client_state.client_state.is_some());
+    assert_eq!(
+        overview.consensus_state.unwrap(),
+        Any::from(mock_consensus_state(5))
+    );
+
+    let status = service
+        .channel_overview(Request::new(QueryChannelOverviewRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-1".to_string(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_packet_commitments_path_limit() {
+    let adapter = MockIbcAdapter::default();
+    for seq in 1..=5u64 {
+        let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(seq));
+        adapter
+            .set_packet_commitment(Context::new(), key, PacketCommitment::from(vec![1u8; 32]))
+            .unwrap();
+    }
+    let adapter = Arc::new(adapter);
+    let request = || {
+        Request::new(QueryPacketCommitmentsRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            pagination: None,
+        })
+    };
+
+    let service = IbcChannelService::new(Arc::clone(&adapter), 0).with_max_query_paths(5);
+    let resp = service.packet_commitments(request()).await.unwrap();
+    assert_eq!(resp.into_inner().commitments.len(), 5);
+
+    let service = IbcChannelService::new(adapter, 0).with_max_query_paths(4);
+    let status = service.packet_commitments(request()).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
+
+#[tokio::test]
+async fn test_packet_commitments_scan_is_bounded_on_large_store() {
+    let adapter = MockIbcAdapter::default();
+    for seq in 1..=10_000u64 {
+        let key = (PortId::transfer(), ChannelId::new(1), Sequence::from(seq));
+        adapter
+            .set_packet_commitment(Context::new(), key, PacketCommitment::from(vec![1u8; 32]))
+            .unwrap();
+    }
+    let adapter = Arc::new(adapter);
+    let service = IbcChannelService::new(Arc::clone(&adapter), 0).with_max_query_paths(100);
+    let request = |channel_id: &str, pagination| {
+        Request::new(QueryPacketCommitmentsRequest {
+            port_id: "transfer".to_string(),
+            channel_id: channel_id.to_string(),
+            pagination,
+        })
+    };
+
+    // The paths of the other channels are not scanned.
+    let resp = service
+        .packet_commitments(request("channel-0", None))
+        .await
+        .unwrap();
+    assert!(resp.into_inner().commitments.is_empty());
+    assert_eq!(adapter.scanned_paths(), 0);
+
+    // Only the paths up to the limit are ever pulled out of the store, instead
+    // of the whole key set of the prefix.
+    let status = service
+        .packet_commitments(request("channel-1", None))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(adapter.scanned_paths(), 101);
+
+    // A requested page is served past the limit.
+    let resp = service
+        .packet_commitments(request(
+            "channel-1",
+            Some(PageRequest {
+                key: 9_990u64.to_be_bytes().to_vec(),
+                limit: 5,
+                ..Default::default()
+            }),
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+    let sequences = resp
+        .commitments
+        .iter()
+        .map(|state| state.sequence)
+        .collect::<Vec<_>>();
+    assert_eq!(sequences, (9_990..9_995).collect::<Vec<_>>());
+    assert_eq!(
+        resp.pagination.unwrap().next_key,
+        9_995u64.to_be_bytes().to_vec()
+    );
+}
+
+#[tokio::test]
+async fn test_packet_commitment_deleted_during_query() {
+    let adapter = MockIbcAdapter::default();
+    for seq in 1..=3u64 {
+        adapter
+            .set_packet_commitment(
+                Context::new(),
+                (PortId::transfer(), ChannelId::new(0), Sequence::from(seq)),
+                PacketCommitment::from(vec![1u8; 32]),
+            )
+            .unwrap();
+    }
+    // The commitment is deleted after the paths are listed but before it is
+    // read, as by a block committed in the middle of the query.
+    adapter.on_read("commitments/ports/transfer/channels/channel-0", |adapter| {
+        adapter
+            .remove_packet_commitment(
+                Context::new(),
+                (PortId::transfer(), ChannelId::new(0), Sequence::from(2)),
+            )
+            .unwrap();
+    });
+    let service = IbcChannelService::new(Arc::new(adapter), 0);
+
+    let resp = service
+        .packet_commitments(Request::new(QueryPacketCommitmentsRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            pagination: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    let sequences = resp
+        .commitments
+        .iter()
+        .map(|state| state.sequence)
+        .collect::<Vec<_>>();
+    assert_eq!(sequences, vec![1, 3]);
+}
+
+#[tokio::test]
+async fn test_packet_states_pagination() {
+    let adapter = MockIbcAdapter::default();
+    for seq in 1..=12u64 {
+        let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(seq));
+        adapter
+            .set_packet_commitment(
+                Context::new(),
+                key.clone(),
+                PacketCommitment::from(vec![1u8; 32]),
+            )
+            .unwrap();
+        adapter
+            .set_packet_acknowledgement(
+                Context::new(),
+                key,
+                AcknowledgementCommitment::from(vec![2u8; 32]),
+            )
+            .unwrap();
+    }
+    let service = IbcChannelService::new(Arc::new(adapter), 0);
+    let page = |key: Vec<u8>| {
+        Some(PageRequest {
+            key,
+            limit: 5,
+            count_total: true,
+            ..Default::default()
+        })
+    };
+    let sequences = |states: &[PacketState]| states.iter().map(|s| s.sequence).collect::<Vec<_>>();
+
+    // The pages are in the order of the sequences, not of the store keys.
+    let resp = service
+        .packet_commitments(Request::new(QueryPacketCommitmentsRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            pagination: page(vec![]),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(sequences(&resp.commitments), vec![1, 2, 3, 4, 5]);
+    let pagination = resp.pagination.unwrap();
+    assert_eq!(pagination.total, 12);
+    assert_eq!(pagination.next_key, 6u64.to_be_bytes().to_vec());
+
+    let resp = service
+        .packet_commitments(Request::new(QueryPacketCommitmentsRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            pagination: page(pagination.next_key),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(sequences(&resp.commitments), vec![6, 7, 8, 9, 10]);
+
+    let resp = service
+        .packet_acknowledgements(Request::new(QueryPacketAcknowledgementsRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            pagination: page(11u64.to_be_bytes().to_vec()),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(sequences(&resp.acknowledgements), vec![11, 12]);
+    assert!(resp.pagination.unwrap().next_key.is_empty());
+}
+
+fn free_addr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn garbage_create_client() -> MsgCreateClient {
+    MsgCreateClient {
+        client_state:    None,
+        consensus_state: None,
+        signer:          "relayer".to_string(),
+    }
+}
+
+#[test]
+fn test_invalid_grpc_addr() {
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(MockIbcAdapter::default())));
+    let res = GrpcService::new(
+        Arc::new(MockIbcAdapter::default()),
+        "127.0.0.1:8010".to_string(),
+        "localhost".to_string(),
+        "axon-0",
+        ctx,
+    );
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn test_grpc_addr_in_use() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(MockIbcAdapter::default())));
+    let service = GrpcService::new(
+        Arc::new(MockIbcAdapter::default()),
+        addr.clone(),
+        addr,
+        "axon-0",
+        ctx,
+    )
+    .unwrap();
+
+    let res = tokio::time::timeout(Duration::from_secs(5), service.run())
+        .await
+        .unwrap();
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn test_separate_query_and_msg_addr() {
+    let (query_addr, msg_addr) = (free_addr(), free_addr());
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(MockIbcAdapter::default())));
+    let service = GrpcService::new(
+        Arc::new(MockIbcAdapter::default()),
+        query_addr.clone(),
+        msg_addr.clone(),
+        "axon-0",
+        ctx,
+    )
+    .unwrap();
+    tokio::spawn(service.run());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let mut client = ClientMsgClient::connect(format!("http://{}", query_addr))
+        .await
+        .unwrap();
+    let status = client
+        .create_client(garbage_create_client())
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+
+    let mut client = ClientMsgClient::connect(format!("http://{}", msg_addr))
+        .await
+        .unwrap();
+    let status = client
+        .create_client(garbage_create_client())
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_served_query_lock_timeout() {
+    let addr = free_addr();
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(MockIbcAdapter::default())));
+    let service = GrpcService::new(
+        Arc::new(MockIbcAdapter::default()),
+        addr.clone(),
+        addr.clone(),
+        "axon-0",
+        Arc::clone(&ctx),
+    )
+    .unwrap()
+    .with_lock_timeout(Some(Duration::from_millis(20)));
+    tokio::spawn(service.run());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let mut client = ClientQueryClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    // A query waits for the msg holding the context, up to the timeout.
+    let writer = ctx.write().unwrap();
+    let status = client
+        .client_states(QueryClientStatesRequest { pagination: None })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    drop(writer);
+
+    assert!(client
+        .client_states(QueryClientStatesRequest { pagination: None })
+        .await
+        .is_ok());
+}
+
+/// Serve the query services of `adapter` on a free address.
+async fn serve_queries(adapter: impl Into<Arc<MockIbcAdapter>>) -> String {
+    serve_queries_with_context(adapter, mock_ibc_impl(MockIbcAdapter::default())).await
+}
+
+/// Serve the query services of `adapter`, and the context queries of `ibc`,
+/// on a free address.
+async fn serve_queries_with_context(
+    adapter: impl Into<Arc<MockIbcAdapter>>,
+    ibc: IbcImpl<MockIbcAdapter, IbcRouter>,
+) -> String {
+    let addr = free_addr();
+    let ctx = Arc::new(RwLock::new(ibc));
+    let service =
+        GrpcService::new(adapter.into(), addr.clone(), addr.clone(), "axon-0", ctx).unwrap();
+    tokio::spawn(service.run());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    addr
+}
+
+/// Call a method of the `axon.ibc` query services, which have no generated
+/// client.
+async fn call_ext_query<Req, Resp>(
+    addr: &str,
+    method: &'static str,
+    request: Req,
+) -> Result<Resp, tonic::Status>
+where
+    Req: prost::Message + Send + Sync + 'static,
+    Resp: prost::Message + Default + Send + Sync + 'static,
+{
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await.unwrap();
+    grpc.unary(
+        Request::new(request),
+        PathAndQuery::from_static(method),
+        ProstCodec::default(),
+    )
+    .await
+    .map(|resp| resp.into_inner())
+}
+
+#[tokio::test]
+async fn test_served_channel_overview() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let addr = serve_queries(adapter).await;
+
+    let overview: QueryChannelOverviewResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.channel.v1.Query/ChannelOverview",
+        QueryChannelOverviewRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(overview.connection.unwrap().client_id, "07-tendermint-0");
+
+    let status = call_ext_query::<_, QueryChannelOverviewResponse>(
+        &addr,
+        "/axon.ibc.channel.v1.Query/Unknown",
+        QueryChannelOverviewRequest::default(),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+}
+
+#[tokio::test]
+async fn test_served_next_sequence_send() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let addr = serve_queries(adapter).await;
+
+    let resp: QueryNextSequenceSendResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.channel.v1.Query/NextSequenceSend",
+        QueryNextSequenceSendRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.next_sequence_send, 1);
+}
+
+#[tokio::test]
+async fn test_next_sequence_send() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let adapter = Arc::new(adapter);
+    let service = IbcChannelService::new(Arc::clone(&adapter), 0);
+    let request = |channel_id: &str| {
+        Request::new(QueryNextSequenceSendRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: channel_id.to_string(),
+        })
+    };
+
+    let resp = service
+        .next_sequence_send(request("channel-0"))
+        .await
+        .unwrap();
+    assert_eq!(resp.into_inner().next_sequence_send, 1);
+
+    adapter.set_height(10);
+    adapter
+        .set_next_sequence_send(
+            Context::new(),
+            PortId::transfer(),
+            ChannelId::new(0),
+            Sequence::from(7),
+        )
+        .unwrap();
+    let resp = service
+        .next_sequence_send(request("channel-0"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.next_sequence_send, 7);
+    assert_eq!(
+        resp.proof,
+        b"proof:nextSequenceSend/ports/transfer/channels/channel-0".to_vec()
+    );
+    assert_eq!(resp.proof_height.unwrap().revision_height, 11);
+
+    // The sequence is read at the query height, along with its proof.
+    let mut at_height = request("channel-0");
+    at_height
+        .metadata_mut()
+        .insert(QUERY_HEIGHT_METADATA, "5".parse().unwrap());
+    let resp = service
+        .next_sequence_send(at_height)
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.next_sequence_send, 1);
+    assert_eq!(resp.proof_height.unwrap().revision_height, 6);
+
+    let status = service
+        .next_sequence_send(request("channel-1"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_packet_acknowledgements_with_status() {
+    let adapter = MockIbcAdapter::default();
+    // The last acknowledgement only has its commitment stored.
+    let acks: [Option<&[u8]>; 3] = [
+        Some(br#"{"result":"AQ=="}"#),
+        Some(br#"{"error":"insufficient funds"}"#),
+        None,
+    ];
+    let commitments = (1..=acks.len() as u8)
+        .map(|seq| vec![seq; 32])
+        .collect::<Vec<_>>();
+    for (seq, (ack, commitment)) in acks.iter().zip(commitments.iter()).enumerate() {
+        let key = (
+            PortId::transfer(),
+            ChannelId::new(0),
+            Sequence::from(seq as u64 + 1),
+        );
+        adapter
+            .set_packet_acknowledgement(
+                Context::new(),
+                key.clone(),
+                AcknowledgementCommitment::from(commitment.clone()),
+            )
+            .unwrap();
+        if let Some(ack) = ack {
+            adapter
+                .set_acknowledgement(Context::new(), key, ack.to_vec())
+                .unwrap();
+        }
+    }
+    let service = IbcChannelService::new(Arc::new(adapter), 0);
+
+    let resp = service
+        .packet_acknowledgements_with_status(Request::new(QueryPacketAcknowledgementsRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            pagination: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+
+    // The commitments are served as they are, and classified by the
+    // acknowledgements written next to them.
+    let data = resp
+        .acknowledgements
+        .iter()
+        .map(|ack| ack.data.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(data, commitments);
+    assert_eq!(resp.statuses, vec![
+        Some(AckStatus::Success),
+        Some(AckStatus::Error),
+        None
+    ]);
+
+    assert_eq!(
+        classify_acknowledgement(br#"{"result":"AQ==","error":"x"}"#),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_unversioned_store_rejects_query_height() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_height(10);
+    // The channel is written in the pending block, above the committed height.
+    setup_open_channel(&adapter, 5);
+    adapter.disable_versions();
+    let adapter = Arc::new(adapter);
+    fn with_height<T>(mut request: Request<T>, height: &str) -> Request<T> {
+        request
+            .metadata_mut()
+            .insert(QUERY_HEIGHT_METADATA, height.parse().unwrap());
+        request
+    }
+    let channel_request = || {
+        Request::new(QueryChannelRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        })
+    };
+
+    let channels = IbcChannelService::new(Arc::clone(&adapter), 0);
+    let status = channels
+        .channel(with_height(channel_request(), "10"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    let resp = channels
+        .channel(with_height(channel_request(), "0"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.channel.is_some());
+    // There is no snapshot to read, so the query stays at pending and sees
+    // the channel.
+    let resp = IbcChannelService::new(Arc::clone(&adapter), 0)
+        .with_snapshot_reads(true)
+        .channel(channel_request())
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.channel.is_some());
+
+    let status = IbcClientService::new(Arc::clone(&adapter), 0)
+        .client_state(with_height(
+            Request::new(QueryClientStateRequest {
+                client_id: "07-tendermint-0".to_string(),
+            }),
+            "5",
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    let status = IbcConnectionService::new(adapter, 0)
+        .connection(with_height(
+            Request::new(QueryConnectionRequest {
+                connection_id: "connection-0".to_string(),
+            }),
+            "5",
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+}
+
+#[tokio::test]
+async fn test_query_pruned_height() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_height(100);
+    adapter.set_oldest_height(50);
+    let adapter = Arc::new(adapter);
+    let service = IbcChannelService::new(Arc::clone(&adapter), 0);
+    let request = |height: &str| {
+        let mut request = Request::new(QueryChannelsRequest { pagination: None });
+        request
+            .metadata_mut()
+            .insert(QUERY_HEIGHT_METADATA, height.parse().unwrap());
+        request
+    };
+
+    let status = service.channels(request("49")).await.unwrap_err();
+    assert_eq!(status.code(), Code::OutOfRange);
+    assert!(status.message().contains("pruned"));
+
+    assert!(service.channels(request("50")).await.is_ok());
+    assert!(service.channels(request("0")).await.is_ok());
+
+    // The oldest height is read on each query, so a later pruning applies to
+    // the running service.
+    adapter.set_oldest_height(60);
+    let status = service.channels(request("50")).await.unwrap_err();
+    assert_eq!(status.code(), Code::OutOfRange);
+    assert_eq!(
+        service.channels(request("abc")).await.unwrap_err().code(),
+        Code::InvalidArgument
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_client_handler_log() {
+    let adapter = MockIbcAdapter::default();
+    (1..=3).for_each(|n| adapter.insert_header(mock_header(n)));
+    adapter.set_height(2);
+    let mut ibc = mock_ibc_impl(adapter);
+    // The processed time of the client is the timestamp of the pending block.
+    ibc.warm_consensus_states(3, 3).await.unwrap();
+    let service = IbcClientMsgService::new(Arc::new(RwLock::new(ibc)));
+
+    let msg = MsgCreateClient {
+        client_state:    Some(mock_client_state(1).into()),
+        consensus_state: Some(mock_consensus_state(1).into()),
+        signer:          "relayer".to_string(),
+    };
+    let response = service.create_client(Request::new(msg)).await.unwrap();
+
+    let log = response
+        .metadata()
+        .get_all(HANDLER_LOG_METADATA)
+        .iter()
+        .map(|value| value.to_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(log, vec![
+        "success: generated new client identifier: 07-tendermint-0".to_string()
+    ]);
+}
+
+#[tokio::test]
+async fn test_poisoned_context_lock() {
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(MockIbcAdapter::default())));
+    let poisoner = Arc::clone(&ctx);
+    std::thread::spawn(move || {
+        let _writer = poisoner.write().unwrap();
+        panic!("a handler panics");
+    })
+    .join()
+    .unwrap_err();
+    assert!(ctx.is_poisoned());
+
+    let service = IbcClientMsgService::new(Arc::clone(&ctx));
+    let msg = MsgCreateClient {
+        client_state:    Some(mock_client_state(1).into()),
+        consensus_state: Some(mock_consensus_state(1).into()),
+        signer:          "relayer".to_string(),
+    };
+    service.create_client(Request::new(msg)).await.unwrap();
+    assert!(read_with_timeout(&ctx, None).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_context_lock_timeout() {
+    let ctx = Arc::new(RwLock::new(mock_ibc_impl(MockIbcAdapter::default())));
+    let service = IbcClientMsgService::new(Arc::clone(&ctx))
+        .with_lock_timeout(Some(Duration::from_millis(20)));
+
+    {
+        let _writer = ctx.write().unwrap();
+        let status = read_with_timeout(&ctx, Some(Duration::from_millis(20)))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+
+        let msg = MsgCreateClient {
+            client_state:    Some(mock_client_state(1).into()),
+            consensus_state: Some(mock_consensus_state(1).into()),
+            signer:          "relayer".to_string(),
+        };
+        let status = service.create_client(Request::new(msg)).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+
+    let _reader = read_with_timeout(&ctx, Some(Duration::from_millis(20)))
+        .await
+        .unwrap();
+    assert!(read_with_timeout(&ctx, None).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_client_latest_height() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    for h in [1, 3, 2] {
+        ibc.store_consensus_state(
+            client_id.clone(),
+            Height::new(0, h).unwrap(),
+            mock_consensus_state(h),
+        )
+        .unwrap();
+    }
+    let service = IbcContextQueryService::new(Arc::new(RwLock::new(ibc)));
+
+    let request = |client_id: &str| {
+        Request::new(QueryClientLatestHeightRequest {
+            client_id: client_id.to_string(),
+        })
+    };
+    let latest_height = service
+        .client_latest_height(request("07-tendermint-0"))
+        .await
+        .unwrap()
+        .into_inner()
+        .latest_height
+        .unwrap();
+    assert_eq!(latest_height.revision_height, 3);
+
+    let status = service
+        .client_latest_height(request("07-tendermint-1"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_served_client_latest_height() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    ibc.store_consensus_state(
+        client_id,
+        Height::new(0, 3).unwrap(),
+        mock_consensus_state(3),
+    )
+    .unwrap();
+    let addr = serve_queries_with_context(MockIbcAdapter::default(), ibc).await;
+
+    let resp: QueryClientLatestHeightResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.context.v1.Query/ClientLatestHeight",
+        QueryClientLatestHeightRequest {
+            client_id: "07-tendermint-0".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.latest_height.unwrap().revision_height, 3);
+}
+
+#[test]
+fn test_client_latest_height_is_persisted() {
+    let adapter = Arc::new(MockIbcAdapter::default());
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let mut ibc = IbcImpl::new(Arc::clone(&adapter), IbcRouter::default()).unwrap();
+    ibc.store_consensus_state(
+        client_id.clone(),
+        Height::new(0, 3).unwrap(),
+        mock_consensus_state(3),
+    )
+    .unwrap();
+
+    // A higher height stored in a discarded batch is rolled back.
+    ibc.begin_write_batch().unwrap();
+    ibc.store_consensus_state(
+        client_id.clone(),
+        Height::new(0, 5).unwrap(),
+        mock_consensus_state(5),
+    )
+    .unwrap();
+    assert_eq!(
+        ibc.client_latest_height(&client_id).unwrap(),
+        Some(Height::new(0, 5).unwrap())
+    );
+    ibc.discard_write_batch().unwrap();
+    assert_eq!(
+        ibc.client_latest_height(&client_id).unwrap(),
+        Some(Height::new(0, 3).unwrap())
+    );
+
+    // A new context on the same store sees the latest height.
+    let ibc = IbcImpl::new(adapter, IbcRouter::default()).unwrap();
+    assert_eq!(
+        ibc.client_latest_height(&client_id).unwrap(),
+        Some(Height::new(0, 3).unwrap())
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_client_store_failure() {
+    let msg = || MsgCreateClient {
+        client_state:    Some(mock_client_state(1).into()),
+        consensus_state: Some(mock_consensus_state(5).into()),
+        signer:          "relayer".to_string(),
+    };
+
+    // A latest height of another type can't be read, which is a failure of
+    // the store.
+    let adapter = MockIbcAdapter::default();
+    (1..=3).for_each(|n| adapter.insert_header(mock_header(n)));
+    adapter.set_height(2);
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    adapter.insert_raw(&format!("latestHeights/{}", client_id), 7u64);
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.warm_consensus_states(3, 3).await.unwrap();
+    let service = IbcClientMsgService::new(Arc::new(RwLock::new(ibc)));
+    let status = service
+        .create_client(Request::new(msg()))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+    assert!(status.message().starts_with("store client result"));
+
+    // A consensus state of the client to create is left in the store, which is
+    // older than the created one, so the created one is rejected.
+    let adapter = MockIbcAdapter::default();
+    (1..=3).for_each(|n| adapter.insert_header(mock_header(n)));
+    adapter.set_height(2);
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    adapter
+        .set_consensus_state(
+            Context::new(),
+            client_id,
+            Height::new(0, 2).unwrap(),
+            mock_consensus_state(1),
+        )
+        .unwrap();
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.warm_consensus_states(3, 3).await.unwrap();
+    let service = IbcClientMsgService::new(Arc::new(RwLock::new(ibc)));
+    let status = service
+        .create_client(Request::new(msg()))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert!(status.message().starts_with("store client result"));
+}
+
+#[tokio::test]
+async fn test_clients_overview() {
+    let adapter = MockIbcAdapter::default();
+    adapter.insert_header(mock_header(30_000));
+    // The consensus state of the first client is fresh, while the one of the
+    // second client is older than the trusting period.
+    for (n, height) in [(0, 29_999), (1, 1)] {
+        let client_id = ClientId::new(ClientType::Tendermint, n).unwrap();
+        adapter
+            .set_client_state(Context::new(), client_id.clone(), mock_client_state(height))
+            .unwrap();
+        adapter
+            .set_consensus_state(
+                Context::new(),
+                client_id,
+                Height::new(0, height).unwrap(),
+                mock_consensus_state(height),
+            )
+            .unwrap();
+    }
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+
+    let clients = service
+        .clients_overview(Request::new(QueryClientsOverviewRequest {}))
+        .await
+        .unwrap()
+        .into_inner()
+        .clients;
+    let rows = clients
+        .iter()
+        .map(|c| {
+            (
+                c.client_id.as_str(),
+                c.client_type.as_str(),
+                c.status.as_str(),
+                c.latest_height.clone().unwrap().revision_height,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![
+        ("07-tendermint-0", "07-tendermint", "Active", 29_999),
+        ("07-tendermint-1", "07-tendermint", "Expired", 1),
+    ]);
+}
+
+#[tokio::test]
+async fn test_served_clients_overview() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    adapter.insert_header(mock_header(5));
+    let addr = serve_queries(adapter).await;
+
+    let resp: QueryClientsOverviewResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.client.v1.Query/ClientsOverview",
+        QueryClientsOverviewRequest {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.clients.len(), 1);
+    assert_eq!(resp.clients[0].client_id, "07-tendermint-0");
+}
+
+#[tokio::test]
+async fn test_corrupt_entries() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    // A path which is not an IBC path can't be parsed, and is skipped.
+    adapter.insert_raw("channelEnds/ports/transfer/garbage", 0u8);
+    let adapter = Arc::new(adapter);
+
+    let channels = IbcChannelService::new(Arc::clone(&adapter), 0)
+        .channels(Request::new(QueryChannelsRequest { pagination: None }))
+        .await
+        .unwrap()
+        .into_inner()
+        .channels;
+    assert_eq!(channels.len(), 1);
+    assert_eq!(channels[0].channel_id, "channel-0");
+
+    // A value of the wrong type can't be decoded into the entry of its path,
+    // which fails the query instead of hiding the entry.
+    adapter.insert_raw("channelEnds/ports/transfer/channels/channel-1", 0u8);
+    let status = IbcChannelService::new(Arc::clone(&adapter), 0)
+        .channels(Request::new(QueryChannelsRequest { pagination: None }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::DataLoss);
+    assert!(status
+        .message()
+        .contains("corrupt entry at channelEnds/ports/transfer/channels/channel-1"));
+
+    adapter.insert_raw("clients/07-tendermint-1/clientState", 0u8);
+    let status = IbcClientService::new(adapter, 0)
+        .client_states(Request::new(QueryClientStatesRequest { pagination: None }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::DataLoss);
+    assert!(status
+        .message()
+        .contains("corrupt entry at clients/07-tendermint-1/clientState"));
+}
+
+#[tokio::test]
+async fn test_client_states_skip_clients_missing_at_query_height() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_height(10);
+    setup_open_channel(&adapter, 5);
+    adapter.set_height(20);
+    let client_id = ClientId::new(ClientType::Tendermint, 1).unwrap();
+    adapter
+        .set_client_state(Context::new(), client_id, mock_client_state(8))
+        .unwrap();
+    adapter.set_height(30);
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+    let request = |height: &str| {
+        let mut request = Request::new(QueryClientStatesRequest { pagination: None });
+        request
+            .metadata_mut()
+            .insert(QUERY_HEIGHT_METADATA, height.parse().unwrap());
+        request
+    };
+
+    // The paths are listed from the latest state, so the client created
+    // after the query height has a path but no state at that height.
+    let client_states = service
+        .client_states(request("15"))
+        .await
+        .unwrap()
+        .into_inner()
+        .client_states;
+    assert_eq!(client_states.len(), 1);
+    assert_eq!(client_states[0].client_id, "07-tendermint-0");
+
+    let client_states = service
+        .client_states(request("25"))
+        .await
+        .unwrap()
+        .into_inner()
+        .client_states;
+    assert_eq!(client_states.len(), 2);
+}
+
+#[tokio::test]
+async fn test_unexpected_paths_are_skipped() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    adapter.insert_raw("connections/garbage", 0u8);
+    adapter.insert_raw("clients/07-tendermint-0/consensusStates/garbage", 0u8);
+    let adapter = Arc::new(adapter);
+
+    let connections = IbcConnectionService::new(Arc::clone(&adapter), 0)
+        .connections(Request::new(QueryConnectionsRequest { pagination: None }))
+        .await
+        .unwrap()
+        .into_inner()
+        .connections;
+    assert_eq!(connections.len(), 1);
+    assert_eq!(connections[0].id, "connection-0");
+
+    let consensus_states = IbcClientService::new(adapter, 0)
+        .consensus_states(Request::new(QueryConsensusStatesRequest {
+            client_id:  "07-tendermint-0".to_string(),
+            pagination: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner()
+        .consensus_states;
+    assert_eq!(consensus_states.len(), 1);
+    assert_eq!(
+        consensus_states[0].height.clone().unwrap().revision_height,
+        5
+    );
+}
+
+#[tokio::test]
+async fn test_client_states_pagination() {
+    let adapter = MockIbcAdapter::default();
+    for n in 0..3 {
+        let client_id = ClientId::new(ClientType::Tendermint, n).unwrap();
+        adapter
+            .set_client_state(Context::new(), client_id, mock_client_state(n + 1))
+            .unwrap();
+    }
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+    let request = |pagination| Request::new(QueryClientStatesRequest { pagination });
+    let client_ids = |resp: &QueryClientStatesResponse| {
+        resp.client_states
+            .iter()
+            .map(|c| c.client_id.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let resp = service
+        .client_states(request(Some(PageRequest {
+            limit: 2,
+            count_total: true,
+            ..Default::default()
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(client_ids(&resp), vec![
+        "07-tendermint-0",
+        "07-tendermint-1"
+    ]);
+    let pagination = resp.pagination.unwrap();
+    assert_eq!(pagination.total, 3);
+    assert_eq!(pagination.next_key, b"07-tendermint-2".to_vec());
+
+    let resp = service
+        .client_states(request(Some(PageRequest {
+            key: pagination.next_key,
+            limit: 2,
+            ..Default::default()
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(client_ids(&resp), vec!["07-tendermint-2"]);
+    assert!(resp.pagination.unwrap().next_key.is_empty());
+
+    // A request without pagination gets the default page.
+    let resp = service
+        .client_states(request(None))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(client_ids(&resp).len(), 3);
+    assert!(resp.pagination.is_some());
+}
+
+#[tokio::test]
+async fn test_connections_and_channels_pagination() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let connection_end = adapter
+        .get_connection_end(
+            StoreHeight::Pending,
+            &path::ConnectionsPath(ConnectionId::new(0)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    let channel_end = adapter
+        .get_channel_end(
+            StoreHeight::Pending,
+            &path::ChannelEndsPath(PortId::transfer(), ChannelId::new(0)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    for n in 1..3 {
+        adapter
+            .set_connection_end(Context::new(), ConnectionId::new(n), connection_end.clone())
+            .unwrap();
+        adapter
+            .set_channel(
+                Context::new(),
+                PortId::transfer(),
+                ChannelId::new(n),
+                channel_end.clone(),
+            )
+            .unwrap();
+    }
+    let adapter = Arc::new(adapter);
+    let page = |key: Vec<u8>| {
+        Some(PageRequest {
+            key,
+            limit: 2,
+            count_total: true,
+            ..Default::default()
+        })
+    };
+
+    let connections = IbcConnectionService::new(Arc::clone(&adapter), 0);
+    let resp = connections
+        .connections(Request::new(QueryConnectionsRequest {
+            pagination: page(vec![]),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.connections.len(), 2);
+    let pagination = resp.pagination.unwrap();
+    assert_eq!(pagination.total, 3);
+    let resp = connections
+        .connections(Request::new(QueryConnectionsRequest {
+            pagination: page(pagination.next_key),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.connections.len(), 1);
+    assert_eq!(resp.connections[0].id, "connection-2");
+    assert!(resp.pagination.unwrap().next_key.is_empty());
+
+    let channels = IbcChannelService::new(adapter, 0);
+    let resp = channels
+        .channels(Request::new(QueryChannelsRequest {
+            pagination: page(vec![]),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.channels.len(), 2);
+    let pagination = resp.pagination.unwrap();
+    assert_eq!(pagination.total, 3);
+    let resp = channels
+        .channels(Request::new(QueryChannelsRequest {
+            pagination: page(pagination.next_key),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.channels.len(), 1);
+    assert_eq!(resp.channels[0].channel_id, "channel-2");
+}
+
+#[tokio::test]
+async fn test_consensus_states_since() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    for h in [1, 100, 200] {
+        adapter
+            .set_consensus_state(
+                Context::new(),
+                client_id.clone(),
+                Height::new(0, h).unwrap(),
+                mock_consensus_state(h),
+            )
+            .unwrap();
+    }
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+    // The timestamp of the consensus state at height 100.
+    let min_timestamp = (1_660_000_000 + 100 * 3) * 1_000_000_000;
+    let request = |pagination| {
+        Request::new(QueryConsensusStatesSinceRequest {
+            client_id: client_id.to_string(),
+            min_timestamp,
+            pagination,
+        })
+    };
+
+    let resp = service
+        .consensus_states_since(request(None))
+        .await
+        .unwrap()
+        .into_inner();
+    let heights = |resp: &QueryConsensusStatesResponse| {
+        resp.consensus_states
+            .iter()
+            .map(|cs| cs.height.clone().unwrap().revision_height)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(heights(&resp), vec![100, 200]);
+    assert!(resp.pagination.unwrap().next_key.is_empty());
+
+    let resp = service
+        .consensus_states_since(request(Some(PageRequest {
+            limit: 1,
+            count_total: true,
+            ..Default::default()
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(heights(&resp), vec![100]);
+    let pagination = resp.pagination.unwrap();
+    assert_eq!(pagination.total, 2);
+
+    let resp = service
+        .consensus_states_since(request(Some(PageRequest {
+            key: pagination.next_key,
+            limit: 1,
+            ..Default::default()
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(heights(&resp), vec![200]);
+    assert!(resp.pagination.unwrap().next_key.is_empty());
+}
+
+#[tokio::test]
+async fn test_consensus_states_min_timestamp() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    for h in [1, 100, 200, 300] {
+        adapter
+            .set_consensus_state(
+                Context::new(),
+                client_id.clone(),
+                Height::new(0, h).unwrap(),
+                mock_consensus_state(h),
+            )
+            .unwrap();
+    }
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+    // The timestamp of the consensus state at height 100.
+    let min_timestamp = (1_660_000_000 + 100 * 3) * 1_000_000_000u64;
+    let request = |min_timestamp: Option<&str>, pagination| {
+        let mut request = Request::new(QueryConsensusStatesRequest {
+            client_id: client_id.to_string(),
+            pagination,
+        });
+        if let Some(min_timestamp) = min_timestamp {
+            request
+                .metadata_mut()
+                .insert(MIN_TIMESTAMP_METADATA, min_timestamp.parse().unwrap());
+        }
+        request
+    };
+    let heights = |resp: &QueryConsensusStatesResponse| {
+        resp.consensus_states
+            .iter()
+            .map(|cs| cs.height.clone().unwrap().revision_height)
+            .collect::<Vec<_>>()
+    };
+
+    let resp = service
+        .consensus_states(request(None, None))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(heights(&resp), vec![1, 100, 200, 300]);
+
+    let resp = service
+        .consensus_states(request(
+            Some(&min_timestamp.to_string()),
+            Some(PageRequest {
+                limit: 2,
+                count_total: true,
+                ..Default::default()
+            }),
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(heights(&resp), vec![100, 200]);
+    let pagination = resp.pagination.unwrap();
+    assert_eq!(pagination.total, 3);
+    assert!(!pagination.next_key.is_empty());
+
+    let status = service
+        .consensus_states(request(Some("garbage"), None))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_response_height_matches_query_height() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_height(100);
+    let service = IbcChannelService::new(Arc::new(adapter), 2);
+    let request = |height: Option<&str>| {
+        let mut request = Request::new(QueryChannelsRequest { pagination: None });
+        if let Some(height) = height {
+            request
+                .metadata_mut()
+                .insert(QUERY_HEIGHT_METADATA, height.parse().unwrap());
+        }
+        request
+    };
+    let response_height = |resp: tonic::Response<QueryChannelsResponse>| {
+        let height = resp.into_inner().height.unwrap();
+        (height.revision_number, height.revision_height)
+    };
+
+    let resp = service.channels(request(Some("60"))).await.unwrap();
+    assert_eq!(response_height(resp), (2, 60));
+    let resp = service.channels(request(Some("0"))).await.unwrap();
+    assert_eq!(response_height(resp), (2, 100));
+    let resp = service.channels(request(None)).await.unwrap();
+    assert_eq!(response_height(resp), (2, 100));
+
+    let status = service.channels(request(Some("101"))).await.unwrap_err();
+    assert_eq!(status.code(), Code::OutOfRange);
+}
+
+#[tokio::test]
+async fn test_client_and_connection_query_height() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_height(10);
+    setup_open_channel(&adapter, 5);
+    adapter.set_height(20);
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    adapter
+        .set_client_state(Context::new(), client_id, mock_client_state(8))
+        .unwrap();
+    adapter.set_height(30);
+    let adapter = Arc::new(adapter);
+    fn with_height<T>(mut request: Request<T>, height: &str) -> Request<T> {
+        request
+            .metadata_mut()
+            .insert(QUERY_HEIGHT_METADATA, height.parse().unwrap());
+        request
+    }
+    let client_state_request = || {
+        Request::new(QueryClientStateRequest {
+            client_id: "07-tendermint-0".to_string(),
+        })
+    };
+
+    let clients = IbcClientService::new(Arc::clone(&adapter), 2);
+    let resp = clients
+        .client_state(with_height(client_state_request(), "15"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.client_state, Some(mock_client_state(5).into()));
+    let proof_height = resp.proof_height.unwrap();
+    assert_eq!(
+        (proof_height.revision_number, proof_height.revision_height),
+        (2, 16)
+    );
+    let resp = clients
+        .client_state(client_state_request())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.client_state, Some(mock_client_state(8).into()));
+    assert_eq!(resp.proof_height.unwrap().revision_height, 31);
+    let status = clients
+        .client_state(with_height(client_state_request(), "5"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    let status = clients
+        .client_state(with_height(client_state_request(), "31"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::OutOfRange);
+
+    let connections = IbcConnectionService::new(adapter, 2);
+    let connection_request = |height: &str| {
+        with_height(
+            Request::new(QueryConnectionRequest {
+                connection_id: "connection-0".to_string(),
+            }),
+            height,
+        )
+    };
+    let resp = connections
+        .connection(connection_request("5"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.connection.is_none());
+    let resp = connections
+        .connection(connection_request("15"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.connection.is_some());
+    let proof_height = resp.proof_height.unwrap();
+    assert_eq!(
+        (proof_height.revision_number, proof_height.revision_height),
+        (2, 16)
+    );
+}
+
+#[tokio::test]
+async fn test_connection_counter_persistence() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    ibc.increase_connection_counter();
+    ibc.increase_connection_counter();
+    // The increase of a discarded batch is not persisted.
+    ibc.begin_write_batch().unwrap();
+    ibc.increase_connection_counter();
+    ibc.discard_write_batch().unwrap();
+
+    // A restarted context on the same store resumes the counter.
+    let restarted = IbcImpl::new(Arc::clone(&ibc.adapter), IbcRouter::default()).unwrap();
+    assert_eq!(ConnectionReader::connection_counter(&restarted).unwrap(), 2);
+
+    let counter = IbcConnectionService::new(Arc::clone(&ibc.adapter), 0)
+        .connection_counter(Request::new(QueryConnectionCounterRequest {}))
+        .await
+        .unwrap()
+        .into_inner()
+        .connection_counter;
+    assert_eq!(counter, 2);
+}
+
+#[tokio::test]
+async fn test_served_connection_counter() {
+    let adapter = MockIbcAdapter::default();
+    adapter.set_connection_counter(Context::new(), 3).unwrap();
+    let addr = serve_queries(adapter).await;
+
+    let resp: QueryConnectionCounterResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.connection.v1.Query/ConnectionCounter",
+        QueryConnectionCounterRequest {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.connection_counter, 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_client_dedup() {
+    let adapter = MockIbcAdapter::default();
+    (1..=3).for_each(|n| adapter.insert_header(mock_header(n)));
+    adapter.set_height(2);
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.warm_consensus_states(3, 3).await.unwrap();
+    let ctx = Arc::new(RwLock::new(ibc));
+    let service = IbcClientMsgService::new(Arc::clone(&ctx)).with_create_client_dedup(true);
+
+    let msg = MsgCreateClient {
+        client_state:    Some(mock_client_state(1).into()),
+        consensus_state: Some(mock_consensus_state(1).into()),
+        signer:          "relayer".to_string(),
+    };
+    service
+        .create_client(Request::new(msg.clone()))
+        .await
+        .unwrap();
+    // The retry of the same msg returns the client created by the first one.
+    let response = service.create_client(Request::new(msg)).await.unwrap();
+    let log = response
+        .metadata()
+        .get(HANDLER_LOG_METADATA)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(log.contains("client 07-tendermint-0 is created already"));
+    assert_eq!(
+        ClientReader::client_counter(&*ctx.read().unwrap()).unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_ids_only_queries() {
+    let adapter = Arc::new(MockIbcAdapter::default());
+    setup_open_channel(&adapter, 5);
+
+    let client_ids = IbcClientService::new(Arc::clone(&adapter), 0)
+        .client_ids(Request::new(QueryIdsRequest {}))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(client_ids.client_ids, vec!["07-tendermint-0".to_string()]);
+
+    let connection_ids = IbcConnectionService::new(Arc::clone(&adapter), 0)
+        .connection_ids(Request::new(QueryIdsRequest {}))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(connection_ids.connection_ids, vec![
+        "connection-0".to_string()
+    ]);
+
+    let channel_ids = IbcChannelService::new(adapter, 0)
+        .channel_ids(Request::new(QueryIdsRequest {}))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(channel_ids.channels, vec![PortChannelId {
+        port_id:    "transfer".to_string(),
+        channel_id: "channel-0".to_string(),
+    }]);
+}
+
+#[tokio::test]
+async fn test_served_ids_only_queries() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let addr = serve_queries(adapter).await;
+
+    let resp: QueryClientIdsResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.client.v1.Query/ClientIds",
+        QueryIdsRequest {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.client_ids, vec!["07-tendermint-0".to_string()]);
+    let resp: QueryConnectionIdsResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.connection.v1.Query/ConnectionIds",
+        QueryIdsRequest {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.connection_ids, vec!["connection-0".to_string()]);
+    let resp: QueryChannelIdsResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.channel.v1.Query/ChannelIds",
+        QueryIdsRequest {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.channels.len(), 1);
+}
+
+#[tokio::test]
+async fn test_connection_consensus_state() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let service = IbcConnectionService::new(Arc::new(adapter), 0);
+    let request = |connection_id: &str, revision_height| {
+        Request::new(QueryConnectionConsensusStateRequest {
+            connection_id: connection_id.to_string(),
+            revision_number: 0,
+            revision_height,
+        })
+    };
+
+    let resp = service
+        .connection_consensus_state(request("connection-0", 5))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.client_id, "07-tendermint-0");
+    assert_eq!(
+        resp.consensus_state.unwrap(),
+        Any::from(mock_consensus_state(5))
+    );
+
+    for (connection_id, revision_height) in [("connection-0", 4), ("connection-1", 5)] {
+        let status = service
+            .connection_consensus_state(request(connection_id, revision_height))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+}
+
+#[tokio::test]
+async fn test_channel_consensus_state() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let service = IbcChannelService::new(Arc::new(adapter), 0);
+    let request = |channel_id: &str, revision_height| {
+        Request::new(QueryChannelConsensusStateRequest {
+            port_id: "transfer".to_string(),
+            channel_id: channel_id.to_string(),
+            revision_number: 0,
+            revision_height,
+        })
+    };
+
+    let resp = service
+        .channel_consensus_state(request("channel-0", 5))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.client_id, "07-tendermint-0");
+    assert_eq!(
+        resp.consensus_state.unwrap(),
+        Any::from(mock_consensus_state(5))
+    );
+
+    for (channel_id, revision_height) in [("channel-0", 4), ("channel-1", 5)] {
+        let status = service
+            .channel_consensus_state(request(channel_id, revision_height))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+}
+
+#[tokio::test]
+async fn test_proposed_app_version() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let module_id = ModuleId::new(Cow::Borrowed("transfer")).unwrap();
+    ibc.router
+        .add_route_with_version(
+            module_id.clone(),
+            MockModule::with_ack(vec![1]),
+            Version::new("ics20-1".to_string()),
+        )
+        .unwrap();
+    ibc.bind_port(PortId::transfer(), module_id).unwrap();
+    let service = IbcContextQueryService::new(Arc::new(RwLock::new(ibc)));
+
+    let request = |port_id: &str| {
+        Request::new(QueryProposedAppVersionRequest {
+            port_id: port_id.to_string(),
+        })
+    };
+    // No channel exists on the bound port yet.
+    let version = service
+        .proposed_app_version(request("transfer"))
+        .await
+        .unwrap()
+        .into_inner()
+        .version;
+    assert_eq!(version, "ics20-1");
+
+    let status = service
+        .proposed_app_version(request("unbound"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_served_proposed_app_version() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let module_id = ModuleId::new(Cow::Borrowed("transfer")).unwrap();
+    ibc.router
+        .add_route_with_version(
+            module_id.clone(),
+            MockModule::with_ack(vec![1]),
+            Version::new("ics20-1".to_string()),
+        )
+        .unwrap();
+    ibc.bind_port(PortId::transfer(), module_id).unwrap();
+    let addr = serve_queries_with_context(MockIbcAdapter::default(), ibc).await;
+
+    let resp: QueryProposedAppVersionResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.context.v1.Query/ProposedAppVersion",
+        QueryProposedAppVersionRequest {
+            port_id: "transfer".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.version, "ics20-1");
+}
+
+#[tokio::test]
+async fn test_consensus_state_heights() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    for h in [2, 10, 9] {
+        adapter
+            .set_consensus_state(
+                Context::new(),
+                client_id.clone(),
+                Height::new(0, h).unwrap(),
+                mock_consensus_state(h),
+            )
+            .unwrap();
+    }
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+    let request = |pagination| {
+        Request::new(QueryConsensusStateHeightsRequest {
+            client_id: client_id.to_string(),
+            pagination,
+        })
+    };
+    let heights = |resp: QueryConsensusStateHeightsResponse| {
+        resp.consensus_state_heights
+            .into_iter()
+            .map(|h| h.revision_height)
+            .collect::<Vec<_>>()
+    };
+
+    let resp = service
+        .consensus_state_heights(request(None))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.pagination.as_ref().unwrap().next_key.is_empty());
+    assert_eq!(heights(resp), vec![2, 9, 10]);
+
+    let resp = service
+        .consensus_state_heights(request(Some(PageRequest {
+            limit: 2,
+            count_total: true,
+            ..Default::default()
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    let next_key = resp.pagination.as_ref().unwrap().next_key.clone();
+    assert_eq!(resp.pagination.as_ref().unwrap().total, 3);
+    assert_eq!(heights(resp), vec![2, 9]);
+
+    // The next page starts at the height after the last one, not at the next
+    // key of the store.
+    let resp = service
+        .consensus_state_heights(request(Some(PageRequest {
+            key: next_key,
+            limit: 2,
+            ..Default::default()
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(heights(resp), vec![10]);
+}
+
+#[tokio::test]
+async fn test_consensus_state_latest_height() {
+    let adapter = MockIbcAdapter::default();
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    // The stored paths are not in the order of the heights.
+    for h in [2, 10, 9] {
+        adapter
+            .set_consensus_state(
+                Context::new(),
+                client_id.clone(),
+                Height::new(0, h).unwrap(),
+                mock_consensus_state(h),
+            )
+            .unwrap();
+    }
+    adapter
+        .set_client_latest_height(
+            Context::new(),
+            client_id.clone(),
+            Height::new(0, 10).unwrap(),
+        )
+        .unwrap();
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+    let request = |revision_height, latest_height| {
+        Request::new(QueryConsensusStateRequest {
+            client_id: client_id.to_string(),
+            revision_number: 0,
+            revision_height,
+            latest_height,
+        })
+    };
+
+    let resp = service.consensus_state(request(2, true)).await.unwrap();
+    assert_eq!(
+        resp.into_inner().consensus_state.unwrap(),
+        Any::from(mock_consensus_state(10))
+    );
+
+    let resp = service.consensus_state(request(9, false)).await.unwrap();
+    assert_eq!(
+        resp.into_inner().consensus_state.unwrap(),
+        Any::from(mock_consensus_state(9))
+    );
+
+    let status = service
+        .consensus_state(request(5, false))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_consensus_state_of_unknown_client() {
+    let service = IbcClientService::new(Arc::new(MockIbcAdapter::default()), 0);
+    let request = |client_id: &str, latest_height| {
+        Request::new(QueryConsensusStateRequest {
+            client_id: client_id.to_string(),
+            revision_number: 0,
+            revision_height: 1,
+            latest_height,
+        })
+    };
+
+    // A client without any consensus state has no latest one either.
+    for latest_height in [true, false] {
+        let status = service
+            .consensus_state(request("07-tendermint-0", latest_height))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    let status = service
+        .consensus_state(request("", true))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_query_client_state() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    adapter.set_height(100);
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+    let request = |client_id: &str| {
+        Request::new(QueryClientStateRequest {
+            client_id: client_id.to_string(),
+        })
+    };
+
+    let resp = service
+        .client_state(request("07-tendermint-0"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.client_state.unwrap(), Any::from(mock_client_state(5)));
+    assert_eq!(resp.proof_height.unwrap().revision_height, 101);
+
+    let status = service
+        .client_state(request("07-tendermint-1"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    let status = service.client_state(request("")).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_client_status() {
+    let adapter = MockIbcAdapter::default();
+    adapter.insert_header(mock_header(30_000));
+    for (n, height) in [(0, 29_999), (1, 1)] {
+        let client_id = ClientId::new(ClientType::Tendermint, n).unwrap();
+        adapter
+            .set_client_state(Context::new(), client_id.clone(), mock_client_state(height))
+            .unwrap();
+        adapter
+            .set_consensus_state(
+                Context::new(),
+                client_id,
+                Height::new(0, height).unwrap(),
+                mock_consensus_state(height),
+            )
+            .unwrap();
+    }
+    let service = IbcClientService::new(Arc::new(adapter), 0);
+    let request = |client_id: &str| {
+        Request::new(QueryClientStatusRequest {
+            client_id: client_id.to_string(),
+        })
+    };
+
+    for (client_id, expect) in [
+        ("07-tendermint-0", "Active"),
+        ("07-tendermint-1", "Expired"),
+    ] {
+        let resp = service.client_status(request(client_id)).await.unwrap();
+        assert_eq!(resp.into_inner().status, expect);
+    }
+
+    let status = service
+        .client_status(request("07-tendermint-2"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_unsupported_method_is_counted() {
+    let service = IbcClientService::new(Arc::new(MockIbcAdapter::default()), 0);
+    let counter = IBC_GRPC_UNSUPPORTED_COUNTER_VEC.with_label_values(&["upgraded_client_state"]);
+    let hits = counter.get();
+
+    let status = service
+        .upgraded_client_state(Request::new(QueryUpgradedClientStateRequest {}))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+    assert_eq!(counter.get(), hits + 1);
+}
+
+#[tokio::test]
+async fn test_query_without_proof_serves_empty_proof() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    adapter.disable_proofs();
+    let adapter = Arc::new(adapter);
+
+    let resp = IbcChannelService::new(Arc::clone(&adapter), 0)
+        .channel(Request::new(QueryChannelRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.channel.is_some());
+    assert!(resp.proof.is_empty());
+    let resp = IbcConnectionService::new(adapter, 0)
+        .connection(Request::new(QueryConnectionRequest {
+            connection_id: "connection-0".to_string(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(resp.connection.is_some());
+    assert!(resp.proof.is_empty());
+}
+
+#[tokio::test]
+async fn test_proof_height_is_above_query_height() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    adapter.set_height(100);
+    let adapter = Arc::new(adapter);
+    let service = IbcChannelService::new(Arc::clone(&adapter), 2);
+    let request = |height: Option<&str>| {
+        let mut request = Request::new(QueryChannelRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        });
+        if let Some(height) = height {
+            request
+                .metadata_mut()
+                .insert(QUERY_HEIGHT_METADATA, height.parse().unwrap());
+        }
+        request
+    };
+    let proof_height = |resp: tonic::Response<QueryChannelResponse>| {
+        let height = resp.into_inner().proof_height.unwrap();
+        (height.revision_number, height.revision_height)
+    };
+
+    // The state at height 60 is committed by the header at 61, which is the
+    // consensus state the counterparty verifies the proof against.
+    let resp = service.channel(request(Some("60"))).await.unwrap();
+    assert_eq!(proof_height(resp), (2, 61));
+    let resp = service.channel(request(None)).await.unwrap();
+    assert_eq!(proof_height(resp), (2, 101));
+
+    // So are the proofs of the client and the connection queries.
+    fn with_height<T>(mut request: Request<T>) -> Request<T> {
+        request
+            .metadata_mut()
+            .insert(QUERY_HEIGHT_METADATA, "60".parse().unwrap());
+        request
+    }
+    let height = |height: Option<RawHeight>| {
+        let height = height.unwrap();
+        (height.revision_number, height.revision_height)
+    };
+
+    let clients = IbcClientService::new(Arc::clone(&adapter), 2);
+    let resp = clients
+        .client_state(with_height(Request::new(QueryClientStateRequest {
+            client_id: "07-tendermint-0".to_string(),
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(height(resp.proof_height), (2, 61));
+    let resp = clients
+        .consensus_state(with_height(Request::new(QueryConsensusStateRequest {
+            client_id:       "07-tendermint-0".to_string(),
+            revision_number: 0,
+            revision_height: 5,
+            latest_height:   false,
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(height(resp.proof_height), (2, 61));
+
+    let connections = IbcConnectionService::new(adapter, 2);
+    let resp = connections
+        .connection(with_height(Request::new(QueryConnectionRequest {
+            connection_id: "connection-0".to_string(),
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(height(resp.proof_height), (2, 61));
+    let resp = connections
+        .client_connections(with_height(Request::new(QueryClientConnectionsRequest {
+            client_id: "07-tendermint-0".to_string(),
+        })))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(height(resp.proof_height), (2, 61));
+    let resp = connections
+        .connection_consensus_state(with_height(Request::new(
+            QueryConnectionConsensusStateRequest {
+                connection_id:   "connection-0".to_string(),
+                revision_number: 0,
+                revision_height: 5,
+            },
+        )))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(height(resp.proof_height), (2, 61));
+}
+
+#[tokio::test]
+async fn test_query_full_packet() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let mut ibc = mock_ibc_impl(adapter);
+    let service = IbcChannelService::new(Arc::clone(&ibc.adapter), 0);
+    let request = |sequence: u64| {
+        Request::new(QueryPacketRequest {
+            port_id: "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            sequence,
+        })
+    };
+
+    let packet = Packet {
+        sequence: Sequence::from(1),
+        source_port: PortId::transfer(),
+        source_channel: ChannelId::new(0),
+        destination_port: PortId::transfer(),
+        destination_channel: ChannelId::new(0),
+        data: b"{\"amount\":\"100\"}".to_vec(),
+        ..Default::default()
+    };
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(1));
+    ibc.adapter
+        .set_next_sequence_send(
+            Context::new(),
+            PortId::transfer(),
+            ChannelId::new(0),
+            Sequence::from(1),
+        )
+        .unwrap();
+    // The packet is stored in full by the send path, next to its commitment.
+    ibc.send_packet(packet.clone()).unwrap();
+    assert!(ibc
+        .adapter
+        .get_current_packet_commitment(Context::new(), &key)
+        .unwrap()
+        .is_some());
+
+    let resp = service.packet(request(1)).await.unwrap().into_inner();
+    assert_eq!(resp.packet, Some(RawPacket::from(packet.clone())));
+
+    let addr = serve_queries(Arc::clone(&ibc.adapter)).await;
+    let resp: QueryPacketResponse = call_ext_query(
+        &addr,
+        "/axon.ibc.channel.v1.Query/Packet",
+        QueryPacketRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            sequence:   1,
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.packet, Some(RawPacket::from(packet)));
+
+    let status = service.packet(request(2)).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    // The packet goes away with its commitment once acknowledged.
+    ibc.delete_packet_commitment(key.clone()).unwrap();
+    let status = service.packet(request(1)).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    assert!(ibc
+        .adapter
+        .get_packet(Context::new(), &key)
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_query_packet_commitment() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let mut ibc = mock_ibc_impl(adapter);
+    let service = IbcChannelService::new(Arc::clone(&ibc.adapter), 0);
+    let request = |sequence: u64| {
+        Request::new(QueryPacketCommitmentRequest {
+            port_id: "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            sequence,
+        })
+    };
+
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(1));
+    ibc.store_packet_commitment(key.clone(), PacketCommitment::from(vec![1u8; 32]))
+        .unwrap();
+
+    let resp = service
+        .packet_commitment(request(1))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.commitment, vec![1u8; 32]);
+    assert!(resp.proof_height.is_some());
+
+    let status = service.packet_commitment(request(2)).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    let status = service.packet_commitment(request(0)).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    ibc.delete_packet_commitment(key).unwrap();
+    let status = service.packet_commitment(request(1)).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_query_packet_receipt() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let mut ibc = mock_ibc_impl(adapter);
+    let service = IbcChannelService::new(Arc::clone(&ibc.adapter), 0);
+    let request = |sequence: u64| {
+        Request::new(QueryPacketReceiptRequest {
+            port_id: "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            sequence,
+        })
+    };
+
+    ibc.store_packet_receipt(
+        (PortId::transfer(), ChannelId::new(0), Sequence::from(1)),
+        Receipt::Ok,
+    )
+    .unwrap();
+
+    let resp = service.packet_receipt(request(1)).await.unwrap();
+    assert!(resp.into_inner().received);
+    let resp = service.packet_receipt(request(2)).await.unwrap();
+    assert!(!resp.into_inner().received);
+    let status = service.packet_receipt(request(0)).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_query_packet_acknowledgement() {
+    let adapter = MockIbcAdapter::default();
+    adapter
+        .set_packet_acknowledgement(
+            Context::new(),
+            (PortId::transfer(), ChannelId::new(0), Sequence::from(1)),
+            AcknowledgementCommitment::from(vec![7u8; 32]),
+        )
+        .unwrap();
+    let service = IbcChannelService::new(Arc::new(adapter), 0);
+    let request = |sequence: u64| {
+        Request::new(QueryPacketAcknowledgementRequest {
+            port_id: "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            sequence,
+        })
+    };
+
+    let resp = service
+        .packet_acknowledgement(request(1))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.acknowledgement, vec![7u8; 32]);
+
+    let status = service
+        .packet_acknowledgement(request(2))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_query_proofs() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(1));
+    adapter
+        .set_packet_commitment(
+            Context::new(),
+            key.clone(),
+            PacketCommitment::from(vec![1u8; 32]),
+        )
+        .unwrap();
+    adapter
+        .set_packet_acknowledgement(
+            Context::new(),
+            key,
+            AcknowledgementCommitment::from(vec![7u8; 32]),
+        )
+        .unwrap();
+    let adapter = Arc::new(adapter);
+    let proof_of = |path: String| format!("proof:{}", path).into_bytes();
+
+    let resp = IbcConnectionService::new(Arc::clone(&adapter), 0)
+        .connection(Request::new(QueryConnectionRequest {
+            connection_id: "connection-0".to_string(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(
+        resp.proof,
+        proof_of(path::ConnectionsPath(ConnectionId::new(0)).to_string())
+    );
+    assert!(resp.proof_height.is_some());
+
+    let service = IbcChannelService::new(adapter, 0);
+    let resp = service
+        .channel(Request::new(QueryChannelRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(
+        resp.proof,
+        proof_of(path::ChannelEndsPath(PortId::transfer(), ChannelId::new(0)).to_string())
+    );
+
+    let resp = service
+        .packet_commitment(Request::new(QueryPacketCommitmentRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            sequence:   1,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    let commitment_path = path::CommitmentsPath {
+        port_id:    PortId::transfer(),
+        channel_id: ChannelId::new(0),
+        sequence:   Sequence::from(1),
+    };
+    assert_eq!(resp.proof, proof_of(commitment_path.to_string()));
+
+    let resp = service
+        .packet_acknowledgement(Request::new(QueryPacketAcknowledgementRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+            sequence:   1,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    let ack_path = path::AcksPath {
+        port_id:    PortId::transfer(),
+        channel_id: ChannelId::new(0),
+        sequence:   Sequence::from(1),
+    };
+    assert_eq!(resp.proof, proof_of(ack_path.to_string()));
+}
+
+#[test]
+fn test_log_slow_query() {
+    let method = "/ibc.core.channel.v1.Query/PacketCommitments";
+    let threshold = DEFAULT_SLOW_QUERY_THRESHOLD;
+
+    assert!(!log_slow_query(
+        method,
+        Duration::from_millis(20),
+        threshold
+    ));
+    assert!(!log_slow_query(method, threshold, threshold));
+    assert!(log_slow_query(
+        method,
+        Duration::from_millis(501),
+        threshold
+    ));
+    assert!(log_slow_query(
+        method,
+        Duration::from_millis(20),
+        Duration::from_millis(10)
+    ));
+}
+
+#[tokio::test]
+async fn test_snapshot_reads() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    adapter.set_height(10);
+    let adapter = Arc::new(adapter);
+    let request = || {
+        Request::new(QueryChannelOverviewRequest {
+            port_id:    "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        })
+    };
+
+    // The block in execution updates the client between the reads of the
+    // connection end and the client state.
+    adapter.on_read("clients/07-tendermint-0/clientState", |adapter| {
+        adapter
+            .set_client_state(
+                Context::new(),
+                ClientId::new(ClientType::Tendermint, 0).unwrap(),
+                mock_client_state(8),
+            )
+            .unwrap();
+    });
+    let service = IbcChannelService::new(Arc::clone(&adapter), 0).with_snapshot_reads(true);
+    let overview = service
+        .channel_overview(request())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(
+        overview.client_state.unwrap().client_state,
+        Some(Any::from(mock_client_state(5)))
+    );
+    assert_eq!(
+        overview.consensus_state,
+        Some(Any::from(mock_consensus_state(5)))
+    );
+    assert_eq!(overview.height.unwrap().revision_height, 10);
+
+    // At pending the update shows, without the consensus state at its height.
+    let service = IbcChannelService::new(Arc::clone(&adapter), 0);
+    let overview = service
+        .channel_overview(request())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(
+        overview.client_state.unwrap().client_state,
+        Some(Any::from(mock_client_state(8)))
+    );
+    assert_eq!(overview.consensus_state, None);
+}
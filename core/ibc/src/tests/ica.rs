@@ -0,0 +1,211 @@
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use ibc::core::ics04_channel::channel::{Counterparty, Order};
+use ibc::core::ics04_channel::packet::Packet;
+use ibc::core::ics04_channel::Version;
+use ibc::core::ics24_host::identifier::{ChannelId, ConnectionId, PortId};
+use ibc::core::ics26_routing::context::{Module, ModuleOutputBuilder, OnRecvPacketAck};
+use ibc::signer::Signer;
+
+use protocol::types::{H160, U256};
+
+use crate::ica::{
+    IcaAcknowledgement, IcaCall, IcaExecutor, IcaHostModule, IcaMetadata, ICA_VERSION,
+};
+use crate::tests::MockIbcAdapter;
+
+type HostModule = IcaHostModule<MockExecutor, MockIbcAdapter>;
+
+#[derive(Default)]
+struct MockExecutor {
+    calls: Mutex<Vec<(H160, IcaCall)>>,
+}
+
+impl IcaExecutor for MockExecutor {
+    fn execute(&self, sender: H160, call: &IcaCall) -> Result<Vec<u8>, String> {
+        self.calls.lock().unwrap().push((sender, call.clone()));
+        if call.data.is_empty() {
+            return Err("empty call data".to_string());
+        }
+        Ok(call.data.clone())
+    }
+}
+
+fn controller_port() -> PortId {
+    PortId::from_str("icacontroller-owner").unwrap()
+}
+
+fn metadata(version: &str, host_connection_id: &str) -> IcaMetadata {
+    IcaMetadata {
+        version:                  version.to_string(),
+        controller_connection_id: "connection-0".to_string(),
+        host_connection_id:       host_connection_id.to_string(),
+        address:                  String::new(),
+        encoding:                 "proto3".to_string(),
+        tx_type:                  "sdk_multi_msg".to_string(),
+    }
+}
+
+fn try_open_channel(
+    module: &mut HostModule,
+    counterparty_version: &str,
+) -> Result<Version, ibc::core::ics04_channel::error::Error> {
+    let mut output = ModuleOutputBuilder::new();
+    module.on_chan_open_try(
+        &mut output,
+        Order::Ordered,
+        &[ConnectionId::new(0)],
+        &HostModule::port_id(),
+        &ChannelId::new(0),
+        &Counterparty::new(controller_port(), Some(ChannelId::new(0))),
+        &Version::new(ICA_VERSION.to_string()),
+        &Version::new(counterparty_version.to_string()),
+    )
+}
+
+fn open_channel(module: &mut HostModule, counterparty_version: &str) -> bool {
+    try_open_channel(module, counterparty_version).is_ok()
+}
+
+fn mock_host_module(executor: Arc<MockExecutor>) -> HostModule {
+    IcaHostModule::new(executor, Arc::new(MockIbcAdapter::default()))
+}
+
+fn host_packet(call: &IcaCall) -> Packet {
+    Packet {
+        destination_port: HostModule::port_id(),
+        destination_channel: ChannelId::new(0),
+        data: call.encode(),
+        ..Default::default()
+    }
+}
+
+fn ack_bytes(ack: OnRecvPacketAck) -> (bool, Vec<u8>) {
+    match ack {
+        OnRecvPacketAck::Successful(ack, _) => (true, (*ack).as_ref().to_vec()),
+        OnRecvPacketAck::Failed(ack) => (false, (*ack).as_ref().to_vec()),
+        OnRecvPacketAck::Nil(_) => panic!("unexpected nil ack"),
+    }
+}
+
+#[test]
+fn test_ica_call_codec() {
+    let call = IcaCall {
+        to:    H160::random(),
+        value: U256::from(100u64),
+        data:  vec![1, 2, 3],
+    };
+    assert_eq!(IcaCall::decode(&call.encode()).unwrap(), call);
+    assert!(IcaCall::decode(&[0u8; 51]).is_err());
+}
+
+#[test]
+fn test_ica_register_account() {
+    let executor = Arc::new(MockExecutor::default());
+    let adapter = Arc::new(MockIbcAdapter::default());
+    let mut module = IcaHostModule::new(Arc::clone(&executor), Arc::clone(&adapter));
+    let port_id = HostModule::port_id();
+
+    // The version must be the ICS-27 metadata of a channel on the connection.
+    assert!(!open_channel(&mut module, ICA_VERSION));
+    assert!(!open_channel(
+        &mut module,
+        &metadata("ics20-1", "connection-0").encode()
+    ));
+    assert!(!open_channel(
+        &mut module,
+        &metadata(ICA_VERSION, "connection-1").encode()
+    ));
+    assert!(module
+        .account(&port_id, &ChannelId::new(0))
+        .unwrap()
+        .is_none());
+
+    let address = HostModule::account_address(&ConnectionId::new(0), &controller_port());
+    let version =
+        try_open_channel(&mut module, &metadata(ICA_VERSION, "connection-0").encode()).unwrap();
+    assert_eq!(
+        IcaMetadata::decode(&version.to_string()).unwrap(),
+        IcaMetadata {
+            address: format!("{:?}", address),
+            ..metadata(ICA_VERSION, "connection-0")
+        }
+    );
+
+    // The account is stored, so a module restarted on the same store finds it.
+    let module = IcaHostModule::new(executor, adapter);
+    assert_eq!(
+        module.account(&port_id, &ChannelId::new(0)).unwrap(),
+        Some(address)
+    );
+}
+
+#[test]
+fn test_ica_reject_chan_open_init() {
+    let mut module = mock_host_module(Arc::new(MockExecutor::default()));
+    let mut output = ModuleOutputBuilder::new();
+    assert!(module
+        .on_chan_open_init(
+            &mut output,
+            Order::Ordered,
+            &[ConnectionId::new(0)],
+            &HostModule::port_id(),
+            &ChannelId::new(0),
+            &Counterparty::new(controller_port(), None),
+            &Version::new(metadata(ICA_VERSION, "connection-0").encode()),
+        )
+        .is_err());
+}
+
+#[test]
+fn test_ica_error_acknowledgement() {
+    // The reason is escaped, so the ack stays valid JSON.
+    let ack = IcaAcknowledgement::error(r#"revert "out of gas""#);
+    let value: serde_json::Value = serde_json::from_slice(ack.as_ref()).unwrap();
+    assert_eq!(value["error"], r#"revert "out of gas""#);
+}
+
+#[test]
+fn test_ica_execute_message() {
+    let executor = Arc::new(MockExecutor::default());
+    let mut module = mock_host_module(Arc::clone(&executor));
+    let call = IcaCall {
+        to:    H160::random(),
+        value: U256::zero(),
+        data:  vec![0xaa, 0xbb],
+    };
+    let relayer = Signer::new("relayer");
+
+    // The packet is rejected before the account is registered.
+    let mut output = ModuleOutputBuilder::new();
+    let (ok, _) = ack_bytes(module.on_recv_packet(&mut output, &host_packet(&call), &relayer));
+    assert!(!ok);
+    assert!(executor.calls.lock().unwrap().is_empty());
+
+    assert!(open_channel(
+        &mut module,
+        &metadata(ICA_VERSION, "connection-0").encode()
+    ));
+    let account = module
+        .account(&HostModule::port_id(), &ChannelId::new(0))
+        .unwrap()
+        .unwrap();
+
+    let (ok, ack) = ack_bytes(module.on_recv_packet(&mut output, &host_packet(&call), &relayer));
+    assert!(ok);
+    assert_eq!(ack, IcaAcknowledgement::success(&call.data).as_ref());
+    assert_eq!(executor.calls.lock().unwrap().as_slice(), &[(
+        account, call
+    )]);
+
+    // A failed execution is reported in an error acknowledgement.
+    let empty_call = IcaCall {
+        data: vec![],
+        ..call
+    };
+    let (ok, ack) =
+        ack_bytes(module.on_recv_packet(&mut output, &host_packet(&empty_call), &relayer));
+    assert!(!ok);
+    assert_eq!(ack, IcaAcknowledgement::error("empty call data").as_ref());
+}
@@ -0,0 +1,1567 @@
+mod batch;
+mod client;
+mod commitment;
+mod events;
+mod grpc;
+mod ica;
+mod router;
+mod transfer;
+
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use ibc::clients::ics07_tendermint::client_state::{AllowUpdate, ClientState as TmClientState};
+use ibc::core::{
+    ics02_client::client_consensus::AnyConsensusState,
+    ics02_client::context::{ClientKeeper, ClientReader},
+    ics02_client::error::ErrorDetail as ClientErrorDetail,
+    ics02_client::trust_threshold::TrustThreshold,
+    ics02_client::{client_state::AnyClientState, client_type::ClientType},
+    ics03_connection::connection::{
+        ConnectionEnd, Counterparty as ConnectionCounterparty, State as ConnectionState,
+    },
+    ics03_connection::context::ConnectionReader,
+    ics03_connection::version::get_compatible_versions,
+    ics04_channel::channel::{
+        ChannelEnd, Counterparty as ChannelCounterparty, Order, State as ChannelState,
+    },
+    ics04_channel::commitment::{AcknowledgementCommitment, PacketCommitment},
+    ics04_channel::context::{ChannelKeeper, ChannelReader},
+    ics04_channel::error::ErrorDetail as ChannelErrorDetail,
+    ics04_channel::packet::{Packet, Receipt, Sequence},
+    ics04_channel::Version,
+    ics05_port::context::PortReader,
+    ics23_commitment::{commitment::CommitmentPrefix, specs::ProofSpecs},
+    ics24_host::{
+        identifier::{ChainId, ChannelId, ClientId, ConnectionId, PortId},
+        path::{
+            AcksPath, ChannelEndsPath, ClientConnectionsPath, ClientConsensusStatePath,
+            ClientStatePath, ClientTypePath, CommitmentsPath, ConnectionsPath, ReceiptsPath,
+            SeqAcksPath, SeqRecvsPath, SeqSendsPath,
+        },
+    },
+};
+use ibc::timestamp::Timestamp;
+use ibc::Height;
+
+use core_storage::StorageError;
+use protocol::codec::error::CodecError;
+use protocol::traits::{Context, IbcAdapter, IbcGrpcAdapter, IterDirection};
+use protocol::types::{Header, Metadata, Path, StoreHeight, H160};
+use protocol::{async_trait, tokio, ProtocolResult};
+
+use crate::error::IbcError;
+use crate::tests::router::mock_module_id;
+use crate::{host_consensus_state, IbcImpl, IbcRouter};
+
+type MockValue = Arc<dyn Any + Send + Sync>;
+type ReadHook = Box<dyn FnOnce(&MockIbcAdapter) + Send>;
+
+/// An in-memory `IbcAdapter` which stores every value under its ICS024 path.
+/// A write lands in the pending block above the current height, and the
+/// versions of each path are kept for the reads at a committed height. The
+/// writes of an open batch are staged aside until it is committed.
+#[derive(Default)]
+pub struct MockIbcAdapter {
+    store:       RwLock<BTreeMap<String, MockValue>>,
+    versions:    RwLock<BTreeMap<String, Vec<(u64, Option<MockValue>)>>>,
+    headers:     RwLock<BTreeMap<u64, Header>>,
+    height:      RwLock<u64>,
+    oldest:      RwLock<Option<u64>>,
+    read_hook:   Mutex<Option<(String, ReadHook)>>,
+    scanned:     AtomicUsize,
+    no_proofs:   AtomicBool,
+    unversioned: AtomicBool,
+    batch:       Mutex<Option<BTreeMap<String, Option<MockValue>>>>,
+}
+
+impl MockIbcAdapter {
+    pub fn insert_header(&self, header: Header) {
+        let mut height = self.height.write().unwrap();
+        *height = (*height).max(header.number);
+        self.headers.write().unwrap().insert(header.number, header);
+    }
+
+    pub fn set_height(&self, height: u64) {
+        *self.height.write().unwrap() = height;
+    }
+
+    /// Set the oldest height kept, which is 1 by default as if nothing was
+    /// pruned.
+    pub fn set_oldest_height(&self, height: u64) {
+        *self.oldest.write().unwrap() = Some(height);
+    }
+
+    /// Insert a raw value under an arbitrary path, which is used to simulate
+    /// corrupt or unexpected store entries.
+    pub fn insert_raw<T: Any + Send + Sync>(&self, key: &str, value: T) {
+        self.write_raw(key, Some(Arc::new(value)));
+    }
+
+    /// Run `hook` once right before the next read of a path starting with
+    /// `prefix`, which is used to simulate a write between the reads of a
+    /// query.
+    pub fn on_read(&self, prefix: &str, hook: impl FnOnce(&MockIbcAdapter) + Send + 'static) {
+        *self.read_hook.lock().unwrap() = Some((prefix.to_owned(), Box::new(hook)));
+    }
+
+    /// The number of the paths yielded by `iter_paths_by_prefix` so far.
+    pub fn scanned_paths(&self) -> usize {
+        self.scanned.load(Ordering::SeqCst)
+    }
+
+    /// Prove nothing like a store which doesn't commit the entries.
+    pub fn disable_proofs(&self) {
+        self.no_proofs.store(true, Ordering::SeqCst);
+    }
+
+    /// Report no versions like a store which keeps only the latest state.
+    pub fn disable_versions(&self) {
+        self.unversioned.store(true, Ordering::SeqCst);
+    }
+
+    fn write_raw(&self, key: &str, value: Option<MockValue>) {
+        if let Some(batch) = self.batch.lock().unwrap().as_mut() {
+            batch.insert(key.to_owned(), value);
+            return;
+        }
+
+        match value.as_ref() {
+            Some(value) => self
+                .store
+                .write()
+                .unwrap()
+                .insert(key.to_owned(), Arc::clone(value)),
+            None => self.store.write().unwrap().remove(key),
+        };
+        self.push_version(key, value);
+    }
+
+    /// The first key after `from` which is stored or staged and not staged to
+    /// be removed, or `None` past the keys with the prefix.
+    fn next_key(&self, from: Option<&str>, prefix: &str) -> Option<String> {
+        let store = self.store.read().unwrap();
+        let batch = self.batch.lock().unwrap();
+        let mut from = match from {
+            Some(from) => Bound::Excluded(from.to_owned()),
+            None => Bound::Included(prefix.to_owned()),
+        };
+
+        loop {
+            let range = (from.clone(), Bound::Unbounded);
+            let stored = store.range::<String, _>(range.clone()).next();
+            let staged = batch
+                .as_ref()
+                .and_then(|batch| batch.range::<String, _>(range).next());
+            let key = match (stored, staged) {
+                (Some((a, _)), Some((b, _))) => a.min(b),
+                (Some((key, _)), None) | (None, Some((key, _))) => key,
+                (None, None) => return None,
+            };
+            if !key.starts_with(prefix) {
+                return None;
+            }
+
+            let removed = batch
+                .as_ref()
+                .and_then(|batch| batch.get(key))
+                .map_or(false, Option::is_none);
+            if !removed {
+                return Some(key.clone());
+            }
+            from = Bound::Excluded(key.clone());
+        }
+    }
+
+    fn keys_with_prefix(&self, prefix: String) -> impl Iterator<Item = String> + '_ {
+        let mut last: Option<String> = None;
+        std::iter::from_fn(move || {
+            let next = self.next_key(last.as_deref(), &prefix)?;
+            last = Some(next.clone());
+            Some(next)
+        })
+    }
+
+    fn push_version(&self, key: &str, value: Option<MockValue>) {
+        let height = *self.height.read().unwrap() + 1;
+        self.versions
+            .write()
+            .unwrap()
+            .entry(key.to_owned())
+            .or_default()
+            .push((height, value));
+    }
+
+    fn run_read_hook(&self, key: &str) {
+        let hook = {
+            let mut read_hook = self.read_hook.lock().unwrap();
+            match read_hook.as_ref() {
+                Some((prefix, _)) if key.starts_with(prefix.as_str()) => read_hook.take(),
+                _ => None,
+            }
+        };
+        if let Some((_, hook)) = hook {
+            hook(self);
+        }
+    }
+
+    fn insert<T: Any + Send + Sync>(&self, key: impl ToString, value: T) -> ProtocolResult<()> {
+        self.insert_raw(&key.to_string(), value);
+        Ok(())
+    }
+
+    fn get<T: Any + Clone>(&self, key: impl ToString) -> ProtocolResult<Option<T>> {
+        self.get_at(StoreHeight::Pending, key)
+    }
+
+    fn get_at<T: Any + Clone>(
+        &self,
+        height: StoreHeight,
+        key: impl ToString,
+    ) -> ProtocolResult<Option<T>> {
+        let key = key.to_string();
+        self.run_read_hook(&key);
+
+        let staged = self
+            .batch
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|batch| batch.get(&key).cloned());
+        let value = match (height, staged) {
+            (StoreHeight::Pending, Some(staged)) => staged,
+            (StoreHeight::Pending, None) => self.store.read().unwrap().get(&key).cloned(),
+            (StoreHeight::Stable(height), _) => self
+                .versions
+                .read()
+                .unwrap()
+                .get(&key)
+                .and_then(|versions| versions.iter().rev().find(|(h, _)| *h <= height))
+                .and_then(|(_, value)| value.clone()),
+        };
+        // A value of another type is what a store fails to decode.
+        match value {
+            Some(value) => match (*value).downcast_ref::<T>() {
+                Some(value) => Ok(Some(value.clone())),
+                None => Err(CodecError::Ibc(format!("{} has a value of another type", key)).into()),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn remove(&self, key: impl ToString) -> ProtocolResult<()> {
+        self.write_raw(&key.to_string(), None);
+        Ok(())
+    }
+
+    /// The sorted `(epoch, height)` of the consensus states of a client.
+    fn consensus_heights(&self, client_id: &ClientId) -> Vec<(u64, u64)> {
+        let prefix = format!("clients/{}/consensusStates/", client_id);
+        let mut heights = self
+            .keys_with_prefix(prefix.clone())
+            .filter_map(|k| k.strip_prefix(&prefix).map(str::to_owned))
+            .filter_map(|h| {
+                let (epoch, height) = h.split_once('-')?;
+                Some((epoch.parse().ok()?, height.parse().ok()?))
+            })
+            .collect::<Vec<(u64, u64)>>();
+        heights.sort_unstable();
+        heights
+    }
+
+    fn get_consensus_state_at(
+        &self,
+        client_id: &ClientId,
+        height: Option<(u64, u64)>,
+    ) -> ProtocolResult<Option<AnyConsensusState>> {
+        match height {
+            Some((epoch, height)) => self.get(ClientConsensusStatePath {
+                client_id: client_id.clone(),
+                epoch,
+                height,
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl IbcGrpcAdapter for MockIbcAdapter {
+    async fn get_client_state(
+        &self,
+        height: StoreHeight,
+        path: &ClientStatePath,
+    ) -> ProtocolResult<Option<AnyClientState>> {
+        self.get_at(height, path)
+    }
+
+    async fn get_consensus_state(
+        &self,
+        height: StoreHeight,
+        path: &ClientConsensusStatePath,
+    ) -> ProtocolResult<Option<AnyConsensusState>> {
+        self.get_at(height, path)
+    }
+
+    async fn get_connection_end(
+        &self,
+        height: StoreHeight,
+        path: &ConnectionsPath,
+    ) -> ProtocolResult<Option<ConnectionEnd>> {
+        self.get_at(height, path)
+    }
+
+    async fn get_connection_ids(
+        &self,
+        height: StoreHeight,
+        path: &ClientConnectionsPath,
+    ) -> ProtocolResult<Vec<ConnectionId>> {
+        Ok(self.get_at(height, path)?.unwrap_or_default())
+    }
+
+    async fn get_acknowledgement_commitment(
+        &self,
+        height: StoreHeight,
+        path: &AcksPath,
+    ) -> ProtocolResult<Option<AcknowledgementCommitment>> {
+        self.get_at(height, path)
+    }
+
+    async fn get_channel_end(
+        &self,
+        height: StoreHeight,
+        path: &ChannelEndsPath,
+    ) -> ProtocolResult<Option<ChannelEnd>> {
+        self.get_at(height, path)
+    }
+
+    fn get_opt(&self, height: StoreHeight, path: &ReceiptsPath) -> ProtocolResult<Option<()>> {
+        Ok(self.get_at::<Receipt>(height, path)?.map(|_| ()))
+    }
+
+    fn get_packet_commitment(
+        &self,
+        height: StoreHeight,
+        path: &CommitmentsPath,
+    ) -> ProtocolResult<Option<PacketCommitment>> {
+        self.get_at(height, path)
+    }
+
+    fn get_seq_sends(
+        &self,
+        height: StoreHeight,
+        path: &SeqSendsPath,
+    ) -> ProtocolResult<Option<Sequence>> {
+        self.get_at(height, path)
+    }
+
+    fn get_client_latest_height(
+        &self,
+        height: StoreHeight,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Option<Height>> {
+        self.get_at(height, client_latest_height_key(client_id))
+    }
+
+    fn get_paths_by_prefix(&self, key_prefix: &Path) -> ProtocolResult<Vec<Path>> {
+        self.keys_with_prefix(key_prefix.to_string())
+            .map(Path::try_from)
+            .collect()
+    }
+
+    fn get_proof(&self, _height: StoreHeight, path: &Path) -> ProtocolResult<Option<Vec<u8>>> {
+        if self.no_proofs.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        // A fake proof which names the proven path.
+        Ok(Some(format!("proof:{}", path).into_bytes()))
+    }
+
+    fn is_versioned(&self) -> bool {
+        !self.unversioned.load(Ordering::SeqCst)
+    }
+
+    // Each step looks up the key after the last one instead of copying the
+    // keys, so that a scan holds a single path at a time.
+    fn iter_paths_by_prefix<'a>(
+        &'a self,
+        key_prefix: &Path,
+    ) -> ProtocolResult<Box<dyn Iterator<Item = ProtocolResult<Path>> + 'a>> {
+        let paths = self
+            .keys_with_prefix(key_prefix.to_string())
+            .inspect(move |_| {
+                self.scanned.fetch_add(1, Ordering::SeqCst);
+            })
+            .map(Path::try_from);
+        Ok(Box::new(paths))
+    }
+}
+
+#[async_trait]
+impl IbcAdapter for MockIbcAdapter {
+    async fn get_metadata(&self, _height: u64) -> ProtocolResult<Metadata> {
+        Ok(Metadata::default())
+    }
+
+    async fn get_header_by_height(&self, height: u64) -> ProtocolResult<Header> {
+        self.headers
+            .read()
+            .unwrap()
+            .get(&height)
+            .cloned()
+            .ok_or_else(|| StorageError::GetNone(height.to_string()).into())
+    }
+
+    fn get_client_type(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Option<ClientType>> {
+        self.get(ClientTypePath(client_id.clone()))
+    }
+
+    fn get_current_client_state(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Option<AnyClientState>> {
+        self.get(ClientStatePath(client_id.clone()))
+    }
+
+    fn get_current_consensus_state(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        epoch: u64,
+        height: u64,
+    ) -> ProtocolResult<Option<AnyConsensusState>> {
+        self.get(ClientConsensusStatePath {
+            client_id: client_id.clone(),
+            epoch,
+            height,
+        })
+    }
+
+    fn get_next_consensus_state(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        height: Height,
+    ) -> ProtocolResult<Option<AnyConsensusState>> {
+        let next = self
+            .consensus_heights(client_id)
+            .into_iter()
+            .find(|(epoch, h)| (*epoch, *h) > (height.revision_number(), height.revision_height()));
+        self.get_consensus_state_at(client_id, next)
+    }
+
+    fn get_prev_consensus_state(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        height: Height,
+    ) -> ProtocolResult<Option<AnyConsensusState>> {
+        let prev = self
+            .consensus_heights(client_id)
+            .into_iter()
+            .rev()
+            .find(|(epoch, h)| (*epoch, *h) < (height.revision_number(), height.revision_height()));
+        self.get_consensus_state_at(client_id, prev)
+    }
+
+    fn get_consensus_heights(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        from: Option<Height>,
+        direction: IterDirection,
+        limit: usize,
+    ) -> ProtocolResult<Vec<Height>> {
+        let from = from.map(|h| (h.revision_number(), h.revision_height()));
+        let mut heights = self.consensus_heights(client_id);
+        if direction == IterDirection::Reverse {
+            heights.reverse();
+        }
+
+        heights
+            .into_iter()
+            .filter(|h| match (from, direction) {
+                (None, _) => true,
+                (Some(from), IterDirection::Forward) => *h > from,
+                (Some(from), IterDirection::Reverse) => *h < from,
+            })
+            .take(limit)
+            .map(|(epoch, height)| {
+                Height::new(epoch, height).map_err(|e| IbcError::Protocol(e).into())
+            })
+            .collect()
+    }
+
+    fn get_connection_end_by_id(
+        &self,
+        _ctx: Context,
+        conn_id: &ConnectionId,
+    ) -> ProtocolResult<Option<ConnectionEnd>> {
+        self.get(ConnectionsPath(conn_id.clone()))
+    }
+
+    fn get_channel_end_by_id(
+        &self,
+        _ctx: Context,
+        port_channel_id: &(PortId, ChannelId),
+    ) -> ProtocolResult<Option<ChannelEnd>> {
+        self.get(ChannelEndsPath(
+            port_channel_id.0.clone(),
+            port_channel_id.1.clone(),
+        ))
+    }
+
+    fn get_connection_channels(
+        &self,
+        _ctx: Context,
+        conn_id: &ConnectionId,
+    ) -> ProtocolResult<Option<Vec<(PortId, ChannelId)>>> {
+        self.get(format!("connectionChannels/{}", conn_id))
+    }
+
+    fn get_next_sequence_send(
+        &self,
+        _ctx: Context,
+        port_channel_id: &(PortId, ChannelId),
+    ) -> ProtocolResult<Option<Sequence>> {
+        self.get(SeqSendsPath(
+            port_channel_id.0.clone(),
+            port_channel_id.1.clone(),
+        ))
+    }
+
+    fn get_next_sequence_recv(
+        &self,
+        _ctx: Context,
+        port_channel_id: &(PortId, ChannelId),
+    ) -> ProtocolResult<Option<Sequence>> {
+        self.get(SeqRecvsPath(
+            port_channel_id.0.clone(),
+            port_channel_id.1.clone(),
+        ))
+    }
+
+    fn get_next_sequence_ack(
+        &self,
+        _ctx: Context,
+        port_channel_id: &(PortId, ChannelId),
+    ) -> ProtocolResult<Option<Sequence>> {
+        self.get(SeqAcksPath(
+            port_channel_id.0.clone(),
+            port_channel_id.1.clone(),
+        ))
+    }
+
+    fn get_current_packet_commitment(
+        &self,
+        _ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<PacketCommitment>> {
+        self.get(commitments_path(key.clone()))
+    }
+
+    fn get_packet(
+        &self,
+        _ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<Packet>> {
+        self.get(format!("packets/{}", commitments_path(key.clone())))
+    }
+
+    fn get_packet_receipt(
+        &self,
+        _ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<Receipt>> {
+        self.get(receipts_path(key.clone()))
+    }
+
+    fn get_packet_acknowledgement(
+        &self,
+        _ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<AcknowledgementCommitment>> {
+        self.get(acks_path(key.clone()))
+    }
+
+    fn get_acknowledgement(
+        &self,
+        _ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<Vec<u8>>> {
+        self.get(format!("acknowledgements/{}", acks_path(key.clone())))
+    }
+
+    fn get_interchain_account(
+        &self,
+        _ctx: Context,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> ProtocolResult<Option<H160>> {
+        self.get(format!("interchainAccounts/{}/{}", port_id, channel_id))
+    }
+
+    fn get_client_processed_time(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        height: Height,
+    ) -> ProtocolResult<Option<Timestamp>> {
+        self.get(client_processed_time_key(client_id, height))
+    }
+
+    fn get_client_processed_height(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+        height: Height,
+    ) -> ProtocolResult<Option<Height>> {
+        self.get(client_processed_height_key(client_id, height))
+    }
+
+    fn get_fork_consensus_states(
+        &self,
+        _ctx: Context,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Vec<(Height, AnyConsensusState)>> {
+        Ok(self
+            .get(fork_consensus_states_key(client_id))?
+            .unwrap_or_default())
+    }
+
+    fn set_client_type(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        client_type: ClientType,
+    ) -> ProtocolResult<()> {
+        self.insert(ClientTypePath(client_id), client_type)
+    }
+
+    fn set_client_state(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        client_state: AnyClientState,
+    ) -> ProtocolResult<()> {
+        self.insert(ClientStatePath(client_id), client_state)
+    }
+
+    fn set_consensus_state(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+        consensus_state: AnyConsensusState,
+    ) -> ProtocolResult<()> {
+        let path = ClientConsensusStatePath {
+            client_id,
+            epoch: height.revision_number(),
+            height: height.revision_height(),
+        };
+        self.insert(path, consensus_state)
+    }
+
+    fn set_client_latest_height(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+    ) -> ProtocolResult<()> {
+        self.insert(client_latest_height_key(&client_id), height)
+    }
+
+    fn set_client_processed_time(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+        timestamp: Timestamp,
+    ) -> ProtocolResult<()> {
+        self.insert(client_processed_time_key(&client_id, height), timestamp)
+    }
+
+    fn set_client_processed_height(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+        host_height: Height,
+    ) -> ProtocolResult<()> {
+        self.insert(client_processed_height_key(&client_id, height), host_height)
+    }
+
+    fn set_fork_consensus_states(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        states: Vec<(Height, AnyConsensusState)>,
+    ) -> ProtocolResult<()> {
+        if states.is_empty() {
+            return self.remove(fork_consensus_states_key(&client_id));
+        }
+        self.insert(fork_consensus_states_key(&client_id), states)
+    }
+
+    fn set_connection_end(
+        &self,
+        _ctx: Context,
+        connection_id: ConnectionId,
+        connection_end: ConnectionEnd,
+    ) -> ProtocolResult<()> {
+        self.insert(ConnectionsPath(connection_id), connection_end)
+    }
+
+    fn set_connection_to_client(
+        &self,
+        _ctx: Context,
+        connection_id: ConnectionId,
+        client_id: ClientId,
+    ) -> ProtocolResult<()> {
+        let path = ClientConnectionsPath(client_id);
+        let mut ids: Vec<ConnectionId> = self.get(path.clone())?.unwrap_or_default();
+        ids.push(connection_id);
+        self.insert(path, ids)
+    }
+
+    fn get_connection_counter(&self, _ctx: Context) -> ProtocolResult<Option<u64>> {
+        self.get("counters/connections")
+    }
+
+    fn set_connection_counter(&self, _ctx: Context, counter: u64) -> ProtocolResult<()> {
+        self.insert("counters/connections", counter)
+    }
+
+    fn set_packet_commitment(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        commitment: PacketCommitment,
+    ) -> ProtocolResult<()> {
+        self.insert(commitments_path(key), commitment)
+    }
+
+    fn set_packet(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        packet: Packet,
+    ) -> ProtocolResult<()> {
+        self.insert(format!("packets/{}", commitments_path(key)), packet)
+    }
+
+    fn set_packet_receipt(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        receipt: Receipt,
+    ) -> ProtocolResult<()> {
+        self.insert(receipts_path(key), receipt)
+    }
+
+    fn set_packet_acknowledgement(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        ack_commitment: AcknowledgementCommitment,
+    ) -> ProtocolResult<()> {
+        self.insert(acks_path(key), ack_commitment)
+    }
+
+    fn set_acknowledgement(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        ack: Vec<u8>,
+    ) -> ProtocolResult<()> {
+        self.insert(format!("acknowledgements/{}", acks_path(key)), ack)
+    }
+
+    fn set_interchain_account(
+        &self,
+        _ctx: Context,
+        port_id: PortId,
+        channel_id: ChannelId,
+        address: H160,
+    ) -> ProtocolResult<()> {
+        self.insert(
+            format!("interchainAccounts/{}/{}", port_id, channel_id),
+            address,
+        )
+    }
+
+    fn set_connection_channels(
+        &self,
+        ctx: Context,
+        conn_id: ConnectionId,
+        port_channel_id: &(PortId, ChannelId),
+    ) -> ProtocolResult<()> {
+        let mut channels = self
+            .get_connection_channels(ctx, &conn_id)?
+            .unwrap_or_default();
+        if !channels.contains(port_channel_id) {
+            channels.push(port_channel_id.clone());
+        }
+        self.insert(format!("connectionChannels/{}", conn_id), channels)
+    }
+
+    fn set_channel(
+        &self,
+        _ctx: Context,
+        port_id: PortId,
+        chan_id: ChannelId,
+        channel_end: ChannelEnd,
+    ) -> ProtocolResult<()> {
+        self.insert(ChannelEndsPath(port_id, chan_id), channel_end)
+    }
+
+    fn set_next_sequence_send(
+        &self,
+        _ctx: Context,
+        port_id: PortId,
+        chan_id: ChannelId,
+        seq: Sequence,
+    ) -> ProtocolResult<()> {
+        self.insert(SeqSendsPath(port_id, chan_id), seq)
+    }
+
+    fn set_next_sequence_recv(
+        &self,
+        _ctx: Context,
+        port_id: PortId,
+        chan_id: ChannelId,
+        seq: Sequence,
+    ) -> ProtocolResult<()> {
+        self.insert(SeqRecvsPath(port_id, chan_id), seq)
+    }
+
+    fn set_next_sequence_ack(
+        &self,
+        _ctx: Context,
+        port_id: PortId,
+        chan_id: ChannelId,
+        seq: Sequence,
+    ) -> ProtocolResult<()> {
+        self.insert(SeqAcksPath(port_id, chan_id), seq)
+    }
+
+    fn remove_packet_commitment(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<()> {
+        self.remove(commitments_path(key))
+    }
+
+    fn remove_packet(
+        &self,
+        _ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<()> {
+        self.remove(format!("packets/{}", commitments_path(key)))
+    }
+
+    fn remove_consensus_state(
+        &self,
+        _ctx: Context,
+        client_id: ClientId,
+        height: Height,
+    ) -> ProtocolResult<()> {
+        self.remove(client_processed_time_key(&client_id, height))?;
+        self.remove(client_processed_height_key(&client_id, height))?;
+        self.remove(ClientConsensusStatePath {
+            client_id,
+            epoch: height.revision_number(),
+            height: height.revision_height(),
+        })
+    }
+
+    fn begin_batch(&self, _ctx: Context) -> ProtocolResult<()> {
+        self.batch.lock().unwrap().get_or_insert_with(BTreeMap::new);
+        Ok(())
+    }
+
+    fn commit_batch(&self, _ctx: Context) -> ProtocolResult<()> {
+        let batch = self.batch.lock().unwrap().take();
+        for (key, value) in batch.into_iter().flatten() {
+            self.write_raw(&key, value);
+        }
+        Ok(())
+    }
+
+    fn discard_batch(&self, _ctx: Context) -> ProtocolResult<()> {
+        self.batch.lock().unwrap().take();
+        Ok(())
+    }
+
+    fn current_height(&self) -> u64 {
+        *self.height.read().unwrap()
+    }
+
+    fn oldest_height(&self) -> ProtocolResult<u64> {
+        Ok(self.oldest.read().unwrap().unwrap_or(1))
+    }
+}
+
+// The latest height of a client has no ICS024 path, so it is kept out of the
+// `clients` prefix.
+fn client_latest_height_key(client_id: &ClientId) -> String {
+    format!("latestHeights/{}", client_id)
+}
+
+fn client_processed_time_key(client_id: &ClientId, height: Height) -> String {
+    format!("processedTimes/{}/{}", client_id, height)
+}
+
+fn client_processed_height_key(client_id: &ClientId, height: Height) -> String {
+    format!("processedHeights/{}/{}", client_id, height)
+}
+
+fn fork_consensus_states_key(client_id: &ClientId) -> String {
+    format!("forkConsensusStates/{}", client_id)
+}
+
+fn commitments_path(
+    (port_id, channel_id, sequence): (PortId, ChannelId, Sequence),
+) -> CommitmentsPath {
+    CommitmentsPath {
+        port_id,
+        channel_id,
+        sequence,
+    }
+}
+
+fn receipts_path((port_id, channel_id, sequence): (PortId, ChannelId, Sequence)) -> ReceiptsPath {
+    ReceiptsPath {
+        port_id,
+        channel_id,
+        sequence,
+    }
+}
+
+fn acks_path((port_id, channel_id, sequence): (PortId, ChannelId, Sequence)) -> AcksPath {
+    AcksPath {
+        port_id,
+        channel_id,
+        sequence,
+    }
+}
+
+pub fn mock_header(number: u64) -> Header {
+    Header {
+        number,
+        timestamp: 1_660_000_000 + number * 3,
+        ..Default::default()
+    }
+}
+
+pub fn mock_client_state(latest_height: u64) -> AnyClientState {
+    let client_state = TmClientState::new(
+        ChainId::new("axon".to_string(), 0),
+        TrustThreshold::ONE_THIRD,
+        Duration::from_secs(64_000),
+        Duration::from_secs(128_000),
+        Duration::from_millis(3_000),
+        Height::new(0, latest_height).unwrap(),
+        ProofSpecs::default(),
+        vec![],
+        AllowUpdate {
+            after_expiry:       false,
+            after_misbehaviour: false,
+        },
+    )
+    .unwrap();
+    AnyClientState::Tendermint(client_state)
+}
+
+pub fn mock_consensus_state(number: u64) -> AnyConsensusState {
+    AnyConsensusState::Tendermint(host_consensus_state(&mock_header(number)).unwrap())
+}
+
+/// Store an open channel `transfer/channel-0` on `connection-0` backed by the
+/// client `07-tendermint-0`, whose latest height is `client_height`.
+pub fn setup_open_channel(adapter: &MockIbcAdapter, client_height: u64) {
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let conn_id = ConnectionId::new(0);
+    let port_id = PortId::transfer();
+    let channel_id = ChannelId::new(0);
+
+    adapter
+        .set_client_state(
+            Context::new(),
+            client_id.clone(),
+            mock_client_state(client_height),
+        )
+        .unwrap();
+    adapter
+        .set_consensus_state(
+            Context::new(),
+            client_id.clone(),
+            Height::new(0, client_height).unwrap(),
+            mock_consensus_state(client_height),
+        )
+        .unwrap();
+
+    let connection_end = ConnectionEnd::new(
+        ConnectionState::Open,
+        client_id.clone(),
+        ConnectionCounterparty::new(
+            client_id,
+            Some(conn_id.clone()),
+            CommitmentPrefix::try_from(b"ibc".to_vec()).unwrap(),
+        ),
+        get_compatible_versions(),
+        Duration::from_secs(0),
+    );
+    adapter
+        .set_connection_end(Context::new(), conn_id.clone(), connection_end)
+        .unwrap();
+
+    let channel_end = ChannelEnd::new(
+        ChannelState::Open,
+        Order::Unordered,
+        ChannelCounterparty::new(port_id.clone(), Some(channel_id.clone())),
+        vec![conn_id.clone()],
+        Version::new("ics20-1".to_string()),
+    );
+    adapter
+        .set_connection_channels(
+            Context::new(),
+            conn_id,
+            &(port_id.clone(), channel_id.clone()),
+        )
+        .unwrap();
+    adapter
+        .set_channel(Context::new(), port_id, channel_id, channel_end)
+        .unwrap();
+}
+
+pub fn mock_ibc_impl(adapter: MockIbcAdapter) -> IbcImpl<MockIbcAdapter, IbcRouter> {
+    IbcImpl::new(Arc::new(adapter), IbcRouter::default()).unwrap()
+}
+
+#[test]
+fn test_ibc_impl_builder() {
+    let ibc = IbcImpl::new(Arc::new(MockIbcAdapter::default()), IbcRouter::default())
+        .unwrap()
+        .with_ports([(PortId::transfer(), mock_module_id())])
+        .unwrap()
+        .with_host_consensus_states([(1, host_consensus_state(&mock_header(1)).unwrap())]);
+
+    assert_eq!(
+        PortReader::lookup_module_by_port(&ibc, &PortId::transfer()).unwrap(),
+        mock_module_id()
+    );
+    // Outside of a multi-thread runtime the consensus states can't be
+    // reloaded from the adapter, so only the preloaded one is served.
+    assert_eq!(
+        ClientReader::host_consensus_state(&ibc, Height::new(0, 1).unwrap()).unwrap(),
+        mock_consensus_state(1)
+    );
+    assert!(ClientReader::host_consensus_state(&ibc, Height::new(0, 2).unwrap()).is_err());
+
+    let rebound = IbcImpl::new(Arc::new(MockIbcAdapter::default()), IbcRouter::default())
+        .unwrap()
+        .with_ports([
+            (PortId::transfer(), mock_module_id()),
+            (PortId::transfer(), mock_module_id()),
+        ]);
+    assert!(rebound.is_err());
+}
+
+#[test]
+fn test_push_host_consensus_state() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    ibc.set_consensus_state_cache_size(NonZeroUsize::new(2).unwrap());
+
+    for n in 1..=3 {
+        ibc.push_host_consensus_state(n, host_consensus_state(&mock_header(n)).unwrap());
+    }
+
+    // The oldest state is evicted, and can't be reloaded outside of a
+    // multi-thread runtime, which is not reported as a missing state.
+    let err = ClientReader::host_consensus_state(&ibc, Height::new(0, 1).unwrap()).unwrap_err();
+    assert!(matches!(
+        err.detail(),
+        ClientErrorDetail::ImplementationSpecific(_)
+    ));
+    assert_eq!(
+        ClientReader::host_consensus_state(&ibc, Height::new(0, 3).unwrap()).unwrap(),
+        mock_consensus_state(3)
+    );
+    assert_eq!(ibc.consensus_states.lock().len(), 2);
+}
+
+#[tokio::test]
+async fn test_warm_consensus_states() {
+    let adapter = MockIbcAdapter::default();
+    (1..=5).for_each(|n| adapter.insert_header(mock_header(n)));
+
+    let mut ibc = mock_ibc_impl(adapter);
+    assert!(ibc.consensus_states.lock().is_empty());
+
+    ibc.warm_consensus_states(2, 4).await.unwrap();
+
+    let consensus_states = ibc.consensus_states.lock();
+    assert_eq!(consensus_states.len(), 3);
+    assert!(consensus_states.contains(&2));
+    assert!(consensus_states.contains(&4));
+    assert!(!consensus_states.contains(&5));
+    drop(consensus_states);
+
+    // Heights the adapter does not know about are reported as an error.
+    assert!(ibc.warm_consensus_states(5, 6).await.is_err());
+}
+
+#[tokio::test]
+async fn test_warm_latest_consensus_states() {
+    let adapter = MockIbcAdapter::default();
+    (1..=5).for_each(|n| adapter.insert_header(mock_header(n)));
+    adapter.set_oldest_height(2);
+
+    // Only as many of the latest states as the cache holds are loaded.
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.set_consensus_state_cache_size(NonZeroUsize::new(3).unwrap());
+    ibc.warm_latest_consensus_states().await.unwrap();
+    let heights = ibc
+        .consensus_states
+        .lock()
+        .iter()
+        .map(|(h, _)| *h)
+        .collect::<BTreeSet<_>>();
+    assert_eq!(heights, BTreeSet::from([3, 4, 5]));
+
+    // The states below the oldest kept block are not loaded.
+    let adapter = MockIbcAdapter::default();
+    (4..=5).for_each(|n| adapter.insert_header(mock_header(n)));
+    adapter.set_oldest_height(4);
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.warm_latest_consensus_states().await.unwrap();
+    assert_eq!(ibc.consensus_states.lock().len(), 2);
+}
+
+#[test]
+fn test_reconcile_counters() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 5);
+    // The clients in between are lost in a crash, and so is the increase of
+    // the counters.
+    let client_id = ClientId::new(ClientType::Tendermint, 5).unwrap();
+    adapter
+        .set_client_state(Context::new(), client_id, mock_client_state(1))
+        .unwrap();
+    let connection_end = adapter
+        .get_connection_end_by_id(Context::new(), &ConnectionId::new(0))
+        .unwrap()
+        .unwrap();
+    adapter
+        .set_connection_end(Context::new(), ConnectionId::new(2), connection_end)
+        .unwrap();
+
+    // The counters are reconciled when the context is built.
+    let mut ibc = mock_ibc_impl(adapter);
+    assert_eq!(ClientReader::client_counter(&ibc).unwrap(), 6);
+    assert_eq!(ConnectionReader::connection_counter(&ibc).unwrap(), 3);
+    assert_eq!(ChannelReader::channel_counter(&ibc).unwrap(), 1);
+
+    // A counter already above the stored ids is kept.
+    ibc.client_counter = 10;
+    ibc.reconcile_counters().unwrap();
+    assert_eq!(ClientReader::client_counter(&ibc).unwrap(), 10);
+}
+
+#[test]
+fn test_max_channels_per_connection() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 1);
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.set_max_channels_per_connection(Some(2));
+
+    let conn_id = ConnectionId::new(0);
+    let new_channel = |n| {
+        let channel_end = ChannelEnd::new(
+            ChannelState::Init,
+            Order::Unordered,
+            ChannelCounterparty::new(PortId::transfer(), None),
+            vec![conn_id.clone()],
+            Version::new("ics20-1".to_string()),
+        );
+        ((PortId::transfer(), ChannelId::new(n)), channel_end)
+    };
+
+    let (port_channel_id, channel_end) = new_channel(1);
+    ibc.store_channel(port_channel_id, &channel_end).unwrap();
+    assert_eq!(ibc.connection_channels(&conn_id).unwrap().len(), 2);
+
+    let (port_channel_id, channel_end) = new_channel(2);
+    assert!(ibc
+        .store_channel(port_channel_id.clone(), &channel_end)
+        .is_err());
+    assert!(ibc.channel_end(&port_channel_id).is_err());
+
+    // Updating an existing channel is not limited.
+    let (port_channel_id, mut channel_end) = new_channel(1);
+    channel_end.set_state(ChannelState::Open);
+    ibc.store_channel(port_channel_id, &channel_end).unwrap();
+
+    // A channel on another connection is not counted.
+    assert!(ibc
+        .connection_channels(&ConnectionId::new(1))
+        .unwrap()
+        .is_empty());
+
+    ibc.set_max_channels_per_connection(None);
+    let (port_channel_id, channel_end) = new_channel(2);
+    ibc.store_channel(port_channel_id, &channel_end).unwrap();
+    assert_eq!(ibc.connection_channels(&conn_id).unwrap().len(), 3);
+}
+
+#[test]
+fn test_check_sequence_tracking() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 1);
+    let port_channel_id = (PortId::transfer(), ChannelId::new(0));
+    adapter
+        .set_next_sequence_recv(
+            Context::new(),
+            port_channel_id.0.clone(),
+            port_channel_id.1.clone(),
+            Sequence::from(1),
+        )
+        .unwrap();
+    let ibc = mock_ibc_impl(adapter);
+    assert!(ibc.check_sequence_tracking(&port_channel_id).is_ok());
+
+    // An unordered channel never advances its next recv sequence.
+    ibc.adapter
+        .set_next_sequence_recv(
+            Context::new(),
+            port_channel_id.0.clone(),
+            port_channel_id.1.clone(),
+            Sequence::from(5),
+        )
+        .unwrap();
+    assert!(ibc.check_sequence_tracking(&port_channel_id).is_err());
+
+    // An ordered channel never stores receipts.
+    let mut channel_end = ibc.channel_end(&port_channel_id).unwrap();
+    channel_end.ordering = Order::Ordered;
+    ibc.adapter
+        .set_channel(
+            Context::new(),
+            port_channel_id.0.clone(),
+            port_channel_id.1.clone(),
+            channel_end,
+        )
+        .unwrap();
+    assert!(ibc.check_sequence_tracking(&port_channel_id).is_ok());
+    ibc.adapter
+        .set_packet_receipt(
+            Context::new(),
+            (
+                port_channel_id.0.clone(),
+                port_channel_id.1.clone(),
+                Sequence::from(1),
+            ),
+            Receipt::Ok,
+        )
+        .unwrap();
+    assert!(ibc.check_sequence_tracking(&port_channel_id).is_err());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "store packet receipt")]
+fn test_receipt_on_ordered_channel_panics_in_debug() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 1);
+    let mut ibc = mock_ibc_impl(adapter);
+    let port_channel_id = (PortId::transfer(), ChannelId::new(0));
+    let mut channel_end = ibc.channel_end(&port_channel_id).unwrap();
+    channel_end.ordering = Order::Ordered;
+    ibc.store_channel(port_channel_id, &channel_end).unwrap();
+
+    let _ = ibc.store_packet_receipt(
+        (PortId::transfer(), ChannelId::new(0), Sequence::from(1)),
+        Receipt::Ok,
+    );
+}
+
+#[tokio::test]
+async fn test_check_recv_packet_timeout() {
+    let adapter = MockIbcAdapter::default();
+    (1..=5).for_each(|n| adapter.insert_header(mock_header(n)));
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.warm_consensus_states(5, 5).await.unwrap();
+    let host_timestamp = (1_660_000_000 + 5 * 3) * 1_000_000_000u64;
+
+    let packet = |timeout_height: u64, timeout_timestamp: u64| Packet {
+        sequence: Sequence::from(1),
+        timeout_height: if timeout_height == 0 {
+            Default::default()
+        } else {
+            Height::new(0, timeout_height).unwrap().into()
+        },
+        timeout_timestamp: Timestamp::from_nanoseconds(timeout_timestamp).unwrap(),
+        ..Default::default()
+    };
+
+    // Zero timeout height and timestamp are unbounded.
+    assert!(ibc.check_recv_packet_timeout(&packet(0, 0)).is_ok());
+    assert!(ibc.check_recv_packet_timeout(&packet(6, 0)).is_ok());
+    assert!(ibc
+        .check_recv_packet_timeout(&packet(0, host_timestamp + 1))
+        .is_ok());
+
+    // Expired by height.
+    assert!(matches!(
+        ibc.check_recv_packet_timeout(&packet(5, 0)),
+        Err(IbcError::PacketTimedOut { sequence: 1, .. })
+    ));
+
+    // Expired by time.
+    assert!(matches!(
+        ibc.check_recv_packet_timeout(&packet(0, host_timestamp)),
+        Err(IbcError::PacketTimedOut { sequence: 1, .. })
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_channel_host_consensus_state() {
+    let adapter = MockIbcAdapter::default();
+    (1..=3).for_each(|n| adapter.insert_header(mock_header(n)));
+    adapter.set_height(2);
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.warm_consensus_states(1, 3).await.unwrap();
+
+    assert_eq!(
+        ChannelReader::host_consensus_state(&ibc, Height::new(0, 1).unwrap()).unwrap(),
+        mock_consensus_state(1)
+    );
+    assert_eq!(
+        ChannelReader::pending_host_consensus_state(&ibc).unwrap(),
+        mock_consensus_state(3)
+    );
+    assert!(ChannelReader::host_consensus_state(&ibc, Height::new(0, 100).unwrap()).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_consensus_state_cache_eviction() {
+    let adapter = MockIbcAdapter::default();
+    (1..=5).for_each(|n| adapter.insert_header(mock_header(n)));
+    let mut ibc = mock_ibc_impl(adapter);
+    ibc.set_consensus_state_cache_size(NonZeroUsize::new(2).unwrap());
+
+    ibc.warm_consensus_states(1, 3).await.unwrap();
+    {
+        let consensus_states = ibc.consensus_states.lock();
+        assert_eq!(consensus_states.len(), 2);
+        assert!(!consensus_states.contains(&1));
+        assert!(consensus_states.contains(&2));
+        assert!(consensus_states.contains(&3));
+    }
+
+    // The evicted consensus state is reloaded from the adapter on access,
+    // evicting the least recently used one.
+    let expect = AnyConsensusState::Tendermint(host_consensus_state(&mock_header(1)).unwrap());
+    assert_eq!(
+        ClientReader::host_consensus_state(&ibc, Height::new(0, 1).unwrap()).unwrap(),
+        expect
+    );
+    {
+        let consensus_states = ibc.consensus_states.lock();
+        assert_eq!(consensus_states.len(), 2);
+        assert!(consensus_states.contains(&1));
+        assert!(!consensus_states.contains(&2));
+        assert!(consensus_states.contains(&3));
+    }
+
+    // Only a height the adapter has no header of is a missing state.
+    let err = ClientReader::host_consensus_state(&ibc, Height::new(0, 100).unwrap()).unwrap_err();
+    assert!(matches!(
+        err.detail(),
+        ClientErrorDetail::MissingLocalConsensusState(_)
+    ));
+}
+
+#[test]
+fn test_channel_over_missing_connection_is_rejected() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 1);
+    let mut ibc = mock_ibc_impl(adapter);
+    let new_channel = |conn_id| {
+        ChannelEnd::new(
+            ChannelState::Init,
+            Order::Unordered,
+            ChannelCounterparty::new(PortId::transfer(), None),
+            vec![conn_id],
+            Version::new("ics20-1".to_string()),
+        )
+    };
+
+    let port_channel_id = (PortId::transfer(), ChannelId::new(1));
+    assert!(ibc
+        .store_channel(port_channel_id.clone(), &new_channel(ConnectionId::new(1)))
+        .is_err());
+    assert!(ibc.channel_end(&port_channel_id).is_err());
+
+    // A connection still in the handshake is rejected as well.
+    let mut connection_end = ConnectionReader::connection_end(&ibc, &ConnectionId::new(0)).unwrap();
+    connection_end.set_state(ConnectionState::TryOpen);
+    ibc.adapter
+        .set_connection_end(Context::new(), ConnectionId::new(1), connection_end)
+        .unwrap();
+    assert!(ibc
+        .store_channel(port_channel_id.clone(), &new_channel(ConnectionId::new(1)))
+        .is_err());
+
+    ibc.store_channel(port_channel_id.clone(), &new_channel(ConnectionId::new(0)))
+        .unwrap();
+    assert!(ibc.channel_end(&port_channel_id).is_ok());
+}
+
+#[test]
+fn test_next_sequence_recv() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let port_channel_id = (PortId::transfer(), ChannelId::new(0));
+    assert!(ibc.get_next_sequence_recv(&port_channel_id).is_err());
+
+    ibc.store_next_sequence_recv(port_channel_id.clone(), Sequence::from(3))
+        .unwrap();
+    assert_eq!(
+        ibc.get_next_sequence_recv(&port_channel_id).unwrap(),
+        Sequence::from(3)
+    );
+    assert!(ibc
+        .get_next_sequence_recv(&(PortId::transfer(), ChannelId::new(1)))
+        .is_err());
+}
+
+#[test]
+fn test_packet_commitment_not_found() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(1));
+    let err = ibc.get_packet_commitment(&key).unwrap_err();
+    assert!(matches!(
+        err.detail(),
+        ChannelErrorDetail::PacketCommitmentNotFound(e) if e.sequence == Sequence::from(1)
+    ));
+
+    let commitment = PacketCommitment::from(vec![1u8; 32]);
+    ibc.store_packet_commitment(key.clone(), commitment.clone())
+        .unwrap();
+    assert_eq!(ibc.get_packet_commitment(&key).unwrap(), commitment);
+
+    ibc.delete_packet_commitment(key.clone()).unwrap();
+    assert!(ibc.get_packet_commitment(&key).is_err());
+}
+
+#[test]
+fn test_packet_receipt_not_found() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(2));
+    let err = ibc.get_packet_receipt(&key).unwrap_err();
+    assert!(matches!(
+        err.detail(),
+        ChannelErrorDetail::PacketReceiptNotFound(e) if e.sequence == Sequence::from(2)
+    ));
+
+    ibc.store_packet_receipt(key.clone(), Receipt::Ok).unwrap();
+    assert!(matches!(ibc.get_packet_receipt(&key), Ok(Receipt::Ok)));
+}
+
+#[test]
+fn test_packet_acknowledgement_round_trip() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(4));
+    let err = ibc.get_packet_acknowledgement(&key).unwrap_err();
+    assert!(matches!(
+        err.detail(),
+        ChannelErrorDetail::PacketAcknowledgementNotFound(e) if e.sequence == Sequence::from(4)
+    ));
+
+    let ack = ChannelReader::hash(&ibc, br#"{"result":"AQ=="}"#.to_vec());
+    ibc.store_packet_acknowledgement(key.clone(), AcknowledgementCommitment::from(ack.clone()))
+        .unwrap();
+    assert_eq!(
+        ibc.get_packet_acknowledgement(&key).unwrap().into_vec(),
+        ack
+    );
+}
+
+#[test]
+fn test_connection_channels_index() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let conn_id = ConnectionId::new(0);
+    assert!(ibc.connection_channels(&conn_id).unwrap().is_empty());
+
+    let port_channel_id = (PortId::transfer(), ChannelId::new(0));
+    ibc.store_connection_channels(conn_id.clone(), &port_channel_id)
+        .unwrap();
+    ibc.store_connection_channels(conn_id.clone(), &port_channel_id)
+        .unwrap();
+    ibc.store_connection_channels(conn_id.clone(), &(PortId::transfer(), ChannelId::new(1)))
+        .unwrap();
+    assert_eq!(ibc.connection_channels(&conn_id).unwrap(), vec![
+        port_channel_id,
+        (PortId::transfer(), ChannelId::new(1)),
+    ]);
+    assert!(ibc
+        .connection_channels(&ConnectionId::new(1))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_channel_counter() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    assert_eq!(ChannelReader::channel_counter(&ibc).unwrap(), 0);
+
+    ibc.increase_channel_counter();
+    ibc.increase_channel_counter();
+    assert_eq!(ChannelReader::channel_counter(&ibc).unwrap(), 2);
+}
+
+#[test]
+fn test_client_update_time_and_height() {
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let height = Height::new(0, 5).unwrap();
+    assert!(matches!(
+        ibc.client_update_time(&client_id, height)
+            .unwrap_err()
+            .detail(),
+        ChannelErrorDetail::ProcessedTimeNotFound(_)
+    ));
+    assert!(matches!(
+        ibc.client_update_height(&client_id, height)
+            .unwrap_err()
+            .detail(),
+        ChannelErrorDetail::ProcessedHeightNotFound(_)
+    ));
+
+    let timestamp = Timestamp::from_nanoseconds(1_660_000_000_000_000_000).unwrap();
+    let host_height = Height::new(0, 12).unwrap();
+    ibc.store_update_time(client_id.clone(), height, timestamp)
+        .unwrap();
+    ibc.store_update_height(client_id.clone(), height, host_height)
+        .unwrap();
+    assert_eq!(
+        ChannelReader::client_update_time(&ibc, &client_id, height).unwrap(),
+        timestamp
+    );
+    assert_eq!(
+        ChannelReader::client_update_height(&ibc, &client_id, height).unwrap(),
+        host_height
+    );
+}
@@ -0,0 +1,300 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use ibc::core::ics04_channel::channel::{Counterparty, Order};
+use ibc::core::ics04_channel::commitment::AcknowledgementCommitment;
+use ibc::core::ics04_channel::context::{ChannelKeeper, ChannelReader};
+use ibc::core::ics04_channel::error::Error as ChannelError;
+use ibc::core::ics04_channel::msgs::acknowledgement::Acknowledgement as GenericAcknowledgement;
+use ibc::core::ics04_channel::packet::{Packet, Receipt, Sequence};
+use ibc::core::ics04_channel::Version;
+use ibc::core::ics05_port::context::PortReader;
+use ibc::core::ics24_host::identifier::{ChannelId, ConnectionId, PortId};
+use ibc::core::ics26_routing::context::{
+    Acknowledgement, Ics26Context, Module, ModuleId, ModuleOutputBuilder, OnRecvPacketAck, Router,
+};
+use ibc::events::IbcEvent;
+use ibc::signer::Signer;
+
+use protocol::traits::{Context, IbcAdapter};
+
+use crate::tests::{setup_open_channel, MockIbcAdapter};
+use crate::{IbcImpl, IbcRouter};
+
+/// The packet callbacks received by a `MockModule`, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockCallback {
+    RecvPacket(Packet),
+    AcknowledgementPacket(Packet, Vec<u8>),
+    TimeoutPacket(Packet),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockAck(pub Vec<u8>);
+
+impl AsRef<[u8]> for MockAck {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Acknowledgement for MockAck {}
+
+/// A `Module` which records the packet callbacks it receives and answers
+/// every received packet with a configurable ack.
+pub struct MockModule {
+    ack:       Option<Vec<u8>>,
+    deferred:  bool,
+    callbacks: Arc<Mutex<Vec<MockCallback>>>,
+}
+
+impl MockModule {
+    /// Answer the received packets with a successful `ack`.
+    pub fn with_ack(ack: Vec<u8>) -> Self {
+        MockModule {
+            ack:       Some(ack),
+            deferred:  false,
+            callbacks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Answer no ack on receipt, the ack is written later.
+    pub fn deferred() -> Self {
+        MockModule {
+            ack:       None,
+            deferred:  true,
+            callbacks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Fail every received packet.
+    pub fn failing() -> Self {
+        MockModule {
+            ack:       None,
+            deferred:  false,
+            callbacks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// The shared record of the callbacks, which is still readable after the
+    /// module is moved into a router.
+    pub fn callbacks(&self) -> Arc<Mutex<Vec<MockCallback>>> {
+        Arc::clone(&self.callbacks)
+    }
+
+    fn record(&self, callback: MockCallback) {
+        self.callbacks.lock().unwrap().push(callback);
+    }
+}
+
+impl Module for MockModule {
+    fn on_chan_open_try(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        _order: Order,
+        _connection_hops: &[ConnectionId],
+        _port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty: &Counterparty,
+        _version: &Version,
+        counterparty_version: &Version,
+    ) -> Result<Version, ChannelError> {
+        Ok(counterparty_version.clone())
+    }
+
+    fn on_recv_packet(
+        &self,
+        _output: &mut ModuleOutputBuilder,
+        packet: &Packet,
+        _relayer: &Signer,
+    ) -> OnRecvPacketAck {
+        self.record(MockCallback::RecvPacket(packet.clone()));
+        if self.deferred {
+            return OnRecvPacketAck::Nil(Box::new(|_| Ok(())));
+        }
+
+        match &self.ack {
+            Some(ack) => {
+                OnRecvPacketAck::Successful(Box::new(MockAck(ack.clone())), Box::new(|_| Ok(())))
+            }
+            None => OnRecvPacketAck::Failed(Box::new(MockAck(b"mock error".to_vec()))),
+        }
+    }
+
+    fn on_acknowledgement_packet(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        packet: &Packet,
+        acknowledgement: &GenericAcknowledgement,
+        _relayer: &Signer,
+    ) -> Result<(), ChannelError> {
+        self.record(MockCallback::AcknowledgementPacket(
+            packet.clone(),
+            acknowledgement.as_ref().to_vec(),
+        ));
+        Ok(())
+    }
+
+    fn on_timeout_packet(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        packet: &Packet,
+        _relayer: &Signer,
+    ) -> Result<(), ChannelError> {
+        self.record(MockCallback::TimeoutPacket(packet.clone()));
+        Ok(())
+    }
+}
+
+pub fn mock_module_id() -> ModuleId {
+    ModuleId::new(Cow::Borrowed("mock")).unwrap()
+}
+
+/// Build an `IbcRouter` with `module` registered as `mock_module_id()`.
+pub fn mock_router(module: MockModule) -> IbcRouter {
+    let mut router = IbcRouter::default();
+    router.add_route(mock_module_id(), module).unwrap();
+    router
+}
+
+/// Build an `IbcImpl` dispatching the callbacks of `port_id` to `module`.
+pub fn mock_ibc_impl_with_module(
+    adapter: MockIbcAdapter,
+    port_id: PortId,
+    module: MockModule,
+) -> IbcImpl<MockIbcAdapter, IbcRouter> {
+    IbcImpl::new(Arc::new(adapter), mock_router(module))
+        .unwrap()
+        .with_ports([(port_id, mock_module_id())])
+        .unwrap()
+}
+
+#[test]
+fn test_router_dispatch() {
+    let module = MockModule::with_ack(b"mock ack".to_vec());
+    let callbacks = module.callbacks();
+    let mut ibc = mock_ibc_impl_with_module(MockIbcAdapter::default(), PortId::transfer(), module);
+
+    let packet = Packet {
+        sequence: Sequence::from(1),
+        destination_port: PortId::transfer(),
+        destination_channel: ChannelId::new(0),
+        data: b"mock packet".to_vec(),
+        ..Default::default()
+    };
+    let relayer = Signer::new("relayer");
+    let mut output = ModuleOutputBuilder::new();
+
+    assert!(ibc
+        .lookup_module_by_port(&PortId::from_str("unknown").unwrap())
+        .is_err());
+    let module_id = ibc.lookup_module_by_port(&PortId::transfer()).unwrap();
+    assert!(ibc.router().has_route(&module_id));
+    let module = ibc.router_mut().get_route_mut(&module_id).unwrap();
+
+    match module.on_recv_packet(&mut output, &packet, &relayer) {
+        OnRecvPacketAck::Successful(ack, _) => assert_eq!((*ack).as_ref(), b"mock ack"),
+        _ => panic!("unexpected ack"),
+    }
+    module
+        .on_acknowledgement_packet(
+            &mut output,
+            &packet,
+            &GenericAcknowledgement::from(b"counterparty ack".to_vec()),
+            &relayer,
+        )
+        .unwrap();
+    module
+        .on_timeout_packet(&mut output, &packet, &relayer)
+        .unwrap();
+
+    assert_eq!(*callbacks.lock().unwrap(), vec![
+        MockCallback::RecvPacket(packet.clone()),
+        MockCallback::AcknowledgementPacket(packet.clone(), b"counterparty ack".to_vec()),
+        MockCallback::TimeoutPacket(packet.clone()),
+    ]);
+
+    let mut router = mock_router(MockModule::failing());
+    assert!(router
+        .add_route(mock_module_id(), MockModule::failing())
+        .is_err());
+    let module = router.get_route_mut(&mock_module_id()).unwrap();
+    assert!(matches!(
+        module.on_recv_packet(&mut output, &packet, &relayer),
+        OnRecvPacketAck::Failed(_)
+    ));
+}
+
+#[test]
+fn test_write_deferred_acknowledgement() {
+    let adapter = MockIbcAdapter::default();
+    setup_open_channel(&adapter, 1);
+    let mut ibc = mock_ibc_impl_with_module(adapter, PortId::transfer(), MockModule::deferred());
+
+    let packet = Packet {
+        sequence: Sequence::from(1),
+        destination_port: PortId::transfer(),
+        destination_channel: ChannelId::new(0),
+        data: b"mock packet".to_vec(),
+        ..Default::default()
+    };
+    let key = (PortId::transfer(), ChannelId::new(0), Sequence::from(1));
+    let ack = b"deferred ack".to_vec();
+
+    let module_id = ibc.lookup_module_by_port(&PortId::transfer()).unwrap();
+    let module = ibc.router_mut().get_route_mut(&module_id).unwrap();
+    let mut output = ModuleOutputBuilder::new();
+    assert!(matches!(
+        module.on_recv_packet(&mut output, &packet, &Signer::new("relayer")),
+        OnRecvPacketAck::Nil(_)
+    ));
+
+    // The ack of a packet which is not received can not be written.
+    assert!(ibc
+        .write_acknowledgement(packet.clone(), ack.clone())
+        .is_err());
+
+    ibc.store_packet_receipt(key.clone(), Receipt::Ok).unwrap();
+    assert!(ibc.get_packet_acknowledgement(&key).is_err());
+
+    match ibc
+        .write_acknowledgement(packet.clone(), ack.clone())
+        .unwrap()
+    {
+        IbcEvent::WriteAcknowledgement(event) => {
+            assert_eq!(event.packet, packet);
+            assert_eq!(event.ack, ack);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+    assert_eq!(
+        ibc.get_packet_acknowledgement(&key).unwrap(),
+        AcknowledgementCommitment::from(ibc.hash(ack.clone()))
+    );
+    // The ack itself is stored next to its commitment.
+    assert_eq!(
+        ibc.adapter
+            .get_acknowledgement(Context::new(), &key)
+            .unwrap(),
+        Some(ack.clone())
+    );
+
+    // The ack is written only once.
+    assert!(ibc.write_acknowledgement(packet, ack).is_err());
+}
+
+#[test]
+fn test_router_unknown_module() {
+    let mut router = IbcRouter::default();
+    assert!(!router.has_route(&mock_module_id()));
+    assert!(router.get_route_mut(&mock_module_id()).is_none());
+
+    router
+        .add_route(mock_module_id(), MockModule::failing())
+        .unwrap();
+    assert!(router.has_route(&mock_module_id()));
+    let unknown = ModuleId::new(Cow::Borrowed("unknown")).unwrap();
+    assert!(!router.has_route(&unknown));
+    assert!(router.get_route_mut(&unknown).is_none());
+}
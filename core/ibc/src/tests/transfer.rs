@@ -0,0 +1,440 @@
+use std::sync::{Arc, Mutex};
+
+use cita_trie::MemoryDB;
+use ibc::core::ics04_channel::channel::{Counterparty, Order};
+use ibc::core::ics04_channel::msgs::acknowledgement::Acknowledgement as GenericAcknowledgement;
+use ibc::core::ics04_channel::packet::{Packet, Sequence};
+use ibc::core::ics04_channel::Version;
+use ibc::core::ics05_port::context::PortReader;
+use ibc::core::ics24_host::identifier::{ChannelId, PortId};
+use ibc::core::ics26_routing::context::{
+    Ics26Context, Module, ModuleOutputBuilder, OnRecvPacketAck, Router,
+};
+use ibc::signer::Signer;
+
+use core_executor::AxonExecutorAdapter;
+use core_storage::{adapter::memory::MemoryAdapter, ImplStorage};
+use protocol::traits::ExecutorAdapter;
+use protocol::types::{H160, U256};
+
+use crate::tests::{mock_ibc_impl, MockIbcAdapter};
+use crate::transfer::{
+    escrow_address, is_receiver_chain_source, is_sender_chain_source, recv_transfer,
+    refund_transfer, resolve_recv_denom, send_transfer, AxonTransferExecutor, RecvDenom,
+    TransferAcknowledgement, TransferExecutor, TransferModule, TransferPacketData, NATIVE_DENOM,
+    TRANSFER_MODULE_ID,
+};
+
+type Backend = AxonExecutorAdapter<ImplStorage<MemoryAdapter>, MemoryDB>;
+
+fn mock_backend() -> Backend {
+    AxonExecutorAdapter::new(
+        Arc::new(MemoryDB::new(false)),
+        Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new()), 10)),
+        Default::default(),
+    )
+    .unwrap()
+}
+
+fn balance(backend: &Backend, address: H160) -> U256 {
+    backend.get_account(&address).balance
+}
+
+#[test]
+fn test_native_token_round_trip() {
+    let mut backend = mock_backend();
+    let alice = H160::random();
+    let port_id = PortId::transfer();
+    let channel_id = ChannelId::new(0);
+    let escrow = escrow_address(&port_id, &channel_id);
+
+    let mut account = backend.get_account(&alice);
+    account.balance = U256::from(1000u64);
+    backend.save_account(&alice, &account);
+
+    // Alice sends 300 axon to the counterparty, which are escrowed.
+    send_transfer(
+        &mut backend,
+        alice,
+        &port_id,
+        &channel_id,
+        NATIVE_DENOM,
+        300u64.into(),
+    )
+    .unwrap();
+    assert_eq!(balance(&backend, alice), U256::from(700u64));
+    assert_eq!(balance(&backend, escrow), U256::from(300u64));
+
+    // Sending more than the balance fails without moving anything.
+    assert!(send_transfer(
+        &mut backend,
+        alice,
+        &port_id,
+        &channel_id,
+        NATIVE_DENOM,
+        800u64.into()
+    )
+    .is_err());
+    assert_eq!(balance(&backend, alice), U256::from(700u64));
+
+    // The counterparty sends 100 of the vouchers back to Bob on the return
+    // trip, which are unescrowed.
+    let bob = H160::random();
+    let counterparty_port = PortId::transfer();
+    let counterparty_channel = ChannelId::new(7);
+    let voucher = format!(
+        "{}/{}/{}",
+        counterparty_port, counterparty_channel, NATIVE_DENOM
+    );
+    recv_transfer(
+        &mut backend,
+        bob,
+        (&counterparty_port, &counterparty_channel),
+        (&port_id, &channel_id),
+        &voucher,
+        100u64.into(),
+    )
+    .unwrap();
+    assert_eq!(balance(&backend, bob), U256::from(100u64));
+    assert_eq!(balance(&backend, escrow), U256::from(200u64));
+
+    // A token which is not a returning voucher can not be received.
+    assert!(recv_transfer(
+        &mut backend,
+        bob,
+        (&counterparty_port, &counterparty_channel),
+        (&port_id, &channel_id),
+        "uatom",
+        100u64.into(),
+    )
+    .is_err());
+}
+
+#[test]
+fn test_refund_transfer() {
+    let mut backend = mock_backend();
+    let alice = H160::random();
+    let port_id = PortId::transfer();
+    let channel_id = ChannelId::new(0);
+
+    let mut account = backend.get_account(&alice);
+    account.balance = U256::from(1000u64);
+    backend.save_account(&alice, &account);
+
+    send_transfer(
+        &mut backend,
+        alice,
+        &port_id,
+        &channel_id,
+        NATIVE_DENOM,
+        300u64.into(),
+    )
+    .unwrap();
+    refund_transfer(
+        &mut backend,
+        alice,
+        &port_id,
+        &channel_id,
+        NATIVE_DENOM,
+        300u64.into(),
+    )
+    .unwrap();
+    assert_eq!(balance(&backend, alice), U256::from(1000u64));
+    assert_eq!(
+        balance(&backend, escrow_address(&port_id, &channel_id)),
+        U256::zero()
+    );
+}
+
+#[test]
+fn test_denom_source_resolution() {
+    let transfer = PortId::transfer();
+    let (channel_a, channel_b, channel_c) =
+        (ChannelId::new(0), ChannelId::new(1), ChannelId::new(2));
+
+    // Home to away: the native token leaves Axon on channel A and a voucher
+    // prefixed with the counterparty end of channel A is minted there.
+    assert!(is_sender_chain_source(&transfer, &channel_a, NATIVE_DENOM));
+    assert_eq!(
+        resolve_recv_denom(
+            (&transfer, &channel_a),
+            (&transfer, &channel_b),
+            NATIVE_DENOM
+        ),
+        RecvDenom::Mint(format!("transfer/channel-1/{}", NATIVE_DENOM))
+    );
+
+    // Away to home: the voucher is sent back through the same channel, so
+    // the prefix is stripped and the native token is unescrowed.
+    let voucher = format!("transfer/channel-1/{}", NATIVE_DENOM);
+    assert!(!is_sender_chain_source(&transfer, &channel_b, &voucher));
+    assert!(is_receiver_chain_source(&transfer, &channel_b, &voucher));
+    assert_eq!(
+        resolve_recv_denom((&transfer, &channel_b), (&transfer, &channel_a), &voucher),
+        RecvDenom::Unescrow(NATIVE_DENOM.to_string())
+    );
+
+    // Two hops: the voucher is relayed further on channel C, which is not the
+    // channel it came from, so another prefix is added on top of the trace.
+    assert!(is_sender_chain_source(&transfer, &channel_c, &voucher));
+    let two_hops = format!("transfer/channel-2/transfer/channel-1/{}", NATIVE_DENOM);
+    assert_eq!(
+        resolve_recv_denom((&transfer, &channel_c), (&transfer, &channel_c), &voucher),
+        RecvDenom::Mint(two_hops.clone())
+    );
+
+    // On the way back only the last hop is stripped.
+    assert_eq!(
+        resolve_recv_denom((&transfer, &channel_c), (&transfer, &channel_c), &two_hops),
+        RecvDenom::Unescrow(voucher)
+    );
+    assert!(!is_receiver_chain_source(&transfer, &channel_b, &two_hops));
+}
+
+/// A `TransferExecutor` which records the transfers it applies.
+#[derive(Default)]
+struct MockTransferExecutor {
+    recvs:   Mutex<Vec<(H160, String, U256)>>,
+    refunds: Mutex<Vec<(H160, String, U256)>>,
+}
+
+impl TransferExecutor for MockTransferExecutor {
+    fn send_transfer(
+        &self,
+        _sender: H160,
+        _source_port: &PortId,
+        _source_channel: &ChannelId,
+        _denom: &str,
+        _amount: U256,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn recv_transfer(
+        &self,
+        receiver: H160,
+        _source: (&PortId, &ChannelId),
+        _dest: (&PortId, &ChannelId),
+        denom: &str,
+        amount: U256,
+    ) -> Result<(), String> {
+        if denom != NATIVE_DENOM {
+            return Err(format!("unsupported denom {}", denom));
+        }
+        self.recvs
+            .lock()
+            .unwrap()
+            .push((receiver, denom.to_string(), amount));
+        Ok(())
+    }
+
+    fn refund_transfer(
+        &self,
+        sender: H160,
+        _source_port: &PortId,
+        _source_channel: &ChannelId,
+        denom: &str,
+        amount: U256,
+    ) -> Result<(), String> {
+        self.refunds
+            .lock()
+            .unwrap()
+            .push((sender, denom.to_string(), amount));
+        Ok(())
+    }
+}
+
+fn transfer_packet(data: &TransferPacketData) -> Packet {
+    Packet {
+        sequence: Sequence::from(1),
+        source_port: PortId::transfer(),
+        source_channel: ChannelId::new(0),
+        destination_port: PortId::transfer(),
+        destination_channel: ChannelId::new(1),
+        data: data.encode(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_transfer_module_routing() {
+    let executor = Arc::new(MockTransferExecutor::default());
+    let mut ibc = mock_ibc_impl(MockIbcAdapter::default());
+    ibc.setup_transfer_module(Arc::clone(&executor)).unwrap();
+    assert!(ibc.setup_transfer_module(Arc::clone(&executor)).is_err());
+
+    let module_id = ibc.lookup_module_by_port(&PortId::transfer()).unwrap();
+    assert_eq!(module_id.to_string(), TRANSFER_MODULE_ID);
+    assert!(ibc.router().has_route(&module_id));
+
+    let alice = H160::random();
+    let mut data = TransferPacketData {
+        denom:    format!("transfer/channel-0/{}", NATIVE_DENOM),
+        amount:   U256::from(300u64),
+        sender:   "cosmos1sender".to_string(),
+        receiver: format!("{:?}", alice),
+    };
+    assert_eq!(TransferPacketData::decode(&data.encode()).unwrap(), data);
+
+    let relayer = Signer::new("relayer");
+    let mut output = ModuleOutputBuilder::new();
+    let module = ibc.router_mut().get_route_mut(&module_id).unwrap();
+    match module.on_recv_packet(&mut output, &transfer_packet(&data), &relayer) {
+        OnRecvPacketAck::Successful(ack, _) => {
+            assert_eq!((*ack).as_ref(), TransferAcknowledgement::success().as_ref())
+        }
+        _ => panic!("unexpected ack"),
+    }
+    assert_eq!(*executor.recvs.lock().unwrap(), vec![(
+        alice,
+        data.denom.clone(),
+        U256::from(300u64)
+    )]);
+
+    data.receiver = "not an address".to_string();
+    assert!(matches!(
+        module.on_recv_packet(&mut output, &transfer_packet(&data), &relayer),
+        OnRecvPacketAck::Failed(_)
+    ));
+
+    // The sender of a sent packet is refunded on an error ack and a timeout,
+    // but not on a successful ack.
+    let sent = TransferPacketData {
+        denom:    NATIVE_DENOM.to_string(),
+        amount:   U256::from(50u64),
+        sender:   format!("{:?}", alice),
+        receiver: "cosmos1receiver".to_string(),
+    };
+    let packet = transfer_packet(&sent);
+    module
+        .on_acknowledgement_packet(
+            &mut output,
+            &packet,
+            &GenericAcknowledgement::from(TransferAcknowledgement::success().as_ref().to_vec()),
+            &relayer,
+        )
+        .unwrap();
+    assert!(executor.refunds.lock().unwrap().is_empty());
+    module
+        .on_acknowledgement_packet(
+            &mut output,
+            &packet,
+            &GenericAcknowledgement::from(
+                TransferAcknowledgement::error("failed").as_ref().to_vec(),
+            ),
+            &relayer,
+        )
+        .unwrap();
+    module
+        .on_timeout_packet(&mut output, &packet, &relayer)
+        .unwrap();
+    assert_eq!(executor.refunds.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_axon_transfer_executor() {
+    let alice = H160::random();
+    let port_id = PortId::transfer();
+    let channel_id = ChannelId::new(0);
+    let mut backend = mock_backend();
+    let mut account = backend.get_account(&alice);
+    account.balance = U256::from(1000u64);
+    backend.save_account(&alice, &account);
+
+    let executor = Arc::new(AxonTransferExecutor::new(backend));
+    executor
+        .send_transfer(alice, &port_id, &channel_id, NATIVE_DENOM, 300u64.into())
+        .unwrap();
+
+    // A voucher returning on the channel is unescrowed through the module.
+    let mut module = TransferModule::new(Arc::clone(&executor));
+    let bob = H160::random();
+    let packet = Packet {
+        sequence: Sequence::from(1),
+        source_port: PortId::transfer(),
+        source_channel: ChannelId::new(7),
+        destination_port: port_id.clone(),
+        destination_channel: channel_id.clone(),
+        data: TransferPacketData {
+            denom:    format!("transfer/channel-7/{}", NATIVE_DENOM),
+            amount:   U256::from(100u64),
+            sender:   "cosmos1sender".to_string(),
+            receiver: format!("{:?}", bob),
+        }
+        .encode(),
+        ..Default::default()
+    };
+    let relayer = Signer::new("relayer");
+    let mut output = ModuleOutputBuilder::new();
+    assert!(matches!(
+        module.on_recv_packet(&mut output, &packet, &relayer),
+        OnRecvPacketAck::Successful(..)
+    ));
+
+    // More than the escrowed amount can't be unescrowed, which is an error ack.
+    let mut packet = packet;
+    packet.data = TransferPacketData {
+        denom:    format!("transfer/channel-7/{}", NATIVE_DENOM),
+        amount:   U256::from(1000u64),
+        sender:   "cosmos1sender".to_string(),
+        receiver: format!("{:?}", bob),
+    }
+    .encode();
+    assert!(matches!(
+        module.on_recv_packet(&mut output, &packet, &relayer),
+        OnRecvPacketAck::Failed(_)
+    ));
+
+    // A timed out packet of Alice is refunded from the escrow.
+    let sent = transfer_packet(&TransferPacketData {
+        denom:    NATIVE_DENOM.to_string(),
+        amount:   U256::from(200u64),
+        sender:   format!("{:?}", alice),
+        receiver: "cosmos1receiver".to_string(),
+    });
+    module
+        .on_timeout_packet(&mut output, &sent, &relayer)
+        .unwrap();
+    drop(module);
+
+    let backend = Arc::try_unwrap(executor).ok().unwrap().into_backend();
+    assert_eq!(balance(&backend, alice), U256::from(900u64));
+    assert_eq!(balance(&backend, bob), U256::from(100u64));
+    assert_eq!(
+        balance(&backend, escrow_address(&port_id, &channel_id)),
+        U256::zero()
+    );
+}
+
+#[test]
+fn test_transfer_error_acknowledgement() {
+    // The reason is escaped, so the ack stays valid JSON.
+    let ack = TransferAcknowledgement::error(r#"invalid "denom""#);
+    let value: serde_json::Value = serde_json::from_slice(ack.as_ref()).unwrap();
+    assert_eq!(value["error"], r#"invalid "denom""#);
+}
+
+#[test]
+fn test_transfer_chan_open_init() {
+    let mut module = TransferModule::new(Arc::new(MockTransferExecutor::default()));
+    let mut output = ModuleOutputBuilder::new();
+    let init = |module: &mut TransferModule<MockTransferExecutor>,
+                output: &mut ModuleOutputBuilder,
+                order: Order,
+                version: &str| {
+        module.on_chan_open_init(
+            output,
+            order,
+            &[],
+            &PortId::transfer(),
+            &ChannelId::new(0),
+            &Counterparty::new(PortId::transfer(), None),
+            &Version::new(version.to_string()),
+        )
+    };
+
+    assert!(init(&mut module, &mut output, Order::Unordered, "ics20-1").is_ok());
+    assert!(init(&mut module, &mut output, Order::Unordered, "").is_ok());
+    assert!(init(&mut module, &mut output, Order::Ordered, "ics20-1").is_err());
+    assert!(init(&mut module, &mut output, Order::Unordered, "ics20-2").is_err());
+}
@@ -1 +1,542 @@
+//! ICS-20 token transfer of the native Axon token. Axon accounts live in the
+//! EVM state, so escrowing, minting and burning move the balances of the
+//! executor accounts instead of the balances of a bank module.
 
+use std::borrow::Cow;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use ibc::core::ics04_channel::channel::{Counterparty, Order};
+use ibc::core::ics04_channel::error::Error as ChannelError;
+use ibc::core::ics04_channel::msgs::acknowledgement::Acknowledgement as GenericAcknowledgement;
+use ibc::core::ics04_channel::packet::Packet;
+use ibc::core::ics04_channel::Version;
+use ibc::core::ics24_host::identifier::{ChannelId, ConnectionId, PortId};
+use ibc::core::ics26_routing::context::{
+    Acknowledgement, Module, ModuleId, ModuleOutputBuilder, OnRecvPacketAck,
+};
+use ibc::signer::Signer;
+use parking_lot::Mutex;
+
+use protocol::traits::ExecutorAdapter;
+use protocol::types::{Hasher, H160, U256};
+use protocol::{Display, ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+use crate::grpc::{classify_acknowledgement, AckStatus};
+
+pub const NATIVE_DENOM: &str = "axon";
+
+/// The id the transfer module is routed by, which is the same as its port.
+pub const TRANSFER_MODULE_ID: &str = "transfer";
+pub const TRANSFER_VERSION: &str = "ics20-1";
+
+/// Each channel has its own escrow account, which is derived from the port
+/// and channel id and has no known private key.
+pub fn escrow_address(port_id: &PortId, channel_id: &ChannelId) -> H160 {
+    Hasher::digest(format!("ibc/escrow/{}/{}", port_id, channel_id)).into()
+}
+
+/// The prefix which a chain adds to the denoms received on a channel, and
+/// strips again when they are sent back through it.
+pub fn denom_prefix(port_id: &PortId, channel_id: &ChannelId) -> String {
+    format!("{}/{}/", port_id, channel_id)
+}
+
+/// Whether the sending chain is the source of `denom`, that is the denom is
+/// not a voucher of a token received on the source channel. The source zone
+/// escrows the token, otherwise the voucher is burned.
+pub fn is_sender_chain_source(
+    source_port: &PortId,
+    source_channel: &ChannelId,
+    denom: &str,
+) -> bool {
+    !is_receiver_chain_source(source_port, source_channel, denom)
+}
+
+/// Whether the receiving chain is the source of `denom`, that is the denom is
+/// prefixed with the source port and channel of the packet, so the token is
+/// returning home.
+pub fn is_receiver_chain_source(
+    source_port: &PortId,
+    source_channel: &ChannelId,
+    denom: &str,
+) -> bool {
+    denom.starts_with(&denom_prefix(source_port, source_channel))
+}
+
+/// The way a received denom is credited on the receiving chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecvDenom {
+    /// The token returns home, the denom with the source prefix stripped is
+    /// unescrowed.
+    Unescrow(String),
+    /// The token goes away from its source, a voucher of the denom with the
+    /// destination prefix added is minted.
+    Mint(String),
+}
+
+/// Resolve the denom of a received packet. Only the first hop is stripped or
+/// added, so a multi-hop voucher keeps the rest of its trace.
+pub fn resolve_recv_denom(
+    (source_port, source_channel): (&PortId, &ChannelId),
+    (dest_port, dest_channel): (&PortId, &ChannelId),
+    denom: &str,
+) -> RecvDenom {
+    let source_prefix = denom_prefix(source_port, source_channel);
+    match denom.strip_prefix(&source_prefix) {
+        Some(unprefixed) => RecvDenom::Unescrow(unprefixed.to_string()),
+        None => RecvDenom::Mint(format!(
+            "{}{}",
+            denom_prefix(dest_port, dest_channel),
+            denom
+        )),
+    }
+}
+
+/// Handle the sending side of a transfer. The native token is escrowed, since
+/// Axon is the source zone of it. Burning vouchers is not supported yet, as
+/// Axon does not keep balances of other denoms.
+pub fn send_transfer<B: ExecutorAdapter>(
+    backend: &mut B,
+    sender: H160,
+    source_port: &PortId,
+    source_channel: &ChannelId,
+    denom: &str,
+    amount: U256,
+) -> ProtocolResult<()> {
+    if !is_sender_chain_source(source_port, source_channel, denom) || denom != NATIVE_DENOM {
+        return Err(TransferError::UnsupportedDenom(denom.to_string()).into());
+    }
+
+    escrow(backend, sender, source_port, source_channel, amount)
+}
+
+/// Handle the receiving side of a transfer. A denom prefixed with the source
+/// port and channel of the packet is a voucher of the native token returning
+/// to Axon, so it is unescrowed. Minting vouchers is not supported yet.
+pub fn recv_transfer<B: ExecutorAdapter>(
+    backend: &mut B,
+    receiver: H160,
+    source: (&PortId, &ChannelId),
+    dest: (&PortId, &ChannelId),
+    denom: &str,
+    amount: U256,
+) -> ProtocolResult<()> {
+    match resolve_recv_denom(source, dest, denom) {
+        RecvDenom::Unescrow(unprefixed) if unprefixed == NATIVE_DENOM => {
+            unescrow(backend, receiver, dest.0, dest.1, amount)
+        }
+        _ => Err(TransferError::UnsupportedDenom(denom.to_string()).into()),
+    }
+}
+
+/// Refund the sender of a packet which timed out or was acknowledged with an
+/// error, reverting `send_transfer`. The escrowed native token is unescrowed.
+pub fn refund_transfer<B: ExecutorAdapter>(
+    backend: &mut B,
+    sender: H160,
+    source_port: &PortId,
+    source_channel: &ChannelId,
+    denom: &str,
+    amount: U256,
+) -> ProtocolResult<()> {
+    if !is_sender_chain_source(source_port, source_channel, denom) || denom != NATIVE_DENOM {
+        return Err(TransferError::UnsupportedDenom(denom.to_string()).into());
+    }
+
+    unescrow(backend, sender, source_port, source_channel, amount)
+}
+
+/// Move `amount` from `from` to the escrow account of the channel.
+pub fn escrow<B: ExecutorAdapter>(
+    backend: &mut B,
+    from: H160,
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    amount: U256,
+) -> ProtocolResult<()> {
+    burn(backend, from, amount)?;
+    mint(backend, escrow_address(port_id, channel_id), amount)
+}
+
+/// Move `amount` from the escrow account of the channel to `to`.
+pub fn unescrow<B: ExecutorAdapter>(
+    backend: &mut B,
+    to: H160,
+    port_id: &PortId,
+    channel_id: &ChannelId,
+    amount: U256,
+) -> ProtocolResult<()> {
+    burn(backend, escrow_address(port_id, channel_id), amount)?;
+    mint(backend, to, amount)
+}
+
+/// Credit `amount` to the balance of `to`.
+pub fn mint<B: ExecutorAdapter>(backend: &mut B, to: H160, amount: U256) -> ProtocolResult<()> {
+    let mut account = backend.get_account(&to);
+    account.balance = account
+        .balance
+        .checked_add(amount)
+        .ok_or(TransferError::BalanceOverflow(to))?;
+    backend.save_account(&to, &account);
+    Ok(())
+}
+
+/// Debit `amount` from the balance of `from`.
+pub fn burn<B: ExecutorAdapter>(backend: &mut B, from: H160, amount: U256) -> ProtocolResult<()> {
+    let mut account = backend.get_account(&from);
+    account.balance = account
+        .balance
+        .checked_sub(amount)
+        .ok_or(TransferError::InsufficientBalance(from))?;
+    backend.save_account(&from, &account);
+    Ok(())
+}
+
+/// Applies the balance changes of the transfer packets, which is implemented
+/// by the node with `send_transfer`, `recv_transfer` and `refund_transfer` on
+/// top of the EVM executor, see `AxonTransferExecutor`.
+pub trait TransferExecutor: Send + Sync {
+    fn send_transfer(
+        &self,
+        sender: H160,
+        source_port: &PortId,
+        source_channel: &ChannelId,
+        denom: &str,
+        amount: U256,
+    ) -> Result<(), String>;
+
+    fn recv_transfer(
+        &self,
+        receiver: H160,
+        source: (&PortId, &ChannelId),
+        dest: (&PortId, &ChannelId),
+        denom: &str,
+        amount: U256,
+    ) -> Result<(), String>;
+
+    fn refund_transfer(
+        &self,
+        sender: H160,
+        source_port: &PortId,
+        source_channel: &ChannelId,
+        denom: &str,
+        amount: U256,
+    ) -> Result<(), String>;
+}
+
+/// The `TransferExecutor` of the node, which moves the balances in the state
+/// of an executor backend.
+pub struct AxonTransferExecutor<B> {
+    backend: Mutex<B>,
+}
+
+impl<B: ExecutorAdapter> AxonTransferExecutor<B> {
+    pub fn new(backend: B) -> Self {
+        AxonTransferExecutor {
+            backend: Mutex::new(backend),
+        }
+    }
+
+    /// Take the backend back, e.g. to commit the state changed by the
+    /// transfers.
+    pub fn into_backend(self) -> B {
+        self.backend.into_inner()
+    }
+}
+
+impl<B: ExecutorAdapter + Send> TransferExecutor for AxonTransferExecutor<B> {
+    fn send_transfer(
+        &self,
+        sender: H160,
+        source_port: &PortId,
+        source_channel: &ChannelId,
+        denom: &str,
+        amount: U256,
+    ) -> Result<(), String> {
+        send_transfer(
+            &mut *self.backend.lock(),
+            sender,
+            source_port,
+            source_channel,
+            denom,
+            amount,
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    fn recv_transfer(
+        &self,
+        receiver: H160,
+        source: (&PortId, &ChannelId),
+        dest: (&PortId, &ChannelId),
+        denom: &str,
+        amount: U256,
+    ) -> Result<(), String> {
+        recv_transfer(
+            &mut *self.backend.lock(),
+            receiver,
+            source,
+            dest,
+            denom,
+            amount,
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    fn refund_transfer(
+        &self,
+        sender: H160,
+        source_port: &PortId,
+        source_channel: &ChannelId,
+        denom: &str,
+        amount: U256,
+    ) -> Result<(), String> {
+        refund_transfer(
+            &mut *self.backend.lock(),
+            sender,
+            source_port,
+            source_channel,
+            denom,
+            amount,
+        )
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// The ICS-20 `FungibleTokenPacketData`, which is carried as JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferPacketData {
+    pub denom:    String,
+    pub amount:   U256,
+    pub sender:   String,
+    pub receiver: String,
+}
+
+impl TransferPacketData {
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::json!({
+            "denom": self.denom,
+            "amount": self.amount.to_string(),
+            "sender": self.sender,
+            "receiver": self.receiver,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    pub fn decode(raw: &[u8]) -> Result<Self, String> {
+        let value: serde_json::Value =
+            serde_json::from_slice(raw).map_err(|e| format!("invalid packet data: {}", e))?;
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(ToString::to_string)
+                .ok_or_else(|| format!("missing packet data field {}", name))
+        };
+
+        let amount = field("amount")?;
+        Ok(TransferPacketData {
+            denom:    field("denom")?,
+            amount:   U256::from_dec_str(&amount)
+                .map_err(|_| format!("invalid amount {}", amount))?,
+            sender:   field("sender")?,
+            receiver: field("receiver")?,
+        })
+    }
+}
+
+/// The acknowledgement of a transfer packet, encoded as `{"result":"AQ=="}`
+/// on success like the Cosmos SDK, and `{"error":"<reason>"}` on failure.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferAcknowledgement(Vec<u8>);
+
+impl TransferAcknowledgement {
+    pub fn success() -> Self {
+        TransferAcknowledgement(br#"{"result":"AQ=="}"#.to_vec())
+    }
+
+    pub fn error(reason: &str) -> Self {
+        TransferAcknowledgement(
+            serde_json::json!({ "error": reason })
+                .to_string()
+                .into_bytes(),
+        )
+    }
+}
+
+impl AsRef<[u8]> for TransferAcknowledgement {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Acknowledgement for TransferAcknowledgement {}
+
+/// The ICS-20 transfer application, routed by `TRANSFER_MODULE_ID` and bound
+/// to the `transfer` port.
+pub struct TransferModule<E> {
+    executor: Arc<E>,
+}
+
+impl<E: TransferExecutor> TransferModule<E> {
+    pub fn new(executor: Arc<E>) -> Self {
+        TransferModule { executor }
+    }
+
+    pub fn module_id() -> ModuleId {
+        ModuleId::new(Cow::Borrowed(TRANSFER_MODULE_ID)).unwrap()
+    }
+
+    /// Refund the sender of a packet sent from Axon.
+    fn refund(&self, packet: &Packet) -> Result<(), ChannelError> {
+        let data = TransferPacketData::decode(&packet.data).map_err(ChannelError::app_module)?;
+        let sender = H160::from_str(&data.sender)
+            .map_err(|_| ChannelError::app_module(format!("invalid sender {}", data.sender)))?;
+
+        self.executor
+            .refund_transfer(
+                sender,
+                &packet.source_port,
+                &packet.source_channel,
+                &data.denom,
+                data.amount,
+            )
+            .map_err(ChannelError::app_module)
+    }
+}
+
+impl<E: TransferExecutor + 'static> Module for TransferModule<E> {
+    fn on_chan_open_init(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        order: Order,
+        _connection_hops: &[ConnectionId],
+        _port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty: &Counterparty,
+        version: &Version,
+    ) -> Result<(), ChannelError> {
+        if order != Order::Unordered {
+            return Err(ChannelError::app_module(
+                "transfer channels must be unordered".to_string(),
+            ));
+        }
+
+        // An empty version is proposed by a relayer which leaves the choice
+        // to the module.
+        let version = version.to_string();
+        if !version.is_empty() && version != TRANSFER_VERSION {
+            return Err(ChannelError::app_module(format!(
+                "unsupported transfer version {}",
+                version
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn on_chan_open_try(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        order: Order,
+        _connection_hops: &[ConnectionId],
+        _port_id: &PortId,
+        _channel_id: &ChannelId,
+        _counterparty: &Counterparty,
+        _version: &Version,
+        counterparty_version: &Version,
+    ) -> Result<Version, ChannelError> {
+        if order != Order::Unordered {
+            return Err(ChannelError::app_module(
+                "transfer channels must be unordered".to_string(),
+            ));
+        }
+
+        if counterparty_version.to_string() != TRANSFER_VERSION {
+            return Err(ChannelError::app_module(format!(
+                "unsupported transfer version {}",
+                counterparty_version
+            )));
+        }
+
+        Ok(Version::new(TRANSFER_VERSION.to_string()))
+    }
+
+    fn on_recv_packet(
+        &self,
+        _output: &mut ModuleOutputBuilder,
+        packet: &Packet,
+        _relayer: &Signer,
+    ) -> OnRecvPacketAck {
+        let failed = |reason: &str| {
+            OnRecvPacketAck::Failed(Box::new(TransferAcknowledgement::error(reason)))
+        };
+
+        let data = match TransferPacketData::decode(&packet.data) {
+            Ok(data) => data,
+            Err(e) => return failed(&e),
+        };
+        let receiver = match H160::from_str(&data.receiver) {
+            Ok(receiver) => receiver,
+            Err(_) => return failed("invalid receiver"),
+        };
+
+        match self.executor.recv_transfer(
+            receiver,
+            (&packet.source_port, &packet.source_channel),
+            (&packet.destination_port, &packet.destination_channel),
+            &data.denom,
+            data.amount,
+        ) {
+            Ok(()) => OnRecvPacketAck::Successful(
+                Box::new(TransferAcknowledgement::success()),
+                Box::new(|_| Ok(())),
+            ),
+            Err(e) => failed(&e),
+        }
+    }
+
+    fn on_acknowledgement_packet(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        packet: &Packet,
+        acknowledgement: &GenericAcknowledgement,
+        _relayer: &Signer,
+    ) -> Result<(), ChannelError> {
+        match classify_acknowledgement(acknowledgement.as_ref()) {
+            Some(AckStatus::Error) => self.refund(packet),
+            _ => Ok(()),
+        }
+    }
+
+    fn on_timeout_packet(
+        &mut self,
+        _output: &mut ModuleOutputBuilder,
+        packet: &Packet,
+        _relayer: &Signer,
+    ) -> Result<(), ChannelError> {
+        self.refund(packet)
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum TransferError {
+    #[display(fmt = "Unsupported denom {}", _0)]
+    UnsupportedDenom(String),
+
+    #[display(fmt = "Insufficient balance of {:?}", _0)]
+    InsufficientBalance(H160),
+
+    #[display(fmt = "Balance of {:?} overflow", _0)]
+    BalanceOverflow(H160),
+}
+
+impl std::error::Error for TransferError {}
+
+impl From<TransferError> for ProtocolError {
+    fn from(err: TransferError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Ibc, Box::new(err))
+    }
+}
@@ -61,7 +61,8 @@ use protocol::traits::{
     CommonStorage, Context, Executor, MemPool, MetadataControl, Network, NodeInfo, Storage,
 };
 use protocol::types::{
-    Account, Address, MerkleRoot, Proposal, RichBlock, Validator, H256, NIL_DATA, RLP_NULL,
+    Account, Address, ExecutorContext, MerkleRoot, Proposal, RichBlock, Validator, H256, NIL_DATA,
+    RLP_NULL,
 };
 use protocol::{
     codec::{hex_decode, ProtocolCodec},
@@ -178,7 +179,8 @@ impl Axon {
             mpt.commit()?,
             trie_db,
             Arc::clone(&storage),
-            proposal.into(),
+            ExecutorContext::from(proposal)
+                .with_gas_schedule(self.config.executor.gas_schedule.clone()),
         )?;
         let resp = executor.exec(&mut backend, &self.genesis.txs);
 
@@ -503,6 +505,7 @@ impl Axon {
             Arc::new(cross_handle),
             Arc::clone(&metadata_controller),
             Arc::clone(&crypto),
+            config.executor.gas_schedule.clone(),
         )?;
 
         let consensus_adapter = Arc::new(consensus_adapter);
@@ -600,6 +603,7 @@ impl Axon {
             Arc::clone(&storage),
             Arc::clone(&trie_db),
             Arc::new(network_handle),
+            config.executor.gas_schedule.clone(),
         ));
         let _handles = run_jsonrpc_server(self.config.clone(), api_adapter).await?;
 
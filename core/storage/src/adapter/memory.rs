@@ -7,7 +7,8 @@ use parking_lot::RwLock;
 
 use protocol::codec::ProtocolCodec;
 use protocol::traits::{
-    IntoIteratorByRef, StorageAdapter, StorageBatchModify, StorageIterator, StorageSchema,
+    IntoIteratorByRef, IterDirection, StorageAdapter, StorageBatchModify, StorageIterator,
+    StorageSchema,
 };
 use protocol::types::Bytes;
 use protocol::{Display, From, ProtocolError, ProtocolErrorKind, ProtocolResult};
@@ -202,6 +203,39 @@ impl StorageAdapter for MemoryAdapter {
             pin_s: PhantomData::<S>,
         }))
     }
+
+    // The keys are copied out and sorted so that the lock is not held by the
+    // iterator, which is fine for the in-memory store of the tests.
+    fn iter_keys<'a, S: StorageSchema + 'static>(
+        &'a self,
+        from: &[u8],
+        direction: IterDirection,
+    ) -> ProtocolResult<Box<dyn Iterator<Item = ProtocolResult<<S as StorageSchema>::Key>> + 'a>>
+    {
+        let mut keys = self
+            .db
+            .read()
+            .get(&S::category().to_string())
+            .map(|category| {
+                category
+                    .keys()
+                    .filter(|key| match direction {
+                        IterDirection::Forward => key.as_slice() >= from,
+                        IterDirection::Reverse => key.as_slice() <= from,
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        keys.sort_unstable();
+        if direction == IterDirection::Reverse {
+            keys.reverse();
+        }
+
+        Ok(Box::new(
+            keys.into_iter().map(|key| <_>::decode(Bytes::from(key))),
+        ))
+    }
 }
 
 #[derive(Debug, Display, From)]
@@ -6,7 +6,8 @@ use std::{fs, io};
 
 use rocksdb::ops::{DeleteCF, GetCF, GetColumnFamilys, IterateCF, OpenCF, PutCF, WriteOps};
 use rocksdb::{
-    ColumnFamily, ColumnFamilyDescriptor, DBIterator, FullOptions, Options, WriteBatch, DB,
+    ColumnFamily, ColumnFamilyDescriptor, DBIterator, Direction, FullOptions, IteratorMode,
+    Options, WriteBatch, DB,
 };
 
 use common_apm::metrics::storage::on_storage_put_cf;
@@ -14,8 +15,8 @@ use common_apm::Instant;
 use common_config_parser::types::ConfigRocksDB;
 use protocol::codec::{hex_encode, ProtocolCodec};
 use protocol::traits::{
-    IntoIteratorByRef, StorageAdapter, StorageBatchModify, StorageCategory, StorageIterator,
-    StorageSchema,
+    IntoIteratorByRef, IterDirection, StorageAdapter, StorageBatchModify, StorageCategory,
+    StorageIterator, StorageSchema,
 };
 use protocol::{types::Bytes, Display, From, ProtocolError, ProtocolErrorKind, ProtocolResult};
 
@@ -251,6 +252,25 @@ impl StorageAdapter for RocksAdapter {
         };
         Ok(Box::new(rocks_iter))
     }
+
+    fn iter_keys<'a, S: StorageSchema + 'static>(
+        &'a self,
+        from: &[u8],
+        direction: IterDirection,
+    ) -> ProtocolResult<Box<dyn Iterator<Item = ProtocolResult<<S as StorageSchema>::Key>> + 'a>>
+    {
+        let column = get_column::<S>(&self.db)?;
+        let direction = match direction {
+            IterDirection::Forward => Direction::Forward,
+            IterDirection::Reverse => Direction::Reverse,
+        };
+        let iter = self
+            .db
+            .iterator_cf(column, IteratorMode::From(from, direction))
+            .map_err(RocksAdapterError::from)?;
+
+        Ok(Box::new(iter.map(|(key, _)| <_>::decode(key))))
+    }
 }
 
 #[derive(Debug, Display, From)]
@@ -9,9 +9,13 @@ mod tests;
 use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::error::Error;
+#[cfg(feature = "ibc")]
+use std::str::FromStr;
 use std::sync::Arc;
 
 use arc_swap::ArcSwap;
+#[cfg(feature = "ibc")]
+use parking_lot::Mutex;
 
 use common_apm::metrics::storage::on_storage_get_cf;
 use common_apm::Instant;
@@ -24,7 +28,7 @@ use cosmos_ibc::{
         ics03_connection::connection::ConnectionEnd,
         ics04_channel::channel::ChannelEnd,
         ics04_channel::commitment::{AcknowledgementCommitment, PacketCommitment},
-        ics04_channel::packet::{Receipt as IbcReceipt, Sequence},
+        ics04_channel::packet::{Packet, Receipt as IbcReceipt, Sequence},
         ics24_host::{
             identifier::{ChannelId, ClientId, ConnectionId, PortId},
             path::{
@@ -32,6 +36,7 @@ use cosmos_ibc::{
                 ClientStatePath, ClientTypePath, CommitmentsPath, ConnectionsPath, ReceiptsPath,
                 SeqAcksPath, SeqRecvsPath, SeqSendsPath,
             },
+            Path as IbcPath,
         },
     },
     Height,
@@ -39,12 +44,14 @@ use cosmos_ibc::{
 #[cfg(feature = "ibc")]
 use protocol::codec::crosschain::ibc::IbcWrapper;
 use protocol::codec::ProtocolCodec;
-#[cfg(feature = "ibc")]
-use protocol::traits::IbcCrossChainStorage;
 use protocol::traits::{
     CkbCrossChainStorage, CommonStorage, Context, Storage, StorageAdapter, StorageBatchModify,
     StorageCategory, StorageSchema,
 };
+#[cfg(feature = "ibc")]
+use protocol::traits::{IbcCrossChainStorage, IterDirection};
+#[cfg(feature = "ibc")]
+use protocol::types::H160;
 use protocol::types::{
     Block, BlockNumber, Bytes, DBBytes, Direction, Hash, HashWithDirection, Hasher, Header, Proof,
     Receipt, RequestTxHashes, SignedTransaction, H256,
@@ -54,10 +61,12 @@ use protocol::{
 };
 #[cfg(feature = "ibc")]
 use schema::ibc_crosschain_schema::{
-    AcknowledgementCommitmentSchema, ChannelEndSchema, ClientConsensusStateSchema,
-    ClientStateSchema, ClientTypeSchema, ConnectionEndSchema, ConnectionIdsSchema,
-    PacketCommitmentSchema, ReceiptSchema as IbcReceiptSchema, SeqAcksSchema, SeqRecvsSchema,
-    SeqSendsSchema,
+    AcknowledgementCommitmentSchema, AcknowledgementSchema, ChannelEndSchema,
+    ClientConsensusStateSchema, ClientLatestHeightSchema, ClientProcessedHeightSchema,
+    ClientProcessedTimeSchema, ClientStateSchema, ClientTypeSchema, ConnectionChannelsSchema,
+    ConnectionEndSchema, ConnectionIdsSchema, ForkConsensusStatesSchema, IbcBytesSchema,
+    IbcCounterSchema, InterchainAccountSchema, PacketCommitmentSchema, PacketSchema,
+    ReceiptSchema as IbcReceiptSchema, SeqAcksSchema, SeqRecvsSchema, SeqSendsSchema,
 };
 
 use crate::cache::StorageCache;
@@ -78,6 +87,8 @@ lazy_static::lazy_static! {
     pub static ref LATEST_PROOF_KEY: Hash = Hasher::digest(Bytes::from("latest_proof"));
     pub static ref OVERLORD_WAL_KEY: Hash = Hasher::digest(Bytes::from("overlord_wal"));
     pub static ref MONITOR_CKB_NUMBER_KEY: Hash = Hasher::digest(Bytes::from("monitor_ckb_number"));
+    pub static ref IBC_CONNECTION_COUNTER_KEY: Hash =
+        Hasher::digest(Bytes::from("ibc_connection_counter"));
 }
 
 macro_rules! get_cache {
@@ -128,6 +139,9 @@ pub struct ImplStorage<Adapter> {
     cache:        Arc<StorageCache>,
     latest_block: ArcSwap<Option<Block>>,
     latest_proof: ArcSwap<Option<Proof>>,
+    // The staged IBC writes by their encoded key, `None` for a removal.
+    #[cfg(feature = "ibc")]
+    ibc_batch:    Mutex<Option<HashMap<Bytes, Option<Bytes>>>>,
 }
 
 impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
@@ -137,6 +151,8 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
             cache: Arc::new(StorageCache::new(cache_size)),
             latest_block: ArcSwap::new(Arc::new(None)),
             latest_proof: ArcSwap::new(Arc::new(None)),
+            #[cfg(feature = "ibc")]
+            ibc_batch: Mutex::new(None),
         }
     }
 
@@ -565,6 +581,29 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         Ok(hashes.iter().map(|h| found.remove(h)).collect::<Vec<_>>())
     }
 
+    async fn get_block_receipts(
+        &self,
+        ctx: Context,
+        block_height: u64,
+    ) -> ProtocolResult<Option<Vec<Receipt>>> {
+        let block = match self.get_block(ctx.clone(), block_height).await? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+
+        let receipts = self
+            .get_receipts(ctx, block_height, &block.tx_hashes)
+            .await?
+            .into_iter()
+            .zip(block.tx_hashes.iter())
+            .map(|(receipt, hash)| {
+                receipt.ok_or_else(|| StorageError::GetNone(format!("receipt {:?}", hash)).into())
+            })
+            .collect::<ProtocolResult<Vec<_>>>()?;
+
+        Ok(Some(receipts))
+    }
+
     async fn update_latest_proof(&self, _ctx: Context, proof: Proof) -> ProtocolResult<()> {
         self.adapter
             .insert::<LatestProofSchema>(*LATEST_PROOF_KEY, proof.clone())?;
@@ -632,20 +671,205 @@ impl<Adapter: StorageAdapter> CkbCrossChainStorage for ImplStorage<Adapter> {
     }
 }
 
+/// The key of a sent packet. It can't reuse the `CommitmentsPath` of its
+/// commitment, which lives in the same category.
+#[cfg(feature = "ibc")]
+fn packet_key(key: &(PortId, ChannelId, Sequence)) -> Hash {
+    let path = CommitmentsPath {
+        port_id:    key.0.clone(),
+        channel_id: key.1.clone(),
+        sequence:   key.2,
+    };
+    Hasher::digest(Bytes::from(format!("packets/{}", path)))
+}
+
+/// The key of a written acknowledgement, which lives next to its commitment
+/// under the `AcksPath` like `packet_key`.
+#[cfg(feature = "ibc")]
+fn acknowledgement_key(key: &(PortId, ChannelId, Sequence)) -> Hash {
+    let path = AcksPath {
+        port_id:    key.0.clone(),
+        channel_id: key.1.clone(),
+        sequence:   key.2,
+    };
+    Hasher::digest(Bytes::from(format!("acknowledgements/{}", path)))
+}
+
+#[cfg(feature = "ibc")]
+fn interchain_account_key(port_id: &PortId, channel_id: &ChannelId) -> Hash {
+    Hasher::digest(Bytes::from(format!(
+        "interchainAccounts/ports/{}/channels/{}",
+        port_id, channel_id
+    )))
+}
+
+/// The key of the channels of a connection, which has no ICS024 path.
+#[cfg(feature = "ibc")]
+fn connection_channels_key(conn_id: &ConnectionId) -> Hash {
+    Hasher::digest(Bytes::from(format!("connectionChannels/{}", conn_id)))
+}
+
+/// The key of the latest consensus height of a client, which has no ICS024
+/// path.
+#[cfg(feature = "ibc")]
+fn client_latest_height_key(client_id: &ClientId) -> Hash {
+    Hasher::digest(Bytes::from(format!("clientLatestHeight/{}", client_id)))
+}
+
+/// The key of the host time a consensus state of a client is processed at,
+/// which has no ICS024 path.
+#[cfg(feature = "ibc")]
+fn client_processed_time_key(client_id: &ClientId, height: Height) -> Hash {
+    Hasher::digest(Bytes::from(format!(
+        "clientProcessedTimes/{}/{}",
+        client_id, height
+    )))
+}
+
+/// The key of the host height a consensus state of a client is processed at,
+/// which has no ICS024 path.
+#[cfg(feature = "ibc")]
+fn client_processed_height_key(client_id: &ClientId, height: Height) -> Hash {
+    Hasher::digest(Bytes::from(format!(
+        "clientProcessedHeights/{}/{}",
+        client_id, height
+    )))
+}
+
+/// The key of the alternative consensus states of a client, which have no
+/// ICS024 path.
+#[cfg(feature = "ibc")]
+fn fork_consensus_states_key(client_id: &ClientId) -> Hash {
+    Hasher::digest(Bytes::from(format!("forkConsensusStates/{}", client_id)))
+}
+
+/// The prefix of the index of the consensus heights of a client.
+#[cfg(feature = "ibc")]
+fn consensus_height_prefix(client_id: &ClientId) -> Vec<u8> {
+    format!("consensusHeights/{}/", client_id).into_bytes()
+}
+
+/// The key of a height in the index of the consensus heights of a client. The
+/// ICS024 paths put the heights in the order of their digits, while the bytes
+/// of these keys are in the order of the heights, so the neighbors of a
+/// height are found by seeking.
+#[cfg(feature = "ibc")]
+fn consensus_height_key(client_id: &ClientId, height: Height) -> Vec<u8> {
+    [
+        consensus_height_prefix(client_id),
+        height.revision_number().to_be_bytes().to_vec(),
+        height.revision_height().to_be_bytes().to_vec(),
+    ]
+    .concat()
+}
+
+#[cfg(feature = "ibc")]
+fn consensus_height_of(bytes: &[u8]) -> ProtocolResult<Height> {
+    if bytes.len() != 16 {
+        return Err(StorageError::ConsensusHeightKey.into());
+    }
+    let mut revision_number = [0u8; 8];
+    let mut revision_height = [0u8; 8];
+    revision_number.copy_from_slice(&bytes[..8]);
+    revision_height.copy_from_slice(&bytes[8..]);
+
+    Height::new(
+        u64::from_be_bytes(revision_number),
+        u64::from_be_bytes(revision_height),
+    )
+    .map_err(|_| StorageError::ConsensusHeightKey.into())
+}
+
+/// The ICS024 path of a raw key, or `None` for the entries keyed by a hash.
+#[cfg(feature = "ibc")]
+fn ibc_path_of(key: &[u8]) -> Option<String> {
+    let path = std::str::from_utf8(key).ok()?;
+    IbcPath::from_str(path).ok().map(|_| path.to_owned())
+}
+
+#[cfg(feature = "ibc")]
+impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
+    fn ibc_get<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<Option<<S as StorageSchema>::Value>> {
+        let staged = match self.ibc_batch.lock().as_ref() {
+            Some(batch) => batch.get(&key.encode()?).cloned(),
+            None => None,
+        };
+
+        match staged {
+            Some(Some(bytes)) => Ok(Some(<_>::decode(bytes)?)),
+            Some(None) => Ok(None),
+            None => self.adapter.get::<S>(key),
+        }
+    }
+
+    fn ibc_insert<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+        val: <S as StorageSchema>::Value,
+    ) -> ProtocolResult<()> {
+        match self.ibc_batch.lock().as_mut() {
+            Some(batch) => {
+                batch.insert(key.encode()?, Some(val.encode()?));
+                Ok(())
+            }
+            None => self.adapter.insert::<S>(key, val),
+        }
+    }
+
+    fn ibc_remove<S: StorageSchema>(&self, key: <S as StorageSchema>::Key) -> ProtocolResult<()> {
+        match self.ibc_batch.lock().as_mut() {
+            Some(batch) => {
+                batch.insert(key.encode()?, None);
+                Ok(())
+            }
+            None => self.adapter.remove::<S>(key),
+        }
+    }
+
+    /// The nearest height of a consensus state of the client beyond `height`
+    /// in the direction.
+    fn nearest_consensus_height(
+        &self,
+        client_id: &ClientId,
+        height: Height,
+        direction: IterDirection,
+    ) -> ProtocolResult<Option<Height>> {
+        Ok(self
+            .get_consensus_heights(client_id, Some(height), direction, 1)?
+            .pop())
+    }
+
+    fn get_consensus_state_at(
+        &self,
+        client_id: &ClientId,
+        height: Option<Height>,
+    ) -> ProtocolResult<Option<AnyConsensusState>> {
+        match height {
+            Some(height) => self.get_consensus_state(
+                client_id,
+                height.revision_number(),
+                height.revision_height(),
+            ),
+            None => Ok(None),
+        }
+    }
+}
+
 #[cfg(feature = "ibc")]
 #[async_trait]
 impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
     fn get_client_type(&self, client_id: &ClientId) -> ProtocolResult<Option<ClientType>> {
         Ok(self
-            .adapter
-            .get::<ClientTypeSchema>(IbcWrapper(ClientTypePath(client_id.clone())))?
+            .ibc_get::<ClientTypeSchema>(IbcWrapper(ClientTypePath(client_id.clone())))?
             .map(|res| res.0))
     }
 
     fn get_client_state(&self, client_id: &ClientId) -> ProtocolResult<Option<AnyClientState>> {
         Ok(self
-            .adapter
-            .get::<ClientStateSchema>(IbcWrapper(ClientStatePath(client_id.clone())))?
+            .ibc_get::<ClientStateSchema>(IbcWrapper(ClientStatePath(client_id.clone())))?
             .map(|res| res.0))
     }
 
@@ -656,8 +880,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         height: u64,
     ) -> ProtocolResult<Option<AnyConsensusState>> {
         Ok(self
-            .adapter
-            .get::<ClientConsensusStateSchema>(IbcWrapper(ClientConsensusStatePath {
+            .ibc_get::<ClientConsensusStateSchema>(IbcWrapper(ClientConsensusStatePath {
                 client_id: client_id.clone(),
                 epoch,
                 height,
@@ -670,11 +893,8 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         client_id: &ClientId,
         height: Height,
     ) -> ProtocolResult<Option<AnyConsensusState>> {
-        self.get_consensus_state(
-            client_id,
-            height.revision_number(),
-            height.revision_height(),
-        )
+        let next = self.nearest_consensus_height(client_id, height, IterDirection::Forward)?;
+        self.get_consensus_state_at(client_id, next)
     }
 
     fn get_prev_consensus_state(
@@ -682,17 +902,75 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         client_id: &ClientId,
         height: Height,
     ) -> ProtocolResult<Option<AnyConsensusState>> {
-        self.get_consensus_state(
-            client_id,
-            height.revision_number(),
-            height.revision_height(),
-        )
+        let prev = self.nearest_consensus_height(client_id, height, IterDirection::Reverse)?;
+        self.get_consensus_state_at(client_id, prev)
+    }
+
+    // The heights are read from an index whose keys are in the order of the
+    // heights, so the walk seeks to `from` instead of reading every consensus
+    // state. The staged writes of a batch are few, so they are merged from a
+    // copy.
+    fn get_consensus_heights(
+        &self,
+        client_id: &ClientId,
+        from: Option<Height>,
+        direction: IterDirection,
+        limit: usize,
+    ) -> ProtocolResult<Vec<Height>> {
+        let prefix = consensus_height_prefix(client_id);
+        let from = from.map(|height| consensus_height_key(client_id, height));
+        // Without a height, the walk starts at either end of the index.
+        let seek = match (&from, direction) {
+            (Some(from), _) => from.clone(),
+            (None, IterDirection::Forward) => prefix.clone(),
+            (None, IterDirection::Reverse) => [prefix.clone(), vec![u8::MAX; 16]].concat(),
+        };
+        let beyond = |key: &[u8]| match (&from, direction) {
+            (None, _) => true,
+            (Some(from), IterDirection::Forward) => key > from.as_slice(),
+            (Some(from), IterDirection::Reverse) => key < from.as_slice(),
+        };
+
+        let staged = self
+            .ibc_batch
+            .lock()
+            .iter()
+            .flatten()
+            .filter(|(key, _)| key.starts_with(&prefix) && beyond(key))
+            .map(|(key, val)| (key.to_vec(), val.is_some()))
+            .collect::<HashMap<_, _>>();
+
+        let mut keys = Vec::new();
+        for key in self.adapter.iter_keys::<IbcBytesSchema>(&seek, direction)? {
+            let key = key?.0;
+            if keys.len() >= limit || !key.starts_with(&prefix) {
+                break;
+            }
+            if beyond(&key) && !staged.contains_key(key.as_ref()) {
+                keys.push(key.to_vec());
+            }
+        }
+        keys.extend(
+            staged
+                .into_iter()
+                .filter(|(_, inserted)| *inserted)
+                .map(|(key, _)| key),
+        );
+
+        keys.sort_unstable();
+        if direction == IterDirection::Reverse {
+            keys.reverse();
+        }
+        keys.truncate(limit);
+
+        keys.iter()
+            .map(|key| consensus_height_of(&key[prefix.len()..]))
+            .collect()
     }
 
     fn set_client_type(&self, client_id: ClientId, client_type: ClientType) -> ProtocolResult<()> {
         let path = IbcWrapper(ClientTypePath(client_id));
-        self.adapter
-            .insert::<ClientTypeSchema>(path, IbcWrapper(client_type))
+        self.ibc_insert::<ClientTypeSchema>(path, IbcWrapper(client_type))
     }
 
     fn set_client_state(
@@ -701,8 +979,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         client_state: AnyClientState,
     ) -> ProtocolResult<()> {
         let path = IbcWrapper(ClientStatePath(client_id));
-        self.adapter
-            .insert::<ClientStateSchema>(path, IbcWrapper(client_state))
+        self.ibc_insert::<ClientStateSchema>(path, IbcWrapper(client_state))
     }
 
     fn set_consensus_state(
@@ -711,13 +988,112 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         height: Height,
         consensus_state: AnyConsensusState,
     ) -> ProtocolResult<()> {
+        self.ibc_insert::<IbcBytesSchema>(
+            DBBytes(consensus_height_key(&client_id, height).into()),
+            DBBytes(Bytes::new()),
+        )?;
         let path = IbcWrapper(ClientConsensusStatePath {
             client_id,
             epoch: height.revision_number(),
             height: height.revision_height(),
         });
-        self.adapter
-            .insert::<ClientConsensusStateSchema>(path, IbcWrapper(consensus_state))
+        self.ibc_insert::<ClientConsensusStateSchema>(path, IbcWrapper(consensus_state))
+    }
+
+    fn get_client_latest_height(&self, client_id: &ClientId) -> ProtocolResult<Option<Height>> {
+        Ok(self
+            .ibc_get::<ClientLatestHeightSchema>(client_latest_height_key(client_id))?
+            .map(|res| res.0))
+    }
+
+    fn set_client_latest_height(&self, client_id: ClientId, height: Height) -> ProtocolResult<()> {
+        self.ibc_insert::<ClientLatestHeightSchema>(
+            client_latest_height_key(&client_id),
+            IbcWrapper(height),
+        )
+    }
+
+    fn get_client_processed_time(
+        &self,
+        client_id: &ClientId,
+        height: Height,
+    ) -> ProtocolResult<Option<u64>> {
+        self.ibc_get::<ClientProcessedTimeSchema>(client_processed_time_key(client_id, height))
+    }
+
+    fn set_client_processed_time(
+        &self,
+        client_id: ClientId,
+        height: Height,
+        timestamp: u64,
+    ) -> ProtocolResult<()> {
+        self.ibc_insert::<ClientProcessedTimeSchema>(
+            client_processed_time_key(&client_id, height),
+            timestamp,
+        )
+    }
+
+    fn get_client_processed_height(
+        &self,
+        client_id: &ClientId,
+        height: Height,
+    ) -> ProtocolResult<Option<Height>> {
+        Ok(self
+            .ibc_get::<ClientProcessedHeightSchema>(client_processed_height_key(client_id, height))?
+            .map(|res| res.0))
+    }
+
+    fn set_client_processed_height(
+        &self,
+        client_id: ClientId,
+        height: Height,
+        host_height: Height,
+    ) -> ProtocolResult<()> {
+        self.ibc_insert::<ClientProcessedHeightSchema>(
+            client_processed_height_key(&client_id, height),
+            IbcWrapper(host_height),
+        )
+    }
+
+    fn get_fork_consensus_states(
+        &self,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Vec<(Height, AnyConsensusState)>> {
+        Ok(self
+            .ibc_get::<ForkConsensusStatesSchema>(fork_consensus_states_key(client_id))?
+            .map(|res| res.0)
+            .unwrap_or_default())
+    }
+
+    fn set_fork_consensus_states(
+        &self,
+        client_id: ClientId,
+        states: Vec<(Height, AnyConsensusState)>,
+    ) -> ProtocolResult<()> {
+        let key = fork_consensus_states_key(&client_id);
+        if states.is_empty() {
+            return self.ibc_remove::<ForkConsensusStatesSchema>(key);
+        }
+        self.ibc_insert::<ForkConsensusStatesSchema>(key, IbcWrapper(states))
+    }
+
+    // The processed time and height of a consensus state go with it.
+    fn delete_consensus_state(&self, client_id: ClientId, height: Height) -> ProtocolResult<()> {
+        self.ibc_remove::<IbcBytesSchema>(DBBytes(
+            consensus_height_key(&client_id, height).into(),
+        ))?;
+        self.ibc_remove::<ClientProcessedTimeSchema>(client_processed_time_key(
+            &client_id, height,
+        ))?;
+        self.ibc_remove::<ClientProcessedHeightSchema>(client_processed_height_key(
+            &client_id, height,
+        ))?;
+        let path = IbcWrapper(ClientConsensusStatePath {
+            client_id,
+            epoch: height.revision_number(),
+            height: height.revision_height(),
+        });
+        self.ibc_remove::<ClientConsensusStateSchema>(path)
     }
 
     fn set_connection_end(
@@ -726,8 +1102,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         connection_end: ConnectionEnd,
     ) -> ProtocolResult<()> {
         let path = IbcWrapper(ConnectionsPath(connection_id));
-        self.adapter
-            .insert::<ConnectionEndSchema>(path, IbcWrapper(connection_end))
+        self.ibc_insert::<ConnectionEndSchema>(path, IbcWrapper(connection_end))
     }
 
     fn get_connection_to_client(
@@ -735,8 +1110,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         client_id: &ClientId,
     ) -> ProtocolResult<Option<Vec<ConnectionId>>> {
         Ok(self
-            .adapter
-            .get::<ConnectionIdsSchema>(IbcWrapper(ClientConnectionsPath(client_id.clone())))?
+            .ibc_get::<ConnectionIdsSchema>(IbcWrapper(ClientConnectionsPath(client_id.clone())))?
             .map(|res| res.0))
     }
 
@@ -746,16 +1120,41 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         client_id: &ClientId,
     ) -> ProtocolResult<()> {
         let path = IbcWrapper(ClientConnectionsPath(client_id.clone()));
-        self.adapter
-            .insert::<ConnectionIdsSchema>(path, IbcWrapper(vec![connection_id]))
+        self.ibc_insert::<ConnectionIdsSchema>(path, IbcWrapper(vec![connection_id]))
+    }
+
+    fn get_connection_counter(&self) -> ProtocolResult<Option<u64>> {
+        self.ibc_get::<IbcCounterSchema>(*IBC_CONNECTION_COUNTER_KEY)
+    }
+
+    fn set_connection_counter(&self, counter: u64) -> ProtocolResult<()> {
+        self.ibc_insert::<IbcCounterSchema>(*IBC_CONNECTION_COUNTER_KEY, counter)
     }
 
     fn set_connection_channels(
         &self,
-        _conn_id: ConnectionId,
-        _port_channel_id: &(PortId, ChannelId),
+        conn_id: ConnectionId,
+        port_channel_id: &(PortId, ChannelId),
     ) -> ProtocolResult<()> {
-        todo!()
+        let mut channels = self.get_connection_channels(&conn_id)?.unwrap_or_default();
+        if channels.contains(port_channel_id) {
+            return Ok(());
+        }
+
+        channels.push(port_channel_id.clone());
+        self.ibc_insert::<ConnectionChannelsSchema>(
+            connection_channels_key(&conn_id),
+            IbcWrapper(channels),
+        )
+    }
+
+    fn get_connection_channels(
+        &self,
+        conn_id: &ConnectionId,
+    ) -> ProtocolResult<Option<Vec<(PortId, ChannelId)>>> {
+        Ok(self
+            .ibc_get::<ConnectionChannelsSchema>(connection_channels_key(conn_id))?
+            .map(|res| res.0))
     }
 
     fn set_channel(
@@ -765,14 +1164,12 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         chan_end: ChannelEnd,
     ) -> ProtocolResult<()> {
         let path = IbcWrapper(ChannelEndsPath(port_id, chan_id));
-        self.adapter
-            .insert::<ChannelEndSchema>(path, IbcWrapper(chan_end))
+        self.ibc_insert::<ChannelEndSchema>(path, IbcWrapper(chan_end))
     }
 
     fn get_connection_end(&self, conn_id: &ConnectionId) -> ProtocolResult<Option<ConnectionEnd>> {
         Ok(self
-            .adapter
-            .get::<ConnectionEndSchema>(IbcWrapper(ConnectionsPath(conn_id.clone())))?
+            .ibc_get::<ConnectionEndSchema>(IbcWrapper(ConnectionsPath(conn_id.clone())))?
             .map(|res| res.0))
     }
 
@@ -786,8 +1183,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
             channel_id: key.1.clone(),
             sequence:   key.2,
         });
-        self.adapter
-            .insert::<PacketCommitmentSchema>(path, IbcWrapper(commitment))
+        self.ibc_insert::<PacketCommitmentSchema>(path, IbcWrapper(commitment))
     }
 
     fn get_packet_commitment(
@@ -795,8 +1191,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         key: &(PortId, ChannelId, Sequence),
     ) -> ProtocolResult<Option<PacketCommitment>> {
         Ok(self
-            .adapter
-            .get::<PacketCommitmentSchema>(IbcWrapper(CommitmentsPath {
+            .ibc_get::<PacketCommitmentSchema>(IbcWrapper(CommitmentsPath {
                 port_id:    key.0.clone(),
                 channel_id: key.1.clone(),
                 sequence:   key.2,
@@ -810,7 +1205,21 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
             channel_id: key.1.clone(),
             sequence:   key.2,
         });
-        self.adapter.remove::<PacketCommitmentSchema>(path)
+        self.ibc_remove::<PacketCommitmentSchema>(path)
+    }
+
+    fn set_packet(&self, key: (PortId, ChannelId, Sequence), packet: Packet) -> ProtocolResult<()> {
+        self.ibc_insert::<PacketSchema>(packet_key(&key), IbcWrapper(packet))
+    }
+
+    fn get_packet(&self, key: &(PortId, ChannelId, Sequence)) -> ProtocolResult<Option<Packet>> {
+        Ok(self
+            .ibc_get::<PacketSchema>(packet_key(key))?
+            .map(|res| res.0))
+    }
+
+    fn delete_packet(&self, key: (PortId, ChannelId, Sequence)) -> ProtocolResult<()> {
+        self.ibc_remove::<PacketSchema>(packet_key(&key))
     }
 
     fn set_packet_receipt(
@@ -823,22 +1232,18 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
             channel_id: key.1.clone(),
             sequence:   key.2,
         });
-        self.adapter
-            .insert::<IbcReceiptSchema>(path, IbcWrapper(()))
+        self.ibc_insert::<IbcReceiptSchema>(path, IbcWrapper(()))
     }
 
     fn get_packet_receipt(
         &self,
         key: &(PortId, ChannelId, Sequence),
     ) -> ProtocolResult<Option<IbcReceipt>> {
-        if let Some(_res) = self
-            .adapter
-            .get::<IbcReceiptSchema>(IbcWrapper(ReceiptsPath {
-                port_id:    key.0.clone(),
-                channel_id: key.1.clone(),
-                sequence:   key.2,
-            }))?
-        {}
+        if let Some(_res) = self.ibc_get::<IbcReceiptSchema>(IbcWrapper(ReceiptsPath {
+            port_id:    key.0.clone(),
+            channel_id: key.1.clone(),
+            sequence:   key.2,
+        }))? {}
         Ok(Some(IbcReceipt::Ok))
     }
 
@@ -852,8 +1257,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
             channel_id: key.1,
             sequence:   key.2,
         });
-        self.adapter
-            .insert::<AcknowledgementCommitmentSchema>(path, IbcWrapper(ack_commitment))
+        self.ibc_insert::<AcknowledgementCommitmentSchema>(path, IbcWrapper(ack_commitment))
     }
 
     fn get_packet_acknowledgement(
@@ -866,8 +1270,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
             sequence:   key.2,
         });
         let ret = self
-            .adapter
-            .get::<AcknowledgementCommitmentSchema>(path)?
+            .ibc_get::<AcknowledgementCommitmentSchema>(path)?
             .unwrap()
             .0;
         Ok(Some(ret))
@@ -882,7 +1285,46 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
             channel_id: key.1.clone(),
             sequence:   key.2,
         });
-        self.adapter.remove::<AcknowledgementCommitmentSchema>(path)
+        self.ibc_remove::<AcknowledgementCommitmentSchema>(path)
+    }
+
+    fn set_acknowledgement(
+        &self,
+        key: (PortId, ChannelId, Sequence),
+        ack: Vec<u8>,
+    ) -> ProtocolResult<()> {
+        self.ibc_insert::<AcknowledgementSchema>(acknowledgement_key(&key), Bytes::from(ack))
+    }
+
+    fn get_acknowledgement(
+        &self,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<Vec<u8>>> {
+        Ok(self
+            .ibc_get::<AcknowledgementSchema>(acknowledgement_key(key))?
+            .map(|ack| ack.to_vec()))
+    }
+
+    fn set_interchain_account(
+        &self,
+        port_id: PortId,
+        channel_id: ChannelId,
+        address: H160,
+    ) -> ProtocolResult<()> {
+        self.ibc_insert::<InterchainAccountSchema>(
+            interchain_account_key(&port_id, &channel_id),
+            Bytes::copy_from_slice(address.as_bytes()),
+        )
+    }
+
+    fn get_interchain_account(
+        &self,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> ProtocolResult<Option<H160>> {
+        Ok(self
+            .ibc_get::<InterchainAccountSchema>(interchain_account_key(port_id, channel_id))?
+            .map(|address| H160::from_slice(&address)))
     }
 
     fn set_next_sequence_send(
@@ -892,7 +1334,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         seq: Sequence,
     ) -> ProtocolResult<()> {
         let path = IbcWrapper(SeqSendsPath(port_id, chan_id));
-        self.adapter.insert::<SeqSendsSchema>(path, IbcWrapper(seq))
+        self.ibc_insert::<SeqSendsSchema>(path, IbcWrapper(seq))
     }
 
     fn set_next_sequence_recv(
@@ -902,7 +1344,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         seq: Sequence,
     ) -> ProtocolResult<()> {
         let path = IbcWrapper(SeqRecvsPath(port_id, chan_id));
-        self.adapter.insert::<SeqRecvsSchema>(path, IbcWrapper(seq))
+        self.ibc_insert::<SeqRecvsSchema>(path, IbcWrapper(seq))
     }
 
     fn set_next_sequence_ack(
@@ -912,7 +1354,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         seq: Sequence,
     ) -> ProtocolResult<()> {
         let path = IbcWrapper(SeqAcksPath(port_id, chan_id));
-        self.adapter.insert::<SeqAcksSchema>(path, IbcWrapper(seq))
+        self.ibc_insert::<SeqAcksSchema>(path, IbcWrapper(seq))
     }
 
     fn get_channel_end(
@@ -920,8 +1362,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         port_channel_id: &(PortId, ChannelId),
     ) -> ProtocolResult<Option<ChannelEnd>> {
         Ok(self
-            .adapter
-            .get::<ChannelEndSchema>(IbcWrapper(ChannelEndsPath(
+            .ibc_get::<ChannelEndSchema>(IbcWrapper(ChannelEndsPath(
                 port_channel_id.0.clone(),
                 port_channel_id.1.clone(),
             )))?
@@ -933,8 +1374,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         port_channel_id: &(PortId, ChannelId),
     ) -> ProtocolResult<Option<Sequence>> {
         Ok(self
-            .adapter
-            .get::<SeqSendsSchema>(IbcWrapper(SeqSendsPath(
+            .ibc_get::<SeqSendsSchema>(IbcWrapper(SeqSendsPath(
                 port_channel_id.0.clone(),
                 port_channel_id.1.clone(),
             )))?
@@ -946,8 +1386,7 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         port_channel_id: &(PortId, ChannelId),
     ) -> ProtocolResult<Option<Sequence>> {
         Ok(self
-            .adapter
-            .get::<SeqRecvsSchema>(IbcWrapper(SeqRecvsPath(
+            .ibc_get::<SeqRecvsSchema>(IbcWrapper(SeqRecvsPath(
                 port_channel_id.0.clone(),
                 port_channel_id.1.clone(),
             )))?
@@ -959,13 +1398,81 @@ impl<Adapter: StorageAdapter> IbcCrossChainStorage for ImplStorage<Adapter> {
         port_channel_id: &(PortId, ChannelId),
     ) -> ProtocolResult<Option<Sequence>> {
         Ok(self
-            .adapter
-            .get::<SeqAcksSchema>(IbcWrapper(SeqAcksPath(
+            .ibc_get::<SeqAcksSchema>(IbcWrapper(SeqAcksPath(
                 port_channel_id.0.clone(),
                 port_channel_id.1.clone(),
             )))?
             .map(|res| res.0))
     }
+
+    // The paths are encoded as their bytes, so the scan seeks to the prefix
+    // and stops at the first key outside of it. The staged writes of a batch
+    // are few, so they are merged from a copy.
+    fn iter_paths_by_prefix<'a>(
+        &'a self,
+        prefix: &str,
+    ) -> ProtocolResult<Box<dyn Iterator<Item = ProtocolResult<String>> + 'a>> {
+        let staged = self
+            .ibc_batch
+            .lock()
+            .iter()
+            .flatten()
+            .filter_map(|(key, val)| Some((ibc_path_of(key)?, val.is_some())))
+            .filter(|(path, _)| path.starts_with(prefix))
+            .collect::<HashMap<_, _>>();
+        let inserted = staged
+            .iter()
+            .filter(|(_, inserted)| **inserted)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+
+        let prefix = prefix.as_bytes().to_vec();
+        let paths = self
+            .adapter
+            .iter_keys::<IbcBytesSchema>(&prefix, IterDirection::Forward)?
+            .take_while(move |key| match key {
+                Ok(key) => key.0.starts_with(&prefix),
+                Err(_) => true,
+            })
+            .filter_map(move |key| match key {
+                Ok(key) => ibc_path_of(&key.0)
+                    .filter(|path| !staged.contains_key(path))
+                    .map(Ok),
+                Err(e) => Some(Err(e)),
+            })
+            .chain(inserted.into_iter().map(Ok));
+
+        Ok(Box::new(paths))
+    }
+
+    fn begin_batch(&self) {
+        self.ibc_batch.lock().get_or_insert_with(HashMap::new);
+    }
+
+    // All the IBC entries live in one category, so the staged writes are
+    // applied by a single write batch of the adapter.
+    fn commit_batch(&self) -> ProtocolResult<()> {
+        let batch = match self.ibc_batch.lock().take() {
+            Some(batch) => batch,
+            None => return Ok(()),
+        };
+
+        let (keys, vals) = batch
+            .into_iter()
+            .map(|(key, val)| {
+                let val = match val {
+                    Some(val) => StorageBatchModify::Insert(DBBytes(val)),
+                    None => StorageBatchModify::Remove,
+                };
+                (DBBytes(key), val)
+            })
+            .unzip();
+        self.adapter.batch_modify::<IbcBytesSchema>(keys, vals)
+    }
+
+    fn discard_batch(&self) {
+        self.ibc_batch.lock().take();
+    }
 }
 
 #[derive(Debug, Display, From)]
@@ -975,6 +1482,9 @@ pub enum StorageError {
 
     #[display(fmt = "decode batch value")]
     BatchDecode,
+
+    #[display(fmt = "invalid consensus height key")]
+    ConsensusHeightKey,
 }
 
 impl Error for StorageError {}
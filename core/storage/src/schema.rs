@@ -66,10 +66,10 @@ pub mod ibc_crosschain_schema {
         ics04_channel::{
             channel::ChannelEnd,
             commitment::{AcknowledgementCommitment, PacketCommitment},
-            packet::Sequence,
+            packet::{Packet, Sequence},
         },
         ics24_host::{
-            identifier::ConnectionId,
+            identifier::{ChannelId, ConnectionId, PortId},
             path::{
                 AcksPath, ChannelEndsPath, ClientConnectionsPath, ClientConsensusStatePath,
                 ClientStatePath, ClientTypePath, CommitmentsPath, ConnectionsPath, ReceiptsPath,
@@ -78,6 +78,7 @@ pub mod ibc_crosschain_schema {
         },
     };
 
+    use cosmos_ibc::Height;
     use protocol::codec::crosschain::ibc::IbcWrapper;
 
     impl_storage_schema_for!(
@@ -152,4 +153,35 @@ pub mod ibc_crosschain_schema {
         IbcWrapper<()>,
         IbcCrossChain
     );
+    impl_storage_schema_for!(IbcCounterSchema, Hash, u64, IbcCrossChain);
+    impl_storage_schema_for!(PacketSchema, Hash, IbcWrapper<Packet>, IbcCrossChain);
+    impl_storage_schema_for!(AcknowledgementSchema, Hash, Bytes, IbcCrossChain);
+    impl_storage_schema_for!(InterchainAccountSchema, Hash, Bytes, IbcCrossChain);
+    impl_storage_schema_for!(
+        ConnectionChannelsSchema,
+        Hash,
+        IbcWrapper<Vec<(PortId, ChannelId)>>,
+        IbcCrossChain
+    );
+    impl_storage_schema_for!(
+        ClientLatestHeightSchema,
+        Hash,
+        IbcWrapper<Height>,
+        IbcCrossChain
+    );
+    impl_storage_schema_for!(ClientProcessedTimeSchema, Hash, u64, IbcCrossChain);
+    impl_storage_schema_for!(
+        ClientProcessedHeightSchema,
+        Hash,
+        IbcWrapper<Height>,
+        IbcCrossChain
+    );
+    impl_storage_schema_for!(
+        ForkConsensusStatesSchema,
+        Hash,
+        IbcWrapper<Vec<(Height, AnyConsensusState)>>,
+        IbcCrossChain
+    );
+    // The raw entries of all the schemas above, which share the category.
+    impl_storage_schema_for!(IbcBytesSchema, DBBytes, DBBytes, IbcCrossChain);
 }
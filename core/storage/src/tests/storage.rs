@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use protocol::traits::{CommonStorage, Context, Storage};
-use protocol::types::Hasher;
+use protocol::types::{cumulative_gas_used, Hasher, Log, Receipt, H160};
 
 use crate::adapter::memory::MemoryAdapter;
 use crate::tests::{get_random_bytes, mock_block, mock_proof, mock_receipt, mock_signed_tx};
@@ -59,6 +59,68 @@ fn test_storage_receipts_insert() {
     }
 }
 
+#[test]
+fn test_storage_block_receipts() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
+    let height = 2078;
+
+    let mut block = mock_block(height, Hasher::digest(get_random_bytes(10)));
+    let mut receipts = Vec::new();
+    let mut log_index = 0;
+    for (idx, (gas, logs)) in [(21_000u64, 0usize), (53_000, 2), (30_000, 1)]
+        .into_iter()
+        .enumerate()
+    {
+        let hash = Hasher::digest(get_random_bytes(10));
+        block.tx_hashes.push(hash);
+        receipts.push(Receipt {
+            block_number: height,
+            tx_index: idx as u32,
+            used_gas: gas.into(),
+            logs: vec![mock_log(); logs],
+            log_index,
+            ..mock_receipt(hash)
+        });
+        log_index += logs as u32;
+    }
+
+    exec!(storage.insert_block(Context::new(), block));
+    // The receipts are stored unordered.
+    exec!(storage.insert_receipts(
+        Context::new(),
+        height,
+        receipts.iter().rev().cloned().collect()
+    ));
+
+    let block_receipts = exec!(storage.get_block_receipts(Context::new(), height)).unwrap();
+    assert_eq!(block_receipts, receipts);
+    assert_eq!(
+        block_receipts
+            .iter()
+            .map(|r| r.log_index)
+            .collect::<Vec<_>>(),
+        vec![0, 0, 2]
+    );
+
+    let cumulative = cumulative_gas_used(&block_receipts);
+    assert_eq!(cumulative, vec![
+        21_000u64.into(),
+        74_000u64.into(),
+        104_000u64.into()
+    ]);
+    assert!(cumulative.windows(2).all(|w| w[0] < w[1]));
+
+    assert!(exec!(storage.get_block_receipts(Context::new(), height + 1)).is_none());
+}
+
+fn mock_log() -> Log {
+    Log {
+        address: H160::default(),
+        topics:  vec![],
+        data:    vec![],
+    }
+}
+
 #[test]
 fn test_storage_transactions_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
@@ -200,6 +262,110 @@ fn test_ibc_get_set_consensus_state() {
 }
 #[test]
 #[cfg(feature = "ibc")]
+fn test_ibc_next_prev_consensus_state() {
+    use cosmos_ibc::core::ics02_client::client_consensus::AnyConsensusState;
+    use cosmos_ibc::core::ics02_client::client_type::ClientType;
+    use cosmos_ibc::core::ics24_host::identifier::ClientId;
+    use cosmos_ibc::mock::client_state::MockConsensusState;
+    use cosmos_ibc::mock::header::MockHeader;
+    use cosmos_ibc::Height;
+    use protocol::traits::IbcCrossChainStorage;
+
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
+    let client_id = ClientId::new(ClientType::Tendermint, 1).unwrap();
+    // The id of the other client has the id of the first one as a prefix.
+    let other_id = ClientId::new(ClientType::Tendermint, 10).unwrap();
+    let height = |number, height| Height::new(number, height).unwrap();
+    let state = |height| AnyConsensusState::Mock(MockConsensusState::new(MockHeader::new(height)));
+
+    // The path of `0-10` is before the path of `0-2`.
+    let states = [height(0, 2), height(0, 10), height(1, 1)]
+        .into_iter()
+        .map(|h| (h, state(h)))
+        .collect::<Vec<_>>();
+    for (h, s) in states.iter() {
+        assert!(storage
+            .set_consensus_state(client_id.clone(), *h, s.clone())
+            .is_ok());
+    }
+    assert!(storage
+        .set_consensus_state(other_id.clone(), height(0, 5), state(height(0, 5)))
+        .is_ok());
+
+    let next = |h| storage.get_next_consensus_state(&client_id, h).unwrap();
+    let prev = |h| storage.get_prev_consensus_state(&client_id, h).unwrap();
+    assert_eq!(next(height(0, 1)), Some(states[0].1.clone()));
+    // The state at the height itself is skipped.
+    assert_eq!(next(height(0, 2)), Some(states[1].1.clone()));
+    assert_eq!(next(height(0, 10)), Some(states[2].1.clone()));
+    assert_eq!(next(height(1, 1)), None);
+    assert_eq!(prev(height(1, 1)), Some(states[1].1.clone()));
+    assert_eq!(prev(height(0, 5)), Some(states[0].1.clone()));
+    assert_eq!(prev(height(0, 2)), None);
+
+    // The staged writes of a batch are seen by the seeks.
+    let staged = state(height(0, 5));
+    storage.begin_batch();
+    assert!(storage
+        .set_consensus_state(client_id.clone(), height(0, 5), staged.clone())
+        .is_ok());
+    assert!(storage
+        .delete_consensus_state(client_id.clone(), height(0, 10))
+        .is_ok());
+    assert_eq!(next(height(0, 2)), Some(staged.clone()));
+    assert_eq!(next(height(0, 5)), Some(states[2].1.clone()));
+    assert_eq!(prev(height(1, 1)), Some(staged));
+    storage.discard_batch();
+    assert_eq!(next(height(0, 2)), Some(states[1].1.clone()));
+}
+#[test]
+#[cfg(feature = "ibc")]
+fn test_ibc_get_consensus_heights() {
+    use cosmos_ibc::core::ics02_client::client_consensus::AnyConsensusState;
+    use cosmos_ibc::core::ics02_client::client_type::ClientType;
+    use cosmos_ibc::core::ics24_host::identifier::ClientId;
+    use cosmos_ibc::mock::client_state::MockConsensusState;
+    use cosmos_ibc::mock::header::MockHeader;
+    use cosmos_ibc::Height;
+    use protocol::traits::{IbcCrossChainStorage, IterDirection};
+
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
+    let client_id = ClientId::new(ClientType::Tendermint, 1).unwrap();
+    let heights = (1..=12)
+        .map(|h| Height::new(0, h).unwrap())
+        .collect::<Vec<_>>();
+    for h in heights.iter() {
+        let state = AnyConsensusState::Mock(MockConsensusState::new(MockHeader::new(*h)));
+        assert!(storage
+            .set_consensus_state(client_id.clone(), *h, state)
+            .is_ok());
+    }
+
+    let get = |from, direction, limit| {
+        storage
+            .get_consensus_heights(&client_id, from, direction, limit)
+            .unwrap()
+    };
+    assert_eq!(get(None, IterDirection::Forward, 3), heights[..3].to_vec());
+    assert_eq!(
+        get(Some(heights[8]), IterDirection::Forward, 5),
+        heights[9..].to_vec()
+    );
+    assert_eq!(get(None, IterDirection::Reverse, 2), vec![
+        heights[11],
+        heights[10]
+    ]);
+    assert_eq!(get(Some(heights[1]), IterDirection::Reverse, 5), vec![
+        heights[0]
+    ]);
+
+    assert!(storage
+        .delete_consensus_state(client_id.clone(), heights[0])
+        .is_ok());
+    assert_eq!(get(None, IterDirection::Forward, 1), vec![heights[1]]);
+}
+#[test]
+#[cfg(feature = "ibc")]
 fn test_ibc_get_set_connection_end() {
     use cosmos_ibc::core::ics03_connection::connection::ConnectionEnd;
     use cosmos_ibc::core::ics24_host::identifier::ConnectionId;
@@ -325,6 +491,129 @@ fn test_ibc_get_set_packet_acknowledgement() {
     let del_ret = storage.delete_packet_acknowledgement((port_id, channel_id, sequence));
     assert!(del_ret.is_ok());
 }
+#[test]
+#[cfg(feature = "ibc")]
+fn test_ibc_get_set_interchain_account() {
+    use cosmos_ibc::core::ics24_host::identifier::{ChannelId, PortId};
+    use protocol::traits::IbcCrossChainStorage;
+    use protocol::types::H160;
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
+    let port_id = PortId::default();
+    let address = H160::random();
+
+    assert!(storage
+        .get_interchain_account(&port_id, &ChannelId::new(0))
+        .unwrap()
+        .is_none());
+    storage
+        .set_interchain_account(port_id.clone(), ChannelId::new(0), address)
+        .unwrap();
+    assert_eq!(
+        storage
+            .get_interchain_account(&port_id, &ChannelId::new(0))
+            .unwrap(),
+        Some(address)
+    );
+    assert!(storage
+        .get_interchain_account(&port_id, &ChannelId::new(1))
+        .unwrap()
+        .is_none());
+}
+#[test]
+#[cfg(feature = "ibc")]
+fn test_ibc_get_set_client_processed() {
+    use cosmos_ibc::core::ics02_client::client_consensus::AnyConsensusState;
+    use cosmos_ibc::core::ics02_client::client_type::ClientType;
+    use cosmos_ibc::core::ics24_host::identifier::ClientId;
+    use cosmos_ibc::mock::client_state::MockConsensusState;
+    use cosmos_ibc::mock::header::MockHeader;
+    use cosmos_ibc::Height;
+    use protocol::traits::IbcCrossChainStorage;
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let height = Height::new(0, 3).unwrap();
+
+    assert!(storage
+        .get_client_processed_time(&client_id, height)
+        .unwrap()
+        .is_none());
+    storage
+        .set_client_processed_time(client_id.clone(), height, 7)
+        .unwrap();
+    storage
+        .set_client_processed_height(client_id.clone(), height, Height::new(0, 9).unwrap())
+        .unwrap();
+    assert_eq!(
+        storage
+            .get_client_processed_time(&client_id, height)
+            .unwrap(),
+        Some(7)
+    );
+    assert_eq!(
+        storage
+            .get_client_processed_height(&client_id, height)
+            .unwrap(),
+        Some(Height::new(0, 9).unwrap())
+    );
+
+    // They are deleted along with the consensus state.
+    let consensus_state = AnyConsensusState::Mock(MockConsensusState::new(MockHeader::default()));
+    storage
+        .set_consensus_state(client_id.clone(), height, consensus_state)
+        .unwrap();
+    storage
+        .delete_consensus_state(client_id.clone(), height)
+        .unwrap();
+    assert!(storage
+        .get_client_processed_time(&client_id, height)
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .get_client_processed_height(&client_id, height)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+#[cfg(feature = "ibc")]
+fn test_ibc_get_set_fork_consensus_states() {
+    use cosmos_ibc::core::ics02_client::client_consensus::AnyConsensusState;
+    use cosmos_ibc::core::ics02_client::client_type::ClientType;
+    use cosmos_ibc::core::ics24_host::identifier::ClientId;
+    use cosmos_ibc::mock::client_state::MockConsensusState;
+    use cosmos_ibc::mock::header::MockHeader;
+    use cosmos_ibc::Height;
+    use protocol::traits::IbcCrossChainStorage;
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
+    let client_id = ClientId::new(ClientType::Tendermint, 0).unwrap();
+    let state = |h| {
+        let height = Height::new(0, h).unwrap();
+        let state = AnyConsensusState::Mock(MockConsensusState::new(MockHeader::new(height)));
+        (height, state)
+    };
+
+    assert!(storage
+        .get_fork_consensus_states(&client_id)
+        .unwrap()
+        .is_empty());
+    let states = vec![state(3), state(5)];
+    storage
+        .set_fork_consensus_states(client_id.clone(), states.clone())
+        .unwrap();
+    assert_eq!(
+        storage.get_fork_consensus_states(&client_id).unwrap(),
+        states
+    );
+
+    storage
+        .set_fork_consensus_states(client_id.clone(), vec![])
+        .unwrap();
+    assert!(storage
+        .get_fork_consensus_states(&client_id)
+        .unwrap()
+        .is_empty());
+}
+
 #[test]
 #[cfg(feature = "ibc")]
 fn test_ibc_get_set_next_sequence_send() {
@@ -379,3 +668,84 @@ fn test_ibc_get_set_next_sequence_ack() {
 
     assert_eq!(sequence, get_ret.unwrap().unwrap());
 }
+#[test]
+#[cfg(feature = "ibc")]
+fn test_ibc_iter_paths_by_prefix() {
+    use cosmos_ibc::core::ics04_channel::commitment::PacketCommitment;
+    use cosmos_ibc::core::ics04_channel::packet::Sequence;
+    use cosmos_ibc::core::ics24_host::identifier::{ChannelId, PortId};
+    use protocol::traits::IbcCrossChainStorage;
+    use protocol::ProtocolResult;
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
+    for (channel, seq) in [(0, 1), (0, 2), (1, 1)] {
+        let key = (
+            PortId::transfer(),
+            ChannelId::new(channel),
+            Sequence::from(seq),
+        );
+        let set_ret = storage.set_packet_commitment(key, PacketCommitment::from(vec![1u8; 32]));
+        assert!(set_ret.is_ok());
+    }
+    // An entry keyed by a hash is skipped.
+    assert!(storage.set_connection_counter(1).is_ok());
+
+    // The scan starts at the prefix and stops at the first path of the next
+    // channel, in the order of the keys.
+    let paths = storage
+        .iter_paths_by_prefix("commitments/ports/transfer/channels/channel-0/")
+        .unwrap()
+        .collect::<ProtocolResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(paths, vec![
+        "commitments/ports/transfer/channels/channel-0/sequences/1".to_string(),
+        "commitments/ports/transfer/channels/channel-0/sequences/2".to_string(),
+    ]);
+}
+#[test]
+#[cfg(feature = "ibc")]
+fn test_ibc_batch() {
+    use cosmos_ibc::core::ics04_channel::commitment::PacketCommitment;
+    use cosmos_ibc::core::ics04_channel::packet::Sequence;
+    use cosmos_ibc::core::ics24_host::identifier::{ChannelId, PortId};
+    use protocol::traits::IbcCrossChainStorage;
+    use protocol::ProtocolResult;
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter), 10);
+    let key = |seq: u64| (PortId::transfer(), ChannelId::new(0), Sequence::from(seq));
+    let commitment = PacketCommitment::from(vec![1u8; 32]);
+    assert!(storage
+        .set_packet_commitment(key(1), commitment.clone())
+        .is_ok());
+
+    // The staged writes are seen by the reads, but not by another storage on
+    // the same adapter, until the batch is committed.
+    let other = ImplStorage::new(Arc::clone(&adapter), 10);
+    storage.begin_batch();
+    assert!(storage
+        .set_packet_commitment(key(2), commitment.clone())
+        .is_ok());
+    assert!(storage.delete_packet_commitment(key(1)).is_ok());
+    assert!(storage.get_packet_commitment(&key(1)).unwrap().is_none());
+    assert!(storage.get_packet_commitment(&key(2)).unwrap().is_some());
+    let paths = storage
+        .iter_paths_by_prefix("commitments/")
+        .unwrap()
+        .collect::<ProtocolResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(paths, vec![
+        "commitments/ports/transfer/channels/channel-0/sequences/2".to_string()
+    ]);
+    assert!(other.get_packet_commitment(&key(1)).unwrap().is_some());
+    assert!(other.get_packet_commitment(&key(2)).unwrap().is_none());
+
+    storage.discard_batch();
+    assert!(storage.get_packet_commitment(&key(1)).unwrap().is_some());
+    assert!(storage.get_packet_commitment(&key(2)).unwrap().is_none());
+
+    storage.begin_batch();
+    assert!(storage.set_packet_commitment(key(2), commitment).is_ok());
+    assert!(storage.delete_packet_commitment(key(1)).is_ok());
+    assert!(storage.commit_batch().is_ok());
+    assert!(other.get_packet_commitment(&key(1)).unwrap().is_none());
+    assert!(other.get_packet_commitment(&key(2)).unwrap().is_some());
+}
@@ -98,10 +98,10 @@ pub mod ibc {
         ics04_channel::{
             channel::ChannelEnd,
             commitment::{AcknowledgementCommitment, PacketCommitment},
-            packet::Sequence,
+            packet::{Packet, Sequence},
         },
         ics24_host::{
-            identifier::ConnectionId,
+            identifier::{ChannelId, ConnectionId, PortId},
             path::{
                 AcksPath, ChannelEndsPath, ClientConnectionsPath, ClientConsensusStatePath,
                 ClientStatePath, ClientTypePath, CommitmentsPath, ConnectionsPath, ReceiptsPath,
@@ -110,10 +110,14 @@ pub mod ibc {
             Path,
         },
     };
+    use cosmos_ibc::Height;
     use ibc_proto::google::protobuf::Any;
     use ibc_proto::ibc::core::{
-        channel::v1::Channel as RawChannelEnd, connection::v1::ConnectionEnd as RawConnectionEnd,
+        channel::v1::{Channel as RawChannelEnd, Packet as RawPacket},
+        client::v1::Height as RawHeight,
+        connection::v1::ConnectionEnd as RawConnectionEnd,
     };
+    use prost::Message;
     use tendermint_proto::Protobuf;
 
     use crate::codec::error::CodecError;
@@ -146,19 +150,19 @@ pub mod ibc {
     macro_rules! path_codec_impl {
         ($name:ty, $variant:ident) => {
             impl ProtocolCodec for IbcWrapper<$name> {
+                // The path is kept as its bytes, so that the keys under a
+                // prefix of the path are next to each other in the store.
                 fn encode(&self) -> ProtocolResult<bytes::Bytes> {
                     let path: Path = self.0.clone().into();
-                    let string = path.to_string();
-                    ProtocolCodec::encode(&string)
+                    Ok(path.to_string().into_bytes().into())
                 }
 
                 fn decode<B: AsRef<[u8]>>(bytes: B) -> ProtocolResult<Self> {
-                    let raw = <String as ProtocolCodec>::decode(bytes.as_ref())?;
-                    let path = Path::from_str(&raw).unwrap();
-                    if let Path::$variant(p) = path {
-                        Ok(IbcWrapper(p))
-                    } else {
-                        Err(ProtocolError::from(CodecError::Ibc(raw)))
+                    let raw = std::str::from_utf8(bytes.as_ref())
+                        .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+                    match Path::from_str(raw) {
+                        Ok(Path::$variant(p)) => Ok(IbcWrapper(p)),
+                        _ => Err(ProtocolError::from(CodecError::Ibc(raw.to_owned()))),
                     }
                 }
             }
@@ -201,6 +205,7 @@ pub mod ibc {
     bincode_codec_impl!(ClientType);
     bincode_codec_impl!(Sequence);
     bincode_codec_impl!(Vec<ConnectionId>);
+    bincode_codec_impl!(Vec<(PortId, ChannelId)>);
     raw_codec_impl!(PacketCommitment);
     raw_codec_impl!(AcknowledgementCommitment);
     protobuf_codec_impl!(AnyClientState, Any);
@@ -208,6 +213,69 @@ pub mod ibc {
     protobuf_codec_impl!(ChannelEnd, RawChannelEnd);
     protobuf_codec_impl!(ConnectionEnd, RawConnectionEnd);
     path_codec_impl!(ClientTypePath, ClientType);
+
+    impl ProtocolCodec for IbcWrapper<Packet> {
+        fn encode(&self) -> ProtocolResult<bytes::Bytes> {
+            Ok(RawPacket::from(self.0.clone()).encode_to_vec().into())
+        }
+
+        fn decode<B: AsRef<[u8]>>(bytes: B) -> ProtocolResult<Self> {
+            let raw = RawPacket::decode(bytes.as_ref())
+                .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+            let packet = Packet::try_from(raw)
+                .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+            Ok(IbcWrapper(packet))
+        }
+    }
+
+    impl ProtocolCodec for IbcWrapper<Height> {
+        fn encode(&self) -> ProtocolResult<bytes::Bytes> {
+            Ok(RawHeight::from(self.0).encode_to_vec().into())
+        }
+
+        fn decode<B: AsRef<[u8]>>(bytes: B) -> ProtocolResult<Self> {
+            let raw = RawHeight::decode(bytes.as_ref())
+                .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+            let height = Height::try_from(raw)
+                .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+            Ok(IbcWrapper(height))
+        }
+    }
+    // The consensus states each with its height, which are kept as the
+    // revision number, the revision height and the protobuf bytes.
+    impl ProtocolCodec for IbcWrapper<Vec<(Height, AnyConsensusState)>> {
+        fn encode(&self) -> ProtocolResult<bytes::Bytes> {
+            let raw = self
+                .0
+                .iter()
+                .map(|(height, state)| {
+                    let state = Protobuf::<Any>::encode_vec(state)
+                        .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+                    Ok((height.revision_number(), height.revision_height(), state))
+                })
+                .collect::<ProtocolResult<Vec<_>>>()?;
+            let r = bincode::serialize(&raw)
+                .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+            Ok(r.into())
+        }
+
+        fn decode<B: AsRef<[u8]>>(bytes: B) -> ProtocolResult<Self> {
+            let raw: Vec<(u64, u64, Vec<u8>)> = bincode::deserialize(bytes.as_ref())
+                .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+            let states = raw
+                .into_iter()
+                .map(|(revision_number, revision_height, state)| {
+                    let height = Height::new(revision_number, revision_height)
+                        .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+                    let state = <AnyConsensusState as Protobuf<Any>>::decode_vec(&state)
+                        .map_err(|e| ProtocolError::from(CodecError::Ibc(e.to_string())))?;
+                    Ok((height, state))
+                })
+                .collect::<ProtocolResult<Vec<_>>>()?;
+            Ok(IbcWrapper(states))
+        }
+    }
+
     path_codec_impl!(ClientStatePath, ClientState);
     path_codec_impl!(ClientConsensusStatePath, ClientConsensusState);
     path_codec_impl!(SeqSendsPath, SeqSends);
@@ -81,6 +81,7 @@ impl Decodable for ExecutorContext {
                     block_gas_limit,
                     block_base_fee_per_gas,
                     logs,
+                    gas_schedule: Default::default(),
                 })
             }
             _ => Err(DecoderError::RlpExpectedToBeList),
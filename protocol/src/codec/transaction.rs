@@ -278,18 +278,19 @@ impl Encodable for UnverifiedTransaction {
     }
 }
 
+/// Decode an EIP-2718 envelope. A legacy transaction is an RLP list, while a
+/// typed transaction is its type byte in `[0x00, 0x7f]` followed by the
+/// payload of the type.
 impl Decodable for UnverifiedTransaction {
     fn decode(r: &Rlp) -> Result<Self, DecoderError> {
         let raw = r.as_raw();
-        let header = raw[0];
-
-        if (header & 0x80) != 0x00 {
-            return LegacyTransaction::rlp_decode(r);
-        }
+        let header = *raw.first().ok_or(DecoderError::RlpIsTooShort)?;
 
         match header {
+            0xc0..=0xff => LegacyTransaction::rlp_decode(r),
             0x01 => Eip2930Transaction::rlp_decode(&Rlp::new(&raw[1..])),
             0x02 => Eip1559Transaction::rlp_decode(&Rlp::new(&raw[1..])),
+            0x00..=0x7f => Err(DecoderError::Custom("Unsupported transaction type")),
             _ => Err(DecoderError::Custom("Invalid transaction header")),
         }
     }
@@ -400,6 +401,45 @@ mod tests {
         assert_eq!(tx.chain_id, 0);
     }
 
+    #[test]
+    fn test_decode_envelope() {
+        let legacy = hex_decode("f85f800182520894095e7baea6a6c7c4c2dfeb977efac326af552d870a801ba048b55bfa915ac795c431978d8a6a992b628d557da5ff759b307d495a36649353a0efffd310ac743f371de3b9f7f9cb56c0b28ad43601b4ab949f53faa07bd2c804").unwrap();
+        let tx = UnverifiedTransaction::decode(&Rlp::new(&legacy)).unwrap();
+        assert!(tx.unsigned.is_legacy());
+
+        let eip2930 = UnverifiedTransaction {
+            unsigned:  UnsignedTransaction::Eip2930(Eip2930Transaction {
+                nonce:       U256::one(),
+                gas_price:   U256::one(),
+                gas_limit:   U256::one(),
+                action:      TransactionAction::Create,
+                value:       U256::one(),
+                data:        rand_bytes(32),
+                access_list: vec![],
+            }),
+            chain_id:  5,
+            hash:      H256::default(),
+            signature: Some(mock_sig_component()),
+        }
+        .rlp_bytes();
+        assert_eq!(eip2930[0], 0x01);
+        let tx = UnverifiedTransaction::decode(&Rlp::new(&eip2930)).unwrap();
+        assert!(matches!(tx.unsigned, UnsignedTransaction::Eip2930(_)));
+
+        let eip1559 = hex_decode("02f8670582010582012c82012c825208945cf83df52a32165a7f392168ac009b168c9e89150180c001a0a68aeb0db4d84cf16da5a6918becefd254654854cfc23f0112ef78154ce84db89f4b0af1cbf12f5bfaec81c3d4d495717d720b574a05092f6b436c2ab255cd35").unwrap();
+        let tx = UnverifiedTransaction::decode(&Rlp::new(&eip1559)).unwrap();
+        assert!(matches!(tx.unsigned, UnsignedTransaction::Eip1559(_)));
+
+        // An unknown type, such as the blob tx of EIP-4844, is rejected.
+        let mut unknown = eip1559.clone();
+        unknown[0] = 0x03;
+        assert!(UnverifiedTransaction::decode(&Rlp::new(&unknown)).is_err());
+
+        // Neither an RLP string nor empty input is an envelope.
+        assert!(UnverifiedTransaction::decode(&Rlp::new(&[0x80])).is_err());
+        assert!(UnverifiedTransaction::decode(&Rlp::new(&[])).is_err());
+    }
+
     #[test]
     fn test_signed_tx_codec() {
         let raw = hex_decode("02f8670582010582012c82012c825208945cf83df52a32165a7f392168ac009b168c9e89150180c001a0a68aeb0db4d84cf16da5a6918becefd254654854cfc23f0112ef78154ce84db89f4b0af1cbf12f5bfaec81c3d4d495717d720b574a05092f6b436c2ab255cd35").unwrap();
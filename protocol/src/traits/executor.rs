@@ -24,7 +24,7 @@ pub trait ExecutorAdapter {
 }
 
 pub trait Executor: Send + Sync {
-    fn call<B: Backend>(
+    fn call<B: Backend + ExecutorAdapter>(
         &self,
         backend: &B,
         gas_limit: u64,
@@ -4,15 +4,17 @@ use cosmos_ibc::core::ics02_client::client_type::ClientType;
 use cosmos_ibc::core::ics03_connection::connection::ConnectionEnd;
 use cosmos_ibc::core::ics04_channel::channel::ChannelEnd;
 use cosmos_ibc::core::ics04_channel::commitment::{AcknowledgementCommitment, PacketCommitment};
-use cosmos_ibc::core::ics04_channel::packet::{Receipt, Sequence};
+use cosmos_ibc::core::ics04_channel::packet::{Packet, Receipt, Sequence};
 use cosmos_ibc::core::ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId};
 use cosmos_ibc::core::ics24_host::path::{
     AcksPath, ChannelEndsPath, ClientConnectionsPath, ClientConsensusStatePath, ClientStatePath,
-    CommitmentsPath, ConnectionsPath, ReceiptsPath,
+    CommitmentsPath, ConnectionsPath, ReceiptsPath, SeqSendsPath,
 };
+use cosmos_ibc::timestamp::Timestamp;
 use creep::Context;
 
-use crate::types::{Header, Metadata, Path, StoreHeight};
+use crate::traits::IterDirection;
+use crate::types::{Header, Metadata, Path, StoreHeight, H160};
 use crate::{async_trait, ProtocolResult};
 
 #[async_trait]
@@ -61,7 +63,48 @@ pub trait IbcGrpcAdapter {
         path: &CommitmentsPath,
     ) -> ProtocolResult<Option<PacketCommitment>>;
 
+    fn get_seq_sends(
+        &self,
+        height: StoreHeight,
+        path: &SeqSendsPath,
+    ) -> ProtocolResult<Option<Sequence>>;
+
+    /// The highest height of the consensus states stored for the client,
+    /// which is tracked on each store instead of scanning all the heights.
+    fn get_client_latest_height(
+        &self,
+        height: StoreHeight,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Option<cosmos_ibc::Height>>;
+
     fn get_paths_by_prefix(&self, key_prefix: &Path) -> ProtocolResult<Vec<Path>>;
+
+    /// The proof of the entry at `path` at `height`, which the counterparty
+    /// verifies against the commitment root of the block. `None` if the store
+    /// doesn't commit the entry to the root, so there is nothing to prove it
+    /// with.
+    fn get_proof(&self, height: StoreHeight, path: &Path) -> ProtocolResult<Option<Vec<u8>>>;
+
+    /// Whether the reads at a committed height see the state as of that
+    /// height. A store which keeps only the latest state reads it at any
+    /// height, so the queries at a height are rejected instead.
+    fn is_versioned(&self) -> bool {
+        false
+    }
+
+    /// Yield the paths with the prefix one by one, so that the caller can stop
+    /// early without holding the whole key set. The iterator may borrow the
+    /// storage, so it should be consumed before the next await. The default
+    /// implementation collects `get_paths_by_prefix`, the storage which
+    /// supports iteration should override it.
+    fn iter_paths_by_prefix<'a>(
+        &'a self,
+        key_prefix: &Path,
+    ) -> ProtocolResult<Box<dyn Iterator<Item = ProtocolResult<Path>> + 'a>> {
+        Ok(Box::new(
+            self.get_paths_by_prefix(key_prefix)?.into_iter().map(Ok),
+        ))
+    }
 }
 
 #[async_trait]
@@ -104,6 +147,18 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         height: cosmos_ibc::Height,
     ) -> ProtocolResult<Option<AnyConsensusState>>;
 
+    /// At most `limit` heights of the consensus states of the client in the
+    /// direction, starting next to `from`, or at the lowest or the highest
+    /// height without it.
+    fn get_consensus_heights(
+        &self,
+        ctx: Context,
+        client_id: &ClientId,
+        from: Option<cosmos_ibc::Height>,
+        direction: IterDirection,
+        limit: usize,
+    ) -> ProtocolResult<Vec<cosmos_ibc::Height>>;
+
     fn get_connection_end_by_id(
         &self,
         ctx: Context,
@@ -116,6 +171,12 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         port_channel_id: &(PortId, ChannelId),
     ) -> ProtocolResult<Option<ChannelEnd>>;
 
+    fn get_connection_channels(
+        &self,
+        ctx: Context,
+        conn_id: &ConnectionId,
+    ) -> ProtocolResult<Option<Vec<(PortId, ChannelId)>>>;
+
     fn get_next_sequence_send(
         &self,
         ctx: Context,
@@ -140,6 +201,12 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         key: &(PortId, ChannelId, Sequence),
     ) -> ProtocolResult<Option<PacketCommitment>>;
 
+    fn get_packet(
+        &self,
+        ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<Packet>>;
+
     fn get_packet_receipt(
         &self,
         ctx: Context,
@@ -152,6 +219,45 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         key: &(PortId, ChannelId, Sequence),
     ) -> ProtocolResult<Option<AcknowledgementCommitment>>;
 
+    fn get_acknowledgement(
+        &self,
+        ctx: Context,
+        key: &(PortId, ChannelId, Sequence),
+    ) -> ProtocolResult<Option<Vec<u8>>>;
+
+    fn get_interchain_account(
+        &self,
+        ctx: Context,
+        port_id: &PortId,
+        channel_id: &ChannelId,
+    ) -> ProtocolResult<Option<H160>>;
+
+    /// The host time the consensus state of the client at the height is
+    /// processed at, which the packet delay is verified against.
+    fn get_client_processed_time(
+        &self,
+        ctx: Context,
+        client_id: &ClientId,
+        height: cosmos_ibc::Height,
+    ) -> ProtocolResult<Option<Timestamp>>;
+
+    /// The host height the consensus state of the client at the height is
+    /// processed at, which the packet delay is verified against.
+    fn get_client_processed_height(
+        &self,
+        ctx: Context,
+        client_id: &ClientId,
+        height: cosmos_ibc::Height,
+    ) -> ProtocolResult<Option<cosmos_ibc::Height>>;
+
+    /// The alternative consensus states of a forked counterparty of the
+    /// client, each with its height, which are not confirmed yet.
+    fn get_fork_consensus_states(
+        &self,
+        ctx: Context,
+        client_id: &ClientId,
+    ) -> ProtocolResult<Vec<(cosmos_ibc::Height, AnyConsensusState)>>;
+
     fn set_client_type(
         &self,
         ctx: Context,
@@ -174,6 +280,38 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         consensus_state: AnyConsensusState,
     ) -> ProtocolResult<()>;
 
+    fn set_client_latest_height(
+        &self,
+        ctx: Context,
+        client_id: ClientId,
+        height: cosmos_ibc::Height,
+    ) -> ProtocolResult<()>;
+
+    fn set_client_processed_time(
+        &self,
+        ctx: Context,
+        client_id: ClientId,
+        height: cosmos_ibc::Height,
+        timestamp: Timestamp,
+    ) -> ProtocolResult<()>;
+
+    fn set_client_processed_height(
+        &self,
+        ctx: Context,
+        client_id: ClientId,
+        height: cosmos_ibc::Height,
+        host_height: cosmos_ibc::Height,
+    ) -> ProtocolResult<()>;
+
+    /// Replace the alternative consensus states of the client. An empty set
+    /// removes them.
+    fn set_fork_consensus_states(
+        &self,
+        ctx: Context,
+        client_id: ClientId,
+        states: Vec<(cosmos_ibc::Height, AnyConsensusState)>,
+    ) -> ProtocolResult<()>;
+
     fn set_connection_end(
         &self,
         ctx: Context,
@@ -188,6 +326,10 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         client_id: ClientId,
     ) -> ProtocolResult<()>;
 
+    fn get_connection_counter(&self, ctx: Context) -> ProtocolResult<Option<u64>>;
+
+    fn set_connection_counter(&self, ctx: Context, counter: u64) -> ProtocolResult<()>;
+
     fn set_packet_commitment(
         &self,
         ctx: Context,
@@ -195,6 +337,13 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         commitment: PacketCommitment,
     ) -> ProtocolResult<()>;
 
+    fn set_packet(
+        &self,
+        ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        packet: Packet,
+    ) -> ProtocolResult<()>;
+
     fn set_packet_receipt(
         &self,
         ctx: Context,
@@ -209,6 +358,30 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         ack_commitment: AcknowledgementCommitment,
     ) -> ProtocolResult<()>;
 
+    fn set_acknowledgement(
+        &self,
+        ctx: Context,
+        key: (PortId, ChannelId, Sequence),
+        ack: Vec<u8>,
+    ) -> ProtocolResult<()>;
+
+    /// Register the interchain account of an ICS-27 host channel.
+    fn set_interchain_account(
+        &self,
+        ctx: Context,
+        port_id: PortId,
+        channel_id: ChannelId,
+        address: H160,
+    ) -> ProtocolResult<()>;
+
+    /// Append a channel to the channels of a connection.
+    fn set_connection_channels(
+        &self,
+        ctx: Context,
+        conn_id: ConnectionId,
+        port_channel_id: &(PortId, ChannelId),
+    ) -> ProtocolResult<()>;
+
     fn set_channel(
         &self,
         ctx: Context,
@@ -247,5 +420,32 @@ pub trait IbcAdapter: IbcGrpcAdapter + Send + Sync {
         key: (PortId, ChannelId, Sequence),
     ) -> ProtocolResult<()>;
 
+    fn remove_packet(&self, ctx: Context, key: (PortId, ChannelId, Sequence))
+        -> ProtocolResult<()>;
+
+    /// Remove the consensus state of the client at the height, along with its
+    /// processed time and height.
+    fn remove_consensus_state(
+        &self,
+        ctx: Context,
+        client_id: ClientId,
+        height: cosmos_ibc::Height,
+    ) -> ProtocolResult<()>;
+
+    /// Stage the following writes instead of applying them, until
+    /// `commit_batch` applies them atomically or `discard_batch` drops them.
+    /// The reads see the staged writes. Beginning a batch when one is already
+    /// staged is a no-op.
+    fn begin_batch(&self, ctx: Context) -> ProtocolResult<()>;
+
+    fn commit_batch(&self, ctx: Context) -> ProtocolResult<()>;
+
+    fn discard_batch(&self, ctx: Context) -> ProtocolResult<()>;
+
     fn current_height(&self) -> u64;
+
+    /// The oldest height whose block is still kept by the storage, below
+    /// which the host consensus states and the queries at a height can't be
+    /// served.
+    fn oldest_height(&self) -> ProtocolResult<u64>;
 }
@@ -32,7 +32,7 @@ pub use network::{
 #[cfg(feature = "ibc")]
 pub use storage::ibc::IbcCrossChainStorage;
 pub use storage::{
-    CkbCrossChainStorage, CommonStorage, IntoIteratorByRef, Storage, StorageAdapter,
+    CkbCrossChainStorage, CommonStorage, IntoIteratorByRef, IterDirection, Storage, StorageAdapter,
     StorageBatchModify, StorageCategory, StorageIterator, StorageSchema,
 };
 pub use tx_assembler::{TxAssembler, TxAssemblerAdapter};
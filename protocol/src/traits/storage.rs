@@ -17,6 +17,13 @@ pub enum StorageCategory {
     IbcCrossChain,
 }
 
+/// The direction to iterate the keys of a category in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IterDirection {
+    Forward,
+    Reverse,
+}
+
 pub type StorageIterator<'a, S> = Box<
     dyn Iterator<Item = ProtocolResult<(<S as StorageSchema>::Key, <S as StorageSchema>::Value)>>
         + 'a,
@@ -116,6 +123,14 @@ pub trait Storage: CommonStorage + CkbCrossChainStorage {
         hashes: &[Hash],
     ) -> ProtocolResult<Vec<Option<Receipt>>>;
 
+    /// Get all the receipts of a block in transaction order, or `None` if the
+    /// block does not exist.
+    async fn get_block_receipts(
+        &self,
+        ctx: Context,
+        block_height: u64,
+    ) -> ProtocolResult<Option<Vec<Receipt>>>;
+
     async fn update_latest_proof(&self, ctx: Context, proof: Proof) -> ProtocolResult<()>;
 
     async fn get_latest_proof(&self, ctx: Context) -> ProtocolResult<Proof>;
@@ -189,6 +204,15 @@ pub trait StorageAdapter: Send + Sync {
         &'b self,
         prefix: &'a P,
     ) -> ProtocolResult<Box<dyn IntoIteratorByRef<S> + 'a>>;
+
+    /// Iterate the keys of the category of the schema in the order of their
+    /// bytes without reading the values. The iteration starts at the first
+    /// key not below `from`, or not above it in the reverse direction.
+    fn iter_keys<'a, S: StorageSchema + 'static>(
+        &'a self,
+        from: &[u8],
+        direction: IterDirection,
+    ) -> ProtocolResult<Box<dyn Iterator<Item = ProtocolResult<<S as StorageSchema>::Key>> + 'a>>;
 }
 
 #[cfg(feature = "ibc")]
@@ -200,12 +224,14 @@ pub mod ibc {
             ics03_connection::connection::ConnectionEnd,
             ics04_channel::channel::ChannelEnd,
             ics04_channel::commitment::{AcknowledgementCommitment, PacketCommitment},
-            ics04_channel::packet::{Receipt, Sequence},
+            ics04_channel::packet::{Packet, Receipt, Sequence},
             ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
         },
         Height,
     };
 
+    use super::IterDirection;
+    use crate::types::H160;
     use crate::ProtocolResult;
 
     pub trait IbcCrossChainStorage {
@@ -232,6 +258,17 @@ pub mod ibc {
             height: Height,
         ) -> ProtocolResult<Option<AnyConsensusState>>;
 
+        /// At most `limit` heights of the consensus states of the client in
+        /// the direction, starting next to `from`, or at the lowest or the
+        /// highest height without it.
+        fn get_consensus_heights(
+            &self,
+            client_id: &ClientId,
+            from: Option<Height>,
+            direction: IterDirection,
+            limit: usize,
+        ) -> ProtocolResult<Vec<Height>>;
+
         fn set_client_type(
             &self,
             client_id: ClientId,
@@ -251,6 +288,63 @@ pub mod ibc {
             consensus_state: AnyConsensusState,
         ) -> ProtocolResult<()>;
 
+        fn delete_consensus_state(&self, client_id: ClientId, height: Height)
+            -> ProtocolResult<()>;
+
+        /// The highest height of the consensus states stored for the client.
+        fn get_client_latest_height(&self, client_id: &ClientId) -> ProtocolResult<Option<Height>>;
+
+        fn set_client_latest_height(
+            &self,
+            client_id: ClientId,
+            height: Height,
+        ) -> ProtocolResult<()>;
+
+        /// The host time in nanoseconds the consensus state of the client at
+        /// the height is processed at.
+        fn get_client_processed_time(
+            &self,
+            client_id: &ClientId,
+            height: Height,
+        ) -> ProtocolResult<Option<u64>>;
+
+        fn set_client_processed_time(
+            &self,
+            client_id: ClientId,
+            height: Height,
+            timestamp: u64,
+        ) -> ProtocolResult<()>;
+
+        /// The host height the consensus state of the client at the height is
+        /// processed at.
+        fn get_client_processed_height(
+            &self,
+            client_id: &ClientId,
+            height: Height,
+        ) -> ProtocolResult<Option<Height>>;
+
+        fn set_client_processed_height(
+            &self,
+            client_id: ClientId,
+            height: Height,
+            host_height: Height,
+        ) -> ProtocolResult<()>;
+
+        /// The alternative consensus states of a forked counterparty of the
+        /// client, each with its height, which are not confirmed yet.
+        fn get_fork_consensus_states(
+            &self,
+            client_id: &ClientId,
+        ) -> ProtocolResult<Vec<(Height, AnyConsensusState)>>;
+
+        /// Replace the alternative consensus states of the client. An empty
+        /// set removes the entry.
+        fn set_fork_consensus_states(
+            &self,
+            client_id: ClientId,
+            states: Vec<(Height, AnyConsensusState)>,
+        ) -> ProtocolResult<()>;
+
         fn set_connection_end(
             &self,
             connection_id: ConnectionId,
@@ -273,6 +367,10 @@ pub mod ibc {
             conn_id: &ConnectionId,
         ) -> ProtocolResult<Option<ConnectionEnd>>;
 
+        fn get_connection_counter(&self) -> ProtocolResult<Option<u64>>;
+
+        fn set_connection_counter(&self, counter: u64) -> ProtocolResult<()>;
+
         fn set_packet_commitment(
             &self,
             key: (PortId, ChannelId, Sequence),
@@ -289,6 +387,17 @@ pub mod ibc {
             key: (PortId, ChannelId, Sequence),
         ) -> ProtocolResult<()>;
 
+        fn set_packet(
+            &self,
+            key: (PortId, ChannelId, Sequence),
+            packet: Packet,
+        ) -> ProtocolResult<()>;
+
+        fn get_packet(&self, key: &(PortId, ChannelId, Sequence))
+            -> ProtocolResult<Option<Packet>>;
+
+        fn delete_packet(&self, key: (PortId, ChannelId, Sequence)) -> ProtocolResult<()>;
+
         fn set_packet_receipt(
             &self,
             key: (PortId, ChannelId, Sequence),
@@ -316,12 +425,45 @@ pub mod ibc {
             key: (PortId, ChannelId, Sequence),
         ) -> ProtocolResult<()>;
 
+        /// Store a written acknowledgement, whose commitment only keeps its
+        /// hash.
+        fn set_acknowledgement(
+            &self,
+            key: (PortId, ChannelId, Sequence),
+            ack: Vec<u8>,
+        ) -> ProtocolResult<()>;
+
+        fn get_acknowledgement(
+            &self,
+            key: &(PortId, ChannelId, Sequence),
+        ) -> ProtocolResult<Option<Vec<u8>>>;
+
+        /// Register the interchain account of an ICS-27 host channel.
+        fn set_interchain_account(
+            &self,
+            port_id: PortId,
+            channel_id: ChannelId,
+            address: H160,
+        ) -> ProtocolResult<()>;
+
+        fn get_interchain_account(
+            &self,
+            port_id: &PortId,
+            channel_id: &ChannelId,
+        ) -> ProtocolResult<Option<H160>>;
+
+        /// Append a channel to the channels of a connection.
         fn set_connection_channels(
             &self,
             conn_id: ConnectionId,
             port_channel_id: &(PortId, ChannelId),
         ) -> ProtocolResult<()>;
 
+        fn get_connection_channels(
+            &self,
+            conn_id: &ConnectionId,
+        ) -> ProtocolResult<Option<Vec<(PortId, ChannelId)>>>;
+
         fn set_channel(
             &self,
             port_id: PortId,
@@ -369,5 +511,23 @@ pub mod ibc {
             &self,
             port_channel_id: &(PortId, ChannelId),
         ) -> ProtocolResult<Option<Sequence>>;
+
+        /// Iterate the ICS024 paths of the stored entries which start with
+        /// `prefix`. The keys are scanned lazily from the prefix, so the
+        /// caller can stop early without loading the whole key set.
+        fn iter_paths_by_prefix<'a>(
+            &'a self,
+            prefix: &str,
+        ) -> ProtocolResult<Box<dyn Iterator<Item = ProtocolResult<String>> + 'a>>;
+
+        /// Stage the following writes instead of applying them, until
+        /// `commit_batch` applies them all at once or `discard_batch` drops
+        /// them. The reads see the staged writes. Beginning a batch when one
+        /// is already staged is a no-op.
+        fn begin_batch(&self);
+
+        fn commit_batch(&self) -> ProtocolResult<()>;
+
+        fn discard_batch(&self);
     }
 }
@@ -1,8 +1,12 @@
 pub use ethereum::{AccessList, AccessListItem, Account};
 pub use evm::{backend::Log, Config, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed};
 
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
 use crate::codec::ProtocolCodec;
-use crate::types::{Hash, Hasher, Header, MerkleRoot, Proposal, H160, U256};
+use crate::types::{Hash, Hasher, Header, MerkleRoot, Proposal, H160, H256, U256};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ExecResp {
@@ -37,6 +41,39 @@ impl Default for TxResp {
     }
 }
 
+/// The account fields replaced for a simulated call. A `None` field and the
+/// storage slots which are not listed keep the value of the base state.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct AccountOverride {
+    pub balance: Option<U256>,
+    pub nonce:   Option<U256>,
+    pub code:    Option<Vec<u8>>,
+    pub storage: BTreeMap<H256, H256>,
+}
+
+pub type StateOverride = BTreeMap<H160, AccountOverride>;
+
+/// The state of an account after a block. The storage slots are written over
+/// the storage of the account, which is cleared first if `reset_storage` is
+/// set. A `None` code keeps the code of the account.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub nonce:         U256,
+    pub balance:       U256,
+    pub code:          Option<Vec<u8>>,
+    pub storage:       BTreeMap<H256, H256>,
+    pub reset_storage: bool,
+}
+
+/// The accounts changed by a block and the state root after the block, which
+/// a syncing node applies instead of executing the txs of the block. An
+/// account mapped to `None` is deleted.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct StateDiff {
+    pub accounts:   BTreeMap<H160, Option<AccountDiff>>,
+    pub state_root: MerkleRoot,
+}
+
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub struct ExecutorContext {
     pub block_number:           U256,
@@ -50,6 +87,73 @@ pub struct ExecutorContext {
     pub block_gas_limit:        U256,
     pub block_base_fee_per_gas: U256,
     pub logs:                   Vec<Log>,
+    /// A local setting of the chain, which is not encoded with the context.
+    pub gas_schedule:           GasSchedule,
+}
+
+/// The gas costs which override the ones of the London schedule, so that a
+/// chain can tune its economics. A `None` cost keeps the London one.
+#[derive(Default, Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct GasSchedule {
+    pub sload_cold:           Option<u64>,
+    pub storage_read_warm:    Option<u64>,
+    pub sstore_set:           Option<u64>,
+    pub sstore_reset:         Option<u64>,
+    pub refund_sstore_clears: Option<i64>,
+    pub account_access_cold:  Option<u64>,
+    pub balance:              Option<u64>,
+    pub ext_code:             Option<u64>,
+    pub ext_code_hash:        Option<u64>,
+    pub call:                 Option<u64>,
+    pub expbyte:              Option<u64>,
+    pub suicide:              Option<u64>,
+    pub suicide_new_account:  Option<u64>,
+}
+
+impl GasSchedule {
+    /// The EVM config of the London hard fork with the costs overridden.
+    pub fn config(&self) -> Config {
+        let mut config = Config::london();
+        let overrides = [
+            (self.sload_cold, &mut config.gas_sload_cold),
+            (self.storage_read_warm, &mut config.gas_storage_read_warm),
+            (self.sstore_set, &mut config.gas_sstore_set),
+            (self.sstore_reset, &mut config.gas_sstore_reset),
+            (
+                self.account_access_cold,
+                &mut config.gas_account_access_cold,
+            ),
+            (self.balance, &mut config.gas_balance),
+            (self.ext_code, &mut config.gas_ext_code),
+            (self.ext_code_hash, &mut config.gas_ext_code_hash),
+            (self.call, &mut config.gas_call),
+            (self.expbyte, &mut config.gas_expbyte),
+            (self.suicide, &mut config.gas_suicide),
+            (
+                self.suicide_new_account,
+                &mut config.gas_suicide_new_account,
+            ),
+        ];
+        for (cost, field) in overrides {
+            if let Some(cost) = cost {
+                *field = cost;
+            }
+        }
+        if let Some(refund) = self.refund_sstore_clears {
+            config.refund_sstore_clears = refund;
+        }
+
+        config
+    }
+}
+
+impl ExecutorContext {
+    /// The context run with the costs of `gas_schedule`, which is the one of
+    /// the node config rather than the one of a block.
+    pub fn with_gas_schedule(mut self, gas_schedule: GasSchedule) -> Self {
+        self.gas_schedule = gas_schedule;
+        self
+    }
 }
 
 impl From<Proposal> for ExecutorContext {
@@ -66,6 +170,7 @@ impl From<Proposal> for ExecutorContext {
             block_gas_limit:        h.gas_limit,
             block_base_fee_per_gas: h.base_fee_per_gas,
             logs:                   Vec::new(),
+            gas_schedule:           GasSchedule::default(),
         }
     }
 }
@@ -84,6 +189,7 @@ impl From<&Header> for ExecutorContext {
             block_gas_limit:        h.gas_limit,
             block_base_fee_per_gas: h.base_fee_per_gas,
             logs:                   Vec::new(),
+            gas_schedule:           GasSchedule::default(),
         }
     }
 }
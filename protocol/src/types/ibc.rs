@@ -250,11 +250,3 @@ impl_into_path_for!(
     path::ReceiptsPath,
     path::AcksPath
 );
-
-/// Store height to query
-#[derive(Debug, Copy, Clone)]
-pub enum StoreHeight {
-    Pending,
-    // Latest,
-    // Stable(RawHeight),
-}
@@ -7,7 +7,8 @@ pub use ckb_client::*;
 pub use crosschain::*;
 pub use evm::{backend::*, ExitError, ExitRevert, ExitSucceed};
 pub use executor::{
-    AccessList, AccessListItem, Account, Config, ExecResp, ExecutorContext, ExitReason, TxResp,
+    AccessList, AccessListItem, Account, AccountDiff, AccountOverride, Config, ExecResp,
+    ExecutorContext, ExitReason, GasSchedule, StateDiff, StateOverride, TxResp,
 };
 #[cfg(feature = "ibc")]
 pub use ibc::*;
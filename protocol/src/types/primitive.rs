@@ -430,6 +430,15 @@ pub fn checksum(address: &str) -> String {
         })
 }
 
+/// Store height to query
+#[derive(Debug, Copy, Clone)]
+pub enum StoreHeight {
+    Pending,
+    // Latest,
+    /// The state after the committed block of the number.
+    Stable(u64),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
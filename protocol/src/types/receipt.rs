@@ -48,3 +48,15 @@ impl Receipt {
         }
     }
 }
+
+/// The cumulative gas used of each receipt of a block, which must be in
+/// transaction order.
+pub fn cumulative_gas_used(receipts: &[Receipt]) -> Vec<U256> {
+    receipts
+        .iter()
+        .scan(U256::zero(), |acc, receipt| {
+            *acc += receipt.used_gas;
+            Some(*acc)
+        })
+        .collect()
+}